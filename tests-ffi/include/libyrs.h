@@ -255,6 +255,11 @@ typedef struct YSubscription {} YSubscription;
  */
 #define ERR_CUSTOM 9
 
+/**
+ * Error code: decoded data exceeds configured resource limits.
+ */
+#define ERR_LIMIT_EXCEEDED 10
+
 #define YCHANGE_ADD 1
 
 #define YCHANGE_RETAIN 0
@@ -0,0 +1,355 @@
+//! Conversion of the current content of [Yrs](yrs) documents from and into
+//! [Automerge](automerge) documents, meant for migrating data between both libraries.
+//!
+//! Only a current state of a document is converted: its history of changes, deleted content and
+//! formatting attributes are not preserved. Conversion uses following mapping:
+//!
+//! | Yrs                                  | Automerge              |
+//! |--------------------------------------|------------------------|
+//! | [MapRef], [Any::Map]                 | [ObjType::Map]         |
+//! | [ArrayRef], [Any::Array]             | [ObjType::List]        |
+//! | [TextRef], XML types                 | [ObjType::Text]        |
+//! | [Any::String]                        | [ScalarValue::Str]     |
+//! | [Any::BigInt], integer [Any::Number] | [ScalarValue::Int]     |
+//! | [Any::Number]                        | [ScalarValue::F64]     |
+//! | [Any::Bool]                          | [ScalarValue::Boolean] |
+//! | [Any::Buffer]                        | [ScalarValue::Bytes]   |
+//! | [Any::Null], [Any::Undefined]        | [ScalarValue::Null]    |
+//!
+//! XML types are exported as texts containing their string representation. Automerge integers
+//! are imported the same way as [Any::from] does it for `i64`: as [Any::Number] if they fit into
+//! a safe float integer range and as [Any::BigInt] otherwise. Counters and timestamps are imported
+//! as integers, while tables are imported as maps.
+//!
+//! [MapRef]: yrs::MapRef
+//! [ArrayRef]: yrs::ArrayRef
+//! [TextRef]: yrs::TextRef
+
+use automerge::transaction::Transactable;
+use automerge::{AutoCommit, AutomergeError, ObjId, ObjType, ReadDoc, ScalarValue, Value, ROOT};
+use std::convert::TryFrom;
+use std::iter::FromIterator;
+use std::sync::Arc;
+use thiserror::Error;
+use yrs::types::text::DeltaPrelim;
+use yrs::types::ToJson;
+use yrs::{
+    Any, Array, ArrayPrelim, Doc, GetString, In, Map, MapPrelim, Options, Out, ReadTxn, Text,
+    TextPrelim, Transact, WriteTxn,
+};
+
+/// Error returned when converting documents between Yrs and Automerge.
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error(transparent)]
+    Automerge(#[from] AutomergeError),
+    /// Root level entry of an Automerge document is not an object, while all root types of Yrs
+    /// documents must be shared types.
+    #[error("root entry '{0}' is a scalar value, while only maps, lists and texts are supported")]
+    UnsupportedRoot(String),
+    /// Automerge value of unknown type, which cannot be represented in Yrs.
+    #[error("unsupported Automerge value: {0}")]
+    UnsupportedValue(String),
+}
+
+/// Exports the current content of a document read by a given transaction into a new Automerge
+/// document, with every root type stored as an entry of Automerge root map. It can be persisted
+/// with [AutoCommit::save] or its changes can be obtained with [AutoCommit::get_changes].
+pub fn export<T: ReadTxn>(txn: &T) -> Result<AutoCommit, Error> {
+    let mut am = AutoCommit::new();
+    for (name, value) in txn.root_refs() {
+        match value {
+            Out::UndefinedRef(_) => { /* root type was never defined on current document */ }
+            value => export_map_entry(&mut am, txn, &ROOT, name, value)?,
+        }
+    }
+    Ok(am)
+}
+
+/// Imports the current content of an Automerge document into a new Yrs document created with
+/// given `options`. Every entry of Automerge root map becomes a root type of the same name.
+pub fn import<R: ReadDoc>(am: &R, options: Options) -> Result<Doc, Error> {
+    let doc = Doc::with_options(options);
+    {
+        let mut txn = doc.transact_mut();
+        for name in am.keys(ROOT) {
+            let (value, id) = match am.get(ROOT, name.as_str())? {
+                Some(entry) => entry,
+                None => continue,
+            };
+            match value {
+                Value::Object(ObjType::Map) | Value::Object(ObjType::Table) => {
+                    let map = txn.get_or_insert_map(name.as_str());
+                    for (key, value) in import_map(am, &id)? {
+                        map.insert(&mut txn, key, value);
+                    }
+                }
+                Value::Object(ObjType::List) => {
+                    let array = txn.get_or_insert_array(name.as_str());
+                    for (index, value) in import_list(am, &id)?.into_iter().enumerate() {
+                        array.insert(&mut txn, index as u32, value);
+                    }
+                }
+                Value::Object(ObjType::Text) => {
+                    let text = txn.get_or_insert_text(name.as_str());
+                    text.insert(&mut txn, 0, &am.text(&id)?);
+                }
+                Value::Scalar(_) => return Err(Error::UnsupportedRoot(name)),
+            }
+        }
+    }
+    Ok(doc)
+}
+
+fn export_map_entry<T: ReadTxn>(
+    am: &mut AutoCommit,
+    txn: &T,
+    obj: &ObjId,
+    key: &str,
+    value: Out,
+) -> Result<(), Error> {
+    match value {
+        Out::Any(any) => export_any_entry(am, obj, key, any),
+        Out::YMap(map) => {
+            let id = am.put_object(obj, key, ObjType::Map)?;
+            for (key, value) in map.iter(txn) {
+                export_map_entry(am, txn, &id, key, value)?;
+            }
+            Ok(())
+        }
+        Out::YArray(array) => {
+            let id = am.put_object(obj, key, ObjType::List)?;
+            for (index, value) in array.iter(txn).enumerate() {
+                export_list_item(am, txn, &id, index, value)?;
+            }
+            Ok(())
+        }
+        Out::YText(text) => export_text(am, obj, key, &text.get_string(txn)),
+        Out::YXmlElement(xml) => export_text(am, obj, key, &xml.get_string(txn)),
+        Out::YXmlFragment(xml) => export_text(am, obj, key, &xml.get_string(txn)),
+        Out::YXmlText(xml) => export_text(am, obj, key, &xml.get_string(txn)),
+        other => export_any_entry(am, obj, key, other.to_json(txn)),
+    }
+}
+
+fn export_list_item<T: ReadTxn>(
+    am: &mut AutoCommit,
+    txn: &T,
+    list: &ObjId,
+    index: usize,
+    value: Out,
+) -> Result<(), Error> {
+    match value {
+        Out::Any(any) => export_any_item(am, list, index, any),
+        Out::YMap(map) => {
+            let id = am.insert_object(list, index, ObjType::Map)?;
+            for (key, value) in map.iter(txn) {
+                export_map_entry(am, txn, &id, key, value)?;
+            }
+            Ok(())
+        }
+        Out::YArray(array) => {
+            let id = am.insert_object(list, index, ObjType::List)?;
+            for (index, value) in array.iter(txn).enumerate() {
+                export_list_item(am, txn, &id, index, value)?;
+            }
+            Ok(())
+        }
+        Out::YText(text) => export_text_item(am, list, index, &text.get_string(txn)),
+        Out::YXmlElement(xml) => export_text_item(am, list, index, &xml.get_string(txn)),
+        Out::YXmlFragment(xml) => export_text_item(am, list, index, &xml.get_string(txn)),
+        Out::YXmlText(xml) => export_text_item(am, list, index, &xml.get_string(txn)),
+        other => export_any_item(am, list, index, other.to_json(txn)),
+    }
+}
+
+fn export_text(am: &mut AutoCommit, obj: &ObjId, key: &str, value: &str) -> Result<(), Error> {
+    let id = am.put_object(obj, key, ObjType::Text)?;
+    am.splice_text(&id, 0, 0, value)?;
+    Ok(())
+}
+
+fn export_text_item(
+    am: &mut AutoCommit,
+    list: &ObjId,
+    index: usize,
+    value: &str,
+) -> Result<(), Error> {
+    let id = am.insert_object(list, index, ObjType::Text)?;
+    am.splice_text(&id, 0, 0, value)?;
+    Ok(())
+}
+
+fn export_any_entry(am: &mut AutoCommit, obj: &ObjId, key: &str, value: Any) -> Result<(), Error> {
+    match value {
+        Any::Map(entries) => {
+            let id = am.put_object(obj, key, ObjType::Map)?;
+            for (key, value) in entries.iter() {
+                export_any_entry(am, &id, key, value.clone())?;
+            }
+        }
+        Any::Array(values) => {
+            let id = am.put_object(obj, key, ObjType::List)?;
+            for (index, value) in values.iter().enumerate() {
+                export_any_item(am, &id, index, value.clone())?;
+            }
+        }
+        scalar => am.put(obj, key, scalar_value(scalar))?,
+    }
+    Ok(())
+}
+
+fn export_any_item(
+    am: &mut AutoCommit,
+    list: &ObjId,
+    index: usize,
+    value: Any,
+) -> Result<(), Error> {
+    match value {
+        Any::Map(entries) => {
+            let id = am.insert_object(list, index, ObjType::Map)?;
+            for (key, value) in entries.iter() {
+                export_any_entry(am, &id, key, value.clone())?;
+            }
+        }
+        Any::Array(values) => {
+            let id = am.insert_object(list, index, ObjType::List)?;
+            for (index, value) in values.iter().enumerate() {
+                export_any_item(am, &id, index, value.clone())?;
+            }
+        }
+        scalar => am.insert(list, index, scalar_value(scalar))?,
+    }
+    Ok(())
+}
+
+fn scalar_value(value: Any) -> ScalarValue {
+    match value {
+        Any::Null | Any::Undefined => ScalarValue::Null,
+        Any::Bool(v) => ScalarValue::Boolean(v),
+        // since JS doesn't clearly recognise difference between integers and floats,
+        // we check if it's possible to perform lossless conversion to i64
+        Any::Number(v) if v as i64 as f64 == v => ScalarValue::Int(v as i64),
+        Any::Number(v) => ScalarValue::F64(v),
+        Any::BigInt(v) => ScalarValue::Int(v),
+        Any::String(v) => ScalarValue::Str(v.as_ref().into()),
+        Any::Buffer(v) => ScalarValue::Bytes(v.to_vec()),
+        Any::Array(_) | Any::Map(_) => unreachable!("collections are exported as objects"),
+    }
+}
+
+fn import_value<R: ReadDoc>(am: &R, value: Value, id: &ObjId) -> Result<In, Error> {
+    match value {
+        Value::Object(ObjType::Map) | Value::Object(ObjType::Table) => {
+            Ok(In::Map(MapPrelim::from_iter(import_map(am, id)?)))
+        }
+        Value::Object(ObjType::List) => Ok(In::Array(ArrayPrelim::from_iter(import_list(am, id)?))),
+        Value::Object(ObjType::Text) => {
+            Ok(In::Text(DeltaPrelim::from(TextPrelim::new(am.text(id)?))))
+        }
+        Value::Scalar(scalar) => Ok(In::Any(import_scalar(scalar.as_ref())?)),
+    }
+}
+
+fn import_map<R: ReadDoc>(am: &R, obj: &ObjId) -> Result<Vec<(Arc<str>, In)>, Error> {
+    let mut entries = Vec::new();
+    for key in am.keys(obj) {
+        if let Some((value, id)) = am.get(obj, key.as_str())? {
+            entries.push((Arc::from(key), import_value(am, value, &id)?));
+        }
+    }
+    Ok(entries)
+}
+
+fn import_list<R: ReadDoc>(am: &R, obj: &ObjId) -> Result<Vec<In>, Error> {
+    let len = am.length(obj);
+    let mut values = Vec::with_capacity(len);
+    for index in 0..len {
+        if let Some((value, id)) = am.get(obj, index)? {
+            values.push(import_value(am, value, &id)?);
+        }
+    }
+    Ok(values)
+}
+
+fn import_scalar(value: &ScalarValue) -> Result<Any, Error> {
+    match value {
+        ScalarValue::Null => Ok(Any::Null),
+        ScalarValue::Boolean(v) => Ok(Any::Bool(*v)),
+        ScalarValue::Int(v) => Ok(Any::from(*v)),
+        ScalarValue::Uint(v) => Any::try_from(*v)
+            .map_err(|v| Error::UnsupportedValue(format!("{} is out of range for i64", v))),
+        ScalarValue::F64(v) => Ok(Any::Number(*v)),
+        ScalarValue::Str(v) => Ok(Any::from(v.as_str())),
+        ScalarValue::Bytes(v) => Ok(Any::from(v.as_slice())),
+        ScalarValue::Counter(v) => Ok(Any::from(i64::from(v))),
+        ScalarValue::Timestamp(v) => Ok(Any::from(*v)),
+        other => Err(Error::UnsupportedValue(other.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{export, import, Error};
+    use automerge::transaction::Transactable;
+    use automerge::{AutoCommit, ObjType, ReadDoc, ScalarValue, Value, ROOT};
+    use yrs::types::ToJson;
+    use yrs::{
+        any, Array, ArrayPrelim, Doc, GetString, Map, MapPrelim, Options, ReadTxn, Text,
+        TextPrelim, Transact, WriteTxn,
+    };
+
+    #[test]
+    fn roundtrip_through_automerge() {
+        let doc = Doc::with_client_id(1);
+        {
+            let mut txn = doc.transact_mut();
+            let text = txn.get_or_insert_text("text");
+            text.insert(&mut txn, 0, "hello world");
+            text.remove_range(&mut txn, 5, 6);
+            let map = txn.get_or_insert_map("map");
+            map.insert(&mut txn, "int", 1);
+            map.insert(&mut txn, "json", any!({"a": [true, null]}));
+            map.insert(&mut txn, "text", TextPrelim::new("abc"));
+            let array = txn.get_or_insert_array("array");
+            array.push_back(&mut txn, 1.5);
+            array.push_back(&mut txn, MapPrelim::from([("k", "v")]));
+            array.push_back(&mut txn, ArrayPrelim::from(["x"]));
+        }
+
+        let am = export(&doc.transact()).unwrap();
+        let (value, text_id) = am.get(ROOT, "text").unwrap().unwrap();
+        assert_eq!(value, Value::Object(ObjType::Text));
+        assert_eq!(am.text(&text_id).unwrap(), "hello");
+        let (_, map_id) = am.get(ROOT, "map").unwrap().unwrap();
+        assert_eq!(
+            am.get(&map_id, "int").unwrap().unwrap().0,
+            Value::Scalar(std::borrow::Cow::Owned(ScalarValue::Int(1)))
+        );
+
+        // saved Automerge document can be loaded by other Automerge peers
+        let loaded = automerge::Automerge::load(&am.clone().save()).unwrap();
+        let copy = import(&loaded, Options::with_client_id(2)).unwrap();
+        let txn = copy.transact();
+        assert_eq!(txn.get_text("text").unwrap().get_string(&txn), "hello");
+        let map = txn.get_map("map").unwrap();
+        assert_eq!(
+            map.to_json(&txn),
+            any!({"int": 1, "json": {"a": [true, null]}, "text": "abc"})
+        );
+        assert_eq!(
+            txn.get_array("array").unwrap().to_json(&txn),
+            any!([1.5, {"k": "v"}, ["x"]])
+        );
+    }
+
+    #[test]
+    fn import_rejects_scalar_roots() {
+        let mut am = AutoCommit::new();
+        am.put(ROOT, "counter", ScalarValue::counter(1)).unwrap();
+        assert!(matches!(
+            import(&am, Options::default()),
+            Err(Error::UnsupportedRoot(name)) if name == "counter"
+        ));
+    }
+}
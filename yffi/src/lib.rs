@@ -22,10 +22,11 @@ use yrs::undo::EventKind;
 use yrs::updates::decoder::{Decode, DecoderV1};
 use yrs::updates::encoder::{Encode, Encoder, EncoderV1, EncoderV2};
 use yrs::{
-    uuid_v4, Any, Array, ArrayRef, Assoc, BranchID, DeleteSet, GetString, Map, MapRef, Observable,
-    OffsetKind, Options, Origin, Out, Quotable, ReadTxn, Snapshot, StateVector, StickyIndex, Store,
-    SubdocsEvent, SubdocsEventIter, Text, TextRef, Transact, TransactionCleanupEvent, Update, Xml,
-    XmlElementPrelim, XmlElementRef, XmlFragmentRef, XmlTextPrelim, XmlTextRef, ID,
+    uuid_v4, Any, Array, ArrayRef, Assoc, BranchID, DeleteSet, GetString, Map, MapRef, MovePolicy,
+    Observable, OffsetKind, Options, Origin, Out, Quotable, ReadTxn, Snapshot, StateVector,
+    StickyIndex, Store, SubdocsEvent, SubdocsEventIter, Text, TextRef, Transact,
+    TransactionCleanupEvent, Update, Xml, XmlElementPrelim, XmlElementRef, XmlFragmentRef,
+    XmlTextPrelim, XmlTextRef, ID,
 };
 
 /// Flag used by `YInput` and `YOutput` to tag boolean values.
@@ -312,6 +313,8 @@ impl Into<Options> for YOptions {
             auto_load: if self.auto_load == 0 { false } else { true },
             should_load: if self.should_load == 0 { false } else { true },
             offset_kind: encoding,
+            apply_only: false,
+            move_policy: MovePolicy::default(),
         }
     }
 }
@@ -1161,6 +1164,9 @@ pub const ERR_TYPE_MISMATCH: u8 = 8;
 /// Error code: miscallaneous error comming from serde, not covered by other error codes.
 pub const ERR_CUSTOM: u8 = 9;
 
+/// Error code: decoded data exceeds configured resource limits.
+pub const ERR_LIMIT_EXCEEDED: u8 = 10;
+
 fn err_code(e: Error) -> u8 {
     match e {
         Error::InvalidVarInt => ERR_CODE_VAR_INT,
@@ -1170,6 +1176,7 @@ fn err_code(e: Error) -> u8 {
         Error::NotEnoughMemory(_) => ERR_NOT_ENOUGH_MEMORY,
         Error::TypeMismatch(_) => ERR_TYPE_MISMATCH,
         Error::Custom(_) => ERR_CUSTOM,
+        Error::LimitExceeded(_) => ERR_LIMIT_EXCEEDED,
     }
 }
 
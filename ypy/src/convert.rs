@@ -0,0 +1,148 @@
+use crate::map::YMap;
+use crate::text::YText;
+use pyo3::exceptions::PyTypeError;
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyBytes, PyDict, PyFloat, PyList, PyLong, PyString, PyTuple};
+use std::collections::HashMap;
+use std::sync::Arc;
+use yrs::types::{Attrs, Delta, EntryChange, ToJson};
+use yrs::{Any, Out, ReadTxn};
+
+/// Converts a Python object into a primitive [Any] value. Supported types are: `None`, `bool`,
+/// `int`, `float`, `str`, `bytes`, and lists, tuples and dictionaries (with string keys) of them.
+pub fn py_to_any(value: &Bound<'_, PyAny>) -> PyResult<Any> {
+    if value.is_none() {
+        Ok(Any::Null)
+    } else if let Ok(v) = value.downcast::<PyBool>() {
+        Ok(Any::Bool(v.is_true()))
+    } else if let Ok(v) = value.downcast::<PyLong>() {
+        Ok(Any::BigInt(v.extract()?))
+    } else if let Ok(v) = value.downcast::<PyFloat>() {
+        Ok(Any::Number(v.value()))
+    } else if let Ok(v) = value.downcast::<PyString>() {
+        Ok(Any::from(v.to_str()?))
+    } else if let Ok(v) = value.downcast::<PyBytes>() {
+        Ok(Any::from(v.as_bytes()))
+    } else if let Ok(v) = value.downcast::<PyList>() {
+        let items: PyResult<Vec<Any>> = v.iter().map(|item| py_to_any(&item)).collect();
+        Ok(Any::from(items?))
+    } else if let Ok(v) = value.downcast::<PyTuple>() {
+        let items: PyResult<Vec<Any>> = v.iter().map(|item| py_to_any(&item)).collect();
+        Ok(Any::from(items?))
+    } else if let Ok(v) = value.downcast::<PyDict>() {
+        let mut map = HashMap::with_capacity(v.len());
+        for (key, value) in v.iter() {
+            map.insert(key.extract::<String>()?, py_to_any(&value)?);
+        }
+        Ok(Any::from(map))
+    } else {
+        Err(PyTypeError::new_err(format!(
+            "cannot convert {} into a ypy value",
+            value.get_type().name()?
+        )))
+    }
+}
+
+/// Converts a dictionary of formatting attributes into [Attrs].
+pub fn py_to_attrs(value: &Bound<'_, PyDict>) -> PyResult<Attrs> {
+    let mut attrs = Attrs::with_capacity(value.len());
+    for (key, value) in value.iter() {
+        let key: Arc<str> = key.extract::<String>()?.into();
+        attrs.insert(key, py_to_any(&value)?);
+    }
+    Ok(attrs)
+}
+
+/// Converts a primitive [Any] value into a Python object.
+pub fn any_to_py(py: Python<'_>, value: &Any) -> PyObject {
+    match value {
+        Any::Null | Any::Undefined => py.None(),
+        Any::Bool(v) => v.into_py(py),
+        Any::Number(v) => v.into_py(py),
+        Any::BigInt(v) => v.into_py(py),
+        Any::String(v) => v.as_ref().into_py(py),
+        Any::Buffer(v) => PyBytes::new_bound(py, v).into(),
+        Any::Array(v) => {
+            let items: Vec<PyObject> = v.iter().map(|item| any_to_py(py, item)).collect();
+            PyList::new_bound(py, items).into()
+        }
+        Any::Map(v) => {
+            let dict = PyDict::new_bound(py);
+            for (key, value) in v.iter() {
+                dict.set_item(key.as_str(), any_to_py(py, value)).unwrap();
+            }
+            dict.into()
+        }
+    }
+}
+
+/// Converts an output value of a shared collection into a Python object. Text and map shared
+/// types are returned as `YText` and `YMap`, while other shared types are converted into their
+/// JSON-like representation.
+pub fn out_to_py<T: ReadTxn>(py: Python<'_>, txn: &T, value: Out) -> PyObject {
+    match value {
+        Out::Any(v) => any_to_py(py, &v),
+        Out::YText(v) => YText(v).into_py(py),
+        Out::YMap(v) => YMap(v).into_py(py),
+        other => any_to_py(py, &other.to_json(txn)),
+    }
+}
+
+fn attrs_to_py(py: Python<'_>, attrs: &Attrs) -> PyObject {
+    let dict = PyDict::new_bound(py);
+    for (key, value) in attrs.iter() {
+        dict.set_item(key.as_ref(), any_to_py(py, value)).unwrap();
+    }
+    dict.into()
+}
+
+/// Converts a text [Delta] into a Quill-like dictionary, ie. `{"insert": "abc"}`,
+/// `{"delete": 3}` or `{"retain": 2, "attributes": {"bold": True}}`.
+pub fn delta_to_py<T: ReadTxn>(py: Python<'_>, txn: &T, delta: &Delta) -> PyObject {
+    let dict = PyDict::new_bound(py);
+    match delta {
+        Delta::Inserted(value, attrs) => {
+            dict.set_item("insert", out_to_py(py, txn, value.clone()))
+                .unwrap();
+            if let Some(attrs) = attrs {
+                dict.set_item("attributes", attrs_to_py(py, attrs)).unwrap();
+            }
+        }
+        Delta::Deleted(len) => {
+            dict.set_item("delete", len).unwrap();
+        }
+        Delta::Retain(len, attrs) => {
+            dict.set_item("retain", len).unwrap();
+            if let Some(attrs) = attrs {
+                dict.set_item("attributes", attrs_to_py(py, attrs)).unwrap();
+            }
+        }
+    }
+    dict.into()
+}
+
+/// Converts a map [EntryChange] into a dictionary, ie. `{"action": "update", "oldValue": 1,
+/// "newValue": 2}`.
+pub fn entry_change_to_py<T: ReadTxn>(py: Python<'_>, txn: &T, change: &EntryChange) -> PyObject {
+    let dict = PyDict::new_bound(py);
+    match change {
+        EntryChange::Inserted(new) => {
+            dict.set_item("action", "add").unwrap();
+            dict.set_item("newValue", out_to_py(py, txn, new.clone()))
+                .unwrap();
+        }
+        EntryChange::Updated(old, new) => {
+            dict.set_item("action", "update").unwrap();
+            dict.set_item("oldValue", out_to_py(py, txn, old.clone()))
+                .unwrap();
+            dict.set_item("newValue", out_to_py(py, txn, new.clone()))
+                .unwrap();
+        }
+        EntryChange::Removed(old) => {
+            dict.set_item("action", "delete").unwrap();
+            dict.set_item("oldValue", out_to_py(py, txn, old.clone()))
+                .unwrap();
+        }
+    }
+    dict.into()
+}
@@ -0,0 +1,108 @@
+use crate::map::YMap;
+use crate::subscription::YSubscription;
+use crate::text::YText;
+use crate::transaction::YTransaction;
+use pyo3::exceptions::PyRuntimeError;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use yrs::{Doc, OffsetKind, Options, Transact, Transaction, TransactionMut, WriteTxn};
+
+/// A ypy document type. Documents are most important units of collaborative resources management.
+/// All shared collections live within a scope of their corresponding documents. All operations on
+/// shared collections happen via [YTransaction], which lifetime is also bound to a document.
+#[pyclass(unsendable)]
+#[derive(Clone)]
+pub struct YDoc(pub(crate) Doc);
+
+impl YDoc {
+    pub(crate) fn try_transact(&self) -> PyResult<Transaction<'_>> {
+        self.0
+            .try_transact()
+            .map_err(|e| PyRuntimeError::new_err(e.to_string()))
+    }
+
+    pub(crate) fn try_transact_mut(&self) -> PyResult<TransactionMut<'_>> {
+        self.0
+            .try_transact_mut()
+            .map_err(|e| PyRuntimeError::new_err(e.to_string()))
+    }
+}
+
+#[pymethods]
+impl YDoc {
+    /// Creates a new ypy document. If `client_id` parameter was passed it will be used as this
+    /// document's unique peer identifier (it's up to caller to ensure that requirement). Otherwise
+    /// it will be assigned a randomly generated number.
+    #[new]
+    #[pyo3(signature = (client_id=None))]
+    pub fn new(client_id: Option<u64>) -> Self {
+        let mut options = match client_id {
+            Some(client_id) => Options::with_client_id(client_id),
+            None => Options::default(),
+        };
+        options.offset_kind = OffsetKind::Utf16;
+        YDoc(Doc::with_options(options))
+    }
+
+    /// Gets unique peer identifier of this `YDoc` instance.
+    #[getter]
+    pub fn client_id(&self) -> u64 {
+        self.0.client_id()
+    }
+
+    /// Gets globally unique identifier of this `YDoc` instance.
+    #[getter]
+    pub fn guid(&self) -> String {
+        self.0.guid().to_string()
+    }
+
+    /// Starts a new read-write transaction on this document. It can be used as a context manager,
+    /// which commits a transaction once the `with` block is left:
+    ///
+    /// ```python
+    /// with doc.transact() as txn:
+    ///     text.insert(txn, 0, "hello")
+    /// ```
+    ///
+    /// Only one transaction can be active at the time.
+    #[pyo3(signature = (origin=None))]
+    pub fn transact(&self, origin: Option<&str>) -> PyResult<YTransaction> {
+        YTransaction::new(&self.0, origin)
+    }
+
+    /// Returns a `YText` shared data type, that's accessible for subsequent accesses using given
+    /// `name`. If there was no instance with this name before, it will be created.
+    ///
+    /// This method cannot be called while a transaction is active.
+    pub fn get_text(&self, name: &str) -> PyResult<YText> {
+        let mut txn = self.try_transact_mut()?;
+        Ok(YText(txn.get_or_insert_text(name)))
+    }
+
+    /// Returns a `YMap` shared data type, that's accessible for subsequent accesses using given
+    /// `name`. If there was no instance with this name before, it will be created.
+    ///
+    /// This method cannot be called while a transaction is active.
+    pub fn get_map(&self, name: &str) -> PyResult<YMap> {
+        let mut txn = self.try_transact_mut()?;
+        Ok(YMap(txn.get_or_insert_map(name)))
+    }
+
+    /// Subscribes a `callback` to be called with a lib0 v1 encoded update every time a transaction
+    /// that changed this document is committed. Updates can be applied on remote peers using
+    /// `apply_update` function.
+    pub fn observe_update(&self, callback: PyObject) -> PyResult<YSubscription> {
+        let sub = self
+            .0
+            .observe_update_v1(move |_, e| {
+                Python::with_gil(|py| {
+                    let update = PyBytes::new_bound(py, &e.update);
+                    if let Err(err) = callback.call1(py, (update,)) {
+                        err.write_unraisable_bound(py, None)
+                    }
+                })
+            })
+            .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+        Ok(YSubscription::new(sub))
+    }
+}
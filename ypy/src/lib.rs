@@ -0,0 +1,190 @@
+//! Python bindings for [Yrs](yrs), built with PyO3. A basic workflow sample:
+//!
+//! ```python
+//! from ypy import YDoc, encode_state_vector, encode_state_as_update, apply_update
+//!
+//! doc = YDoc()
+//! text = doc.get_text("name")
+//! with doc.transact() as txn:
+//!     text.insert(txn, 0, "hello world")
+//!
+//! remote = YDoc()
+//! update = encode_state_as_update(doc, encode_state_vector(remote))
+//! apply_update(remote, update)
+//! ```
+//!
+//! All text indexes and lengths are expressed in UTF-16 code units, which makes them compatible
+//! with Yjs peers.
+
+// PyO3 macros wrap every `PyResult` returned by `#[pymethods]` in a conversion to `PyResult`.
+#![allow(clippy::useless_conversion)]
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use yrs::updates::decoder::Decode;
+use yrs::updates::encoder::Encode;
+use yrs::{ReadTxn, StateVector, Update};
+
+mod convert;
+mod doc;
+mod map;
+mod subscription;
+mod text;
+mod transaction;
+
+pub use crate::doc::YDoc;
+pub use crate::map::{YMap, YMapEvent};
+pub use crate::subscription::YSubscription;
+pub use crate::text::{YText, YTextEvent};
+pub use crate::transaction::YTransaction;
+
+/// Encodes a state vector of a given document. It can be passed to a remote peer, which can use
+/// it with `encode_state_as_update` to produce a delta update containing only the missing changes.
+#[pyfunction]
+pub fn encode_state_vector(py: Python<'_>, doc: &YDoc) -> PyResult<PyObject> {
+    let txn = doc.try_transact()?;
+    let bytes = txn.state_vector().encode_v1();
+    Ok(PyBytes::new_bound(py, &bytes).into())
+}
+
+/// Encodes the state of a given document as a lib0 v1 update. If a `vector` of a remote peer has
+/// been provided, only the changes this peer hasn't seen yet are encoded.
+#[pyfunction]
+#[pyo3(signature = (doc, vector=None))]
+pub fn encode_state_as_update(
+    py: Python<'_>,
+    doc: &YDoc,
+    vector: Option<&[u8]>,
+) -> PyResult<PyObject> {
+    let sv = match vector {
+        Some(bytes) => {
+            StateVector::decode_v1(bytes).map_err(|e| PyValueError::new_err(e.to_string()))?
+        }
+        None => StateVector::default(),
+    };
+    let txn = doc.try_transact()?;
+    let bytes = txn.encode_state_as_update_v1(&sv);
+    Ok(PyBytes::new_bound(py, &bytes).into())
+}
+
+/// Applies a lib0 v1 encoded `update` produced by a remote peer onto a given document.
+#[pyfunction]
+pub fn apply_update(doc: &YDoc, update: &[u8]) -> PyResult<()> {
+    let update = Update::decode_v1(update).map_err(|e| PyValueError::new_err(e.to_string()))?;
+    let mut txn = doc.try_transact_mut()?;
+    txn.apply_update(update);
+    Ok(())
+}
+
+#[pymodule]
+fn ypy(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<YDoc>()?;
+    m.add_class::<YTransaction>()?;
+    m.add_class::<YText>()?;
+    m.add_class::<YTextEvent>()?;
+    m.add_class::<YMap>()?;
+    m.add_class::<YMapEvent>()?;
+    m.add_class::<YSubscription>()?;
+    m.add_function(wrap_pyfunction!(encode_state_vector, m)?)?;
+    m.add_function(wrap_pyfunction!(encode_state_as_update, m)?)?;
+    m.add_function(wrap_pyfunction!(apply_update, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use pyo3::prelude::*;
+    use pyo3::types::PyDict;
+
+    fn run(script: &str) {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let module = PyModule::new_bound(py, "ypy").unwrap();
+            super::ypy(&module).unwrap();
+            let globals = PyDict::new_bound(py);
+            globals.set_item("ypy", module).unwrap();
+            if let Err(e) = py.run_bound(script, Some(&globals), None) {
+                e.print(py);
+                panic!("python script failed: {}", e);
+            }
+        })
+    }
+
+    #[test]
+    fn text_sync_and_observe() {
+        run(r#"
+d1 = ypy.YDoc(1)
+t1 = d1.get_text("text")
+updates = []
+sub = d1.observe_update(lambda u: updates.append(u))
+with d1.transact() as txn:
+    t1.insert(txn, 0, "hello world")
+    t1.format(txn, 0, 5, {"bold": True})
+
+d2 = ypy.YDoc(2)
+t2 = d2.get_text("text")
+deltas = []
+sub2 = t2.observe(lambda e: deltas.append(e.delta))
+ypy.apply_update(d2, ypy.encode_state_as_update(d1, ypy.encode_state_vector(d2)))
+with d2.transact() as txn:
+    assert t2.to_string(txn) == "hello world"
+    assert t2.len(txn) == 11
+assert deltas == [[{"insert": "hello", "attributes": {"bold": True}}, {"insert": " world"}]], deltas
+assert len(updates) == 1
+
+with d2.transact() as txn:
+    try:
+        t2.insert(txn, 100, "!")
+        assert False, "index out of bounds expected"
+    except IndexError:
+        pass
+"#);
+    }
+
+    #[test]
+    fn map_values_and_observe() {
+        run(r#"
+doc = ypy.YDoc(1)
+m = doc.get_map("map")
+events = []
+sub = m.observe(lambda e: events.append(e.keys))
+with doc.transact() as txn:
+    m.insert(txn, "a", 1)
+    m.insert(txn, "b", {"nested": [1, 2.5, "x", None, b"\x01"]})
+with doc.transact() as txn:
+    assert m.get(txn, "a") == 1
+    assert m.len(txn) == 2
+    assert m.to_json(txn) == {"a": 1, "b": {"nested": [1, 2.5, "x", None, b"\x01"]}}
+    assert m.remove(txn, "a") == 1
+    assert m.get(txn, "a") is None
+assert events[0]["a"] == {"action": "add", "newValue": 1}
+assert events[1] == {"a": {"action": "delete", "oldValue": 1}}
+
+sub.unsubscribe()
+txn = doc.transact()
+m.insert(txn, "c", True)
+txn.commit()
+assert len(events) == 2
+try:
+    m.get(txn, "c")
+    assert False, "committed transaction should not be usable"
+except RuntimeError:
+    pass
+"#);
+    }
+
+    #[test]
+    fn transaction_outlives_document() {
+        run(r#"
+doc = ypy.YDoc(1)
+text = doc.get_text("text")
+txn = doc.transact()
+del doc
+text.insert(txn, 0, "hello")
+assert text.to_string(txn) == "hello"
+assert txn.doc.client_id == 1
+txn.commit()
+"#);
+    }
+}
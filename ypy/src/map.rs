@@ -0,0 +1,91 @@
+use crate::convert::{any_to_py, entry_change_to_py, out_to_py, py_to_any};
+use crate::subscription::YSubscription;
+use crate::transaction::YTransaction;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use yrs::types::ToJson;
+use yrs::{Map, MapRef, Observable};
+
+/// A shared data type used for storing key-value pairs. Keys are strings, while values can be
+/// any JSON-like Python objects.
+#[pyclass(unsendable)]
+#[derive(Clone)]
+pub struct YMap(pub(crate) MapRef);
+
+#[pymethods]
+impl YMap {
+    /// Returns a number of entries stored within this map.
+    pub fn len(&self, txn: &YTransaction) -> PyResult<u32> {
+        Ok(self.0.len(txn.as_ref()?))
+    }
+
+    /// Returns a value stored under a given `key` or `None` if no such entry existed.
+    pub fn get(&self, py: Python<'_>, txn: &YTransaction, key: &str) -> PyResult<PyObject> {
+        let txn = txn.as_ref()?;
+        Ok(match self.0.get(txn, key) {
+            Some(value) => out_to_py(py, txn, value),
+            None => py.None(),
+        })
+    }
+
+    /// Inserts a `value` under a given `key`, replacing the previous entry if any.
+    pub fn insert(
+        &self,
+        txn: &mut YTransaction,
+        key: &str,
+        value: &Bound<'_, PyAny>,
+    ) -> PyResult<()> {
+        let value = py_to_any(value)?;
+        txn.with_mut(|txn| {
+            self.0.insert(txn, key, value);
+        })
+    }
+
+    /// Removes an entry stored under a given `key`, returning its value or `None` if no such entry
+    /// existed.
+    pub fn remove(&self, py: Python<'_>, txn: &mut YTransaction, key: &str) -> PyResult<PyObject> {
+        txn.with_mut(|txn| match self.0.remove(txn, key) {
+            Some(value) => out_to_py(py, txn, value),
+            None => py.None(),
+        })
+    }
+
+    /// Converts the contents of this map into a Python dictionary.
+    pub fn to_json(&self, py: Python<'_>, txn: &YTransaction) -> PyResult<PyObject> {
+        Ok(any_to_py(py, &self.0.to_json(txn.as_ref()?)))
+    }
+
+    /// Subscribes a `callback` to be called with a `YMapEvent` every time a transaction which
+    /// changed this map is committed.
+    pub fn observe(&self, callback: PyObject) -> YSubscription {
+        let sub = self.0.observe(move |txn, e| {
+            Python::with_gil(|py| {
+                let keys = PyDict::new_bound(py);
+                for (key, change) in e.keys(txn).iter() {
+                    keys.set_item(key.as_ref(), entry_change_to_py(py, txn, change))
+                        .unwrap();
+                }
+                let event = YMapEvent {
+                    target: YMap(e.target().clone()),
+                    keys: keys.into(),
+                };
+                if let Err(err) = callback.call1(py, (event,)) {
+                    err.write_unraisable_bound(py, None)
+                }
+            })
+        });
+        YSubscription::new(sub)
+    }
+}
+
+/// Event passed to callbacks subscribed with `YMap.observe`.
+#[pyclass(unsendable)]
+pub struct YMapEvent {
+    /// Map instance that has been changed.
+    #[pyo3(get)]
+    target: YMap,
+    /// Dictionary of changed keys, ie. `{"key": {"action": "update", "oldValue": 1,
+    /// "newValue": 2}}`. Action can be one of `add`, `update` or `delete`.
+    #[pyo3(get)]
+    keys: PyObject,
+}
@@ -0,0 +1,21 @@
+use pyo3::prelude::*;
+use yrs::Subscription;
+
+/// Handle to a callback subscribed to a document or shared type. Callback stays subscribed as long
+/// as this handle is alive or until `unsubscribe` is called.
+#[pyclass(unsendable)]
+pub struct YSubscription(Option<Subscription>);
+
+impl YSubscription {
+    pub(crate) fn new(sub: Subscription) -> Self {
+        YSubscription(Some(sub))
+    }
+}
+
+#[pymethods]
+impl YSubscription {
+    /// Unsubscribes a callback. Calling this method more than once has no effect.
+    pub fn unsubscribe(&mut self) {
+        self.0.take();
+    }
+}
@@ -0,0 +1,118 @@
+use crate::convert::{delta_to_py, py_to_any, py_to_attrs};
+use crate::subscription::YSubscription;
+use crate::transaction::YTransaction;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+use yrs::{GetString, Observable, Text, TextRef};
+
+/// A shared data type used for collaborative text editing. It enables multiple users to add and
+/// remove chunks of text in efficient manner. All indexes are expressed in UTF-16 code units.
+#[pyclass(unsendable)]
+#[derive(Clone)]
+pub struct YText(pub(crate) TextRef);
+
+#[pymethods]
+impl YText {
+    /// Returns length of the text, measured in UTF-16 code units.
+    pub fn len(&self, txn: &YTransaction) -> PyResult<u32> {
+        Ok(self.0.len(txn.as_ref()?))
+    }
+
+    /// Returns the underlying string stored in this text instance.
+    pub fn to_string(&self, txn: &YTransaction) -> PyResult<String> {
+        Ok(self.0.get_string(txn.as_ref()?))
+    }
+
+    /// Inserts a given `chunk` of text at a given `index`, optionally formatted with a dictionary
+    /// of `attributes`.
+    #[pyo3(signature = (txn, index, chunk, attributes=None))]
+    pub fn insert(
+        &self,
+        txn: &mut YTransaction,
+        index: u32,
+        chunk: &str,
+        attributes: Option<&Bound<'_, PyDict>>,
+    ) -> PyResult<()> {
+        let attrs = attributes.map(py_to_attrs).transpose()?;
+        txn.with_mut(|txn| match attrs {
+            Some(attrs) => self.0.try_insert_with_attributes(txn, index, chunk, attrs),
+            None => self.0.try_insert(txn, index, chunk),
+        })?
+        .map_err(index_error)
+    }
+
+    /// Appends a given `chunk` of text at the end of this text instance.
+    pub fn push(&self, txn: &mut YTransaction, chunk: &str) -> PyResult<()> {
+        txn.with_mut(|txn| self.0.push(txn, chunk))
+    }
+
+    /// Removes `length` code units of text, starting at a given `index`.
+    pub fn remove_range(&self, txn: &mut YTransaction, index: u32, length: u32) -> PyResult<()> {
+        txn.with_mut(|txn| self.0.try_remove_range(txn, index, length))?
+            .map_err(index_error)
+    }
+
+    /// Applies formatting `attributes` over `length` code units of text, starting at a given
+    /// `index`.
+    pub fn format(
+        &self,
+        txn: &mut YTransaction,
+        index: u32,
+        length: u32,
+        attributes: &Bound<'_, PyDict>,
+    ) -> PyResult<()> {
+        let attrs = py_to_attrs(attributes)?;
+        txn.with_mut(|txn| self.0.try_format(txn, index, length, attrs))?
+            .map_err(index_error)
+    }
+
+    /// Inserts a non-string `value` (eg. a number or a dictionary) at a given `index`.
+    pub fn insert_embed(
+        &self,
+        txn: &mut YTransaction,
+        index: u32,
+        value: &Bound<'_, PyAny>,
+    ) -> PyResult<()> {
+        let value = py_to_any(value)?;
+        txn.with_mut(|txn| self.0.try_insert_embed(txn, index, value).map(|_| ()))?
+            .map_err(index_error)
+    }
+
+    /// Subscribes a `callback` to be called with a `YTextEvent` every time a transaction which
+    /// changed this text instance is committed.
+    pub fn observe(&self, callback: PyObject) -> YSubscription {
+        let sub = self.0.observe(move |txn, e| {
+            Python::with_gil(|py| {
+                let delta: Vec<PyObject> = e
+                    .delta(txn)
+                    .iter()
+                    .map(|d| delta_to_py(py, txn, d))
+                    .collect();
+                let event = YTextEvent {
+                    target: YText(e.target().clone()),
+                    delta: PyList::new_bound(py, delta).into(),
+                };
+                if let Err(err) = callback.call1(py, (event,)) {
+                    err.write_unraisable_bound(py, None)
+                }
+            })
+        });
+        YSubscription::new(sub)
+    }
+}
+
+pub(crate) fn index_error(e: yrs::IndexOutOfBounds) -> PyErr {
+    pyo3::exceptions::PyIndexError::new_err(e.to_string())
+}
+
+/// Event passed to callbacks subscribed with `YText.observe`.
+#[pyclass(unsendable)]
+pub struct YTextEvent {
+    /// Text instance that has been changed.
+    #[pyo3(get)]
+    target: YText,
+    /// List of changes made to a text instance, in a Quill delta format, ie.
+    /// `[{"retain": 2}, {"insert": "abc", "attributes": {"bold": True}}, {"delete": 1}]`.
+    #[pyo3(get)]
+    delta: PyObject,
+}
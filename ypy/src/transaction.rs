@@ -0,0 +1,163 @@
+use crate::doc::YDoc;
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use yrs::doc::TransactionAcqError;
+use yrs::updates::decoder::Decode;
+use yrs::updates::encoder::Encode;
+use yrs::{Doc, ReadTxn, StateVector, Transact, TransactionMut, Update};
+
+/// A read-write transaction bundled together with a document owning the store it borrows from.
+///
+/// [TransactionMut] borrows a store living behind a reference counted pointer of a [Doc], so
+/// keeping a clone of that document next to the transaction guarantees that the store is neither
+/// freed nor moved while the transaction is alive, even if all `YDoc` objects on Python side are
+/// gone by then. The `'static` lifetime required to keep both in one struct never leaves this
+/// type: its accessors only hand out transactions limited to a lifetime of their own borrow.
+struct OwnedTransaction {
+    // declared first, so that it's dropped before the document
+    txn: TransactionMut<'static>,
+    _doc: Doc,
+}
+
+impl OwnedTransaction {
+    fn new(doc: Doc, origin: Option<&str>) -> Result<Self, TransactionAcqError> {
+        let txn = match origin {
+            Some(origin) => doc.try_transact_mut_with(origin)?,
+            None => doc.try_transact_mut()?,
+        };
+        // SAFETY: `txn` borrows a store allocated on the heap and kept alive by `_doc`, not `doc`
+        // variable itself, so moving `doc` into the returned struct doesn't invalidate it.
+        let txn =
+            unsafe { std::mem::transmute::<TransactionMut<'_>, TransactionMut<'static>>(txn) };
+        Ok(OwnedTransaction { txn, _doc: doc })
+    }
+
+    fn get(&self) -> &TransactionMut<'_> {
+        &self.txn
+    }
+
+    fn with_mut<F, R>(&mut self, f: F) -> R
+    where
+        F: FnOnce(&mut TransactionMut<'_>) -> R,
+    {
+        // `f` must work with a transaction of any lifetime, so it is unable to leak anything
+        // bound to the `'static` lifetime out of it
+        f(&mut self.txn)
+    }
+}
+
+/// A read-write transaction, created with `YDoc.transact()`. All changes made within
+/// a transaction are committed and propagated to observers once `commit` is called or the
+/// `with` block using this transaction is left.
+#[pyclass(unsendable)]
+pub struct YTransaction {
+    txn: Option<OwnedTransaction>,
+    doc: Doc,
+}
+
+impl YTransaction {
+    pub(crate) fn new(doc: &Doc, origin: Option<&str>) -> PyResult<Self> {
+        let txn = OwnedTransaction::new(doc.clone(), origin)
+            .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+        Ok(YTransaction {
+            txn: Some(txn),
+            doc: doc.clone(),
+        })
+    }
+
+    pub(crate) fn as_ref(&self) -> PyResult<&TransactionMut<'_>> {
+        match self.txn.as_ref() {
+            Some(txn) => Ok(txn.get()),
+            None => Err(committed_error()),
+        }
+    }
+
+    /// Calls `f` with a read-write transaction, failing if it has already been committed.
+    pub(crate) fn with_mut<F, R>(&mut self, f: F) -> PyResult<R>
+    where
+        F: FnOnce(&mut TransactionMut<'_>) -> R,
+    {
+        match self.txn.as_mut() {
+            Some(txn) => Ok(txn.with_mut(f)),
+            None => Err(committed_error()),
+        }
+    }
+}
+
+fn committed_error() -> PyErr {
+    PyRuntimeError::new_err("transaction has already been committed")
+}
+
+#[pymethods]
+impl YTransaction {
+    /// Returns a document this transaction has been created for.
+    #[getter]
+    pub fn doc(&self) -> YDoc {
+        YDoc(self.doc.clone())
+    }
+
+    /// Returns a lib0 v1 encoded state vector describing the current state of the document.
+    pub fn state_vector(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let bytes = self.as_ref()?.state_vector().encode_v1();
+        Ok(PyBytes::new_bound(py, &bytes).into())
+    }
+
+    /// Encodes all changes made by this transaction so far as a lib0 v1 update.
+    pub fn encode_update(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let bytes = self.as_ref()?.encode_update_v1();
+        Ok(PyBytes::new_bound(py, &bytes).into())
+    }
+
+    /// Encodes the state of the document as a lib0 v1 update. If a `vector` of a remote peer has
+    /// been provided, only the changes this peer hasn't seen yet are encoded.
+    #[pyo3(signature = (vector=None))]
+    pub fn encode_state_as_update(
+        &self,
+        py: Python<'_>,
+        vector: Option<&[u8]>,
+    ) -> PyResult<PyObject> {
+        let sv = match vector {
+            Some(bytes) => {
+                StateVector::decode_v1(bytes).map_err(|e| PyValueError::new_err(e.to_string()))?
+            }
+            None => StateVector::default(),
+        };
+        let bytes = self.as_ref()?.encode_state_as_update_v1(&sv);
+        Ok(PyBytes::new_bound(py, &bytes).into())
+    }
+
+    /// Applies a lib0 v1 encoded `update` produced by a remote peer.
+    pub fn apply_update(&mut self, update: &[u8]) -> PyResult<()> {
+        let update = Update::decode_v1(update).map_err(|e| PyValueError::new_err(e.to_string()))?;
+        self.with_mut(|txn| txn.apply_update(update))
+    }
+
+    /// Commits all changes made by this transaction and releases it. Once committed, transaction
+    /// can no longer be used.
+    pub fn commit(&mut self) -> PyResult<()> {
+        match self.txn.take() {
+            Some(txn) => {
+                drop(txn);
+                Ok(())
+            }
+            None => Err(committed_error()),
+        }
+    }
+
+    fn __enter__(slf: Py<Self>) -> Py<Self> {
+        slf
+    }
+
+    #[pyo3(signature = (_exc_type=None, _exc_value=None, _traceback=None))]
+    fn __exit__(
+        &mut self,
+        _exc_type: Option<PyObject>,
+        _exc_value: Option<PyObject>,
+        _traceback: Option<PyObject>,
+    ) -> bool {
+        // transaction could have been committed explicitly inside of `with` block
+        self.txn.take();
+        false
+    }
+}
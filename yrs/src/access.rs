@@ -0,0 +1,532 @@
+//! Access control enforced on remote updates, allowing a single document to contain sections
+//! writable only by certain peers. See [Doc::set_access_policy].
+//!
+//! Every change of an applied update is attributed to a scope: a root type it belongs to and,
+//! for changes made within map root types, a top-level key of that map. Access policy is consulted
+//! for every scope touched by an update together with an origin of a transaction applying that
+//! update (ie. an identifier of a connection it came from), so that providers can map
+//! authenticated peers to their roles.
+//!
+//! Changes which scope cannot be determined yet, because they depend on blocks which have not
+//! been delivered, are stashed together with the rest of pending update and checked once their
+//! dependencies arrive, using an origin of a transaction which delivered them. Changes which
+//! scope cannot be determined at all (ie. they belong to garbage collected content) are denied.
+//!
+//! [Doc::set_access_policy]: crate::Doc::set_access_policy
+
+use crate::block::{BlockCell, BlockRange, ClientID, Item, ItemPtr, ID};
+use crate::id_set::{DeleteSet, IdSet};
+use crate::store::Store;
+use crate::types::TypePtr;
+use crate::update::{BlockCarrier, Update};
+use crate::Origin;
+use std::sync::Arc;
+
+/// Kind of change, which access is requested for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AccessKind {
+    /// Insertion of a new block by a client with a given ID.
+    Insert(ClientID),
+    /// Deletion of existing content. Delete sets don't carry information about clients who
+    /// deleted a given block, so only an origin of a transaction is known.
+    Delete,
+}
+
+/// A change of a remote update checked by an access policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccessRequest<'a> {
+    /// Origin of a transaction used to apply an update.
+    pub origin: Option<&'a Origin>,
+    /// Kind of a change.
+    pub kind: AccessKind,
+    /// Name of a root type affected by a change.
+    pub root: &'a str,
+    /// Top-level key of a map root type affected by a change. It's `None` for changes within
+    /// other root types.
+    pub key: Option<&'a str>,
+}
+
+/// Callback used to decide if a change described by [AccessRequest] is allowed. Returning `false`
+/// denies a change.
+#[cfg(feature = "sync")]
+pub type AccessFn = Box<dyn Fn(&AccessRequest) -> bool + Send + Sync + 'static>;
+
+/// Callback used to decide if a change described by [AccessRequest] is allowed. Returning `false`
+/// denies a change.
+#[cfg(not(feature = "sync"))]
+pub type AccessFn = Box<dyn Fn(&AccessRequest) -> bool + 'static>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Scope {
+    root: Arc<str>,
+    key: Option<Arc<str>>,
+}
+
+/// Result of determining a scope of a change.
+enum Resolution {
+    Scope(Scope),
+    /// Scope cannot be determined, ie. because a change refers to garbage collected content or
+    /// an update is malformed.
+    Invalid,
+    /// Scope depends on blocks, which have not been delivered yet.
+    Missing,
+}
+
+impl From<Option<Scope>> for Resolution {
+    fn from(scope: Option<Scope>) -> Self {
+        match scope {
+            Some(scope) => Resolution::Scope(scope),
+            None => Resolution::Invalid,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Decision {
+    Allowed,
+    Denied,
+    Missing,
+}
+
+/// Changes of remote updates, which scope couldn't be determined yet, together with an origin of
+/// a transaction which delivered them.
+#[derive(Debug, Default)]
+pub(crate) struct PendingAccess {
+    origin: Option<Origin>,
+    blocks: IdSet,
+    deletes: IdSet,
+}
+
+impl PendingAccess {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.blocks.is_empty() && self.deletes.is_empty()
+    }
+}
+
+enum Located<'a> {
+    Stored(ItemPtr),
+    Update(&'a Item),
+    /// Garbage collected range ending before a given clock.
+    Gc(u32),
+}
+
+/// Upper bound of parent lookups, which protects from cycles in malformed updates.
+const MAX_DEPTH: usize = 1024;
+
+struct Enforcer<'a> {
+    store: &'a Store,
+    update: &'a Update,
+    origin: Option<&'a Origin>,
+    policy: &'a AccessFn,
+}
+
+impl<'a> Enforcer<'a> {
+    fn decide(&self, kind: AccessKind, resolution: Resolution) -> Decision {
+        match resolution {
+            Resolution::Scope(scope) => {
+                let allowed = (self.policy)(&AccessRequest {
+                    origin: self.origin,
+                    kind,
+                    root: &scope.root,
+                    key: scope.key.as_deref(),
+                });
+                if allowed {
+                    Decision::Allowed
+                } else {
+                    Decision::Denied
+                }
+            }
+            Resolution::Invalid => Decision::Denied,
+            Resolution::Missing => Decision::Missing,
+        }
+    }
+
+    fn locate(&self, id: &ID) -> Option<Located<'a>> {
+        match self.store.blocks.get_block(id) {
+            Some(BlockCell::Block(item)) => return Some(Located::Stored(ItemPtr::from(item))),
+            Some(BlockCell::GC(gc)) => return Some(Located::Gc(gc.end + 1)),
+            None => {}
+        }
+        let blocks = self.update.blocks.clients.get(&id.client)?;
+        let i = blocks.partition_point(|b| b.id().clock + b.len() <= id.clock);
+        match blocks.get(i)? {
+            BlockCarrier::Item(item) if item.id.clock <= id.clock => Some(Located::Update(item)),
+            BlockCarrier::GC(range) if range.id.clock <= id.clock => {
+                Some(Located::Gc(range.id.clock + range.len))
+            }
+            _ => None,
+        }
+    }
+
+    fn resolve(&self, id: &ID, depth: usize) -> Resolution {
+        match self.locate(id) {
+            Some(Located::Stored(item)) => stored_scope(item).into(),
+            Some(Located::Update(item)) => self.update_scope(item, depth),
+            Some(Located::Gc(_)) => Resolution::Invalid,
+            None => Resolution::Missing,
+        }
+    }
+
+    fn update_scope(&self, item: &Item, depth: usize) -> Resolution {
+        if depth > MAX_DEPTH {
+            return Resolution::Invalid;
+        }
+        match &item.parent {
+            TypePtr::Named(root) => Resolution::Scope(Scope {
+                root: root.clone(),
+                key: item.parent_sub.clone(),
+            }),
+            TypePtr::ID(id) => self.resolve(id, depth + 1),
+            TypePtr::Branch(branch) => match branch.item {
+                Some(parent) => stored_scope(parent).into(),
+                None => branch
+                    .name
+                    .clone()
+                    .map(|root| Scope {
+                        root,
+                        key: item.parent_sub.clone(),
+                    })
+                    .into(),
+            },
+            TypePtr::Unknown => match item.origin.or(item.right_origin) {
+                // parent is inherited from neighbours
+                Some(neighbour) => self.resolve(&neighbour, depth + 1),
+                None => Resolution::Invalid,
+            },
+        }
+    }
+
+    /// Checks if deletion of a block containing a given `id` is allowed. Returns a decision and
+    /// a clock, at which that block ends.
+    fn delete_decision(&self, id: &ID) -> (Decision, u32) {
+        match self.locate(id) {
+            Some(Located::Stored(item)) => (
+                self.decide(AccessKind::Delete, stored_scope(item).into()),
+                item.id.clock + item.len,
+            ),
+            Some(Located::Update(item)) => (
+                self.decide(AccessKind::Delete, self.update_scope(item, 0)),
+                item.id.clock + item.len,
+            ),
+            // deleting garbage collected content has no effect
+            Some(Located::Gc(end)) => (Decision::Allowed, end),
+            None => (Decision::Missing, u32::MAX),
+        }
+    }
+}
+
+fn stored_scope(mut item: ItemPtr) -> Option<Scope> {
+    loop {
+        let branch = item.parent.as_branch()?;
+        match branch.item {
+            Some(parent) => item = parent,
+            None => {
+                return Some(Scope {
+                    root: branch.name.clone()?,
+                    key: item.parent_sub.clone(),
+                })
+            }
+        }
+    }
+}
+
+/// Replaces all blocks of an `update`, which insertion is denied by a `policy`, with garbage
+/// collected blocks of the same range, and removes denied ranges from update's delete set.
+/// Changes which scope depends on blocks that have not been delivered yet are left untouched
+/// and returned as [PendingAccess]. If `recheck` is provided, only changes it contains are
+/// checked, and ones that could be resolved this time are removed from it. Returns an ID of the
+/// first denied change, if any.
+pub(crate) fn strip_denied(
+    store: &Store,
+    origin: Option<&Origin>,
+    policy: &AccessFn,
+    update: &mut Update,
+    recheck: Option<&mut PendingAccess>,
+) -> (Option<ID>, PendingAccess) {
+    let mut pending = PendingAccess {
+        origin: origin.cloned(),
+        ..PendingAccess::default()
+    };
+    let mut resolved = PendingAccess::default();
+    let filter = recheck.as_deref();
+    let (denied_blocks, delete_set, denied_deletes) = {
+        let enforcer = Enforcer {
+            store,
+            update,
+            origin,
+            policy,
+        };
+        let mut denied_blocks = Vec::new();
+        for (&client, blocks) in update.blocks.clients.iter() {
+            for (i, block) in blocks.iter().enumerate() {
+                if let BlockCarrier::Item(item) = block {
+                    if filter.is_some_and(|r| !r.blocks.contains(&item.id)) {
+                        continue;
+                    }
+                    let scope = enforcer.update_scope(item, 0);
+                    let decision = enforcer.decide(AccessKind::Insert(item.id.client), scope);
+                    match decision {
+                        Decision::Allowed => {}
+                        Decision::Denied => denied_blocks.push((client, i)),
+                        Decision::Missing => pending.blocks.insert(item.id, item.len),
+                    }
+                    if decision != Decision::Missing {
+                        resolved.blocks.insert(item.id, item.len);
+                    }
+                }
+            }
+        }
+
+        let mut delete_set = DeleteSet::new();
+        let mut denied_deletes = Vec::new();
+        for (&client, range) in update.delete_set.iter() {
+            for r in range.iter() {
+                let mut clock = r.start;
+                while clock < r.end {
+                    let id = ID::new(client, clock);
+                    let (mut decision, end) = enforcer.delete_decision(&id);
+                    let len = end.min(r.end) - clock;
+                    if filter.is_some_and(|r| !r.deletes.contains(&id)) {
+                        decision = Decision::Allowed;
+                    } else if decision != Decision::Missing {
+                        resolved.deletes.insert(id, len);
+                    }
+                    match decision {
+                        Decision::Allowed => delete_set.insert(id, len),
+                        Decision::Denied => denied_deletes.push(id),
+                        Decision::Missing => {
+                            // it will be stashed as pending delete set
+                            delete_set.insert(id, len);
+                            pending.deletes.insert(id, len);
+                        }
+                    }
+                    clock += len;
+                }
+            }
+        }
+        (denied_blocks, delete_set, denied_deletes)
+    };
+
+    let mut first_denied = None;
+    for (client, i) in denied_blocks {
+        let block = &mut update.blocks.clients.get_mut(&client).unwrap()[i];
+        let range = BlockRange::new(*block.id(), block.len());
+        first_denied.get_or_insert(range.id);
+        *block = BlockCarrier::GC(range);
+    }
+    if !denied_deletes.is_empty() {
+        first_denied = first_denied.or(denied_deletes.first().copied());
+        update.delete_set = delete_set;
+    }
+    if let Some(recheck) = recheck {
+        recheck.blocks = recheck.blocks.difference(&resolved.blocks);
+        recheck.deletes = recheck.deletes.difference(&resolved.deletes);
+    }
+    (first_denied, pending)
+}
+
+/// Checks changes of a pending `update`, which scope couldn't be determined at the moment they
+/// were delivered. Changes which scope is still unknown remain in store's pending access list.
+pub(crate) fn recheck_pending(store: &mut Store, update: &mut Update) {
+    if store.access_pending.is_empty() {
+        return;
+    }
+    let mut records = std::mem::take(&mut store.access_pending);
+    let policy = match store.access.as_ref() {
+        Some(policy) => policy,
+        None => return, // policy has been removed, all changes are allowed
+    };
+    for record in records.iter_mut() {
+        let origin = record.origin.clone();
+        strip_denied(store, origin.as_ref(), policy, update, Some(record));
+    }
+    records.retain(|record| !record.is_empty());
+    store.access_pending = records;
+}
+
+#[cfg(test)]
+mod test {
+    use crate::access::AccessRequest;
+    use crate::update::UpdateError;
+    use crate::updates::decoder::Decode;
+    use crate::{Doc, GetString, Map, MapPrelim, MapRef, Origin, ReadTxn, Text, Transact, Update};
+    use assert_matches2::assert_matches;
+
+    #[test]
+    fn access_policy_strips_denied_changes() {
+        let author = Doc::with_client_id(1);
+        let public = author.get_or_insert_text("public");
+        let admin = author.get_or_insert_text("admin");
+        let meta = author.get_or_insert_map("meta");
+        let update = {
+            let mut txn = author.transact_mut();
+            public.insert(&mut txn, 0, "hello");
+            admin.insert(&mut txn, 0, "secret");
+            let settings = meta.insert(&mut txn, "settings", MapPrelim::default());
+            settings.insert(&mut txn, "theme", "dark");
+            meta.insert(&mut txn, "title", "draft");
+            txn.encode_update_v1()
+        };
+
+        let server = Doc::with_client_id(2);
+        server
+            .set_access_policy(|req: &AccessRequest| {
+                let is_admin = req.origin == Some(&Origin::from("admin"));
+                match (req.root, req.key) {
+                    ("admin", _) | ("meta", Some("settings")) => is_admin,
+                    _ => true,
+                }
+            })
+            .unwrap();
+        let public2 = server.get_or_insert_text("public");
+        let admin2 = server.get_or_insert_text("admin");
+        let meta2 = server.get_or_insert_map("meta");
+
+        // strict mode rejects an update as a whole
+        let strict = server
+            .transact_mut_with("user")
+            .apply_update_strict(Update::decode_v1(&update).unwrap());
+        assert_matches!(strict, Err(UpdateError::AccessDenied(_)));
+        assert_eq!(public2.get_string(&server.transact()), "");
+
+        server
+            .transact_mut_with("user")
+            .apply_update(Update::decode_v1(&update).unwrap());
+        {
+            let txn = server.transact();
+            assert_eq!(public2.get_string(&txn), "hello");
+            assert_eq!(admin2.get_string(&txn), "");
+            assert_eq!(meta2.get(&txn, "title"), Some("draft".into()));
+            assert_eq!(meta2.get(&txn, "settings"), None);
+            // the whole history is integrated, but denied blocks are garbage collected
+            assert_eq!(txn.state_vector(), author.transact().state_vector());
+        }
+
+        // deletions are checked as well
+        let sv = server.transact().state_vector();
+        let admin_sync = Doc::with_client_id(3);
+        let admin3 = admin_sync.get_or_insert_text("admin");
+        let public3 = admin_sync.get_or_insert_text("public");
+        admin_sync
+            .transact_mut()
+            .apply_update(Update::decode_v1(&update).unwrap());
+        {
+            let mut txn = admin_sync.transact_mut();
+            admin3.push(&mut txn, "!");
+            public3.remove_range(&mut txn, 0, 5);
+        }
+        let update = admin_sync.transact().encode_state_as_update_v1(&sv);
+        server
+            .transact_mut_with("user")
+            .apply_update(Update::decode_v1(&update).unwrap());
+        assert_eq!(public2.get_string(&server.transact()), "");
+
+        assert_eq!(admin2.get_string(&server.transact()), "");
+
+        // policy can be removed
+        server.remove_access_policy().unwrap();
+        server
+            .transact_mut_with("user")
+            .apply_update(Update::decode_v1(&update).unwrap());
+        assert_eq!(admin2.get_string(&server.transact()), "");
+    }
+
+    fn admin_only_settings() -> Doc {
+        let server = Doc::with_client_id(2);
+        server
+            .set_access_policy(|req: &AccessRequest| match (req.root, req.key) {
+                ("meta", Some("settings")) => req.origin == Some(&Origin::from("admin")),
+                _ => true,
+            })
+            .unwrap();
+        server
+    }
+
+    #[test]
+    fn access_policy_checks_out_of_order_updates() {
+        let author = Doc::with_client_id(1);
+        let meta = author.get_or_insert_map("meta");
+        let text = author.get_or_insert_text("text");
+        let (u0, u1, u2, u3) = {
+            let mut txn = author.transact_mut();
+            text.insert(&mut txn, 0, "x");
+            let u0 = txn.encode_update_v1();
+            drop(txn);
+            let mut txn = author.transact_mut();
+            meta.insert(&mut txn, "settings", MapPrelim::default());
+            text.insert(&mut txn, 0, "abc");
+            let u1 = txn.encode_update_v1();
+            drop(txn);
+            let mut txn = author.transact_mut();
+            let settings: MapRef = meta.get(&txn, "settings").unwrap().cast().unwrap();
+            settings.insert(&mut txn, "theme", "dark");
+            let u2 = txn.encode_update_v1();
+            drop(txn);
+            let mut txn = author.transact_mut();
+            text.remove_range(&mut txn, 0, 1);
+            (u0, u1, u2, txn.encode_update_v1())
+        };
+        let theme = |doc: &Doc| {
+            let meta = doc.get_or_insert_map("meta");
+            let txn = doc.transact();
+            let settings: Option<MapRef> = meta.get(&txn, "settings").and_then(|v| v.cast().ok());
+            settings.and_then(|s| s.get(&txn, "theme"))
+        };
+
+        // allowed changes delivered before their dependencies are integrated once these arrive
+        let server = admin_only_settings();
+        for u in [&u0, &u3, &u2, &u1] {
+            server
+                .transact_mut_with("admin")
+                .apply_update(Update::decode_v1(u).unwrap());
+        }
+        assert_eq!(theme(&server), Some("dark".into()));
+        let text2 = server.get_or_insert_text("text");
+        assert_eq!(text2.get_string(&server.transact()), "bcx");
+        assert!(server.transact().store().access_pending.is_empty());
+
+        // denied changes are still denied, even if dependencies arrive from a privileged origin
+        let server = admin_only_settings();
+        for (origin, u) in [("user", &u0), ("user", &u2)] {
+            server
+                .transact_mut_with(origin)
+                .apply_update(Update::decode_v1(u).unwrap());
+        }
+        server
+            .transact_mut_with("admin")
+            .apply_update(Update::decode_v1(&u1).unwrap());
+        let meta2 = server.get_or_insert_map("meta");
+        assert!(meta2.get(&server.transact(), "settings").is_some());
+        assert_eq!(theme(&server), None);
+        assert_eq!(
+            server.transact().state_vector(),
+            author.transact().state_vector()
+        );
+    }
+
+    #[test]
+    fn access_denials_are_reported() {
+        let author = Doc::with_client_id(1);
+        let meta = author.get_or_insert_map("meta");
+        let update = {
+            let mut txn = author.transact_mut();
+            meta.insert(&mut txn, "settings", MapPrelim::default());
+            meta.insert(&mut txn, "title", "draft");
+            txn.encode_update_v1()
+        };
+        let server = admin_only_settings();
+        let res = server
+            .transact_mut_with("user")
+            .try_apply_update(Update::decode_v1(&update).unwrap());
+        assert_matches!(res, Err(UpdateError::AccessDenied(_)));
+        assert!(server.transact().state_vector().is_empty());
+
+        let report = server
+            .transact_mut_with("user")
+            .apply_updates_lenient_v1([update.as_slice()]);
+        assert_matches!(&report.denied[..], [(0, _)]);
+        let meta2 = server.get_or_insert_map("meta");
+        assert_eq!(meta2.get(&server.transact(), "title"), Some("draft".into()));
+        assert_eq!(meta2.get(&server.transact(), "settings"), None);
+    }
+}
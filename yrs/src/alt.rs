@@ -2,8 +2,9 @@
 //! over document [Update]s directly on their binary representation.
 
 use crate::encoding::read::{Cursor, Error};
+use crate::id_set::IdSet;
 use crate::update::Update;
-use crate::updates::decoder::{Decode, DecoderV2};
+use crate::updates::decoder::{Decode, DecoderV1, DecoderV2};
 use crate::updates::encoder::{Encode, Encoder, EncoderV1, EncoderV2};
 use crate::StateVector;
 
@@ -65,6 +66,26 @@ pub fn encode_state_vector_from_update_v2(update: &[u8]) -> Result<Vec<u8>, Erro
     Ok(update.state_vector().encode_v2())
 }
 
+/// Checks if all information carried by an input `update` (encoded using lib0 v1 encoding)
+/// is already known to a document described by a given `state_vector`. See [Update::is_subset_of].
+/// Block contents are not decoded, only their clock ranges are read.
+///
+/// Returns an error whenever the input update couldn't be decoded.
+pub fn is_update_subset_of_v1(update: &[u8], state_vector: &StateVector) -> Result<bool, Error> {
+    let mut decoder = DecoderV1::from(update);
+    Update::is_encoded_subset_of(&mut decoder, state_vector)
+}
+
+/// Checks if all information carried by an input `update` (encoded using lib0 v2 encoding)
+/// is already known to a document described by a given `state_vector`. See [Update::is_subset_of].
+/// Block contents are not decoded, only their clock ranges are read.
+///
+/// Returns an error whenever the input update couldn't be decoded.
+pub fn is_update_subset_of_v2(update: &[u8], state_vector: &StateVector) -> Result<bool, Error> {
+    let mut decoder = DecoderV2::new(Cursor::new(update))?;
+    Update::is_encoded_subset_of(&mut decoder, state_vector)
+}
+
 /// Givens an input `update` (encoded using lib0 v1 encoding) of document **A** and an encoded
 /// `state_vector` of document **B**, returns a lib0 v1 encoded update, that contains all changes
 /// from **A** which have not been observed by **B** (based on its state vector).
@@ -96,9 +117,120 @@ pub fn diff_updates_v2(update: &[u8], state_vector: &[u8]) -> Result<Vec<u8>, Er
     Ok(encoder.to_vec())
 }
 
+/// Result of a three-way merge of divergent document states, see [merge3_updates_v1].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThreeWayMerge {
+    /// Encoded update containing a merged state of a common ancestor and both of its descendants.
+    pub update: Vec<u8>,
+    /// Changes contributed by a left descendant.
+    pub left: MergeContribution,
+    /// Changes contributed by a right descendant.
+    pub right: MergeContribution,
+}
+
+/// Changes made by one of the descendants of a common ancestor state, being part of
+/// [ThreeWayMerge].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MergeContribution {
+    /// ID ranges of blocks present in a descendant, but not in a common ancestor.
+    pub inserted: IdSet,
+    /// ID ranges deleted by a descendant, that are not deleted in a common ancestor.
+    pub deleted: IdSet,
+    /// ID ranges of blocks present in a common ancestor, but missing from a descendant. If not
+    /// empty, a descendant doesn't really originate from a given common ancestor (ie. it was
+    /// restored from an incomplete backup). These blocks are still present in a merged update.
+    pub missing: IdSet,
+}
+
+/// Merges two divergent descendants (ie. document states restored from two different backup
+/// locations) of a common ancestor `base` state into a single consistent document state and
+/// reports changes contributed by each one of them. All inputs, as well as produced update are
+/// encoded using lib0 v1 encoding.
+///
+/// Since documents are CRDTs, merge itself never produces conflicts: concurrent insertions are
+/// ordered the same way they would be if both descendants were synchronized live.
+///
+/// Returns an error whenever any of the input updates couldn't be decoded.
+pub fn merge3_updates_v1(base: &[u8], left: &[u8], right: &[u8]) -> Result<ThreeWayMerge, Error> {
+    let (update, left, right) = merge3(
+        Update::decode_v1(base)?,
+        Update::decode_v1(left)?,
+        Update::decode_v1(right)?,
+    );
+    Ok(ThreeWayMerge {
+        update: update.encode_v1(),
+        left,
+        right,
+    })
+}
+
+/// Merges two divergent descendants of a common ancestor `base` state, just like
+/// [merge3_updates_v1], but using lib0 v2 encoding for all inputs and produced update.
+///
+/// Returns an error whenever any of the input updates couldn't be decoded.
+pub fn merge3_updates_v2(base: &[u8], left: &[u8], right: &[u8]) -> Result<ThreeWayMerge, Error> {
+    let (update, left, right) = merge3(
+        Update::decode_v2(base)?,
+        Update::decode_v2(left)?,
+        Update::decode_v2(right)?,
+    );
+    Ok(ThreeWayMerge {
+        update: update.encode_v2(),
+        left,
+        right,
+    })
+}
+
+fn merge3(
+    base: Update,
+    left: Update,
+    right: Update,
+) -> (Update, MergeContribution, MergeContribution) {
+    let base_blocks = block_ranges(&base);
+    let base_deleted = deleted_ranges(&base);
+    let contribution = |update: &Update| {
+        let blocks = block_ranges(update);
+        MergeContribution {
+            inserted: blocks.difference(&base_blocks),
+            deleted: deleted_ranges(update).difference(&base_deleted),
+            missing: base_blocks.difference(&blocks),
+        }
+    };
+    let left_contribution = contribution(&left);
+    let right_contribution = contribution(&right);
+    let update = Update::merge_updates([base, left, right]);
+    (update, left_contribution, right_contribution)
+}
+
+fn block_ranges(update: &Update) -> IdSet {
+    let mut ranges = IdSet::new();
+    for blocks in update.blocks.clients.values() {
+        for block in blocks.iter().filter(|block| !block.is_skip()) {
+            ranges.insert(*block.id(), block.len());
+        }
+    }
+    ranges.squash();
+    ranges
+}
+
+fn deleted_ranges(update: &Update) -> IdSet {
+    let mut ranges = IdSet::new();
+    for (&client, range) in update.delete_set.iter() {
+        ranges.insert_range(client, range.clone());
+    }
+    ranges.squash();
+    ranges
+}
+
 #[cfg(test)]
 mod test {
-    use crate::{diff_updates_v1, encode_state_vector_from_update_v1, merge_updates_v1};
+    use crate::id_set::IdSet;
+    use crate::updates::decoder::Decode;
+    use crate::{
+        diff_updates_v1, encode_state_vector_from_update_v1, is_update_subset_of_v1,
+        is_update_subset_of_v2, merge3_updates_v1, merge_updates_v1, Doc, GetString, Map, ReadTxn,
+        StateVector, Text, Transact, Update, ID,
+    };
 
     #[test]
     fn merge_updates_compatibility_v1() {
@@ -145,6 +277,65 @@ mod test {
         assert_eq!(actual, expected);
     }
 
+    #[test]
+    fn is_update_subset_of_v1_check() {
+        let update = &[
+            2, 1, 220, 240, 237, 172, 15, 0, 4, 1, 4, 116, 101, 115, 116, 3, 97, 98, 99, 1, 201,
+            139, 250, 201, 1, 0, 4, 1, 4, 116, 101, 115, 116, 2, 100, 101, 0,
+        ];
+        let sv = StateVector::decode_v1(&[2, 220, 240, 237, 172, 15, 3, 201, 139, 250, 201, 1, 2])
+            .unwrap();
+        assert!(is_update_subset_of_v1(update, &sv).unwrap());
+
+        let sv = StateVector::decode_v1(&[2, 220, 240, 237, 172, 15, 4, 201, 139, 250, 201, 1, 1])
+            .unwrap();
+        assert!(!is_update_subset_of_v1(update, &sv).unwrap());
+        assert!(!is_update_subset_of_v1(update, &StateVector::default()).unwrap());
+
+        // delete set is never known for sure
+        let delete = &[0, 1, 220, 240, 237, 172, 15, 1, 0, 1];
+        assert!(!is_update_subset_of_v1(delete, &sv).unwrap());
+
+        // truncated update is rejected even if its known part is a subset
+        let sv = StateVector::decode_v1(&[2, 220, 240, 237, 172, 15, 3, 201, 139, 250, 201, 1, 2])
+            .unwrap();
+        assert!(is_update_subset_of_v1(&update[..update.len() - 4], &sv).is_err());
+        let sv = StateVector::default();
+        assert!(is_update_subset_of_v1(&update[..update.len() - 4], &sv).is_err());
+    }
+
+    #[test]
+    fn is_update_subset_of_v2_check() {
+        let doc = Doc::with_client_id(1);
+        let text = doc.get_or_insert_text("text");
+        text.insert(&mut doc.transact_mut(), 0, "hello 🌍");
+        let map = doc.get_or_insert_map("map");
+        map.insert(&mut doc.transact_mut(), "key", vec![1, 2, 3]);
+        let sv = doc.transact().state_vector();
+        let update = doc
+            .transact()
+            .encode_state_as_update_v2(&StateVector::default());
+        assert!(is_update_subset_of_v2(&update, &sv).unwrap());
+        assert!(!is_update_subset_of_v2(&update, &StateVector::default()).unwrap());
+
+        let mut txn = doc.transact_mut();
+        text.remove_range(&mut txn, 0, 2);
+        let update = txn.encode_update_v2();
+        drop(txn);
+        // deletions are never known for sure
+        assert!(!is_update_subset_of_v2(&update, &sv).unwrap());
+        assert!(!Update::decode_v2(&update).unwrap().is_subset_of(&sv));
+
+        let mut txn = doc.transact_mut();
+        text.push(&mut txn, "!");
+        let update = txn.encode_update_v2();
+        drop(txn);
+        assert!(!is_update_subset_of_v2(&update, &sv).unwrap());
+        let sv = doc.transact().state_vector();
+        assert!(is_update_subset_of_v2(&update, &sv).unwrap());
+        assert!(is_update_subset_of_v2(&update[..update.len() - 1], &sv).is_err());
+    }
+
     #[test]
     fn diff_updates_compatibility_v1() {
         let state_vector = &[1, 148, 189, 145, 162, 9, 3];
@@ -158,4 +349,64 @@ mod test {
         let actual = diff_updates_v1(update, state_vector).unwrap();
         assert_eq!(actual, expected);
     }
+
+    #[test]
+    fn merge3_divergent_descendants() {
+        let doc = Doc::with_client_id(1);
+        let text = doc.get_or_insert_text("text");
+        text.insert(&mut doc.transact_mut(), 0, "hello world");
+        let base = doc
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+
+        let fork = |client_id, f: &dyn Fn(&Doc)| {
+            let doc = Doc::with_client_id(client_id);
+            let update = Update::decode_v1(&base).unwrap();
+            doc.transact_mut().apply_update(update);
+            f(&doc);
+            let txn = doc.transact();
+            txn.encode_state_as_update_v1(&StateVector::default())
+        };
+        let left = fork(2, &|doc| {
+            let text = doc.get_or_insert_text("text");
+            text.insert(&mut doc.transact_mut(), 0, "> ");
+        });
+        let right = fork(3, &|doc| {
+            let text = doc.get_or_insert_text("text");
+            let mut txn = doc.transact_mut();
+            text.remove_range(&mut txn, 5, 6);
+            text.push(&mut txn, "!");
+        });
+
+        let merge = merge3_updates_v1(&base, &left, &right).unwrap();
+        let merged = Doc::with_client_id(4);
+        let text = merged.get_or_insert_text("text");
+        merged
+            .transact_mut()
+            .apply_update(Update::decode_v1(&merge.update).unwrap());
+        assert_eq!(text.get_string(&merged.transact()), "> hello!");
+
+        let mut inserted = IdSet::new();
+        inserted.insert(ID::new(2, 0), 2);
+        assert_eq!(merge.left.inserted, inserted);
+        assert!(merge.left.deleted.is_empty());
+        assert!(merge.left.missing.is_empty());
+
+        let mut inserted = IdSet::new();
+        inserted.insert(ID::new(3, 0), 1);
+        let mut deleted = IdSet::new();
+        deleted.insert(ID::new(1, 5), 6);
+        assert_eq!(merge.right.inserted, inserted);
+        assert_eq!(merge.right.deleted, deleted);
+
+        // descendant which doesn't contain the whole ancestor state
+        let truncated = Doc::with_client_id(5);
+        let truncated = truncated
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+        let merge = merge3_updates_v1(&base, &left, &truncated).unwrap();
+        let mut missing = IdSet::new();
+        missing.insert(ID::new(1, 0), 11);
+        assert_eq!(merge.right.missing, missing);
+    }
 }
@@ -0,0 +1,714 @@
+//! Batched dispatch of shared type events. When many transactions are applied in a rapid
+//! succession (ie. during initial sync or bulk import), observers of shared types would be called
+//! once per every transaction. [Batched] observers coalesce changes of many transactions into
+//! a single, merged change set instead, and call a callback once per batch.
+//!
+//! Batches are flushed when a configured number of transactions has been coalesced, when the
+//! oldest coalesced transaction is older than a configured time window or when [Batched::flush]
+//! is called explicitly. Time windows are measured using a [Clock] of choice and checked only
+//! when a new transaction is committed, so a trailing batch stays buffered until the next
+//! transaction or an explicit flush, ie. one done on the next UI animation frame.
+//!
+//! ```rust
+//! use yrs::batch::{BatchConfig, Batched};
+//! use yrs::types::Delta;
+//! use yrs::{Doc, Text, Transact};
+//! use std::sync::{Arc, Mutex};
+//!
+//! let doc = Doc::new();
+//! let text = doc.get_or_insert_text("text");
+//! let deltas = Arc::new(Mutex::new(Vec::new()));
+//! let batched = {
+//!     let deltas = deltas.clone();
+//!     Batched::new(&text, BatchConfig::transactions(3), move |delta: &Vec<Delta>| {
+//!         deltas.lock().unwrap().push(delta.clone());
+//!     })
+//! };
+//!
+//! for chunk in ["a", "b", "c"] {
+//!     text.push(&mut doc.transact_mut(), chunk);
+//! }
+//! // all 3 transactions have been coalesced into a single delta
+//! assert_eq!(
+//!     deltas.lock().unwrap().as_slice(),
+//!     &[vec![Delta::Inserted("abc".into(), None)]]
+//! );
+//! drop(batched);
+//! ```
+
+use crate::sync::{Clock, Timestamp};
+use crate::types::{Attrs, Change, Delta, EntryChange, Event};
+use crate::{
+    Any, ArrayRef, MapRef, Observable, OffsetKind, Out, Subscription, TextRef, TransactionMut,
+};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Configures when [Batched] observers flush coalesced changes. If neither limit is set, changes
+/// are flushed only when [Batched::flush] is called.
+#[derive(Clone, Default)]
+pub struct BatchConfig {
+    /// Flush changes once this many transactions have been coalesced.
+    pub max_transactions: Option<usize>,
+    /// Flush changes once the oldest coalesced transaction is older than a given duration.
+    pub window: Option<Duration>,
+    /// Clock used to measure [BatchConfig::window]. If not provided, a system clock is used.
+    /// There's no system clock on WebAssembly targets, so windows take effect there only when
+    /// a clock is provided explicitly.
+    pub clock: Option<Arc<dyn Clock>>,
+}
+
+impl BatchConfig {
+    /// Flush changes every `n` transactions.
+    pub fn transactions(n: usize) -> Self {
+        BatchConfig {
+            max_transactions: Some(n),
+            ..BatchConfig::default()
+        }
+    }
+
+    /// Flush changes, when the oldest coalesced transaction is older than a given `window`.
+    pub fn window(window: Duration) -> Self {
+        BatchConfig {
+            window: Some(window),
+            ..BatchConfig::default()
+        }
+    }
+
+    /// Sets a clock used to measure time windows, ie. a [ManualClock] in tests.
+    ///
+    /// [ManualClock]: crate::sync::time::ManualClock
+    pub fn with_clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Some(Arc::new(clock));
+        self
+    }
+
+    fn now(&self) -> Option<Timestamp> {
+        match &self.clock {
+            Some(clock) => Some(clock.now()),
+            #[cfg(not(target_family = "wasm"))]
+            None => Some(crate::sync::time::SystemClock.now()),
+            #[cfg(target_family = "wasm")]
+            None => None,
+        }
+    }
+}
+
+impl std::fmt::Debug for BatchConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BatchConfig")
+            .field("max_transactions", &self.max_transactions)
+            .field("window", &self.window)
+            .field("clock", &self.clock.as_ref().map(|_| ".."))
+            .finish()
+    }
+}
+
+/// Shared types, which changes can be coalesced by [Batched] observers.
+pub trait Coalesce: Observable {
+    /// Owned set of changes described by a single event.
+    #[cfg(feature = "sync")]
+    type Changes: Default + Send + 'static;
+
+    /// Owned set of changes described by a single event.
+    #[cfg(not(feature = "sync"))]
+    type Changes: Default + 'static;
+
+    /// Returns changes described by a given `event`.
+    fn changes(txn: &TransactionMut, event: &Self::Event) -> Self::Changes;
+
+    /// Merges `next` changes into a `batch`, so that the result is equivalent to applying them
+    /// one after another. Lengths are measured using a given offset `kind`.
+    fn merge(batch: &mut Self::Changes, next: Self::Changes, kind: OffsetKind);
+}
+
+impl Coalesce for TextRef {
+    type Changes = Vec<Delta>;
+
+    fn changes(txn: &TransactionMut, event: &Self::Event) -> Self::Changes {
+        event.delta(txn).to_vec()
+    }
+
+    fn merge(batch: &mut Self::Changes, next: Self::Changes, kind: OffsetKind) {
+        *batch = compose(std::mem::take(batch), next, kind);
+    }
+}
+
+impl Coalesce for ArrayRef {
+    type Changes = Vec<Change>;
+
+    fn changes(txn: &TransactionMut, event: &Self::Event) -> Self::Changes {
+        event.delta(txn).to_vec()
+    }
+
+    fn merge(batch: &mut Self::Changes, next: Self::Changes, kind: OffsetKind) {
+        fn to_delta(changes: Vec<Change>) -> Vec<Delta<Vec<Out>>> {
+            changes
+                .into_iter()
+                .map(|change| match change {
+                    Change::Added(values) => Delta::Inserted(values, None),
+                    Change::Removed(len) => Delta::Deleted(len),
+                    Change::Retain(len) => Delta::Retain(len, None),
+                })
+                .collect()
+        }
+        let delta = compose(to_delta(std::mem::take(batch)), to_delta(next), kind);
+        *batch = delta
+            .into_iter()
+            .map(|delta| match delta {
+                Delta::Inserted(values, _) => Change::Added(values),
+                Delta::Deleted(len) => Change::Removed(len),
+                Delta::Retain(len, _) => Change::Retain(len),
+            })
+            .collect();
+    }
+}
+
+impl Coalesce for MapRef {
+    type Changes = HashMap<Arc<str>, EntryChange>;
+
+    fn changes(txn: &TransactionMut, event: &Self::Event) -> Self::Changes {
+        event.keys(txn).clone()
+    }
+
+    fn merge(batch: &mut Self::Changes, next: Self::Changes, _kind: OffsetKind) {
+        for (key, change) in next {
+            let merged = match (batch.remove(&key), change) {
+                (None, change) => Some(change),
+                (Some(EntryChange::Inserted(_)), EntryChange::Updated(_, new)) => {
+                    Some(EntryChange::Inserted(new))
+                }
+                (Some(EntryChange::Inserted(_)), EntryChange::Removed(_)) => None,
+                (Some(EntryChange::Updated(old, _)), EntryChange::Updated(_, new))
+                | (Some(EntryChange::Removed(old)), EntryChange::Inserted(new)) => {
+                    Some(EntryChange::Updated(old, new))
+                }
+                (Some(EntryChange::Updated(old, _)), EntryChange::Removed(_)) => {
+                    Some(EntryChange::Removed(old))
+                }
+                // inconsistent sequence, keep the latest change
+                (Some(_), change) => Some(change),
+            };
+            if let Some(change) = merged {
+                batch.insert(key, change);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "sync")]
+type BatchFn<C> = Box<dyn Fn(&C) + Send + Sync + 'static>;
+#[cfg(not(feature = "sync"))]
+type BatchFn<C> = Box<dyn Fn(&C) + 'static>;
+
+struct Batch<C> {
+    changes: C,
+    transactions: usize,
+    started: Option<Timestamp>,
+}
+
+impl<C: Default> Batch<C> {
+    fn take(&mut self) -> Option<C> {
+        if self.transactions == 0 {
+            None
+        } else {
+            self.transactions = 0;
+            self.started = None;
+            Some(std::mem::take(&mut self.changes))
+        }
+    }
+}
+
+struct Shared<C> {
+    batch: Mutex<Batch<C>>,
+    callback: BatchFn<C>,
+}
+
+impl<C: Default> Shared<C> {
+    fn flush(&self) {
+        // release the lock before calling back, so that callback is free to call flush itself
+        let changes = self.batch.lock().unwrap().take();
+        if let Some(changes) = changes {
+            (self.callback)(&changes);
+        }
+    }
+}
+
+/// An observer of a shared type, which coalesces changes of many transactions into a single
+/// change set, see [crate::batch]. Changes which have not been flushed yet are dropped together
+/// with this observer.
+pub struct Batched<T: Coalesce> {
+    shared: Arc<Shared<T::Changes>>,
+    _subscription: Subscription,
+}
+
+impl<T> Batched<T>
+where
+    T: Coalesce,
+    Event: AsRef<T::Event>,
+{
+    /// Subscribes to changes of a given shared type, calling `f` with merged changes whenever
+    /// a batch is flushed according to a given `config`.
+    #[cfg(feature = "sync")]
+    pub fn new<F>(shared_ref: &T, config: BatchConfig, f: F) -> Self
+    where
+        F: Fn(&T::Changes) + Send + Sync + 'static,
+    {
+        Self::with_callback(shared_ref, config, Box::new(f))
+    }
+
+    /// Subscribes to changes of a given shared type, calling `f` with merged changes whenever
+    /// a batch is flushed according to a given `config`.
+    #[cfg(not(feature = "sync"))]
+    pub fn new<F>(shared_ref: &T, config: BatchConfig, f: F) -> Self
+    where
+        F: Fn(&T::Changes) + 'static,
+    {
+        Self::with_callback(shared_ref, config, Box::new(f))
+    }
+
+    fn with_callback(shared_ref: &T, config: BatchConfig, callback: BatchFn<T::Changes>) -> Self {
+        let shared = Arc::new(Shared {
+            batch: Mutex::new(Batch {
+                changes: T::Changes::default(),
+                transactions: 0,
+                started: None,
+            }),
+            callback,
+        });
+        let inner = shared.clone();
+        let subscription = shared_ref.observe(move |txn, event| {
+            let kind = txn.store().options.offset_kind;
+            let changes = T::changes(txn, event);
+            let due = {
+                let mut batch = inner.batch.lock().unwrap();
+                T::merge(&mut batch.changes, changes, kind);
+                batch.transactions += 1;
+                let elapsed = config.window.and_then(|_| {
+                    let now = config.now()?;
+                    let started = *batch.started.get_or_insert(now);
+                    Some(Duration::from_millis(now.saturating_sub(started)))
+                });
+                config
+                    .max_transactions
+                    .is_some_and(|max| batch.transactions >= max)
+                    || config.window.zip(elapsed).is_some_and(|(w, e)| e >= w)
+            };
+            if due {
+                inner.flush();
+            }
+        });
+        Batched {
+            shared,
+            _subscription: subscription,
+        }
+    }
+
+    /// Returns a number of transactions, which changes have been coalesced but not flushed yet.
+    pub fn pending_transactions(&self) -> usize {
+        self.shared.batch.lock().unwrap().transactions
+    }
+
+    /// Immediately calls a callback with all changes coalesced so far. Does nothing if there are
+    /// no pending changes.
+    pub fn flush(&self) {
+        self.shared.flush()
+    }
+}
+
+/// Piece of content inserted into a sequence, which can be split at a given offset.
+trait Chunk: Sized {
+    fn len(&self, kind: OffsetKind) -> u32;
+    fn split_at(self, offset: u32, kind: OffsetKind) -> (Self, Self);
+    fn concat(&mut self, other: &Self) -> bool;
+}
+
+impl Chunk for Out {
+    fn len(&self, kind: OffsetKind) -> u32 {
+        match self {
+            Out::Any(Any::String(s)) => match kind {
+                OffsetKind::Bytes => s.len() as u32,
+                OffsetKind::Utf16 => s.encode_utf16().count() as u32,
+            },
+            // embeds always have a length of 1
+            _ => 1,
+        }
+    }
+
+    fn split_at(self, offset: u32, kind: OffsetKind) -> (Self, Self) {
+        match &self {
+            Out::Any(Any::String(s)) => {
+                let index = match kind {
+                    OffsetKind::Bytes => offset as usize,
+                    OffsetKind::Utf16 => {
+                        let mut units = 0;
+                        s.char_indices()
+                            .find(|(_, c)| {
+                                let found = units >= offset;
+                                units += c.len_utf16() as u32;
+                                found
+                            })
+                            .map(|(i, _)| i)
+                            .unwrap_or(s.len())
+                    }
+                };
+                let (left, right) = s.split_at(index);
+                (Out::from(left), Out::from(right))
+            }
+            _ => unreachable!("embeds are never split"),
+        }
+    }
+
+    fn concat(&mut self, other: &Self) -> bool {
+        match (self, other) {
+            (Out::Any(Any::String(a)), Out::Any(Any::String(b))) => {
+                *a = format!("{}{}", a, b).into();
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Chunk for Vec<Out> {
+    fn len(&self, _kind: OffsetKind) -> u32 {
+        Vec::len(self) as u32
+    }
+
+    fn split_at(mut self, offset: u32, _kind: OffsetKind) -> (Self, Self) {
+        let right = self.split_off(offset as usize);
+        (self, right)
+    }
+
+    fn concat(&mut self, other: &Self) -> bool {
+        self.extend(other.iter().cloned());
+        true
+    }
+}
+
+fn delta_len<T: Chunk>(delta: &Delta<T>, kind: OffsetKind) -> u32 {
+    match delta {
+        Delta::Inserted(chunk, _) => chunk.len(kind),
+        Delta::Deleted(len) | Delta::Retain(len, _) => *len,
+    }
+}
+
+/// Splits a given `delta` into a first `len` elements and the rest. Deletions are not split.
+fn split_delta<T: Chunk>(
+    delta: Delta<T>,
+    len: u32,
+    kind: OffsetKind,
+) -> (Delta<T>, Option<Delta<T>>) {
+    let total = delta_len(&delta, kind);
+    if total <= len {
+        return (delta, None);
+    }
+    match delta {
+        Delta::Inserted(chunk, attrs) => {
+            let (left, right) = chunk.split_at(len, kind);
+            (
+                Delta::Inserted(left, attrs.clone()),
+                Some(Delta::Inserted(right, attrs)),
+            )
+        }
+        Delta::Retain(_, attrs) => (
+            Delta::Retain(len, attrs.clone()),
+            Some(Delta::Retain(total - len, attrs)),
+        ),
+        Delta::Deleted(_) => (Delta::Deleted(len), Some(Delta::Deleted(total - len))),
+    }
+}
+
+fn merge_attrs(
+    base: Option<Box<Attrs>>,
+    next: &Option<Box<Attrs>>,
+    drop_nulls: bool,
+) -> Option<Box<Attrs>> {
+    let next = match next {
+        None => return base,
+        Some(next) => next,
+    };
+    let mut attrs = base.unwrap_or_default();
+    for (key, value) in next.iter() {
+        if drop_nulls && *value == Any::Null {
+            attrs.remove(key);
+        } else {
+            attrs.insert(key.clone(), value.clone());
+        }
+    }
+    if attrs.is_empty() {
+        None
+    } else {
+        Some(attrs)
+    }
+}
+
+/// Composes two consecutive deltas into a single one.
+fn compose<T: Chunk>(a: Vec<Delta<T>>, b: Vec<Delta<T>>, kind: OffsetKind) -> Vec<Delta<T>> {
+    let mut result = Vec::with_capacity(a.len() + b.len());
+    let mut a = a.into_iter();
+    let mut head: Option<Delta<T>> = None;
+    for op in b {
+        let (mut remaining, attrs, delete) = match op {
+            Delta::Inserted(chunk, attrs) => {
+                push(&mut result, Delta::Inserted(chunk, attrs), kind);
+                continue;
+            }
+            Delta::Retain(len, attrs) => (len, attrs, false),
+            Delta::Deleted(len) => (len, None, true),
+        };
+        while remaining > 0 {
+            let current = match head.take().or_else(|| a.next()) {
+                Some(current) => current,
+                None => {
+                    let rest = if delete {
+                        Delta::Deleted(remaining)
+                    } else {
+                        Delta::Retain(remaining, attrs.clone())
+                    };
+                    push(&mut result, rest, kind);
+                    break;
+                }
+            };
+            if let Delta::Deleted(_) = current {
+                // deletions of a first delta don't take space in the second one
+                push(&mut result, current, kind);
+                continue;
+            }
+            let (current, rest) = split_delta(current, remaining, kind);
+            head = rest;
+            remaining -= delta_len(&current, kind);
+            match (current, delete) {
+                (Delta::Inserted(..), true) => { /* inserted and deleted within a batch */ }
+                (Delta::Retain(len, _), true) => push(&mut result, Delta::Deleted(len), kind),
+                (Delta::Inserted(chunk, a_attrs), false) => push(
+                    &mut result,
+                    Delta::Inserted(chunk, merge_attrs(a_attrs, &attrs, true)),
+                    kind,
+                ),
+                (Delta::Retain(len, a_attrs), false) => push(
+                    &mut result,
+                    Delta::Retain(len, merge_attrs(a_attrs, &attrs, false)),
+                    kind,
+                ),
+                (Delta::Deleted(_), _) => unreachable!(),
+            }
+        }
+    }
+    for rest in head.into_iter().chain(a) {
+        push(&mut result, rest, kind);
+    }
+    // trailing retains without formatting carry no information
+    while let Some(Delta::Retain(_, None)) = result.last() {
+        result.pop();
+    }
+    result
+}
+
+fn push<T: Chunk>(result: &mut Vec<Delta<T>>, delta: Delta<T>, kind: OffsetKind) {
+    if delta_len(&delta, kind) == 0 {
+        return;
+    }
+    let merged = match (result.last_mut(), &delta) {
+        (Some(Delta::Deleted(len)), Delta::Deleted(other)) => {
+            *len += other;
+            true
+        }
+        (Some(Delta::Retain(len, attrs)), Delta::Retain(other, other_attrs))
+            if attrs == other_attrs =>
+        {
+            *len += other;
+            true
+        }
+        (Some(Delta::Inserted(chunk, attrs)), Delta::Inserted(other, other_attrs))
+            if attrs == other_attrs =>
+        {
+            chunk.concat(other)
+        }
+        _ => false,
+    };
+    if !merged {
+        result.push(delta);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::batch::{BatchConfig, Batched, Coalesce};
+    use crate::sync::time::ManualClock;
+    use crate::types::{Attrs, Change, Delta, EntryChange};
+    use crate::updates::decoder::Decode;
+    use crate::{
+        Any, Array, Doc, Map, MapRef, OffsetKind, Out, Text, Transact, TransactionMut, Update,
+        WriteTxn,
+    };
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[test]
+    fn batched_text_deltas() {
+        let doc = Doc::with_client_id(1);
+        let text = doc.get_or_insert_text("text");
+        text.insert(&mut doc.transact_mut(), 0, "hello world");
+
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let batched = {
+            let calls = calls.clone();
+            Batched::new(&text, BatchConfig::default(), move |delta: &Vec<Delta>| {
+                calls.lock().unwrap().push(delta.clone())
+            })
+        };
+        text.insert(&mut doc.transact_mut(), 5, ",");
+        text.remove_range(&mut doc.transact_mut(), 0, 1);
+        text.insert(&mut doc.transact_mut(), 0, "J");
+        let bold = Attrs::from([(Arc::from("bold"), Any::Bool(true))]);
+        text.format(&mut doc.transact_mut(), 0, 2, bold.clone());
+        text.insert(&mut doc.transact_mut(), 6, "!");
+        text.remove_range(&mut doc.transact_mut(), 6, 1);
+        assert!(calls.lock().unwrap().is_empty());
+        assert_eq!(batched.pending_transactions(), 6);
+
+        batched.flush();
+        batched.flush();
+        assert_eq!(
+            calls.lock().unwrap().as_slice(),
+            &[vec![
+                Delta::Inserted("J".into(), Some(Box::new(bold.clone()))),
+                Delta::Deleted(1),
+                Delta::Retain(1, Some(Box::new(bold))),
+                Delta::Retain(3, None),
+                Delta::Inserted(",".into(), None),
+            ]]
+        );
+        assert_eq!(batched.pending_transactions(), 0);
+    }
+
+    #[test]
+    fn batched_time_window() {
+        let clock = ManualClock::new(1000);
+        let doc = Doc::with_client_id(1);
+        let text = doc.get_or_insert_text("text");
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let batched = {
+            let calls = calls.clone();
+            let config = BatchConfig::window(Duration::from_millis(100)).with_clock(clock.clone());
+            Batched::new(&text, config, move |delta: &Vec<Delta>| {
+                calls.lock().unwrap().push(delta.clone())
+            })
+        };
+
+        text.push(&mut doc.transact_mut(), "a");
+        clock.advance(99);
+        text.push(&mut doc.transact_mut(), "b");
+        assert!(calls.lock().unwrap().is_empty());
+        assert_eq!(batched.pending_transactions(), 2);
+
+        // window is measured since the oldest transaction of a batch
+        clock.advance(1);
+        text.push(&mut doc.transact_mut(), "c");
+        clock.advance(1000);
+        text.push(&mut doc.transact_mut(), "d");
+        clock.advance(99);
+        text.push(&mut doc.transact_mut(), "e");
+        assert_eq!(
+            calls.lock().unwrap().as_slice(),
+            &[vec![Delta::Inserted("abc".into(), None)]]
+        );
+        assert_eq!(batched.pending_transactions(), 2);
+    }
+
+    #[test]
+    fn batched_array_and_map_changes() {
+        let source = Doc::with_client_id(1);
+        let doc = Doc::with_client_id(2);
+        let array = doc.get_or_insert_array("array");
+        let map = doc.get_or_insert_map("map");
+        map.insert(&mut doc.transact_mut(), "b", "initial");
+
+        let array_calls = Arc::new(Mutex::new(Vec::new()));
+        let _array_batch = {
+            let calls = array_calls.clone();
+            Batched::new(
+                &array,
+                BatchConfig::transactions(3),
+                move |c: &Vec<Change>| calls.lock().unwrap().push(c.clone()),
+            )
+        };
+        let map_calls = Arc::new(Mutex::new(Vec::new()));
+        let _map_batch = {
+            let calls = map_calls.clone();
+            let config = BatchConfig::window(Duration::ZERO);
+            Batched::new(&map, config, move |c: &HashMap<Arc<str>, EntryChange>| {
+                calls.lock().unwrap().push(c.clone())
+            })
+        };
+
+        // remote updates arriving one by one
+        let apply = |f: &dyn Fn(&mut TransactionMut)| {
+            let update = {
+                let mut txn = source.transact_mut();
+                f(&mut txn);
+                txn.encode_update_v1()
+            };
+            doc.transact_mut()
+                .apply_update(Update::decode_v1(&update).unwrap());
+        };
+        apply(&|txn| {
+            txn.get_or_insert_array("array")
+                .insert_range(txn, 0, [1, 2, 3]);
+        });
+        apply(&|txn| txn.get_or_insert_array("array").remove(txn, 1));
+        apply(&|txn| {
+            txn.get_or_insert_array("array").push_back(txn, 4);
+        });
+        assert_eq!(
+            array_calls.lock().unwrap().as_slice(),
+            &[vec![Change::Added(vec![
+                Out::Any(Any::Number(1.0)),
+                Out::Any(Any::Number(3.0)),
+                Out::Any(Any::Number(4.0)),
+            ])]]
+        );
+
+        // with zero window every transaction is flushed immediately
+        map.insert(&mut doc.transact_mut(), "a", 1);
+        map.insert(&mut doc.transact_mut(), "a", 2);
+        assert_eq!(map_calls.lock().unwrap().len(), 2);
+
+        let mut batch = HashMap::new();
+        let a: Arc<str> = "a".into();
+        let b: Arc<str> = "b".into();
+        let changes = [
+            (a.clone(), EntryChange::Inserted(Out::from("1"))),
+            (
+                b.clone(),
+                EntryChange::Updated(Out::from("0"), Out::from("1")),
+            ),
+            (
+                a.clone(),
+                EntryChange::Updated(Out::from("1"), Out::from("2")),
+            ),
+            (
+                b.clone(),
+                EntryChange::Updated(Out::from("1"), Out::from("2")),
+            ),
+        ];
+        for (key, change) in changes {
+            MapRef::merge(
+                &mut batch,
+                HashMap::from([(key, change)]),
+                OffsetKind::Bytes,
+            );
+        }
+        assert_eq!(batch[&a], EntryChange::Inserted(Out::from("2")));
+        assert_eq!(
+            batch[&b],
+            EntryChange::Updated(Out::from("0"), Out::from("2"))
+        );
+        let removal = HashMap::from([(a.clone(), EntryChange::Removed(Out::from("2")))]);
+        MapRef::merge(&mut batch, removal, OffsetKind::Bytes);
+        assert!(!batch.contains_key(&a));
+    }
+}
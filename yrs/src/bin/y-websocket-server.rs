@@ -0,0 +1,93 @@
+//! Runs a y-websocket compatible collaboration server, see [yrs::sync::server] for details.
+//!
+//! ```bash
+//! y-websocket-server [--host HOST] [--port PORT] [--data-dir DIR] [--token TOKEN]
+//! ```
+
+use std::process::exit;
+use std::sync::Arc;
+use yrs::sync::server::{FilePersistence, Server};
+
+const USAGE: &str =
+    "usage: y-websocket-server [--host HOST] [--port PORT] [--data-dir DIR] [--token TOKEN]
+
+Serves y-websocket clients connecting to ws://HOST:PORT/<room>.
+
+Options:
+  --host HOST      address to listen on (default: $HOST or 0.0.0.0)
+  --port PORT      port to listen on (default: $PORT or 1234)
+  --data-dir DIR   persist room documents in a given directory
+  --token TOKEN    accept only connections with `token=TOKEN` query parameter";
+
+fn main() {
+    let mut host = std::env::var("HOST").unwrap_or_else(|_| "0.0.0.0".to_string());
+    let mut port = std::env::var("PORT").unwrap_or_else(|_| "1234".to_string());
+    let mut data_dir = None;
+    let mut token = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--host" => {
+                host = args
+                    .next()
+                    .unwrap_or_else(|| fail("--host requires a value"))
+            }
+            "--port" => {
+                port = args
+                    .next()
+                    .unwrap_or_else(|| fail("--port requires a value"))
+            }
+            "--data-dir" => {
+                data_dir = Some(
+                    args.next()
+                        .unwrap_or_else(|| fail("--data-dir requires a value")),
+                )
+            }
+            "--token" => {
+                token = Some(
+                    args.next()
+                        .unwrap_or_else(|| fail("--token requires a value")),
+                )
+            }
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ => fail(&format!("unexpected argument: {}", arg)),
+        }
+    }
+
+    let mut server = Server::new();
+    if let Some(dir) = data_dir {
+        let persistence = FilePersistence::new(&dir)
+            .unwrap_or_else(|e| fail(&format!("failed to open '{}': {}", dir, e)));
+        server = server.with_persistence(persistence);
+    }
+    if let Some(token) = token {
+        server = server.with_auth(move |req| {
+            if req.has_token(&token) {
+                Ok(())
+            } else {
+                Err("invalid or missing token".to_string())
+            }
+        });
+    }
+
+    let runtime = tokio::runtime::Runtime::new()
+        .unwrap_or_else(|e| fail(&format!("failed to start runtime: {}", e)));
+    runtime.block_on(async move {
+        let addr = format!("{}:{}", host, port);
+        let listener = tokio::net::TcpListener::bind(&addr)
+            .await
+            .unwrap_or_else(|e| fail(&format!("failed to listen on {}: {}", addr, e)));
+        println!("listening on ws://{}", addr);
+        if let Err(e) = axum::serve(listener, Arc::new(server).router()).await {
+            fail(&format!("server failure: {}", e));
+        }
+    });
+}
+
+fn fail(msg: &str) -> ! {
+    eprintln!("error: {}\n\n{}", msg, USAGE);
+    exit(1)
+}
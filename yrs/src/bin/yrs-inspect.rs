@@ -0,0 +1,63 @@
+//! Prints a human-readable summary of an encoded update or a persisted document state, see
+//! [yrs::inspect] for details.
+//!
+//! ```bash
+//! yrs-inspect [--v2] [--top N] <FILE|->
+//! ```
+
+use std::io::Read;
+use std::process::exit;
+
+const USAGE: &str = "usage: yrs-inspect [--v2] [--top N] <FILE|->
+
+Prints structure, per-client statistics, largest blocks and delete set summary
+of an update or a document state encoded using lib0 v1 (default) or v2 encoding.
+Use `-` to read a payload from standard input.";
+
+fn main() {
+    let mut v2 = false;
+    let mut top = 10;
+    let mut path = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--v2" => v2 = true,
+            "--top" => match args.next().and_then(|n| n.parse().ok()) {
+                Some(n) => top = n,
+                None => fail("--top requires a number"),
+            },
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ if path.is_none() => path = Some(arg),
+            _ => fail(&format!("unexpected argument: {}", arg)),
+        }
+    }
+    let data = match path.as_deref() {
+        None => fail("missing input file"),
+        Some("-") => {
+            let mut buf = Vec::new();
+            std::io::stdin()
+                .read_to_end(&mut buf)
+                .unwrap_or_else(|e| fail(&format!("failed to read standard input: {}", e)));
+            buf
+        }
+        Some(path) => std::fs::read(path)
+            .unwrap_or_else(|e| fail(&format!("failed to read '{}': {}", path, e))),
+    };
+    let report = if v2 {
+        yrs::inspect::inspect_update_v2(&data, top)
+    } else {
+        yrs::inspect::inspect_update_v1(&data, top)
+    };
+    match report {
+        Ok(report) => print!("{}", report),
+        Err(e) => fail(&format!("failed to decode update: {}", e)),
+    }
+}
+
+fn fail(msg: &str) -> ! {
+    eprintln!("error: {}\n\n{}", msg, USAGE);
+    exit(1)
+}
@@ -0,0 +1,410 @@
+//! Binding between a local editor buffer (ie. of a terminal editor, GUI widget or a language
+//! server) and a [TextRef], which takes care of the reconciliation loop, so that integrations
+//! only need a small adapter. See [TextBinding].
+//!
+//! All positions and lengths used by a binding are measured using [OffsetKind] configured for
+//! a document. Bound text is expected to contain plain text only: embedded values are passed to
+//! a buffer as U+FFFC (object replacement character) and formatting attributes are ignored.
+//!
+//! ```rust
+//! use std::sync::{Arc, Mutex};
+//! use yrs::binding::{BufferChange, BufferEdit, TextBinding};
+//! use yrs::{Doc, GetString, Text, Transact};
+//!
+//! let doc = Doc::new();
+//! let text = doc.get_or_insert_text("text");
+//! let buffer = Arc::new(Mutex::new(String::new()));
+//! let binding = {
+//!     let buffer = buffer.clone();
+//!     TextBinding::new(&doc, text.clone(), move |change: &BufferChange| {
+//!         let mut buffer = buffer.lock().unwrap();
+//!         for edit in change.edits.iter() {
+//!             let range = edit.index as usize..(edit.index + edit.delete) as usize;
+//!             buffer.replace_range(range, &edit.insert);
+//!         }
+//!     })
+//! };
+//!
+//! // buffer edits are propagated to a document
+//! buffer.lock().unwrap().push_str("hello");
+//! binding.edit(&[BufferEdit::insert(0, "hello")]).unwrap();
+//! assert_eq!(text.get_string(&doc.transact()), "hello");
+//!
+//! // document changes made by anyone else are propagated to a buffer
+//! text.push(&mut doc.transact_mut(), " world");
+//! assert_eq!(buffer.lock().unwrap().as_str(), "hello world");
+//! ```
+
+use crate::doc::TransactionAcqError;
+use crate::types::text::TextEvent;
+use crate::types::{Delta, IndexOutOfBounds};
+use crate::{
+    Any, Assoc, Doc, IndexedSequence, Observable, OffsetKind, Origin, Out, ReadTxn, StickyIndex,
+    Subscription, Text, TextRef, Transact, TransactionMut,
+};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+
+/// A single edit of an editor buffer: replacement of `delete` elements starting at `index` with
+/// `insert` string.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BufferEdit {
+    /// Position, at which an edit starts.
+    pub index: u32,
+    /// Number of removed elements.
+    pub delete: u32,
+    /// Inserted string.
+    pub insert: String,
+}
+
+impl BufferEdit {
+    /// Creates an edit inserting a `chunk` at a given `index`.
+    pub fn insert<S: Into<String>>(index: u32, chunk: S) -> Self {
+        BufferEdit {
+            index,
+            delete: 0,
+            insert: chunk.into(),
+        }
+    }
+
+    /// Creates an edit removing `len` elements starting at a given `index`.
+    pub fn delete(index: u32, len: u32) -> Self {
+        BufferEdit {
+            index,
+            delete: len,
+            insert: String::new(),
+        }
+    }
+}
+
+/// Selection within an editor buffer. For collapsed selections (ie. a caret) `anchor` is equal
+/// to `head`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Selection {
+    /// An end of a selection which stays in place when a selection is extended.
+    pub anchor: u32,
+    /// An end of a selection which moves when a selection is extended.
+    pub head: u32,
+}
+
+/// Changes, which must be applied to an editor buffer to reflect changes made on a bound text by
+/// other parties.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BufferChange {
+    /// List of edits, which should be applied in order. Positions of every edit take into account
+    /// all edits preceding it.
+    pub edits: Vec<BufferEdit>,
+    /// Selection remapped to reflect changes, if it has been set with [TextBinding::set_selection].
+    pub selection: Option<Selection>,
+}
+
+/// Error returned by [TextBinding] operations.
+#[derive(Debug, Error)]
+pub enum BindingError {
+    /// Read-write transaction could not be created.
+    #[error(transparent)]
+    Transaction(#[from] TransactionAcqError),
+    /// Buffer edit didn't fit within bounds of a bound text. This usually means that a buffer and
+    /// a text have diverged.
+    #[error(transparent)]
+    OutOfBounds(#[from] IndexOutOfBounds),
+}
+
+type StickySelection = Option<(StickyIndex, StickyIndex)>;
+
+static NEXT_BINDING_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Keeps an editor buffer and a [TextRef] in sync:
+///
+/// - edits made in a buffer should be reported with [TextBinding::edit], which applies them to
+///   a text,
+/// - changes made on a text by anyone else (ie. remote peers or an [UndoManager]) are passed to
+///   a callback provided on binding creation as [BufferChange], which should be applied to
+///   a buffer.
+///
+/// Buffer edits are applied using a transaction with a unique [TextBinding::origin], so they are
+/// not echoed back to a buffer. Selection set with [TextBinding::set_selection] is tracked using
+/// [StickyIndex]es, so that it's remapped on concurrent changes.
+///
+/// Buffer should be initialized with a content of a bound text before binding is created.
+/// Dropping a binding unsubscribes it from text changes.
+///
+/// [UndoManager]: crate::UndoManager
+pub struct TextBinding {
+    doc: Doc,
+    text: TextRef,
+    origin: Origin,
+    selection: Arc<Mutex<StickySelection>>,
+    _subscription: Subscription,
+}
+
+impl TextBinding {
+    /// Binds a given `text` of a `doc` to an editor buffer. Changes made on a text by other
+    /// parties are passed to `apply` callback.
+    #[cfg(feature = "sync")]
+    pub fn new<F>(doc: &Doc, text: TextRef, apply: F) -> Self
+    where
+        F: Fn(&BufferChange) + Send + Sync + 'static,
+    {
+        let origin = Self::next_origin();
+        let selection = Arc::new(Mutex::new(None));
+        let subscription = {
+            let origin = origin.clone();
+            let selection = selection.clone();
+            text.observe(move |txn, event| {
+                if let Some(change) = Self::buffer_change(txn, event, &origin, &selection) {
+                    apply(&change);
+                }
+            })
+        };
+        TextBinding {
+            doc: doc.clone(),
+            text,
+            origin,
+            selection,
+            _subscription: subscription,
+        }
+    }
+
+    /// Binds a given `text` of a `doc` to an editor buffer. Changes made on a text by other
+    /// parties are passed to `apply` callback.
+    #[cfg(not(feature = "sync"))]
+    pub fn new<F>(doc: &Doc, text: TextRef, apply: F) -> Self
+    where
+        F: Fn(&BufferChange) + 'static,
+    {
+        let origin = Self::next_origin();
+        let selection = Arc::new(Mutex::new(None));
+        let subscription = {
+            let origin = origin.clone();
+            let selection = selection.clone();
+            text.observe(move |txn, event| {
+                if let Some(change) = Self::buffer_change(txn, event, &origin, &selection) {
+                    apply(&change);
+                }
+            })
+        };
+        TextBinding {
+            doc: doc.clone(),
+            text,
+            origin,
+            selection,
+            _subscription: subscription,
+        }
+    }
+
+    fn next_origin() -> Origin {
+        let id = NEXT_BINDING_ID.fetch_add(1, Ordering::Relaxed);
+        Origin::from(format!("text-binding-{}", id))
+    }
+
+    /// Returns a bound text.
+    pub fn text(&self) -> &TextRef {
+        &self.text
+    }
+
+    /// Returns an origin of transactions used to apply buffer edits. It can be used ie. to include
+    /// buffer edits in the scope of an [UndoManager](crate::UndoManager).
+    pub fn origin(&self) -> &Origin {
+        &self.origin
+    }
+
+    /// Applies edits made in an editor buffer to a bound text within a single transaction. Edits
+    /// are applied in order, so positions of every edit should take into account all edits
+    /// preceding it.
+    ///
+    /// # Errors
+    ///
+    /// Returns [BindingError::OutOfBounds] if any of the edits doesn't fit within a text. In such
+    /// case no edits are applied.
+    pub fn edit(&self, edits: &[BufferEdit]) -> Result<(), BindingError> {
+        let mut txn = self.doc.try_transact_mut_with(self.origin.clone())?;
+        let kind = txn.doc().options().offset_kind;
+        let mut len = self.text.len(&txn);
+        for edit in edits {
+            IndexOutOfBounds::check_range(edit.index, edit.delete, len)?;
+            len = len - edit.delete + str_len(&edit.insert, kind);
+        }
+        for edit in edits {
+            if edit.delete > 0 {
+                self.text.remove_range(&mut txn, edit.index, edit.delete);
+            }
+            if !edit.insert.is_empty() {
+                self.text.insert(&mut txn, edit.index, &edit.insert);
+            }
+        }
+        Ok(())
+    }
+
+    /// Sets a current selection of an editor buffer, so that it can be remapped when a text is
+    /// changed by other parties. Passing `None` stops selection tracking.
+    ///
+    /// # Errors
+    ///
+    /// Returns [BindingError::OutOfBounds] if selection doesn't fit within a text.
+    pub fn set_selection(&self, selection: Option<Selection>) -> Result<(), BindingError> {
+        let sticky = match selection {
+            None => None,
+            Some(selection) => {
+                let mut txn = self.doc.try_transact_mut_with(self.origin.clone())?;
+                let len = self.text.len(&txn);
+                IndexOutOfBounds::check(selection.anchor.max(selection.head), len)?;
+                let sticky = |txn: &mut TransactionMut, index| {
+                    self.text
+                        .sticky_index(txn, index, Assoc::After)
+                        .or_else(|| self.text.sticky_index(txn, index, Assoc::Before))
+                        .expect("index has been checked to fit within a text")
+                };
+                Some((
+                    sticky(&mut txn, selection.anchor),
+                    sticky(&mut txn, selection.head),
+                ))
+            }
+        };
+        *self.selection.lock().unwrap() = sticky;
+        Ok(())
+    }
+
+    /// Returns a current selection remapped to reflect all changes made on a text since it has
+    /// been set with [TextBinding::set_selection].
+    pub fn selection<T: ReadTxn>(&self, txn: &T) -> Option<Selection> {
+        resolve_selection(txn, &self.selection.lock().unwrap())
+    }
+
+    fn buffer_change(
+        txn: &TransactionMut,
+        event: &TextEvent,
+        origin: &Origin,
+        selection: &Mutex<StickySelection>,
+    ) -> Option<BufferChange> {
+        if txn.origin() == Some(origin) {
+            // buffer edits are already present in a buffer
+            return None;
+        }
+        let kind = txn.doc().options().offset_kind;
+        let mut edits: Vec<BufferEdit> = Vec::new();
+        let mut index = 0;
+        for delta in event.delta(txn) {
+            match delta {
+                Delta::Retain(len, _) => index += len,
+                Delta::Deleted(len) => match edits.last_mut() {
+                    Some(last) if last.index + str_len(&last.insert, kind) == index => {
+                        last.delete += len
+                    }
+                    _ => edits.push(BufferEdit::delete(index, *len)),
+                },
+                Delta::Inserted(value, _) => {
+                    let chunk = match value {
+                        Out::Any(Any::String(s)) => s.to_string(),
+                        _ => '\u{FFFC}'.to_string(),
+                    };
+                    let len = str_len(&chunk, kind);
+                    match edits.last_mut() {
+                        Some(last) if last.index + str_len(&last.insert, kind) == index => {
+                            last.insert.push_str(&chunk)
+                        }
+                        _ => edits.push(BufferEdit::insert(index, chunk)),
+                    }
+                    index += len;
+                }
+            }
+        }
+        let selection = resolve_selection(txn, &selection.lock().unwrap());
+        Some(BufferChange { edits, selection })
+    }
+}
+
+fn resolve_selection<T: ReadTxn>(txn: &T, selection: &StickySelection) -> Option<Selection> {
+    let (anchor, head) = selection.as_ref()?;
+    Some(Selection {
+        anchor: anchor.get_offset(txn)?.index,
+        head: head.get_offset(txn)?.index,
+    })
+}
+
+fn str_len(s: &str, kind: OffsetKind) -> u32 {
+    match kind {
+        OffsetKind::Bytes => s.len() as u32,
+        OffsetKind::Utf16 => s.encode_utf16().count() as u32,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::binding::{BindingError, BufferChange, BufferEdit, Selection, TextBinding};
+    use crate::updates::decoder::Decode;
+    use crate::{Doc, GetString, ReadTxn, Text, Transact, Update};
+    use assert_matches2::assert_matches;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn text_binding_reconciliation() {
+        let local = Doc::with_client_id(1);
+        let remote = Doc::with_client_id(2);
+        let text = local.get_or_insert_text("text");
+        let remote_text = remote.get_or_insert_text("text");
+
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let binding = {
+            let changes = changes.clone();
+            TextBinding::new(&local, text.clone(), move |change: &BufferChange| {
+                changes.lock().unwrap().push(change.clone())
+            })
+        };
+        binding
+            .edit(&[
+                BufferEdit::insert(0, "hello world"),
+                BufferEdit::delete(0, 1),
+            ])
+            .unwrap();
+        binding.edit(&[BufferEdit::insert(0, "H")]).unwrap();
+        assert_eq!(text.get_string(&local.transact()), "Hello world");
+        // buffer edits are never echoed back
+        assert!(changes.lock().unwrap().is_empty());
+        assert_matches!(
+            binding.edit(&[BufferEdit::delete(5, 100)]),
+            Err(BindingError::OutOfBounds(_))
+        );
+
+        // caret placed before "world"
+        binding
+            .set_selection(Some(Selection { anchor: 6, head: 6 }))
+            .unwrap();
+
+        let sync = |from: &Doc, to: &Doc| {
+            let sv = to.transact().state_vector();
+            let update = from.transact().encode_state_as_update_v1(&sv);
+            to.transact_mut()
+                .apply_update(Update::decode_v1(&update).unwrap());
+        };
+        sync(&local, &remote);
+        {
+            let mut txn = remote.transact_mut();
+            remote_text.remove_range(&mut txn, 0, 5);
+            remote_text.insert(&mut txn, 0, "Goodbye,");
+        }
+        sync(&remote, &local);
+
+        let changes = changes.lock().unwrap();
+        assert_eq!(
+            changes.as_slice(),
+            &[BufferChange {
+                edits: vec![BufferEdit {
+                    index: 0,
+                    delete: 5,
+                    insert: "Goodbye,".into()
+                }],
+                selection: Some(Selection { anchor: 9, head: 9 }),
+            }]
+        );
+        assert_eq!(
+            binding.selection(&local.transact()),
+            Some(Selection { anchor: 9, head: 9 })
+        );
+        assert_eq!(
+            text.get_string(&local.transact()),
+            remote_text.get_string(&remote.transact())
+        );
+    }
+}
@@ -3,6 +3,7 @@ use crate::doc::{DocAddr, OffsetKind};
 use crate::encoding::read::Error;
 use crate::gc::GCCollector;
 use crate::moving::Move;
+use crate::sequence::SequenceItem;
 use crate::slice::{BlockSlice, GCSlice, ItemSlice};
 use crate::store::Store;
 use crate::transaction::TransactionMut;
@@ -387,8 +388,7 @@ impl ItemPtr {
             }
         }
 
-        let next_clock = txn.store.get_local_state();
-        let next_id = ID::new(txn.store.options.client_id, next_clock);
+        let next_id = txn.store.next_local_id();
         let mut redone_item = Item::new(
             next_id,
             left,
@@ -522,6 +522,16 @@ impl ItemPtr {
             TypePtr::Unknown => return true,
         };
 
+        if let (Some(parent_ref), Some(sub)) = (parent, &this.parent_sub) {
+            // reuse a key of an existing entry, so that all blocks stored under the same key share
+            // a single string allocation instead of keeping their own copies
+            if let Some((key, _)) = parent_ref.map.get_key_value(sub) {
+                if !Arc::ptr_eq(key, sub) {
+                    this.parent_sub = Some(key.clone());
+                }
+            }
+        }
+
         let left: Option<&Item> = this.left.as_deref();
         let right: Option<&Item> = this.right.as_deref();
 
@@ -553,52 +563,87 @@ impl ItemPtr {
                     parent_ref.start
                 };
 
-                let mut left = this.left.clone();
-                let mut conflicting_items = HashSet::new();
-                let mut items_before_origin = HashSet::new();
-
-                // Let c in conflicting_items, b in items_before_origin
-                // ***{origin}bbbb{this}{c,b}{c,b}{o}***
-                // Note that conflicting_items is a subset of items_before_origin
-                while let Some(item) = o {
-                    if Some(item) == this.right {
-                        break;
+                if let Some(integrator) = store.sequence.clone() {
+                    let mut conflicts = Vec::new();
+                    while let Some(item) = o {
+                        if Some(item) == this.right {
+                            break;
+                        }
+                        conflicts.push(item);
+                        o = item.right;
                     }
-
-                    items_before_origin.insert(item);
-                    conflicting_items.insert(item);
-                    if this.origin == item.origin {
-                        // case 1
-                        if item.id.client < this.id.client {
-                            left = Some(item.clone());
-                            conflicting_items.clear();
-                        } else if this.right_origin == item.right_origin {
-                            // `self` and `item` are conflicting and point to the same integration
-                            // points. The id decides which item comes first. Since `self` is to
-                            // the left of `item`, we can break here.
+                    let candidates: Vec<SequenceItem> = conflicts
+                        .iter()
+                        .map(|item| SequenceItem::from(item.deref()))
+                        .collect();
+                    let item = SequenceItem::from(&*this);
+                    let position = match &this.parent_sub {
+                        Some(key) => {
+                            integrator.entry_position(&parent_ref, key, &item, &candidates)
+                        }
+                        None => integrator.position(&parent_ref, &item, &candidates),
+                    };
+                    if position > conflicts.len() {
+                        // don't leave the store half-integrated because of a faulty integrator
+                        #[cfg(feature = "tracing")]
+                        tracing::warn!(
+                            position,
+                            conflicts = conflicts.len(),
+                            "sequence integrator position out of range"
+                        );
+                    }
+                    let position = position.min(conflicts.len());
+                    if position > 0 {
+                        this.left = Some(conflicts[position - 1]);
+                    }
+                } else {
+                    let mut left = this.left;
+                    let mut conflicting_items = HashSet::new();
+                    let mut items_before_origin = HashSet::new();
+
+                    // Let c in conflicting_items, b in items_before_origin
+                    // ***{origin}bbbb{this}{c,b}{c,b}{o}***
+                    // Note that conflicting_items is a subset of items_before_origin
+                    while let Some(item) = o {
+                        if Some(item) == this.right {
                             break;
                         }
-                    } else {
-                        if let Some(origin_ptr) = item
-                            .origin
-                            .as_ref()
-                            .and_then(|id| store.blocks.get_item(id))
-                        {
-                            if items_before_origin.contains(&origin_ptr) {
-                                if !conflicting_items.contains(&origin_ptr) {
-                                    left = Some(item.clone());
-                                    conflicting_items.clear();
+
+                        items_before_origin.insert(item);
+                        conflicting_items.insert(item);
+                        if this.origin == item.origin {
+                            // case 1
+                            if item.id.client < this.id.client {
+                                left = Some(item);
+                                conflicting_items.clear();
+                            } else if this.right_origin == item.right_origin {
+                                // `self` and `item` are conflicting and point to the same
+                                // integration points. The id decides which item comes first.
+                                // Since `self` is to the left of `item`, we can break here.
+                                break;
+                            }
+                        } else {
+                            if let Some(origin_ptr) = item
+                                .origin
+                                .as_ref()
+                                .and_then(|id| store.blocks.get_item(id))
+                            {
+                                if items_before_origin.contains(&origin_ptr) {
+                                    if !conflicting_items.contains(&origin_ptr) {
+                                        left = Some(item);
+                                        conflicting_items.clear();
+                                    }
+                                } else {
+                                    break;
                                 }
                             } else {
                                 break;
                             }
-                        } else {
-                            break;
                         }
+                        o = item.right;
                     }
-                    o = item.right.clone();
+                    this.left = left;
                 }
-                this.left = left;
             }
 
             if this.parent_sub.is_none() {
@@ -659,13 +704,17 @@ impl ItemPtr {
             }
 
             // adjust length of parent
-            if this.parent_sub.is_none() && !this.is_deleted() {
-                if this.is_countable() {
-                    // adjust length of parent
+            if !this.is_deleted() {
+                if this.parent_sub.is_some() {
+                    // overridden entries are deleted as part of this integration
+                    parent_ref.map_len += 1;
+                } else if this.is_countable() {
                     parent_ref.block_len += this.len;
                     parent_ref.content_len += this.content_len(encoding);
                 }
-                #[cfg(feature = "weak")]
+            }
+            #[cfg(feature = "weak")]
+            if this.parent_sub.is_none() && !this.is_deleted() {
                 match (this.left, this.right) {
                     (Some(l), Some(r)) if l.info.is_linked() || r.info.is_linked() => {
                         crate::types::weak::join_linked_range(self_ptr, txn)
@@ -704,6 +753,9 @@ impl ItemPtr {
             match &mut this.content {
                 ItemContent::Deleted(len) => {
                     txn.delete_set.insert(this.id, *len);
+                    if this.parent_sub.is_some() && !this.is_deleted() {
+                        parent_ref.map_len -= 1;
+                    }
                     this.mark_as_deleted();
                 }
                 ItemContent::Move(m) => m.integrate_block(txn, self_ptr),
@@ -837,7 +889,7 @@ impl Eq for ItemPtr {}
 
 impl PartialEq for ItemPtr {
     fn eq(&self, other: &Self) -> bool {
-        // BlockPtr.pivot may differ, but logically it doesn't affect block equality
+        // items are compared by their logical ID, their pivots within a block list may differ
         self.id() == other.id()
     }
 }
@@ -1172,10 +1224,10 @@ impl BlockRange {
         next
     }
 
-    pub(crate) fn integrate(&mut self, pivot: u32) -> bool {
-        if pivot > 0 {
-            self.id.clock += pivot;
-            self.len -= pivot;
+    pub(crate) fn integrate(&mut self, offset: u32) -> bool {
+        if offset > 0 {
+            self.id.clock += offset;
+            self.len -= offset;
         }
 
         false
@@ -1332,8 +1384,9 @@ impl Item {
                         ItemContent::Type(branch) => {
                             TypePtr::Branch(BranchPtr::from(branch.as_ref()))
                         }
-                        ItemContent::Deleted(_) => TypePtr::Unknown,
-                        _ => panic!("Defect: parent points to a block which is not a shared type"),
+                        // parent pointing to a block which is not a shared type can only come
+                        // from a malformed update: treat it like a deleted parent
+                        _ => TypePtr::Unknown,
                     }
                 } else {
                     TypePtr::Unknown
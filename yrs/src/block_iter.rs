@@ -4,6 +4,7 @@ use crate::moving::{Move, StickyIndex};
 use crate::transaction::{ReadTxn, TransactionMut};
 use crate::types::TypePtr;
 use crate::{Assoc, Out, ID};
+use std::sync::Arc;
 
 /// Struct used for iterating over the sequence of item's values with respect to a potential
 /// [Move] markers that may change their order.
@@ -20,6 +21,22 @@ pub(crate) struct BlockIter {
     reached_end: bool,
 }
 
+/// Cached position of a block within a sequence component of its parent branch. See
+/// [BlockIter::seek].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct SearchMarker {
+    /// ID of the first element of a marked block.
+    id: ID,
+    /// Index of the first element of a marked block.
+    index: u32,
+}
+
+impl SearchMarker {
+    fn new(id: ID, index: u32) -> Self {
+        SearchMarker { id, index }
+    }
+}
+
 impl BlockIter {
     pub fn new(branch: BranchPtr) -> Self {
         let next_item = branch.start;
@@ -96,6 +113,42 @@ impl BlockIter {
         false
     }
 
+    /// Moves a newly created iterator forward to a given `index`. If a [SearchMarker] left by
+    /// a previous lookup is placed at or before that index, iteration starts from the marked block
+    /// instead of the beginning of a sequence. Afterwards the marker is set to a current position.
+    pub fn seek<T: ReadTxn>(&mut self, txn: &T, index: u32) -> bool {
+        if let Some(marker) = self.branch.marker.load().as_deref() {
+            if marker.index <= index {
+                let item = txn.store().blocks.get_item(&marker.id).filter(|item| {
+                    item.id == marker.id
+                        && !item.is_deleted()
+                        && item.moved.is_none()
+                        && item.parent.as_branch() == Some(&self.branch)
+                });
+                if item.is_some() {
+                    self.index = marker.index;
+                    self.next_item = item;
+                    self.reached_end = false;
+                }
+            }
+        }
+        if !self.try_forward(txn, index - self.index) {
+            return false;
+        }
+        if self.curr_move.is_none() && !self.reached_end {
+            if let Some(item) = self.next_item {
+                // items inside of moved ranges are not reachable from the outside
+                if item.moved.is_none() && !item.is_deleted() && item.is_countable() {
+                    let marker = SearchMarker::new(item.id, self.index - self.rel);
+                    if self.branch.marker.load().as_deref() != Some(&marker) {
+                        self.branch.marker.store(Some(Arc::new(marker)));
+                    }
+                }
+            }
+        }
+        true
+    }
+
     pub fn forward<T: ReadTxn>(&mut self, txn: &T, len: u32) {
         if !self.try_forward(txn, len) {
             panic!("Length exceeded")
@@ -464,12 +517,7 @@ impl BlockIter {
     ) -> Option<ItemPtr> {
         self.reduce_moves(txn);
         self.split_rel(txn);
-        let id = {
-            let store = txn.store();
-            let client_id = store.options.client_id;
-            let clock = store.blocks.get_clock(&client_id);
-            ID::new(client_id, clock)
-        };
+        let id = txn.store().next_local_id();
         let parent = TypePtr::Branch(self.branch);
         let right = self.right();
         let left = self.left();
@@ -10,6 +10,35 @@ use std::hash::BuildHasherDefault;
 use std::ops::{Index, IndexMut};
 use std::vec::Vec;
 
+/// Physical position of a block within a [ClientBlockList].
+///
+/// Unlike block clocks - which are logical and never change for a given piece of inserted
+/// content - pivots are shifted every time a block is split, squashed or inserted into a list.
+/// Having a distinct type for them prevents using one in place of the other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub(crate) struct Pivot(usize);
+
+impl Pivot {
+    /// Pivot of the first block in a list.
+    pub const FIRST: Pivot = Pivot(0);
+
+    /// Returns a raw position of a block within its list.
+    pub(crate) const fn index(self) -> usize {
+        self.0
+    }
+
+    /// Returns pivot of the right neighbour of a current block.
+    pub(crate) const fn next(self) -> Self {
+        Pivot(self.0 + 1)
+    }
+
+    /// Returns pivot of the left neighbour of a current block or `None` if current block is
+    /// the first one.
+    pub(crate) fn prev(self) -> Option<Self> {
+        Some(Pivot(self.0.checked_sub(1)?))
+    }
+}
+
 /// A resizable list of blocks inserted by a single client.
 #[derive(PartialEq, Default)]
 pub(crate) struct ClientBlockList {
@@ -56,30 +85,44 @@ impl ClientBlockList {
         }
     }
 
-    pub(crate) fn get(&self, index: usize) -> Option<&BlockCell> {
-        self.list.get(index)
+    pub(crate) fn get(&self, pivot: Pivot) -> Option<&BlockCell> {
+        self.list.get(pivot.0)
+    }
+
+    pub(crate) fn get_mut(&mut self, pivot: Pivot) -> Option<&mut BlockCell> {
+        self.list.get_mut(pivot.0)
+    }
+
+    /// Returns pivot of the last block in this list or `None` if list is empty.
+    pub(crate) fn last_pivot(&self) -> Option<Pivot> {
+        Some(Pivot(self.list.len().checked_sub(1)?))
     }
 
     /// Given a block's identifier clock value, return an offset under which this block could be
     /// found using binary search algorithm, or a index under which this block should be inserted.
-    pub(crate) fn find_pivot(&self, clock: u32) -> Option<usize> {
+    pub(crate) fn find_pivot(&self, clock: u32) -> Option<Pivot> {
         let mut left = 0;
-        let mut right = self.list.len() - 1;
-        let mut block = &self[right];
+        let mut right = self.list.len().checked_sub(1)?;
+        let mut block = &self.list[right];
         let (mut start, mut end) = block.clock_range();
         if start == clock {
             // a common case is to just append a block at the end, so check first if we can do that
-            Some(right)
+            Some(Pivot(right))
+        } else if clock > end {
+            // clock is past the last block, it may come from a malformed update
+            None
         } else {
             let mut mid = ((clock / end) * right as u32) as usize;
             while left <= right {
-                block = &self[mid];
+                block = &self.list[mid];
                 (start, end) = block.clock_range();
                 if start <= clock {
                     if clock <= end {
-                        return Some(mid);
+                        return Some(Pivot(mid));
                     }
                     left = mid + 1;
+                } else if mid == 0 {
+                    break;
                 } else {
                     right = mid - 1;
                 }
@@ -109,10 +152,10 @@ impl ClientBlockList {
         self.list.push(cell);
     }
 
-    /// Inserts a new block at a given `index` position within this block list. This method may
-    /// panic if `index` is greater than a length of the list.
-    pub(crate) fn insert(&mut self, index: usize, cell: BlockCell) {
-        self.list.insert(index, cell);
+    /// Inserts a new block at a given `pivot` position within this block list. This method may
+    /// panic if `pivot` is greater than a length of the list.
+    pub(crate) fn insert(&mut self, pivot: Pivot, cell: BlockCell) {
+        self.list.insert(pivot.0, cell);
     }
 
     /// Returns a number of blocks stored within this list.
@@ -124,12 +167,13 @@ impl ClientBlockList {
         ClientBlockListIter(self.list.iter())
     }
 
-    /// Attempts to squash block at a given `index` with a corresponding block on its left side.
-    /// If this succeeds, block under a given `index` will be removed, and its contents will be
+    /// Attempts to squash block at a given `pivot` with a corresponding block on its left side.
+    /// If this succeeds, block under a given `pivot` will be removed, and its contents will be
     /// squashed into its left neighbor. In such case a squash result will be returned in order to
     /// later on rewire left/right neighbor changes that may have occurred as a result of squashing
     /// and block removal.
-    pub(crate) fn squash_left(&mut self, index: usize) {
+    pub(crate) fn squash_left(&mut self, pivot: Pivot) {
+        let index = pivot.0;
         let (l, r) = self.list.split_at_mut(index);
         let left = &mut l[index - 1];
         let right = &mut r[0];
@@ -159,17 +203,17 @@ impl ClientBlockList {
     }
 }
 
-impl Index<usize> for ClientBlockList {
+impl Index<Pivot> for ClientBlockList {
     type Output = BlockCell;
 
-    fn index(&self, index: usize) -> &Self::Output {
-        &self.list[index]
+    fn index(&self, pivot: Pivot) -> &Self::Output {
+        &self.list[pivot.0]
     }
 }
 
-impl IndexMut<usize> for ClientBlockList {
-    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
-        &mut self.list[index]
+impl IndexMut<Pivot> for ClientBlockList {
+    fn index_mut(&mut self, pivot: Pivot) -> &mut Self::Output {
+        &mut self.list[pivot.0]
     }
 }
 
@@ -350,10 +394,10 @@ impl BlockStore {
     ) -> Option<ItemPtr> {
         let id = block.id().clone();
         let blocks = self.clients.get_mut(&id.client)?;
-        let index = blocks.find_pivot(id.clock)?;
+        let pivot = blocks.find_pivot(id.clock)?;
         let mut right = block.splice(offset, encoding)?;
         let right_ptr = ItemPtr::from(&mut right);
-        blocks.insert(index + 1, right.into());
+        blocks.insert(pivot.next(), right.into());
 
         Some(right_ptr)
     }
@@ -1,4 +1,5 @@
 use crate::block::{BlockCell, Item, ItemContent, ItemPosition, ItemPtr, Prelim};
+use crate::block_iter::SearchMarker;
 use crate::types::array::ArrayEvent;
 use crate::types::map::MapEvent;
 use crate::types::text::TextEvent;
@@ -10,6 +11,7 @@ use crate::{
     ArrayRef, Doc, MapRef, Observer, Origin, Out, ReadTxn, Subscription, TextRef, TransactionMut,
     WriteTxn, XmlElementRef, XmlFragmentRef, XmlTextRef, ID,
 };
+use arc_swap::ArcSwapOption;
 use serde::{Deserialize, Serialize};
 use std::borrow::Borrow;
 use std::collections::{HashMap, HashSet, VecDeque};
@@ -183,6 +185,16 @@ pub struct Branch {
     ///   text data structure.
     pub(crate) start: Option<ItemPtr>,
 
+    /// An ID of the block, which was last appended at the end of a sequence component of this
+    /// branch. It's only a hint used to speed up appends - block it points to may no longer be the
+    /// last one (or may have been garbage collected), so it must be verified before use.
+    pub(crate) tail: Option<ID>,
+
+    /// Position of a block visited by the most recent indexed lookup, used to speed up subsequent
+    /// lookups placed at or after it. It's reset whenever the sequence component of this branch
+    /// changes.
+    pub(crate) marker: ArcSwapOption<SearchMarker>,
+
     /// A map component of this branch node, used by some of the specialized complex types
     /// including:
     ///
@@ -206,6 +218,10 @@ pub struct Branch {
 
     pub content_len: u32,
 
+    /// A number of live (non-deleted) entries of a map component of a current branch node. It's
+    /// updated incrementally whenever map entries are integrated or deleted.
+    pub(crate) map_len: u32,
+
     /// An identifier of an underlying complex data type (eg. is it an Array or a Map).
     pub(crate) type_ref: TypeRef,
 
@@ -246,9 +262,12 @@ impl Branch {
     pub fn new(type_ref: TypeRef) -> Arc<Self> {
         Arc::new(Self {
             start: None,
+            tail: None,
+            marker: ArcSwapOption::default(),
             map: HashMap::default(),
             block_len: 0,
             content_len: 0,
+            map_len: 0,
             item: None,
             name: None,
             type_ref,
@@ -1,16 +1,24 @@
+use crate::access::AccessRequest;
 use crate::block::{ClientID, ItemContent, ItemPtr, Prelim};
 use crate::branch::BranchPtr;
 use crate::encoding::read::Error;
 use crate::event::{SubdocsEvent, TransactionCleanupEvent, UpdateEvent};
+use crate::export::ExportFormat;
+use crate::import::ImportError;
+use crate::metrics::MetricsSink;
+use crate::quota::SizeDelta;
+use crate::sequence::SequenceIntegrator;
 use crate::store::{Store, StoreRef};
+#[cfg(debug_assertions)]
+use crate::transaction::ActiveTxn;
 use crate::transaction::{Origin, Transaction, TransactionMut};
 use crate::types::{RootRef, ToJson};
 use crate::updates::decoder::{Decode, Decoder};
 use crate::updates::encoder::{Encode, Encoder};
 use crate::utils::OptionExt;
 use crate::{
-    uuid_v4, uuid_v4_from, ArrayRef, BranchID, MapRef, Out, ReadTxn, TextRef, Uuid, WriteTxn,
-    XmlFragmentRef,
+    uuid_v4, uuid_v4_from, ArrayRef, BranchID, MapRef, Out, ReadTxn, TextRef, Update, Uuid,
+    WriteTxn, XmlFragmentRef,
 };
 use crate::{Any, Subscription};
 use atomic_refcell::{AtomicRefCell, BorrowError, BorrowMutError};
@@ -112,6 +120,26 @@ impl Doc {
         }
     }
 
+    /// Creates a new document containing a text root type called `name`, initialized with a given
+    /// `text`. Content is inserted within a single transaction as a single block, see
+    /// [crate::import].
+    pub fn from_text<N: AsRef<str>>(name: N, text: &str) -> Self {
+        let doc = Doc::new();
+        crate::import::import_text(&mut doc.transact_mut(), name.as_ref(), text);
+        doc
+    }
+
+    /// Creates a new document using a given JSON object, where every entry becomes a root type:
+    /// objects become maps, arrays become arrays and strings become texts. Nested objects and
+    /// arrays become nested maps and arrays. Content is inserted within a single transaction using
+    /// as few blocks as possible, see [crate::import]. The same conversion is used by
+    /// [crate::encoding::serde::deserialize_doc].
+    pub fn from_json(value: &Any) -> Result<Self, ImportError> {
+        let doc = Doc::new();
+        crate::import::import_json(&mut doc.transact_mut(), value)?;
+        Ok(doc)
+    }
+
     pub(crate) fn subdoc(parent: ItemPtr, options: Options) -> Self {
         let mut store = Store::new(options);
         store.parent = Some(parent);
@@ -154,7 +182,7 @@ impl Doc {
     /// is another transaction in process, it will panic. It's advised to define all root shared
     /// types during the document creation.
     pub fn get_or_insert_text<N: Into<Arc<str>>>(&self, name: N) -> TextRef {
-        TextRef::root(name).get_or_create(&mut self.transact_mut())
+        TextRef::root(name).get_or_create(&mut self.transact_mut_unchecked(None))
     }
 
     /// Returns a [MapRef] data structure stored under a given `name`. Maps are used to store key-value
@@ -175,7 +203,7 @@ impl Doc {
     /// is another transaction in process, it will panic. It's advised to define all root shared
     /// types during the document creation.
     pub fn get_or_insert_map<N: Into<Arc<str>>>(&self, name: N) -> MapRef {
-        MapRef::root(name).get_or_create(&mut self.transact_mut())
+        MapRef::root(name).get_or_create(&mut self.transact_mut_unchecked(None))
     }
 
     /// Returns an [ArrayRef] data structure stored under a given `name`. Array structures are used for
@@ -195,7 +223,7 @@ impl Doc {
     /// is another transaction in process, it will panic. It's advised to define all root shared
     /// types during the document creation.
     pub fn get_or_insert_array<N: Into<Arc<str>>>(&self, name: N) -> ArrayRef {
-        ArrayRef::root(name).get_or_create(&mut self.transact_mut())
+        ArrayRef::root(name).get_or_create(&mut self.transact_mut_unchecked(None))
     }
 
     /// Returns a [XmlFragmentRef] data structure stored under a given `name`. XML elements represent
@@ -217,7 +245,168 @@ impl Doc {
     /// is another transaction in process, it will panic. It's advised to define all root shared
     /// types during the document creation.
     pub fn get_or_insert_xml_fragment<N: Into<Arc<str>>>(&self, name: N) -> XmlFragmentRef {
-        XmlFragmentRef::root(name).get_or_create(&mut self.transact_mut())
+        XmlFragmentRef::root(name).get_or_create(&mut self.transact_mut_unchecked(None))
+    }
+
+    /// Configures a [MetricsSink], which will receive counters and gauges emitted by current
+    /// document, replacing the previous one if any. See [crate::metrics] for details.
+    #[cfg(feature = "sync")]
+    pub fn set_metrics_sink<M>(&self, sink: Arc<M>) -> Result<(), BorrowMutError>
+    where
+        M: MetricsSink + Send + Sync + 'static,
+    {
+        let mut r = self.store.try_borrow_mut()?;
+        r.metrics = Some(sink);
+        Ok(())
+    }
+
+    /// Configures a [MetricsSink], which will receive counters and gauges emitted by current
+    /// document, replacing the previous one if any. See [crate::metrics] for details.
+    #[cfg(not(feature = "sync"))]
+    pub fn set_metrics_sink<M>(&self, sink: Arc<M>) -> Result<(), BorrowMutError>
+    where
+        M: MetricsSink + 'static,
+    {
+        let mut r = self.store.try_borrow_mut()?;
+        r.metrics = Some(sink);
+        Ok(())
+    }
+
+    /// Removes a [MetricsSink] previously configured with [Doc::set_metrics_sink].
+    pub fn remove_metrics_sink(&self) -> Result<(), BorrowMutError> {
+        let mut r = self.store.try_borrow_mut()?;
+        r.metrics = None;
+        Ok(())
+    }
+
+    /// Configures a quota hook, replacing the previous one if any. Hook is called before
+    /// integrating every update, which adds new content to current document, with a [SizeDelta]
+    /// describing how much a document would grow. If the hook returns `false`, an update is
+    /// rejected as a whole: [TransactionMut::apply_update] drops it, while
+    /// [TransactionMut::apply_update_strict] returns [UpdateError::QuotaExceeded].
+    ///
+    /// Hook is not called for local changes made on current document.
+    ///
+    /// [UpdateError::QuotaExceeded]: crate::UpdateError::QuotaExceeded
+    #[cfg(feature = "sync")]
+    pub fn set_quota_hook<F>(&self, f: F) -> Result<(), BorrowMutError>
+    where
+        F: Fn(&SizeDelta) -> bool + Send + Sync + 'static,
+    {
+        let mut r = self.store.try_borrow_mut()?;
+        r.quota = Some(Box::new(f));
+        Ok(())
+    }
+
+    /// Configures a quota hook, replacing the previous one if any. Hook is called before
+    /// integrating every update, which adds new content to current document, with a [SizeDelta]
+    /// describing how much a document would grow. If the hook returns `false`, an update is
+    /// rejected as a whole: [TransactionMut::apply_update] drops it, while
+    /// [TransactionMut::apply_update_strict] returns [UpdateError::QuotaExceeded].
+    ///
+    /// Hook is not called for local changes made on current document.
+    ///
+    /// [UpdateError::QuotaExceeded]: crate::UpdateError::QuotaExceeded
+    #[cfg(not(feature = "sync"))]
+    pub fn set_quota_hook<F>(&self, f: F) -> Result<(), BorrowMutError>
+    where
+        F: Fn(&SizeDelta) -> bool + 'static,
+    {
+        let mut r = self.store.try_borrow_mut()?;
+        r.quota = Some(Box::new(f));
+        Ok(())
+    }
+
+    /// Removes a quota hook previously configured with [Doc::set_quota_hook].
+    pub fn remove_quota_hook(&self) -> Result<(), BorrowMutError> {
+        let mut r = self.store.try_borrow_mut()?;
+        r.quota = None;
+        Ok(())
+    }
+
+    /// Configures an access policy, replacing the previous one if any. Policy is consulted for
+    /// every change of remote updates with an [AccessRequest] describing a root type (and
+    /// a top-level map key) it touches. If the policy returns `false`, [TransactionMut::apply_update]
+    /// strips a denied change: inserted blocks are integrated as garbage collected and deletions
+    /// are skipped, while [TransactionMut::apply_update_strict] rejects an update as a whole with
+    /// [UpdateError::AccessDenied]. See [crate::access] for details.
+    ///
+    /// Policy is not consulted for local changes made on current document.
+    ///
+    /// [AccessRequest]: crate::access::AccessRequest
+    /// [UpdateError::AccessDenied]: crate::UpdateError::AccessDenied
+    #[cfg(feature = "sync")]
+    pub fn set_access_policy<F>(&self, f: F) -> Result<(), BorrowMutError>
+    where
+        F: Fn(&AccessRequest) -> bool + Send + Sync + 'static,
+    {
+        let mut r = self.store.try_borrow_mut()?;
+        r.access = Some(Box::new(f));
+        Ok(())
+    }
+
+    /// Configures an access policy, replacing the previous one if any. Policy is consulted for
+    /// every change of remote updates with an [AccessRequest] describing a root type (and
+    /// a top-level map key) it touches. If the policy returns `false`, [TransactionMut::apply_update]
+    /// strips a denied change: inserted blocks are integrated as garbage collected and deletions
+    /// are skipped, while [TransactionMut::apply_update_strict] rejects an update as a whole with
+    /// [UpdateError::AccessDenied]. See [crate::access] for details.
+    ///
+    /// Policy is not consulted for local changes made on current document.
+    ///
+    /// [AccessRequest]: crate::access::AccessRequest
+    /// [UpdateError::AccessDenied]: crate::UpdateError::AccessDenied
+    #[cfg(not(feature = "sync"))]
+    pub fn set_access_policy<F>(&self, f: F) -> Result<(), BorrowMutError>
+    where
+        F: Fn(&AccessRequest) -> bool + 'static,
+    {
+        let mut r = self.store.try_borrow_mut()?;
+        r.access = Some(Box::new(f));
+        Ok(())
+    }
+
+    /// Removes an access policy previously configured with [Doc::set_access_policy].
+    pub fn remove_access_policy(&self) -> Result<(), BorrowMutError> {
+        let mut r = self.store.try_borrow_mut()?;
+        r.access = None;
+        Ok(())
+    }
+
+    /// Configures a [SequenceIntegrator] used to order elements inserted concurrently into
+    /// sequence types of this document, replacing the previous one if any. See
+    /// [crate::sequence] for details.
+    #[cfg(feature = "sync")]
+    pub fn set_sequence_integrator<S>(&self, integrator: Arc<S>) -> Result<(), BorrowMutError>
+    where
+        S: SequenceIntegrator + Send + Sync + 'static,
+    {
+        let mut r = self.store.try_borrow_mut()?;
+        r.sequence = Some(integrator);
+        Ok(())
+    }
+
+    /// Configures a [SequenceIntegrator] used to order elements inserted concurrently into
+    /// sequence types of this document, replacing the previous one if any. See
+    /// [crate::sequence] for details.
+    #[cfg(not(feature = "sync"))]
+    pub fn set_sequence_integrator<S>(&self, integrator: Arc<S>) -> Result<(), BorrowMutError>
+    where
+        S: SequenceIntegrator + 'static,
+    {
+        let mut r = self.store.try_borrow_mut()?;
+        r.sequence = Some(integrator);
+        Ok(())
+    }
+
+    /// Removes a [SequenceIntegrator] previously configured with
+    /// [Doc::set_sequence_integrator], restoring the default [Yata] algorithm.
+    ///
+    /// [Yata]: crate::sequence::Yata
+    pub fn remove_sequence_integrator(&self) -> Result<(), BorrowMutError> {
+        let mut r = self.store.try_borrow_mut()?;
+        r.sequence = None;
+        Ok(())
     }
 
     /// Subscribe callback function for any changes performed within transaction scope. These
@@ -626,7 +815,7 @@ impl Doc {
     where
         T: WriteTxn,
     {
-        let mut txn = self.transact_mut();
+        let mut txn = self.transact_mut_unchecked(None);
         if txn.store.is_subdoc() {
             if !txn.store.options.should_load {
                 parent_txn
@@ -644,7 +833,7 @@ impl Doc {
     where
         T: WriteTxn,
     {
-        let mut txn = self.transact_mut();
+        let mut txn = self.transact_mut_unchecked(None);
         let store = txn.store_mut();
         let subdocs: Vec<_> = store.subdocs.values().cloned().collect();
         for subdoc in subdocs {
@@ -703,6 +892,22 @@ impl Doc {
         Arc::ptr_eq(&a.store.0, &b.store.0)
     }
 
+    /// Exports all root types of this document into a single string using a given `format`, ie.
+    /// to publish read-only artifacts of a collaborative document. See [crate::export] for details.
+    ///
+    /// This method opens a new read-only transaction. Use [crate::export::export] to export
+    /// a document within an existing transaction.
+    pub fn export(&self, format: ExportFormat) -> String {
+        crate::export::export(&self.transact(), format)
+    }
+
+    /// Exports a complete state of this document as lib0 v1 encoded update with all of its
+    /// content anonymized, but its structure and history preserved. Such update can be shared
+    /// ie. for performance debugging. See [crate::export::anonymize_update_v1] for details.
+    pub fn export_anonymized(&self) -> Vec<u8> {
+        crate::export::export_anonymized(&self.transact())
+    }
+
     pub(crate) fn addr(&self) -> DocAddr {
         DocAddr::new(&self)
     }
@@ -784,6 +989,25 @@ pub struct Options {
     ///
     /// Default value: `true`.
     pub should_load: bool,
+    /// Makes a document an apply-only replica, which only accepts updates coming from remote
+    /// peers, ie. a server-side mirror, so that a replica can never author content under its own
+    /// client ID. Read-write transactions of such document are refused with
+    /// [TransactionAcqError::ApplyOnly] (or a panic in case of [Transact::transact_mut]), and
+    /// remote updates are applied with [Doc::apply_update] instead. Subdocuments of apply-only
+    /// replica are apply-only as well. This option is local and not shared with remote peers.
+    ///
+    /// Default value: `false`.
+    pub apply_only: bool,
+    /// Determines which one of the concurrent moves of the same element takes effect. Moves made
+    /// after observing a previous move of the same element always win, so this option only
+    /// matters for moves which are concurrent. It must be set to the same value on all peers,
+    /// otherwise they will not converge. Policy applies to all arrays of a document and its
+    /// subdocuments: it cannot be configured per shared type. It's encoded together with other
+    /// options of subdocuments, but it's not a part of document updates, so peers of the root
+    /// document must agree on it upfront.
+    ///
+    /// Default value: [MovePolicy::HigherIdWins].
+    pub move_policy: MovePolicy,
 }
 
 impl Options {
@@ -796,6 +1020,8 @@ impl Options {
             skip_gc: false,
             auto_load: false,
             should_load: true,
+            apply_only: false,
+            move_policy: MovePolicy::HigherIdWins,
         }
     }
 
@@ -808,9 +1034,24 @@ impl Options {
             skip_gc: false,
             auto_load: false,
             should_load: true,
+            apply_only: false,
+            move_policy: MovePolicy::HigherIdWins,
         }
     }
 
+    /// Creates a new set of options with `client_id` and `guid` generated from a provided `seed`.
+    /// The same seed always produces the same identifiers, which makes it possible to replay
+    /// multi-peer simulation tests.
+    pub fn with_seed(seed: u64) -> Self {
+        Self::with_rng(&mut fastrand::Rng::with_seed(seed))
+    }
+
+    fn with_rng(rng: &mut fastrand::Rng) -> Self {
+        let client_id: u32 = rng.u32(0..u32::MAX);
+        let uuid = uuid_v4_from(rng);
+        Self::with_guid_and_client_id(uuid, client_id as ClientID)
+    }
+
     fn as_any(&self) -> Any {
         let mut m = HashMap::new();
         m.insert("gc".to_owned(), (!self.skip_gc).into());
@@ -824,16 +1065,17 @@ impl Options {
         m.insert("encoding".to_owned(), Any::BigInt(encoding));
         m.insert("autoLoad".to_owned(), self.auto_load.into());
         m.insert("shouldLoad".to_owned(), self.should_load.into());
+        if self.move_policy != MovePolicy::default() {
+            // Yjs doesn't have this option, so it's only written when it differs from Yjs behaviour
+            m.insert("movePolicy".to_owned(), self.move_policy.as_str().into());
+        }
         Any::from(m)
     }
 }
 
 impl Default for Options {
     fn default() -> Self {
-        let mut rng = fastrand::Rng::new();
-        let client_id: u32 = rng.u32(0..u32::MAX);
-        let uuid = uuid_v4_from(&mut rng);
-        Self::with_guid_and_client_id(uuid, client_id as ClientID)
+        Self::with_rng(&mut fastrand::Rng::new())
     }
 }
 
@@ -862,6 +1104,11 @@ impl Decode for Options {
                     }
                     ("encoding", Any::BigInt(1)) => options.offset_kind = OffsetKind::Bytes,
                     ("encoding", _) => options.offset_kind = OffsetKind::Utf16,
+                    ("movePolicy", Any::String(policy)) => {
+                        if let Some(policy) = MovePolicy::from_str(policy) {
+                            options.move_policy = policy;
+                        }
+                    }
                     _ => { /* do nothing */ }
                 }
             }
@@ -871,6 +1118,37 @@ impl Decode for Options {
     }
 }
 
+/// Conflict resolution policy of concurrent moves of the same [ArrayRef] element. See
+/// [Options::move_policy].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+///
+/// Concurrent moves are ordered by their [ID](crate::ID)s (compared by client ID first, then by clock), not
+/// by the time they were made, since peers don't share a common clock.
+pub enum MovePolicy {
+    /// Move with a higher ID wins. This is the same behaviour as the one used by Yjs.
+    #[default]
+    HigherIdWins,
+    /// Move with a lower ID wins.
+    LowerIdWins,
+}
+
+impl MovePolicy {
+    fn as_str(&self) -> &'static str {
+        match self {
+            MovePolicy::HigherIdWins => "higherIdWins",
+            MovePolicy::LowerIdWins => "lowerIdWins",
+        }
+    }
+
+    fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "higherIdWins" => Some(MovePolicy::HigherIdWins),
+            "lowerIdWins" => Some(MovePolicy::LowerIdWins),
+            _ => None,
+        }
+    }
+}
+
 /// Determines how string length and offsets of [Text]/[XmlText] are being determined.
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -891,6 +1169,7 @@ pub trait Transact {
     /// While it's possible to have multiple read-only transactions active at the same time,
     /// this method will return a [TransactionAcqError::SharedAcqFailed] error whenever called
     /// while a read-write transaction (see: [Self::try_transact_mut]) is active at the same time.
+    #[track_caller]
     fn try_transact(&self) -> Result<Transaction, TransactionAcqError>;
 
     /// Creates and returns a read-write capable transaction. This transaction can be used to
@@ -902,6 +1181,7 @@ pub trait Transact {
     /// Only one read-write transaction can be active at the same time. If any other transaction -
     /// be it a read-write or read-only one - is active at the same time, this method will return
     /// a [TransactionAcqError::ExclusiveAcqFailed] error.
+    #[track_caller]
     fn try_transact_mut(&self) -> Result<TransactionMut, TransactionAcqError>;
 
     /// Creates and returns a read-write capable transaction with an `origin` classifier attached.
@@ -916,6 +1196,7 @@ pub trait Transact {
     /// Only one read-write transaction can be active at the same time. If any other transaction -
     /// be it a read-write or read-only one - is active at the same time, this method will return
     /// a [TransactionAcqError::ExclusiveAcqFailed] error.
+    #[track_caller]
     fn try_transact_mut_with<T>(&self, origin: T) -> Result<TransactionMut, TransactionAcqError>
     where
         T: Into<Origin>;
@@ -931,6 +1212,7 @@ pub trait Transact {
     ///
     /// Only one read-write transaction can be active at the same time. If any other transaction -
     /// be it a read-write or read-only one - is active at the same time, this method will panic.
+    #[track_caller]
     fn transact_mut_with<T>(&self, origin: T) -> TransactionMut
     where
         T: Into<Origin>,
@@ -945,6 +1227,7 @@ pub trait Transact {
     /// While it's possible to have multiple read-only transactions active at the same time,
     /// this method will panic whenever called while a read-write transaction
     /// (see: [Self::transact_mut]) is active at the same time.
+    #[track_caller]
     fn transact(&self) -> Transaction {
         self.try_transact()
             .expect("there's another active read-write transaction at the moment")
@@ -958,6 +1241,7 @@ pub trait Transact {
     ///
     /// Only one read-write transaction can be active at the same time. If any other transaction -
     /// be it a read-write or read-only one - is active at the same time, this method will panic.
+    #[track_caller]
     fn transact_mut(&self) -> TransactionMut {
         self.try_transact_mut()
             .expect("there's another active transaction at the moment")
@@ -965,25 +1249,124 @@ pub trait Transact {
 }
 
 impl Transact for Doc {
+    #[track_caller]
     fn try_transact(&self) -> Result<Transaction, TransactionAcqError> {
         Ok(Transaction::new(self.store.try_borrow()?))
     }
 
+    #[track_caller]
     fn try_transact_mut(&self) -> Result<TransactionMut, TransactionAcqError> {
-        let store = self.store.try_borrow_mut()?;
-        Ok(TransactionMut::new(self.clone(), store, None))
+        self.try_transact_local(None)
     }
 
+    #[track_caller]
     fn try_transact_mut_with<T>(&self, origin: T) -> Result<TransactionMut, TransactionAcqError>
+    where
+        T: Into<Origin>,
+    {
+        self.try_transact_local(Some(origin.into()))
+    }
+
+    #[track_caller]
+    fn transact_mut_with<T>(&self, origin: T) -> TransactionMut<'_>
+    where
+        T: Into<Origin>,
+    {
+        match self.try_transact_mut_with(origin) {
+            Ok(txn) => txn,
+            Err(e) => self.acq_failed(e),
+        }
+    }
+
+    #[track_caller]
+    fn transact(&self) -> Transaction<'_> {
+        match self.try_transact() {
+            Ok(txn) => txn,
+            Err(e) => self.acq_failed(e),
+        }
+    }
+
+    #[track_caller]
+    fn transact_mut(&self) -> TransactionMut<'_> {
+        match self.try_transact_mut() {
+            Ok(txn) => txn,
+            Err(e) => self.acq_failed(e),
+        }
+    }
+}
+
+impl Doc {
+    /// Creates a read-write transaction used to make local changes, which is refused for
+    /// apply-only documents (see [Options::apply_only]).
+    #[track_caller]
+    fn try_transact_local(
+        &self,
+        origin: Option<Origin>,
+    ) -> Result<TransactionMut<'_>, TransactionAcqError> {
+        let store = self.store.try_borrow_mut()?;
+        if store.options.apply_only {
+            return Err(TransactionAcqError::ApplyOnly);
+        }
+        Ok(TransactionMut::new(self.clone(), store, origin))
+    }
+
+    /// Creates a read-write transaction regardless of [Options::apply_only]. It's used internally
+    /// by operations, which never author any blocks, ie. applying remote updates.
+    #[track_caller]
+    pub(crate) fn transact_mut_unchecked(&self, origin: Option<Origin>) -> TransactionMut<'_> {
+        match self.store.try_borrow_mut() {
+            Ok(store) => TransactionMut::new(self.clone(), store, origin),
+            Err(e) => self.acq_failed(e.into()),
+        }
+    }
+
+    /// Applies an update received from a remote peer within a new read-write transaction,
+    /// see [TransactionMut::apply_update]. Unlike [Transact::transact_mut], it works for
+    /// apply-only documents as well (see [Options::apply_only]).
+    ///
+    /// # Errors
+    ///
+    /// Returns [TransactionAcqError::ExclusiveAcqFailed] if any other transaction is active at
+    /// the moment.
+    pub fn apply_update(&self, update: Update) -> Result<(), TransactionAcqError> {
+        let store = self.store.try_borrow_mut()?;
+        TransactionMut::new(self.clone(), store, None).apply_update(update);
+        Ok(())
+    }
+
+    /// Applies an update received from a remote peer within a new read-write transaction with an
+    /// `origin` classifier attached. See [Doc::apply_update].
+    pub fn apply_update_with<T>(&self, origin: T, update: Update) -> Result<(), TransactionAcqError>
     where
         T: Into<Origin>,
     {
         let store = self.store.try_borrow_mut()?;
-        Ok(TransactionMut::new(
-            self.clone(),
-            store,
-            Some(origin.into()),
-        ))
+        TransactionMut::new(self.clone(), store, Some(origin.into())).apply_update(update);
+        Ok(())
+    }
+
+    /// Panics with a message describing why a transaction couldn't be acquired. In debug builds
+    /// it also points to the place, where a conflicting transaction has been started.
+    #[cold]
+    #[track_caller]
+    fn acq_failed(&self, e: TransactionAcqError) -> ! {
+        let msg = match e {
+            TransactionAcqError::SharedAcqFailed => {
+                "there's another active read-write transaction at the moment"
+            }
+            TransactionAcqError::ApplyOnly => {
+                "cannot make local changes to an apply-only document replica"
+            }
+            _ => "there's another active transaction at the moment",
+        };
+        #[cfg(debug_assertions)]
+        if let Some(location) = ActiveTxn::location(self.store.0.as_ptr()) {
+            panic!(
+                "{}: transaction already active, started at {}",
+                msg, location
+            );
+        }
+        panic!("{}", msg)
     }
 }
 
@@ -995,6 +1378,8 @@ pub enum TransactionAcqError {
     ExclusiveAcqFailed,
     #[error("All references to a parent document containing this structure has been dropped.")]
     DocumentDropped,
+    #[error("Document is an apply-only replica. Use Doc::apply_update to apply remote updates.")]
+    ApplyOnly,
 }
 
 impl From<BorrowError> for TransactionAcqError {
@@ -1046,8 +1431,8 @@ mod test {
     use crate::updates::decoder::Decode;
     use crate::updates::encoder::{Encode, Encoder, EncoderV1};
     use crate::{
-        any, Any, Array, ArrayPrelim, ArrayRef, Doc, GetString, Map, MapPrelim, MapRef, OffsetKind,
-        Options, StateVector, Subscription, Text, TextRef, Transact, Uuid, WriteTxn,
+        any, Any, Array, ArrayPrelim, ArrayRef, Doc, GetString, Map, MapPrelim, MapRef, MovePolicy,
+        OffsetKind, Options, StateVector, Subscription, Text, TextRef, Transact, Uuid, WriteTxn,
         XmlElementPrelim, XmlFragment, XmlFragmentRef, XmlTextPrelim, XmlTextRef,
     };
     use std::collections::BTreeSet;
@@ -1057,6 +1442,206 @@ mod test {
     use std::sync::atomic::{AtomicU32, Ordering};
     use std::sync::{Arc, Mutex};
 
+    #[test]
+    fn options_with_seed() {
+        let a = Options::with_seed(42);
+        let b = Options::with_seed(42);
+        assert_eq!(a, b);
+
+        let c = Options::with_seed(43);
+        assert_ne!(a.client_id, c.client_id);
+        assert_ne!(a.guid, c.guid);
+    }
+
+    #[test]
+    fn options_encode_move_policy() {
+        let options = Options::with_client_id(1);
+        let decoded = Options::decode_v1(&options.encode_v1()).unwrap();
+        assert_eq!(decoded.move_policy, MovePolicy::HigherIdWins);
+        // default policy is not written, so that encoded options stay the same as in Yjs
+        assert!(!options.encode_v1().windows(10).any(|w| w == b"movePolicy"));
+
+        let options = Options {
+            move_policy: MovePolicy::LowerIdWins,
+            ..Options::with_client_id(1)
+        };
+        let decoded = Options::decode_v1(&options.encode_v1()).unwrap();
+        assert_eq!(decoded.move_policy, MovePolicy::LowerIdWins);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn nested_transaction_reports_location() {
+        let doc = Doc::new();
+        let (txn, line) = (doc.transact_mut(), line!());
+        let err = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            doc.transact();
+        }))
+        .unwrap_err();
+        let msg = err.downcast::<String>().unwrap();
+        let location = format!("started at {}:{}:", file!(), line);
+        assert!(msg.contains(&location), "unexpected message: {}", msg);
+        drop(txn);
+
+        // location is no longer tracked once transaction is dropped
+        let (_read, line) = (doc.transact(), line!());
+        let err = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            doc.transact_mut();
+        }))
+        .unwrap_err();
+        let msg = err.downcast::<String>().unwrap();
+        let location = format!("started at {}:{}:", file!(), line);
+        assert!(msg.contains(&location), "unexpected message: {}", msg);
+    }
+
+    #[test]
+    fn apply_only_replica() {
+        use crate::doc::TransactionAcqError;
+
+        let source = Doc::with_client_id(1);
+        let text_source = source.get_or_insert_text("text");
+        let update = {
+            let mut txn = source.transact_mut();
+            text_source.insert(&mut txn, 0, "hello");
+            txn.encode_update_v1()
+        };
+
+        let replica = Doc::with_options(Options {
+            apply_only: true,
+            ..Options::with_client_id(2)
+        });
+        let text = replica.get_or_insert_text("text");
+        replica
+            .apply_update(Update::decode_v1(&update).unwrap())
+            .unwrap();
+        assert_eq!(text.get_string(&replica.transact()), "hello");
+
+        // local changes are refused upfront
+        assert!(matches!(
+            replica.try_transact_mut(),
+            Err(TransactionAcqError::ApplyOnly)
+        ));
+        assert!(matches!(
+            replica.try_transact_mut_with("local"),
+            Err(TransactionAcqError::ApplyOnly)
+        ));
+        let err = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            text.insert(&mut replica.transact_mut(), 0, "a");
+        }))
+        .unwrap_err();
+        let msg = err.downcast::<String>().unwrap();
+        assert!(msg.contains("apply-only"), "unexpected message: {}", msg);
+
+        let update = {
+            let mut txn = source.transact_mut();
+            text_source.push(&mut txn, " world");
+            txn.encode_update_v1()
+        };
+        replica
+            .apply_update_with("remote", Update::decode_v1(&update).unwrap())
+            .unwrap();
+        assert_eq!(text.get_string(&replica.transact()), "hello world");
+        assert_eq!(replica.transact().state_vector().get(&2), 0);
+    }
+
+    #[test]
+    fn concurrent_readers_and_single_writer() {
+        use crate::doc::TransactionAcqError;
+        use std::sync::Barrier;
+
+        let doc = Doc::with_client_id(1);
+        let txt = doc.get_or_insert_text("text");
+        let barrier = Arc::new(Barrier::new(3));
+
+        // two readers hold their transactions at the same time on different threads
+        let readers: Vec<_> = (0..2)
+            .map(|_| {
+                let doc = doc.clone();
+                let barrier = barrier.clone();
+                std::thread::spawn(move || {
+                    let txn = doc.try_transact().unwrap();
+                    barrier.wait(); // all readers acquired
+                    barrier.wait(); // writer attempt finished
+                    drop(txn);
+                })
+            })
+            .collect();
+
+        barrier.wait();
+        assert!(matches!(
+            doc.try_transact_mut(),
+            Err(TransactionAcqError::ExclusiveAcqFailed)
+        ));
+        barrier.wait();
+        for reader in readers {
+            reader.join().unwrap();
+        }
+
+        // changes of a dropped writer are visible to readers on other threads
+        txt.insert(&mut doc.try_transact_mut().unwrap(), 0, "abc");
+        let d = doc.clone();
+        let content = std::thread::spawn(move || {
+            let txt = d.get_or_insert_text("text");
+            let txn = d.transact();
+            txt.get_string(&txn)
+        })
+        .join()
+        .unwrap();
+        assert_eq!(content, "abc");
+    }
+
+    #[test]
+    fn transaction_acquire_release_stress() {
+        use std::sync::atomic::AtomicI32;
+
+        const THREADS: usize = 4;
+        const ROUNDS: usize = 500;
+
+        let doc = Doc::with_client_id(1);
+        let txt = doc.get_or_insert_text("text");
+        // number of readers currently holding a transaction or -1 when writer holds it
+        let holders = Arc::new(AtomicI32::new(0));
+        let writes = Arc::new(AtomicU32::new(0));
+
+        let threads: Vec<_> = (0..THREADS)
+            .map(|i| {
+                let doc = doc.clone();
+                let txt = txt.clone();
+                let holders = holders.clone();
+                let writes = writes.clone();
+                std::thread::spawn(move || {
+                    for round in 0..ROUNDS {
+                        if (i + round) % 3 == 0 {
+                            if let Ok(mut txn) = doc.try_transact_mut() {
+                                let prev = holders.swap(-1, Ordering::SeqCst);
+                                assert_eq!(prev, 0, "writer acquired next to other transaction");
+                                txt.push(&mut txn, "a");
+                                writes.fetch_add(1, Ordering::SeqCst);
+                                std::thread::yield_now();
+                                holders.store(0, Ordering::SeqCst);
+                            }
+                        } else if let Ok(txn) = doc.try_transact() {
+                            let prev = holders.fetch_add(1, Ordering::SeqCst);
+                            assert!(prev >= 0, "reader acquired next to a writer");
+                            let len = txt.len(&txn);
+                            std::thread::yield_now();
+                            assert_eq!(txt.len(&txn), len);
+                            holders.fetch_sub(1, Ordering::SeqCst);
+                        }
+                    }
+                })
+            })
+            .collect();
+        for t in threads {
+            t.join().unwrap();
+        }
+
+        // every transaction was released and every successful write is visible
+        let txn = doc.try_transact_mut().unwrap();
+        assert!(writes.load(Ordering::SeqCst) > 0);
+        assert_eq!(txt.len(&txn), writes.load(Ordering::SeqCst));
+    }
+
     #[test]
     fn apply_update_basic_v1() {
         /* Result of calling following code:
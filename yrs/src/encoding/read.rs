@@ -1,4 +1,5 @@
 use crate::encoding::varint::{Signed, SignedVarInt, VarInt};
+use crate::update::LimitExceeded;
 use std::any::type_name;
 use std::collections::TryReserveError;
 use thiserror::Error;
@@ -23,6 +24,9 @@ pub enum Error {
     #[error("couldn't deserialize to target type of {0}")]
     TypeMismatch(&'static str),
 
+    #[error("decoded data exceeds configured limits: {0}")]
+    LimitExceeded(#[from] LimitExceeded),
+
     #[error("{0}")]
     Custom(String),
 }
@@ -81,6 +85,47 @@ impl<'a> Read for Cursor<'a> {
     }
 }
 
+/// Fails with [LimitExceeded::StringLength] if `len` bytes of a string or buffer would exceed
+/// `max_len`.
+#[inline]
+pub(crate) fn check_len(len: usize, max_len: usize) -> Result<(), Error> {
+    if len > max_len {
+        Err(Error::LimitExceeded(LimitExceeded::StringLength))
+    } else {
+        Ok(())
+    }
+}
+
+/// [Read] adapter, which refuses to read strings and buffers longer than `max_len` bytes.
+pub(crate) struct LimitedRead<'r, R> {
+    inner: &'r mut R,
+    max_len: usize,
+}
+
+impl<'r, R: Read> LimitedRead<'r, R> {
+    pub(crate) fn new(inner: &'r mut R, max_len: usize) -> Self {
+        LimitedRead { inner, max_len }
+    }
+}
+
+impl<'r, R: Read> Read for LimitedRead<'r, R> {
+    #[inline]
+    fn read_exact(&mut self, len: usize) -> Result<&[u8], Error> {
+        self.inner.read_exact(len)
+    }
+
+    #[inline]
+    fn read_u8(&mut self) -> Result<u8, Error> {
+        self.inner.read_u8()
+    }
+
+    fn read_buf(&mut self) -> Result<&[u8], Error> {
+        let len: u32 = self.inner.read_var()?;
+        check_len(len as usize, self.max_len)?;
+        self.inner.read_exact(len as usize)
+    }
+}
+
 pub trait Read: Sized {
     fn read_exact(&mut self, len: usize) -> Result<&[u8], Error>;
 
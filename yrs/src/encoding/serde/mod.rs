@@ -1,8 +1,10 @@
 mod de;
 mod ser;
+mod shared;
 
 pub use de::from_any;
 pub use ser::to_any;
+pub use shared::{deserialize_doc, Serializable, SerializableDoc};
 
 #[cfg(test)]
 mod test {
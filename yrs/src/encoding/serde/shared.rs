@@ -0,0 +1,219 @@
+use crate::any::Any;
+use crate::import::import_json;
+use crate::types::ToJson;
+use crate::{
+    Array, ArrayRef, Doc, GetString, Map, MapRef, Options, Out, ReadTxn, TextRef, Transact,
+};
+use serde::de::Error as _;
+use serde::ser::{SerializeMap, SerializeSeq};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Wrapper around a shared type and a transaction used to read it, which implements
+/// [Serialize]. Contents are serialized using the same rules as [ToJson]: texts and XML types are
+/// serialized as strings, while maps and arrays are serialized recursively without building an
+/// intermediate [Any] value.
+///
+/// ```rust
+/// use yrs::{Doc, Map, Transact};
+/// use yrs::encoding::serde::Serializable;
+///
+/// let doc = Doc::new();
+/// let map = doc.get_or_insert_map("map");
+/// let mut txn = doc.transact_mut();
+/// map.insert(&mut txn, "key", "value");
+///
+/// let json = serde_json::to_string(&Serializable::new(&map, &txn)).unwrap();
+/// assert_eq!(json, r#"{"key":"value"}"#);
+/// ```
+pub struct Serializable<'a, T, R> {
+    value: &'a T,
+    txn: &'a R,
+}
+
+impl<'a, T, R: ReadTxn> Serializable<'a, T, R> {
+    pub fn new(value: &'a T, txn: &'a R) -> Self {
+        Serializable { value, txn }
+    }
+}
+
+impl<'a, R: ReadTxn> Serialize for Serializable<'a, Out, R> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self.value {
+            Out::Any(any) => any.serialize(serializer),
+            Out::YText(text) => Serializable::new(text, self.txn).serialize(serializer),
+            Out::YArray(array) => Serializable::new(array, self.txn).serialize(serializer),
+            Out::YMap(map) => Serializable::new(map, self.txn).serialize(serializer),
+            other => other.to_json(self.txn).serialize(serializer),
+        }
+    }
+}
+
+impl<'a, R: ReadTxn> Serialize for Serializable<'a, TextRef, R> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.value.get_string(self.txn))
+    }
+}
+
+impl<'a, R: ReadTxn> Serialize for Serializable<'a, ArrayRef, R> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let len = self.value.len(self.txn) as usize;
+        let mut seq = serializer.serialize_seq(Some(len))?;
+        for value in self.value.iter(self.txn) {
+            seq.serialize_element(&Serializable::new(&value, self.txn))?;
+        }
+        seq.end()
+    }
+}
+
+impl<'a, R: ReadTxn> Serialize for Serializable<'a, MapRef, R> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let len = self.value.len(self.txn) as usize;
+        let mut map = serializer.serialize_map(Some(len))?;
+        for (key, value) in self.value.iter(self.txn) {
+            map.serialize_entry(key, &Serializable::new(&value, self.txn))?;
+        }
+        map.end()
+    }
+}
+
+/// Wrapper around a transaction, which implements [Serialize] by serializing all root types of
+/// a transaction's document as a map of root type names to their contents (see [Serializable]).
+/// Entries are ordered by root type names.
+pub struct SerializableDoc<'a, R> {
+    txn: &'a R,
+}
+
+impl<'a, R: ReadTxn> SerializableDoc<'a, R> {
+    pub fn new(txn: &'a R) -> Self {
+        SerializableDoc { txn }
+    }
+}
+
+impl<'a, R: ReadTxn> Serialize for SerializableDoc<'a, R> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut roots: Vec<_> = self.txn.root_refs().collect();
+        roots.sort_by_key(|(name, _)| *name);
+        let mut map = serializer.serialize_map(Some(roots.len()))?;
+        for (name, value) in roots.iter() {
+            map.serialize_entry(name, &Serializable::new(value, self.txn))?;
+        }
+        map.end()
+    }
+}
+
+/// Builds a new document using contents produced by a given `deserializer`, which must provide
+/// a map of root type names to their contents - this is the format produced by
+/// [SerializableDoc]. Contents are imported the same way as by [Doc::from_json]: strings become
+/// [TextRef]s, sequences become [ArrayRef]s and maps become [MapRef]s. Any other root value is
+/// rejected with an error.
+///
+/// Nested maps and sequences are imported as nested [MapRef]s and [ArrayRef]s, so the structure
+/// of nested shared types survives the serialize-deserialize round trip. Nested texts and XML
+/// types are serialized as strings, so they are imported back as primitive string values.
+///
+/// ```rust
+/// use yrs::{GetString, Options, Transact};
+/// use yrs::encoding::serde::deserialize_doc;
+///
+/// let mut de = serde_json::Deserializer::from_str(r#"{"text":"hello","map":{"a":1}}"#);
+/// let doc = deserialize_doc(&mut de, Options::default()).unwrap();
+/// let text = doc.get_or_insert_text("text");
+/// assert_eq!(text.get_string(&doc.transact()), "hello");
+/// ```
+pub fn deserialize_doc<'de, D>(deserializer: D, options: Options) -> Result<Doc, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let roots = Any::deserialize(deserializer)?;
+    let doc = Doc::with_options(options);
+    import_json(&mut doc.transact_mut(), &roots).map_err(D::Error::custom)?;
+    Ok(doc)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::encoding::serde::{deserialize_doc, Serializable, SerializableDoc};
+    use crate::types::ToJson;
+    use crate::{
+        any, Array, ArrayPrelim, Doc, GetString, Map, MapPrelim, Options, Out, ReadTxn, Text,
+        Transact, WriteTxn,
+    };
+
+    #[test]
+    fn serialize_shared_types() {
+        let doc = Doc::with_client_id(1);
+        let mut txn = doc.transact_mut();
+        let text = txn.get_or_insert_text("text");
+        text.insert(&mut txn, 0, "hello");
+        let map = txn.get_or_insert_map("map");
+        map.insert(&mut txn, "a", 1);
+        let nested = map.insert(&mut txn, "nested", ArrayPrelim::from([true, false]));
+        nested.push_back(&mut txn, MapPrelim::from([("b", "c")]));
+        txn.get_or_insert_array("array")
+            .insert_range(&mut txn, 0, [any!(null), any!(1.5)]);
+
+        let json = serde_json::to_value(Serializable::new(&map, &txn)).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"a": 1, "nested": [true, false, {"b": "c"}]})
+        );
+
+        let json = serde_json::to_value(SerializableDoc::new(&txn)).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "array": [null, 1.5],
+                "map": {"a": 1, "nested": [true, false, {"b": "c"}]},
+                "text": "hello"
+            })
+        );
+
+        let json = serde_json::to_string(&SerializableDoc::new(&txn)).unwrap();
+
+        let mut de = serde_json::Deserializer::from_str(&json);
+        let copy = deserialize_doc(&mut de, Options::with_client_id(2)).unwrap();
+        let copy_txn = copy.transact();
+        assert_eq!(
+            copy_txn.get_text("text").unwrap().get_string(&copy_txn),
+            "hello"
+        );
+        assert_eq!(
+            copy_txn.get_map("map").unwrap().to_json(&copy_txn),
+            map.to_json(&txn)
+        );
+        assert_eq!(
+            copy_txn.get_array("array").unwrap().to_json(&copy_txn),
+            any!([null, 1.5])
+        );
+        // nested sequences and maps are imported as shared types, same as by Doc::from_json
+        let copy_map = copy_txn.get_map("map").unwrap();
+        match copy_map.get(&copy_txn, "nested") {
+            Some(Out::YArray(nested)) => match nested.get(&copy_txn, 2) {
+                Some(Out::YMap(_)) => {}
+                other => panic!("expected nested map, got {:?}", other),
+            },
+            other => panic!("expected nested array, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn deserialize_doc_rejects_primitive_roots() {
+        let mut de = serde_json::Deserializer::from_str(r#"{"a":1}"#);
+        assert!(deserialize_doc(&mut de, Options::default()).is_err());
+    }
+}
@@ -0,0 +1,214 @@
+//! End-to-end encryption of document updates. Updates are encrypted with a symmetric
+//! [DocumentKey] shared by all collaborators before they leave the process, and decrypted right
+//! before being applied, so servers relaying them never see document contents.
+//!
+//! Encrypted payloads use XChaCha20-Poly1305 with a random nonce generated for every update, and
+//! have the following layout: `[version: u8][nonce: 24 bytes][ciphertext with 16 bytes tag]`.
+//!
+//! Since relays cannot read encrypted updates, they also cannot merge them nor compute state
+//! vector differences. For use with y-sync protocol, see [EncryptedProtocol], which makes every
+//! sync step and update carry an encrypted payload. Awareness states are **not** encrypted.
+//!
+//! ```rust
+//! use yrs::encryption::DocumentKey;
+//! use yrs::{Doc, GetString, Text, Transact};
+//!
+//! let key = DocumentKey::generate();
+//! let local = Doc::new();
+//! let text = local.get_or_insert_text("text");
+//! let ciphertext = {
+//!     let mut txn = local.transact_mut();
+//!     text.insert(&mut txn, 0, "secret");
+//!     key.encrypt(&txn.encode_update_v1())
+//! };
+//!
+//! let remote = Doc::new();
+//! key.apply_update(&mut remote.transact_mut(), &ciphertext).unwrap();
+//! let text = remote.get_or_insert_text("text");
+//! assert_eq!(text.get_string(&remote.transact()), "secret");
+//! ```
+//!
+//! [EncryptedProtocol]: crate::sync::EncryptedProtocol
+
+use crate::encoding::read;
+use crate::updates::decoder::Decode;
+use crate::{TransactionMut, Update};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use thiserror::Error;
+
+const VERSION: u8 = 1;
+const NONCE_LEN: usize = 24;
+
+/// A symmetric 256-bit key used to encrypt and decrypt updates of a single document. It must be
+/// distributed among collaborators using a secure channel of choice.
+///
+/// Keys are compared in constant time and their bytes are overwritten with zeros once dropped.
+#[derive(Clone)]
+pub struct DocumentKey([u8; 32]);
+
+impl DocumentKey {
+    /// Generates a new random key using a cryptographically secure random number generator
+    /// provided by operating system.
+    pub fn generate() -> Self {
+        DocumentKey(XChaCha20Poly1305::generate_key(&mut OsRng).into())
+    }
+
+    /// Returns a key from its raw bytes.
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        DocumentKey(bytes)
+    }
+
+    /// Returns raw bytes of this key.
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// Encrypts a given payload, ie. lib0 encoded update or document state.
+    pub fn encrypt(&self, plaintext: &[u8]) -> Vec<u8> {
+        let cipher = XChaCha20Poly1305::new(&self.0.into());
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, plaintext)
+            .expect("XChaCha20-Poly1305 encryption is infallible for in-memory buffers");
+        let mut result = Vec::with_capacity(1 + NONCE_LEN + ciphertext.len());
+        result.push(VERSION);
+        result.extend_from_slice(&nonce);
+        result.extend_from_slice(&ciphertext);
+        result
+    }
+
+    /// Decrypts a payload produced by [DocumentKey::encrypt]. Fails if a payload has been
+    /// encrypted with a different key or was tampered with.
+    pub fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>, EncryptionError> {
+        match data.split_first() {
+            Some((&VERSION, rest)) if rest.len() >= NONCE_LEN => {
+                let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+                let cipher = XChaCha20Poly1305::new(&self.0.into());
+                cipher
+                    .decrypt(XNonce::from_slice(nonce), ciphertext)
+                    .map_err(|_| EncryptionError::Decryption)
+            }
+            Some((&VERSION, _)) | None => Err(EncryptionError::Malformed),
+            Some((&version, _)) => Err(EncryptionError::UnsupportedVersion(version)),
+        }
+    }
+
+    /// Decrypts a lib0 v1 encoded update produced by [DocumentKey::encrypt].
+    pub fn decrypt_update_v1(&self, data: &[u8]) -> Result<Update, EncryptionError> {
+        let plaintext = self.decrypt(data)?;
+        Ok(Update::decode_v1(&plaintext)?)
+    }
+
+    /// Decrypts a lib0 v1 encoded update produced by [DocumentKey::encrypt] and applies it
+    /// within a given transaction.
+    pub fn apply_update(
+        &self,
+        txn: &mut TransactionMut,
+        data: &[u8],
+    ) -> Result<(), EncryptionError> {
+        let update = self.decrypt_update_v1(data)?;
+        txn.apply_update(update);
+        Ok(())
+    }
+}
+
+impl PartialEq for DocumentKey {
+    fn eq(&self, other: &Self) -> bool {
+        // don't short-circuit on the first differing byte, so that comparison time doesn't
+        // depend on a length of a matching prefix
+        let diff = self
+            .0
+            .iter()
+            .zip(other.0.iter())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b));
+        std::hint::black_box(diff) == 0
+    }
+}
+
+impl Eq for DocumentKey {}
+
+impl Drop for DocumentKey {
+    fn drop(&mut self) {
+        for byte in self.0.iter_mut() {
+            // volatile writes are never optimized away, even though the memory is about to be freed
+            unsafe { std::ptr::write_volatile(byte, 0) };
+        }
+        std::sync::atomic::compiler_fence(std::sync::atomic::Ordering::SeqCst);
+    }
+}
+
+impl std::fmt::Debug for DocumentKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // never expose key material in logs
+        f.write_str("DocumentKey(..)")
+    }
+}
+
+/// Error returned when an encrypted update couldn't be decrypted.
+#[derive(Debug, Error)]
+pub enum EncryptionError {
+    /// Payload is too short to be an encrypted update.
+    #[error("malformed encrypted payload")]
+    Malformed,
+    /// Payload has been encrypted using an unknown format version.
+    #[error("unsupported encrypted payload version: {0}")]
+    UnsupportedVersion(u8),
+    /// Payload has been encrypted with a different key or has been tampered with.
+    #[error("failed to decrypt payload: wrong key or corrupted data")]
+    Decryption,
+    /// Decrypted payload is not a valid update.
+    #[error("failed to decode decrypted update: {0}")]
+    Decoding(#[from] read::Error),
+}
+
+#[cfg(test)]
+mod test {
+    use crate::encryption::{DocumentKey, EncryptionError};
+    use crate::{Doc, GetString, ReadTxn, StateVector, Text, Transact};
+    use assert_matches2::assert_matches;
+
+    #[test]
+    fn encrypted_updates_roundtrip() {
+        let key = DocumentKey::generate();
+        let d1 = Doc::with_client_id(1);
+        let text = d1.get_or_insert_text("text");
+        text.insert(&mut d1.transact_mut(), 0, "hello world");
+        let state = d1
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+
+        let ciphertext = key.encrypt(&state);
+        assert!(!ciphertext
+            .windows(b"hello".len())
+            .any(|w| w == b"hello".as_slice()));
+        // the same payload is never encrypted the same way twice
+        assert_ne!(key.encrypt(&state), ciphertext);
+
+        let d2 = Doc::with_client_id(2);
+        key.apply_update(&mut d2.transact_mut(), &ciphertext)
+            .unwrap();
+        let text = d2.get_or_insert_text("text");
+        assert_eq!(text.get_string(&d2.transact()), "hello world");
+
+        let other = DocumentKey::generate();
+        assert_matches!(other.decrypt(&ciphertext), Err(EncryptionError::Decryption));
+        let mut tampered = ciphertext.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert_matches!(key.decrypt(&tampered), Err(EncryptionError::Decryption));
+        assert_matches!(
+            key.decrypt(&ciphertext[..10]),
+            Err(EncryptionError::Malformed)
+        );
+        assert_matches!(
+            key.decrypt(&[2, 0, 0]),
+            Err(EncryptionError::UnsupportedVersion(2))
+        );
+        assert_eq!(
+            DocumentKey::from_bytes(*key.as_bytes())
+                .decrypt(&ciphertext)
+                .unwrap(),
+            state
+        );
+    }
+}
@@ -0,0 +1,490 @@
+//! Export of whole documents into flat, read-only representations, ie. for publishing artifacts of
+//! collaborative documents. See [Doc::export] and [export].
+//!
+//! Root types are exported in alphabetical order of their names. Root types which have not been
+//! defined locally (ie. document has been loaded from an update) have their types inferred from
+//! their content.
+//!
+//! Documents can also be exported as anonymized updates, which preserve their structure and
+//! history but not their contents, see [export_anonymized].
+//!
+//! [Doc::export]: crate::Doc::export
+
+use crate::block::{Item, ItemContent};
+use crate::branch::{Branch, BranchPtr};
+use crate::encoding::read;
+use crate::inspect::infer_type_ref;
+use crate::types::text::{Diff, YChange};
+use crate::types::{Attrs, ToJson, TypePtr, TypeRef};
+use crate::update::BlockCarrier;
+use crate::updates::decoder::Decode;
+use crate::updates::encoder::Encode;
+use crate::{
+    Any, ArrayRef, Doc, GetString, MapRef, Out, ReadTxn, StateVector, Text, TextRef, Update,
+    XmlElementRef, XmlFragment, XmlFragmentRef, XmlOut,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Format of a document produced by [export].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExportFormat {
+    /// Contents of all text and XML root types, separated by empty lines. Formatting attributes
+    /// are skipped. Maps and arrays are not included.
+    PlainText,
+    /// JSON object, where every root type is stored under its name, using the same conversion
+    /// rules as [ToJson]. Keys of all JSON objects are sorted, so that the same document always
+    /// produces the same output.
+    Json,
+    /// Markdown produced from text and XML root types, separated by empty lines. Maps and arrays
+    /// are not included.
+    ///
+    /// Text formatting attributes `bold`, `italic`, `strike`, `code` and `link` (either URL or an
+    /// object with `href` field) are converted into corresponding inline markdown. XML root types
+    /// are embedded as inline HTML produced by [GetString] implementation of XML types, which
+    /// markdown renderers display as is.
+    Markdown,
+}
+
+/// Exports all root types of a document visible to a given transaction using a given `format`.
+pub fn export<T: ReadTxn>(txn: &T, format: ExportFormat) -> String {
+    let roots = roots(txn);
+    match format {
+        ExportFormat::Json => {
+            let mut map = HashMap::new();
+            for (name, value) in roots {
+                map.insert(name.to_string(), value.to_json(txn));
+            }
+            serde_json::to_string(&SortedKeys(&Any::from(map)))
+                .expect("JSON values are always serializable")
+        }
+        ExportFormat::PlainText => {
+            let parts: Vec<_> = roots
+                .iter()
+                .filter_map(|(_, value)| match value {
+                    Out::YText(text) => Some(text.get_string(txn)),
+                    Out::YXmlFragment(xml) => Some(plain_children(txn, xml)),
+                    _ => None,
+                })
+                .collect();
+            parts.join("\n\n")
+        }
+        ExportFormat::Markdown => {
+            let parts: Vec<_> = roots
+                .iter()
+                .filter_map(|(_, value)| match value {
+                    Out::YText(text) => Some(inline(txn, text)),
+                    Out::YXmlFragment(xml) => Some(xml.get_string(txn)),
+                    _ => None,
+                })
+                .collect();
+            parts.join("\n\n")
+        }
+    }
+}
+
+/// Exports a complete state of a document visible to a given transaction as lib0 v1 encoded
+/// update, which has all of its content anonymized. See [anonymize_update_v1] for details.
+pub fn export_anonymized<T: ReadTxn>(txn: &T) -> Vec<u8> {
+    let update = txn.encode_state_as_update_v1(&StateVector::default());
+    let update = Update::decode_v1(&update).expect("document state is always a valid update");
+    anonymize(update).encode_v1()
+}
+
+/// Anonymizes lib0 v1 encoded `update`, so that it can be shared ie. for performance debugging
+/// without leaking document contents. Anonymized update has the same structure, block IDs, block
+/// lengths and delete set as the original one, so it can be applied the same way and produces
+/// the same number of blocks with the same history shape:
+///
+/// - every UTF-16 code unit of text chunks is replaced with `x` (new lines are kept),
+/// - strings nested in JSON-like values are replaced with `x` characters of the same length,
+///   numbers with zeros, booleans with `false` and binary buffers with zeroed buffers,
+/// - names of root types, keys of map entries, names of XML elements and attributes, names of
+///   formatting attributes and subdocument GUIDs are replaced with hashes. The same name always
+///   produces the same hash, so that entries of shared types are not merged.
+///
+/// Hashes are not salted, so short names could be guessed by brute force.
+pub fn anonymize_update_v1(update: &[u8]) -> Result<Vec<u8>, read::Error> {
+    Ok(anonymize(Update::decode_v1(update)?).encode_v1())
+}
+
+fn anonymize(mut update: Update) -> Update {
+    for blocks in update.blocks.clients.values_mut() {
+        for block in blocks.iter_mut() {
+            if let BlockCarrier::Item(item) = block {
+                anonymize_item(item);
+            }
+        }
+    }
+    update
+}
+
+fn anonymize_item(item: &mut Item) {
+    if let TypePtr::Named(name) = &item.parent {
+        item.parent = TypePtr::Named(hash_name(name));
+    }
+    if let Some(key) = &item.parent_sub {
+        item.parent_sub = Some(hash_name(key));
+    }
+    let content = match &item.content {
+        ItemContent::Any(values) => ItemContent::Any(values.iter().map(anonymize_any).collect()),
+        ItemContent::Binary(data) => ItemContent::Binary(vec![0; data.len()]),
+        ItemContent::JSON(values) => ItemContent::JSON(vec!["null".to_string(); values.len()]),
+        ItemContent::Embed(value) => ItemContent::Embed(anonymize_any(value)),
+        ItemContent::Format(key, value) => {
+            ItemContent::Format(hash_name(key), Box::new(anonymize_any(value)))
+        }
+        ItemContent::String(chunk) => {
+            let chunk: String = chunk
+                .as_str()
+                .encode_utf16()
+                .map(|unit| if unit == b'\n' as u16 { '\n' } else { 'x' })
+                .collect();
+            ItemContent::String(chunk.as_str().into())
+        }
+        ItemContent::Type(branch) => match &branch.type_ref {
+            TypeRef::XmlElement(name) => {
+                ItemContent::Type(Branch::new(TypeRef::XmlElement(hash_name(name))))
+            }
+            _ => return,
+        },
+        ItemContent::Doc(parent, doc) => {
+            let mut options = doc.options().clone();
+            options.guid = hash_name(&options.guid);
+            options.collection_id = options
+                .collection_id
+                .as_deref()
+                .map(|id| hash_name(id).to_string());
+            ItemContent::Doc(parent.clone(), Doc::with_options(options))
+        }
+        ItemContent::Deleted(_) | ItemContent::Move(_) => return,
+    };
+    item.content = content;
+}
+
+fn anonymize_any(value: &Any) -> Any {
+    match value {
+        Any::Null | Any::Undefined => value.clone(),
+        Any::Bool(_) => Any::Bool(false),
+        Any::Number(_) => Any::Number(0.0),
+        Any::BigInt(_) => Any::BigInt(0),
+        Any::String(s) => Any::from("x".repeat(s.chars().count())),
+        Any::Buffer(data) => Any::from(vec![0u8; data.len()]),
+        Any::Array(values) => Any::from(values.iter().map(anonymize_any).collect::<Vec<_>>()),
+        Any::Map(entries) => Any::from(
+            entries
+                .iter()
+                .map(|(key, value)| (hash_name(key).to_string(), anonymize_any(value)))
+                .collect::<HashMap<_, _>>(),
+        ),
+    }
+}
+
+/// 64-bit FNV-1a hash of a given name. Unlike [std::hash::DefaultHasher] it's guaranteed to be
+/// stable, so anonymized names are the same across processes and versions.
+fn hash_name(name: &str) -> Arc<str> {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in name.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}", hash).into()
+}
+
+fn roots<T: ReadTxn>(txn: &T) -> Vec<(Arc<str>, Out)> {
+    let mut roots: Vec<_> = txn
+        .root_refs()
+        .map(|(name, value)| {
+            let value = match value {
+                Out::UndefinedRef(branch) => resolve_undefined(branch),
+                other => other,
+            };
+            (Arc::<str>::from(name), value)
+        })
+        .collect();
+    roots.sort_by(|a, b| a.0.cmp(&b.0));
+    roots
+}
+
+fn resolve_undefined(branch: BranchPtr) -> Out {
+    match infer_type_ref(&branch) {
+        TypeRef::Text => Out::YText(TextRef::from(branch)),
+        TypeRef::Array => Out::YArray(ArrayRef::from(branch)),
+        TypeRef::Map => Out::YMap(MapRef::from(branch)),
+        TypeRef::XmlFragment => Out::YXmlFragment(XmlFragmentRef::from(branch)),
+        _ => Out::UndefinedRef(branch),
+    }
+}
+
+fn text_chunks<T: ReadTxn, X: Text>(txn: &T, text: &X) -> Vec<Diff<YChange>> {
+    text.diff(txn, YChange::identity)
+}
+
+fn plain_children<T: ReadTxn, X: XmlFragment>(txn: &T, node: &X) -> String {
+    let mut inline = true;
+    let parts: Vec<_> = node
+        .children(txn)
+        .map(|child| match child {
+            XmlOut::Text(text) => text_chunks(txn, &text)
+                .into_iter()
+                .filter_map(|chunk| match chunk.insert {
+                    Out::Any(Any::String(s)) => Some(s.to_string()),
+                    _ => None,
+                })
+                .collect(),
+            XmlOut::Element(e) if is_line_break(&e) => "\n".to_string(),
+            XmlOut::Element(e) => {
+                inline &= is_inline(&e);
+                plain_children(txn, &e)
+            }
+            XmlOut::Fragment(f) => {
+                inline = false;
+                plain_children(txn, &f)
+            }
+        })
+        .collect();
+    parts.join(if inline { "" } else { "\n" })
+}
+
+fn tag(e: &XmlElementRef) -> String {
+    e.tag().to_lowercase()
+}
+
+fn is_line_break(e: &XmlElementRef) -> bool {
+    matches!(tag(e).as_str(), "br" | "hard_break" | "hardbreak")
+}
+
+fn is_inline(e: &XmlElementRef) -> bool {
+    matches!(
+        tag(e).as_str(),
+        "a" | "b" | "strong" | "i" | "em" | "s" | "del" | "code" | "span"
+    )
+}
+
+/// Converts formatted text chunks into inline markdown.
+fn inline<T: ReadTxn, X: Text>(txn: &T, text: &X) -> String {
+    let mut result = String::new();
+    for chunk in text_chunks(txn, text) {
+        if let Out::Any(Any::String(s)) = &chunk.insert {
+            match chunk.attributes.as_deref() {
+                Some(attrs) => result.push_str(&format_inline(s, attrs)),
+                None => result.push_str(s),
+            }
+        }
+    }
+    result
+}
+
+fn format_inline(s: &str, attrs: &Attrs) -> String {
+    let is_set = |key: &str| match attrs.get(key) {
+        None | Some(Any::Null) | Some(Any::Undefined) | Some(Any::Bool(false)) => false,
+        Some(_) => true,
+    };
+    let mut s = s.to_string();
+    if is_set("code") {
+        s = format!("`{}`", s);
+    }
+    if is_set("bold") || is_set("strong") {
+        s = format!("**{}**", s);
+    }
+    if is_set("italic") || is_set("em") {
+        s = format!("_{}_", s);
+    }
+    if is_set("strike") {
+        s = format!("~~{}~~", s);
+    }
+    let href = match attrs.get("link") {
+        Some(Any::String(href)) => Some(href.clone()),
+        Some(Any::Map(link)) => match link.get("href") {
+            Some(Any::String(href)) => Some(href.clone()),
+            _ => None,
+        },
+        _ => None,
+    };
+    if let Some(href) = href {
+        s = format!("[{}]({})", s, href);
+    }
+    s
+}
+
+/// Serializes JSON values with keys of all objects sorted.
+struct SortedKeys<'a>(&'a Any);
+
+impl serde::Serialize for SortedKeys<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::{SerializeMap, SerializeSeq};
+        match self.0 {
+            Any::Array(values) => {
+                let mut seq = serializer.serialize_seq(Some(values.len()))?;
+                for value in values.iter() {
+                    seq.serialize_element(&SortedKeys(value))?;
+                }
+                seq.end()
+            }
+            Any::Map(entries) => {
+                let mut entries: Vec<_> = entries.iter().collect();
+                entries.sort_by(|a, b| a.0.cmp(b.0));
+                let mut map = serializer.serialize_map(Some(entries.len()))?;
+                for (key, value) in entries {
+                    map.serialize_entry(key, &SortedKeys(value))?;
+                }
+                map.end()
+            }
+            other => other.serialize(serializer),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::export::{hash_name, ExportFormat};
+    use crate::inspect::inspect_update_v1;
+    use crate::types::Attrs;
+    use crate::updates::decoder::Decode;
+    use crate::{
+        any, Any, Array, Doc, GetString, Map, Out, ReadTxn, StateVector, Text, Transact, Update,
+        WriteTxn, Xml, XmlElementPrelim, XmlFragment, XmlTextPrelim,
+    };
+    use std::sync::Arc;
+
+    #[test]
+    fn export_formats() {
+        let doc = Doc::with_client_id(1);
+        {
+            let mut txn = doc.transact_mut();
+            let title = txn.get_or_insert_text("title");
+            title.insert(&mut txn, 0, "Hello ");
+            let bold = Attrs::from([(Arc::from("bold"), Any::Bool(true))]);
+            title.insert_with_attributes(&mut txn, 6, "world", bold);
+
+            let map = txn.get_or_insert_map("meta");
+            map.insert(&mut txn, "version", 1);
+
+            let body = txn.get_or_insert_xml_fragment("body");
+            let h = body.push_back(&mut txn, XmlElementPrelim::empty("heading"));
+            h.insert_attribute(&mut txn, "level", "2");
+            h.push_back(&mut txn, XmlTextPrelim::new("Intro"));
+            let p = body.push_back(&mut txn, XmlElementPrelim::empty("paragraph"));
+            let t = p.push_back(&mut txn, XmlTextPrelim::new("see docs"));
+            let link = Attrs::from([(Arc::from("link"), any!({"href": "https://y.rs"}))]);
+            t.format(&mut txn, 4, 4, link);
+            let ul = body.push_back(&mut txn, XmlElementPrelim::empty("bullet_list"));
+            for item in ["one", "two"] {
+                let li = ul.push_back(&mut txn, XmlElementPrelim::empty("list_item"));
+                let p = li.push_back(&mut txn, XmlElementPrelim::empty("paragraph"));
+                p.push_back(&mut txn, XmlTextPrelim::new(item));
+            }
+            let array = txn.get_or_insert_array("tags");
+            array.push_back(&mut txn, "a");
+        }
+
+        // export from a remote replica, where root types are not defined
+        let remote = Doc::with_client_id(2);
+        let update = doc
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+        remote
+            .transact_mut()
+            .apply_update(Update::decode_v1(&update).unwrap());
+
+        for d in [&doc, &remote] {
+            assert_eq!(
+                d.export(ExportFormat::Markdown),
+                "<heading level=\"2\">Intro</heading><paragraph>see <link href=\"https://y.rs\">docs</link></paragraph><bullet_list><list_item><paragraph>one</paragraph></list_item><list_item><paragraph>two</paragraph></list_item></bullet_list>\n\nHello **world**"
+            );
+            assert_eq!(
+                d.export(ExportFormat::PlainText),
+                "Intro\nsee docs\none\ntwo\n\nHello world"
+            );
+            let json = Any::from_json(&d.export(ExportFormat::Json)).unwrap();
+            match json {
+                Any::Map(map) => {
+                    assert_eq!(map["meta"], any!({"version": 1}));
+                    assert_eq!(map["title"], Any::from("Hello world"));
+                    assert_eq!(map["tags"], any!(["a"]));
+                }
+                other => panic!("unexpected JSON: {}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn export_json_sorted_keys() {
+        let doc = Doc::with_client_id(1);
+        {
+            let mut txn = doc.transact_mut();
+            let title = txn.get_or_insert_text("title");
+            title.insert(&mut txn, 0, "hello");
+            let map = txn.get_or_insert_map("meta");
+            for key in ["zeta", "alpha", "mu", "beta"] {
+                map.insert(&mut txn, key, any!({"z": 1, "a": 2, "m": 3}));
+            }
+            let array = txn.get_or_insert_array("tags");
+            array.push_back(&mut txn, any!({"y": true, "b": false}));
+        }
+        let nested = r#"{"a":2,"m":3,"z":1}"#;
+        let expected = format!(
+            r#"{{"meta":{{"alpha":{0},"beta":{0},"mu":{0},"zeta":{0}}},"tags":[{{"b":false,"y":true}}],"title":"hello"}}"#,
+            nested
+        );
+        assert_eq!(doc.export(ExportFormat::Json), expected);
+    }
+
+    #[test]
+    fn export_anonymized() {
+        let doc = Doc::with_client_id(1);
+        {
+            let mut txn = doc.transact_mut();
+            let secret = txn.get_or_insert_text("secret");
+            secret.insert(&mut txn, 0, "hello\nworld 🌍");
+            secret.remove_range(&mut txn, 1, 2);
+            let bold = Attrs::from([(Arc::from("bold"), Any::Bool(true))]);
+            secret.format(&mut txn, 0, 3, bold);
+            let map = txn.get_or_insert_map("private");
+            map.insert(&mut txn, "password", any!({"value": "hunter2", "len": 7}));
+            map.insert(&mut txn, "password", "changed");
+            let array = txn.get_or_insert_array("list");
+            array.insert_range(&mut txn, 0, [1, 2, 3]);
+            let xml = txn.get_or_insert_xml_fragment("body");
+            let p = xml.push_back(&mut txn, XmlElementPrelim::empty("confidential"));
+            p.insert_attribute(&mut txn, "owner", "john");
+        }
+        let update = doc.export_anonymized();
+        for leaked in [
+            "hello",
+            "world",
+            "secret",
+            "password",
+            "hunter2",
+            "confidential",
+            "john",
+        ] {
+            assert!(
+                !update.windows(leaked.len()).any(|w| w == leaked.as_bytes()),
+                "anonymized update contains '{}'",
+                leaked
+            );
+        }
+        let expected = doc
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+        let expected = inspect_update_v1(&expected, 0).unwrap();
+        let actual = inspect_update_v1(&update, 0).unwrap();
+        assert_eq!(actual.clients, expected.clients);
+        assert_eq!(actual.delete_set, expected.delete_set);
+
+        let anonymized = Doc::with_client_id(2);
+        anonymized
+            .transact_mut()
+            .apply_update(Update::decode_v1(&update).unwrap());
+        let txn = anonymized.transact();
+        let name = hash_name("secret");
+        let text = txn.get_text(name.as_ref()).unwrap();
+        assert_eq!(text.get_string(&txn), "xxx\nxxxxxxxx");
+        let map = txn.get_map(hash_name("private").as_ref()).unwrap();
+        assert_eq!(
+            map.get(&txn, hash_name("password").as_ref()),
+            Some(Out::from("xxxxxxx"))
+        );
+    }
+}
@@ -9,8 +9,15 @@ pub(crate) struct GCCollector {
 
 impl GCCollector {
     pub fn collect(txn: &mut TransactionMut) {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("gc").entered();
         let mut gc = Self::default();
         gc.mark_all(txn);
+        #[cfg(feature = "tracing")]
+        tracing::trace!(
+            marked = gc.items.values().map(Vec::len).sum::<usize>(),
+            "blocks marked for collection"
+        );
         gc.collect_all_marked(txn);
     }
 
@@ -20,8 +27,7 @@ impl GCCollector {
                 for delete_item in range.iter().rev() {
                     let mut start = delete_item.start;
                     if let Some(mut i) = blocks.find_pivot(start) {
-                        while i < blocks.len() {
-                            let block = &mut blocks[i];
+                        while let Some(block) = blocks.get_mut(i) {
                             let len = block.len();
                             start += len;
                             if start > delete_item.end {
@@ -30,7 +36,7 @@ impl GCCollector {
                                 if let BlockCell::Block(item) = block {
                                     item.gc(self, false);
                                 }
-                                i += 1;
+                                i = i.next();
                             }
                         }
                     }
@@ -1,9 +1,10 @@
 use crate::block::{ClientID, ID};
-use crate::block_store::BlockStore;
+use crate::block_store::{BlockStore, Pivot};
 use crate::encoding::read::Error;
 use crate::iter::TxnIterator;
 use crate::slice::BlockSlice;
 use crate::store::Store;
+use crate::update::LimitExceeded;
 use crate::updates::decoder::{Decode, Decoder};
 use crate::updates::encoder::{Encode, Encoder};
 use crate::utils::client_hasher::ClientHasher;
@@ -27,7 +28,8 @@ impl Decode for Range<u32> {
     fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, Error> {
         let clock = decoder.read_ds_clock()?;
         let len = decoder.read_ds_len()?;
-        Ok(clock..(clock + len))
+        let end = clock.checked_add(len).ok_or(Error::UnexpectedValue)?;
+        Ok(clock..end)
     }
 }
 
@@ -125,6 +127,33 @@ impl IdRange {
         }
     }
 
+    /// Returns clock ranges of current [IdRange], which are not included in `other`. Both ranges
+    /// are expected to be squashed.
+    fn difference(&self, other: &IdRange) -> IdRange {
+        let mut result = IdRange::with_capacity(0);
+        for r in self.iter() {
+            let mut start = r.start;
+            for o in other.iter() {
+                if o.end <= start {
+                    continue;
+                } else if o.start >= r.end {
+                    break;
+                }
+                if o.start > start {
+                    result.push(start..o.start);
+                }
+                start = o.end;
+                if start >= r.end {
+                    break;
+                }
+            }
+            if start < r.end {
+                result.push(start..r.end);
+            }
+        }
+        result
+    }
+
     /// Alters current [IdRange] by compacting its internal implementation (in fragmented case).
     /// Example: fragmented space of [0,3), [3,5), [6,7) will be compacted into [0,5), [6,7).
     fn squash(&mut self) {
@@ -268,13 +297,27 @@ impl Encode for IdRange {
 
 impl Decode for IdRange {
     fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, Error> {
-        match decoder.read_var::<u32>()? {
+        let mut budget = usize::MAX;
+        Self::decode_limited(decoder, &mut budget)
+    }
+}
+
+impl IdRange {
+    /// Decodes an [IdRange], failing if it contains more than `budget` ranges. On success
+    /// number of decoded ranges is subtracted from the `budget`.
+    fn decode_limited<D: Decoder>(decoder: &mut D, budget: &mut usize) -> Result<Self, Error> {
+        let len = decoder.read_var::<u32>()?;
+        *budget = budget
+            .checked_sub(len as usize)
+            .ok_or(Error::LimitExceeded(LimitExceeded::DeleteRanges))?;
+        match len {
             1 => {
                 let range = Range::decode(decoder)?;
                 Ok(IdRange::Continuous(range))
             }
             len => {
-                let mut ranges = Vec::with_capacity(len as usize);
+                let mut ranges = Vec::new();
+                ranges.try_reserve(len as usize)?;
                 let mut i = 0;
                 while i < len {
                     ranges.push(Range::decode(decoder)?);
@@ -339,7 +382,8 @@ impl IdSet {
         self.0.len()
     }
 
-    pub(crate) fn iter(&self) -> Iter<'_> {
+    /// Returns an iterator over all client-range pairs registered in this ID set.
+    pub fn iter(&self) -> Iter<'_> {
         self.0.iter()
     }
 
@@ -397,6 +441,28 @@ impl IdSet {
     pub fn get(&self, client_id: &ClientID) -> Option<&IdRange> {
         self.0.get(client_id)
     }
+
+    /// Returns a new [IdSet] containing all ID ranges of a current set, which are not included in
+    /// the `other` one.
+    pub fn difference(&self, other: &IdSet) -> IdSet {
+        let mut result = IdSet::new();
+        for (&client, range) in self.0.iter() {
+            let mut range = range.clone();
+            range.squash();
+            let diff = match other.0.get(&client) {
+                None => range,
+                Some(o) => {
+                    let mut o = o.clone();
+                    o.squash();
+                    range.difference(&o)
+                }
+            };
+            if !diff.is_empty() {
+                result.0.insert(client, diff);
+            }
+        }
+        result
+    }
 }
 
 impl Encode for IdSet {
@@ -412,13 +478,25 @@ impl Encode for IdSet {
 
 impl Decode for IdSet {
     fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, Error> {
+        Self::decode_limited(decoder, usize::MAX)
+    }
+}
+
+impl IdSet {
+    /// Decodes an [IdSet], failing with [LimitExceeded::DeleteRanges] if it contains more than
+    /// `max_ranges` clock ranges in total.
+    pub(crate) fn decode_limited<D: Decoder>(
+        decoder: &mut D,
+        max_ranges: usize,
+    ) -> Result<Self, Error> {
         let mut set = Self::new();
+        let mut budget = max_ranges;
         let client_len: u32 = decoder.read_var()?;
         let mut i = 0;
         while i < client_len {
             decoder.reset_ds_cur_val();
             let client: u32 = decoder.read_var()?;
-            let range = IdRange::decode(decoder)?;
+            let range = IdRange::decode_limited(decoder, &mut budget)?;
             set.0.insert(client as ClientID, range);
             i += 1;
         }
@@ -446,6 +524,25 @@ impl From<IdSet> for DeleteSet {
     }
 }
 
+/// Divergences found between a [DeleteSet] and the deleted state of blocks it refers to.
+/// See: [DeleteSet::check] and [Update::check_delete_set].
+///
+/// [Update::check_delete_set]: crate::Update::check_delete_set
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DeleteSetDivergence {
+    /// Ranges included in a delete set, which cover blocks that are not deleted.
+    pub not_deleted: IdSet,
+    /// Ranges of deleted blocks, which are not included in a delete set.
+    pub not_listed: IdSet,
+}
+
+impl DeleteSetDivergence {
+    /// Returns true if no divergences have been found.
+    pub fn is_empty(&self) -> bool {
+        self.not_deleted.is_empty() && self.not_listed.is_empty()
+    }
+}
+
 impl<'a> From<&'a BlockStore> for DeleteSet {
     /// Creates a [DeleteSet] by reading all deleted blocks and including their clock ranges into
     /// the delete set itself.
@@ -569,19 +666,48 @@ impl DeleteSet {
         self.0.get(client_id)
     }
 
+    /// Returns a new [DeleteSet] containing all ID ranges of a current set, which are not
+    /// included in the `other` one.
+    pub(crate) fn difference(&self, other: &DeleteSet) -> DeleteSet {
+        DeleteSet(self.0.difference(&other.0))
+    }
+
+    /// Cross-checks current delete set against the deleted flags of blocks stored in a given
+    /// `store`, assuming that this delete set should describe all deleted blocks stored there
+    /// (like the one encoded as part of [ReadTxn::encode_state_as_update_v1]). Ranges referring
+    /// to blocks not present in the `store` are not taken into account.
+    pub fn check(&self, store: &Store) -> DeleteSetDivergence {
+        let deleted = DeleteSet::from(&store.blocks).0;
+        let mut unknown = IdSet::new();
+        for (client, _) in self.0.iter() {
+            let clock = store.blocks.get_clock(client);
+            unknown.insert(ID::new(*client, clock), u32::MAX - clock);
+        }
+        DeleteSetDivergence {
+            not_deleted: self.0.difference(&deleted).difference(&unknown),
+            not_listed: deleted.difference(&self.0),
+        }
+    }
+
     pub(crate) fn try_squash_with(&mut self, store: &mut Store) {
         // try to merge deleted / gc'd items
         for (&client, range) in self.iter() {
             let blocks = store.blocks.get_client_blocks_mut(client);
             for r in range.iter().rev() {
                 // start with merging the item next to the last deleted item
-                let mut si =
-                    (blocks.len() - 1).min(1 + blocks.find_pivot(r.end - 1).unwrap_or_default());
+                let mut si = blocks
+                    .last_pivot()
+                    .unwrap_or_default()
+                    .min(blocks.find_pivot(r.end - 1).unwrap_or_default().next());
                 let mut block = &blocks[si];
-                while si > 0 && block.clock_start() >= r.start {
-                    blocks.squash_left(si);
-                    si -= 1;
-                    block = &blocks[si];
+                while block.clock_start() >= r.start {
+                    if let Some(prev) = si.prev() {
+                        blocks.squash_left(si);
+                        si = prev;
+                        block = &blocks[si];
+                    } else {
+                        break;
+                    }
                 }
             }
         }
@@ -598,6 +724,17 @@ impl Decode for DeleteSet {
     }
 }
 
+impl DeleteSet {
+    /// Decodes a [DeleteSet], failing with [LimitExceeded::DeleteRanges] if it contains more
+    /// than `max_ranges` clock ranges in total.
+    pub(crate) fn decode_limited<D: Decoder>(
+        decoder: &mut D,
+        max_ranges: usize,
+    ) -> Result<Self, Error> {
+        Ok(DeleteSet(IdSet::decode_limited(decoder, max_ranges)?))
+    }
+}
+
 impl Encode for DeleteSet {
     #[inline]
     fn encode<E: Encoder>(&self, encoder: &mut E) {
@@ -610,7 +747,7 @@ pub(crate) struct DeletedBlocks<'ds> {
     current_range: Option<&'ds Range<u32>>,
     current_client_id: Option<ClientID>,
     range_iter: Option<IdRangeIter<'ds>>,
-    current_index: Option<usize>,
+    current_index: Option<Pivot>,
 }
 
 impl<'ds> DeletedBlocks<'ds> {
@@ -639,7 +776,7 @@ impl<'ds> TxnIterator for DeletedBlocks<'ds> {
                     .unwrap()
                     .get(*idx)
                 {
-                    *idx += 1;
+                    *idx = idx.next();
                     block.as_slice()
                 } else {
                     self.current_range = None;
@@ -661,7 +798,7 @@ impl<'ds> TxnIterator for DeletedBlocks<'ds> {
                     if clock < r.start {
                         block.trim_start(r.start - clock);
                     }
-                    self.current_index = Some(idx + 1);
+                    self.current_index = Some(idx.next());
                     block
                 } else {
                     self.current_range = None;
@@ -731,6 +868,59 @@ mod test {
     use std::collections::HashSet;
     use std::fmt::Debug;
 
+    #[test]
+    fn id_set_difference() {
+        let mut a = IdSet::new();
+        a.insert(ID::new(1, 0), 10);
+        a.insert(ID::new(2, 5), 5);
+        let mut b = IdSet::new();
+        b.insert(ID::new(1, 6), 1);
+        b.insert(ID::new(1, 2), 2);
+        b.insert(ID::new(3, 0), 1);
+
+        let mut expected = IdSet::new();
+        expected.insert_range(1, IdRange::Fragmented(vec![0..2, 4..6, 7..10]));
+        expected.insert(ID::new(2, 5), 5);
+        assert_eq!(a.difference(&b), expected);
+        assert!(a.difference(&a).is_empty());
+    }
+
+    #[test]
+    fn delete_set_check() {
+        let doc = Doc::with_client_id(1);
+        let txt = doc.get_or_insert_text("text");
+        txt.insert(&mut doc.transact_mut(), 0, "hello world");
+        txt.remove_range(&mut doc.transact_mut(), 2, 3);
+        txt.remove_range(&mut doc.transact_mut(), 4, 2);
+
+        let txn = doc.transact();
+        let ds = DeleteSet::from(&txn.store().blocks);
+        assert!(ds.check(txn.store()).is_empty());
+
+        // delete set with one deletion missing and one live block marked as deleted,
+        // ranges past the document state are not reported
+        let mut ds = DeleteSet::new();
+        ds.insert(ID::new(1, 2), 3);
+        ds.insert(ID::new(1, 0), 1);
+        ds.insert(ID::new(1, 20), 5);
+        let divergence = ds.check(txn.store());
+        let mut not_deleted = IdSet::new();
+        not_deleted.insert(ID::new(1, 0), 1);
+        let mut not_listed = IdSet::new();
+        not_listed.insert(ID::new(1, 7), 2);
+        assert_eq!(divergence.not_deleted, not_deleted);
+        assert_eq!(divergence.not_listed, not_listed);
+
+        let bin = txn.encode_state_as_update_v1(&Default::default());
+        let mut update = crate::Update::decode_v1(&bin).unwrap();
+        assert!(update.check_delete_set().is_empty());
+        update.delete_set = DeleteSet::new();
+        let divergence = update.check_delete_set();
+        let mut not_listed = IdSet::new();
+        not_listed.insert_range(1, IdRange::Fragmented(vec![2..5, 7..9]));
+        assert_eq!(divergence.not_listed, not_listed);
+    }
+
     #[test]
     fn id_range_merge_continous() {
         // `b` entirely within `a`
@@ -0,0 +1,172 @@
+//! Bootstrapping documents from existing content, ie. when importing a file into collaboration.
+//! See [Doc::from_text] and [Doc::from_json].
+//!
+//! Whole content is inserted within a single transaction by a single client, producing as few
+//! blocks as possible: texts are inserted as a single chunk and consecutive primitive array
+//! elements are inserted as a single range.
+//!
+//! The same conversion is used when deserializing documents with
+//! [deserialize_doc](crate::encoding::serde::deserialize_doc).
+//!
+//! [Doc::from_text]: crate::Doc::from_text
+//! [Doc::from_json]: crate::Doc::from_json
+
+use crate::{
+    Any, Array, ArrayPrelim, ArrayRef, Map, MapPrelim, MapRef, Text, TransactionMut, WriteTxn,
+};
+use thiserror::Error;
+
+/// Error returned by [Doc::from_json](crate::Doc::from_json) and [import_json].
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ImportError {
+    /// Imported value is not a JSON object.
+    #[error("expected a JSON object with root types")]
+    NotAnObject,
+    /// A value under a given root name is neither object, array nor string, so it cannot be
+    /// represented as a root type.
+    #[error("value of root '{0}' cannot be represented as a shared type")]
+    UnsupportedRoot(String),
+}
+
+/// Inserts a given `text` at the end of a text root type called `name`.
+pub fn import_text(txn: &mut TransactionMut, name: &str, text: &str) {
+    let root = txn.get_or_insert_text(name);
+    let len = root.len(txn);
+    root.insert(txn, len, text);
+}
+
+/// Imports a JSON object into a document, where every entry becomes a root type: objects become
+/// maps, arrays become arrays and strings become texts. Nested objects and arrays are imported
+/// as nested shared types.
+///
+/// The whole `value` is validated first, so if an import fails, document is left unchanged.
+pub fn import_json(txn: &mut TransactionMut, value: &Any) -> Result<(), ImportError> {
+    let roots = match value {
+        Any::Map(roots) => roots,
+        _ => return Err(ImportError::NotAnObject),
+    };
+    if let Some((name, _)) = roots
+        .iter()
+        .find(|(_, v)| !matches!(v, Any::Map(_) | Any::Array(_) | Any::String(_)))
+    {
+        return Err(ImportError::UnsupportedRoot(name.clone()));
+    }
+    let mut names: Vec<_> = roots.keys().collect();
+    // sort root names to make imports deterministic
+    names.sort();
+    for name in names {
+        match &roots[name] {
+            Any::Map(entries) => {
+                let map = txn.get_or_insert_map(name.as_str());
+                fill_map(txn, &map, entries);
+            }
+            Any::Array(values) => {
+                let array = txn.get_or_insert_array(name.as_str());
+                fill_array(txn, &array, values);
+            }
+            Any::String(text) => import_text(txn, name, text),
+            _ => unreachable!("root values have been validated"),
+        }
+    }
+    Ok(())
+}
+
+fn fill_map(
+    txn: &mut TransactionMut,
+    map: &MapRef,
+    entries: &std::collections::HashMap<String, Any>,
+) {
+    let mut keys: Vec<_> = entries.keys().collect();
+    keys.sort();
+    for key in keys {
+        match &entries[key] {
+            Any::Map(nested) => {
+                let nested_map = map.insert(txn, key.as_str(), MapPrelim::default());
+                fill_map(txn, &nested_map, nested);
+            }
+            Any::Array(values) => {
+                let nested_array = map.insert(txn, key.as_str(), ArrayPrelim::default());
+                fill_array(txn, &nested_array, values);
+            }
+            value => {
+                map.insert(txn, key.as_str(), value.clone());
+            }
+        }
+    }
+}
+
+fn fill_array(txn: &mut TransactionMut, array: &ArrayRef, values: &[Any]) {
+    let mut index = array.len(txn);
+    let mut range = Vec::new();
+    for value in values {
+        if !matches!(value, Any::Map(_) | Any::Array(_)) {
+            range.push(value.clone());
+            continue;
+        }
+        if !range.is_empty() {
+            let len = range.len() as u32;
+            array.insert_range(txn, index, std::mem::take(&mut range));
+            index += len;
+        }
+        match value {
+            Any::Map(nested) => {
+                let nested_map = array.insert(txn, index, MapPrelim::default());
+                fill_map(txn, &nested_map, nested);
+            }
+            Any::Array(nested) => {
+                let nested_array = array.insert(txn, index, ArrayPrelim::default());
+                fill_array(txn, &nested_array, nested);
+            }
+            _ => unreachable!(),
+        }
+        index += 1;
+    }
+    if !range.is_empty() {
+        array.insert_range(txn, index, range);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::import::ImportError;
+    use crate::inspect::inspect_update_v1;
+    use crate::types::ToJson;
+    use crate::{any, Any, Doc, GetString, ReadTxn, StateVector, Transact};
+
+    #[test]
+    fn bootstrap_from_text() {
+        let doc = Doc::from_text("content", "hello\nworld");
+        let txn = doc.transact();
+        let text = txn.get_text("content").unwrap();
+        assert_eq!(text.get_string(&txn), "hello\nworld");
+        let state = txn.encode_state_as_update_v1(&StateVector::default());
+        let report = inspect_update_v1(&state, 0).unwrap();
+        assert_eq!(report.clients.len(), 1);
+        assert_eq!(report.clients[&doc.client_id()].items, 1);
+    }
+
+    #[test]
+    fn bootstrap_from_json() {
+        let value = any!({
+            "title": "Draft",
+            "list": [1, 2, 3, {"nested": [true, false]}, 4, 5],
+            "meta": {"tags": ["a", "b"], "version": 2, "author": {"name": "John"}}
+        });
+        let doc = Doc::from_json(&value).unwrap();
+        let txn = doc.transact();
+        assert_eq!(doc.to_json(&txn), value);
+        let state = txn.encode_state_as_update_v1(&StateVector::default());
+        let report = inspect_update_v1(&state, 0).unwrap();
+        // title, [1,2,3], {..}, nested, [true,false], [4,5], author, name, tags, [a,b], version
+        assert_eq!(report.clients[&doc.client_id()].items, 11);
+
+        assert_eq!(
+            Doc::from_json(&Any::from(1)).unwrap_err(),
+            ImportError::NotAnObject
+        );
+        assert_eq!(
+            Doc::from_json(&any!({"ok": "text", "count": 1})).unwrap_err(),
+            ImportError::UnsupportedRoot("count".to_string())
+        );
+    }
+}
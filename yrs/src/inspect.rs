@@ -0,0 +1,304 @@
+//! Functions used to inspect the contents of encoded updates and persisted document states, ie.
+//! when debugging synchronization problems. They are also available from a command line, via
+//! `yrs-inspect` binary:
+//!
+//! ```bash
+//! cargo run -p yrs --bin yrs-inspect -- [--v2] [--top N] <FILE|->
+//! ```
+
+use crate::block::{ClientID, ItemContent};
+use crate::branch::Branch;
+use crate::encoding::read::Error;
+use crate::store::BlockDump;
+use crate::types::TypeRef;
+use crate::update::{BlockCarrier, Update};
+use crate::updates::decoder::Decode;
+use crate::{DeleteSet, Doc, Options, ReadTxn, StateVector, Transact};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Report describing the contents of an update, returned by [inspect_update_v1] and
+/// [inspect_update_v2]. Its [Display](std::fmt::Display) implementation produces
+/// a human-readable summary.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpdateReport {
+    /// Size of an inspected payload in bytes.
+    pub size: usize,
+    /// Statistics of blocks inserted by every client.
+    pub clients: BTreeMap<ClientID, ClientStats>,
+    /// Statistics of a delete set, grouped by client.
+    pub delete_set: BTreeMap<ClientID, DeleteStats>,
+    /// Root types of a document built from an update.
+    pub roots: Vec<RootSummary>,
+    /// Largest blocks (measured by their length) carried by an update, in descending order.
+    pub largest_blocks: Vec<BlockDump>,
+    /// If an update cannot be fully integrated on its own, this field contains a state vector of
+    /// missing dependencies.
+    pub missing: Option<StateVector>,
+    /// Part of a delete set, which refers to blocks not carried by an update.
+    pub pending_delete_set: Option<DeleteSet>,
+}
+
+/// Per-client block statistics, being part of [UpdateReport].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClientStats {
+    /// Number of blocks containing an actual content.
+    pub items: usize,
+    /// Number of garbage collected blocks.
+    pub gc: usize,
+    /// Number of skip blocks, which mark clock ranges not carried by an update.
+    pub skip: usize,
+    /// Clock of the first block.
+    pub start: u32,
+    /// Clock following the last block.
+    pub end: u32,
+    /// Sum of lengths of all item and garbage collected blocks.
+    pub elements: u32,
+}
+
+/// Per-client delete set statistics, being part of [UpdateReport].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeleteStats {
+    /// Number of continuous ranges of deleted elements.
+    pub ranges: usize,
+    /// Total number of deleted elements.
+    pub deleted: u32,
+}
+
+/// Description of a root type, being part of [UpdateReport].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RootSummary {
+    pub name: Arc<str>,
+    /// Type of a root. Since updates don't carry the information about the types of the root
+    /// types, it's inferred from their content.
+    pub type_ref: TypeRef,
+    /// Length of a sequence content, measured using [OffsetKind::Utf16].
+    ///
+    /// [OffsetKind::Utf16]: crate::OffsetKind::Utf16
+    pub content_len: u32,
+    /// Number of non-deleted map entries.
+    pub entries: usize,
+}
+
+/// Inspects an update (or a document state) encoded using lib0 v1 encoding, returning up to
+/// `top` largest blocks as part of a report.
+pub fn inspect_update_v1(data: &[u8], top: usize) -> Result<UpdateReport, Error> {
+    Ok(inspect(Update::decode_v1(data)?, data.len(), top))
+}
+
+/// Inspects an update (or a document state) encoded using lib0 v2 encoding, returning up to
+/// `top` largest blocks as part of a report.
+pub fn inspect_update_v2(data: &[u8], top: usize) -> Result<UpdateReport, Error> {
+    Ok(inspect(Update::decode_v2(data)?, data.len(), top))
+}
+
+fn inspect(update: Update, size: usize, top: usize) -> UpdateReport {
+    let mut clients = BTreeMap::new();
+    let mut largest_blocks = Vec::new();
+    for (client, blocks) in update.blocks.clients.iter() {
+        let mut stats = ClientStats::default();
+        if let (Some(first), Some(last)) = (blocks.front(), blocks.back()) {
+            stats.start = first.id().clock;
+            stats.end = last.last_id().clock + 1;
+        }
+        for block in blocks.iter() {
+            match block {
+                BlockCarrier::Item(item) => {
+                    stats.items += 1;
+                    stats.elements += item.len;
+                    largest_blocks.push(BlockDump::from_item(item));
+                }
+                BlockCarrier::GC(range) => {
+                    stats.gc += 1;
+                    stats.elements += range.len;
+                }
+                BlockCarrier::Skip(_) => stats.skip += 1,
+            }
+        }
+        clients.insert(*client, stats);
+    }
+    largest_blocks.sort_by(|a, b| b.len.cmp(&a.len).then_with(|| a.id.cmp(&b.id)));
+    largest_blocks.truncate(top);
+
+    let mut delete_set = BTreeMap::new();
+    for (client, range) in update.delete_set.iter() {
+        let stats = DeleteStats {
+            ranges: range.iter().count(),
+            deleted: range.iter().map(|r| r.end - r.start).sum(),
+        };
+        delete_set.insert(*client, stats);
+    }
+
+    // integrate an update into an empty document to resolve its structure
+    let doc = Doc::with_options(Options {
+        skip_gc: true,
+        ..Options::default()
+    });
+    let mut txn = doc.transact_mut();
+    txn.apply_update(update);
+    let mut roots: Vec<_> = txn
+        .root_refs()
+        .filter_map(|(name, value)| {
+            let branch = value.try_branch()?;
+            Some(RootSummary {
+                name: name.into(),
+                type_ref: infer_type_ref(branch),
+                content_len: branch.content_len,
+                entries: branch.map.values().filter(|i| !i.is_deleted()).count(),
+            })
+        })
+        .collect();
+    roots.sort_by(|a, b| a.name.cmp(&b.name));
+    let store = txn.store();
+    UpdateReport {
+        size,
+        clients,
+        delete_set,
+        roots,
+        largest_blocks,
+        missing: store.pending_update().map(|p| p.missing.clone()),
+        pending_delete_set: store.pending_ds().cloned(),
+    }
+}
+
+/// Infers a type of a root type, which has not been defined locally, based on its content.
+pub(crate) fn infer_type_ref(branch: &Branch) -> TypeRef {
+    match (branch.type_ref(), branch.start) {
+        (TypeRef::Undefined, Some(start)) => match &start.content {
+            ItemContent::String(_) | ItemContent::Format(_, _) | ItemContent::Embed(_) => {
+                TypeRef::Text
+            }
+            ItemContent::Type(child)
+                if matches!(
+                    child.type_ref(),
+                    TypeRef::XmlElement(_) | TypeRef::XmlText | TypeRef::XmlHook
+                ) =>
+            {
+                TypeRef::XmlFragment
+            }
+            _ => TypeRef::Array,
+        },
+        (TypeRef::Undefined, None) if !branch.map.is_empty() => TypeRef::Map,
+        (type_ref, _) => type_ref.clone(),
+    }
+}
+
+impl std::fmt::Display for UpdateReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "size: {} bytes", self.size)?;
+        writeln!(f, "clients:")?;
+        for (client, s) in self.clients.iter() {
+            writeln!(
+                f,
+                "  {}: clocks {}..{}, {} elements in {} items, {} gc, {} skip",
+                client, s.start, s.end, s.elements, s.items, s.gc, s.skip
+            )?;
+        }
+        writeln!(f, "delete set:")?;
+        for (client, s) in self.delete_set.iter() {
+            writeln!(
+                f,
+                "  {}: {} deleted elements in {} ranges",
+                client, s.deleted, s.ranges
+            )?;
+        }
+        writeln!(f, "roots:")?;
+        for root in self.roots.iter() {
+            writeln!(
+                f,
+                "  '{}' {}: length {}, {} entries",
+                root.name, root.type_ref, root.content_len, root.entries
+            )?;
+        }
+        writeln!(f, "largest blocks:")?;
+        for block in self.largest_blocks.iter() {
+            writeln!(f, "  {}", block)?;
+        }
+        if let Some(missing) = self.missing.as_ref() {
+            writeln!(f, "missing dependencies: {:?}", missing)?;
+        }
+        if let Some(ds) = self.pending_delete_set.as_ref() {
+            writeln!(f, "pending delete set: {}", ds)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::inspect::{inspect_update_v1, inspect_update_v2, ClientStats, DeleteStats};
+    use crate::types::TypeRef;
+    use crate::{Doc, Map, ReadTxn, StateVector, Text, Transact, ID};
+
+    #[test]
+    fn inspect_document_state() {
+        let d1 = Doc::with_client_id(1);
+        let text = d1.get_or_insert_text("text");
+        let map = d1.get_or_insert_map("map");
+        {
+            let mut txn = d1.transact_mut();
+            text.insert(&mut txn, 0, "hello world");
+            map.insert(&mut txn, "a", 1);
+            map.insert(&mut txn, "b", 2);
+        }
+        text.remove_range(&mut d1.transact_mut(), 5, 6);
+        let state_v1 = d1
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+
+        let report = inspect_update_v1(&state_v1, 1).unwrap();
+        assert_eq!(report.size, state_v1.len());
+        assert_eq!(
+            report.clients[&1],
+            ClientStats {
+                items: 4,
+                gc: 0,
+                skip: 0,
+                start: 0,
+                end: 13,
+                elements: 13,
+            }
+        );
+        assert_eq!(
+            report.delete_set[&1],
+            DeleteStats {
+                ranges: 1,
+                deleted: 6
+            }
+        );
+        assert_eq!(report.roots.len(), 2);
+        assert_eq!(report.roots[0].name.as_ref(), "map");
+        assert_eq!(report.roots[0].entries, 2);
+        assert_eq!(report.roots[1].type_ref, TypeRef::Text);
+        assert_eq!(report.roots[1].content_len, 5);
+        assert_eq!(report.largest_blocks.len(), 1);
+        assert_eq!(report.largest_blocks[0].id, ID::new(1, 5));
+        assert_eq!(report.largest_blocks[0].len, 6);
+        assert!(report.missing.is_none());
+        assert!(report
+            .to_string()
+            .contains("1: 6 deleted elements in 1 ranges"));
+
+        let state_v2 = d1
+            .transact()
+            .encode_state_as_update_v2(&StateVector::default());
+        let report_v2 = inspect_update_v2(&state_v2, 1).unwrap();
+        assert_eq!(report_v2.clients, report.clients);
+    }
+
+    #[test]
+    fn inspect_update_with_missing_dependencies() {
+        let d1 = Doc::with_client_id(1);
+        let text = d1.get_or_insert_text("text");
+        text.insert(&mut d1.transact_mut(), 0, "abc");
+        let update = {
+            let mut txn = d1.transact_mut();
+            text.insert(&mut txn, 3, "def");
+            txn.encode_update_v1()
+        };
+        let report = inspect_update_v1(&update, 10).unwrap();
+        assert_eq!(report.clients[&1].start, 3);
+        assert_eq!(report.missing.as_ref().unwrap().get(&1), 2);
+        assert!(report.to_string().contains("missing dependencies"));
+    }
+}
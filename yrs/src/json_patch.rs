@@ -0,0 +1,480 @@
+//! Support for [RFC 6902](https://datatracker.ietf.org/doc/html/rfc6902) JSON Patch documents,
+//! which allows to modify a tree of [MapRef]s and [ArrayRef]s without using CRDT specific API.
+//!
+//! [MapRef]: crate::MapRef
+//! [ArrayRef]: crate::ArrayRef
+
+use crate::branch::BranchPtr;
+use crate::types::{SharedRef, ToJson};
+use crate::{Any, Array, ArrayPrelim, In, Map, MapPrelim, Out, TransactionMut};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use thiserror::Error;
+
+/// A single JSON Patch operation. Paths are expressed using
+/// [JSON pointers](https://datatracker.ietf.org/doc/html/rfc6901).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum PatchOperation {
+    /// Inserts a `value` into an array at a given index (use `-` to append it at the end of an
+    /// array) or sets a map entry.
+    Add { path: String, value: Any },
+    /// Removes an existing array element or map entry.
+    Remove { path: String },
+    /// Replaces an existing array element or map entry with a new `value`.
+    Replace { path: String, value: Any },
+    /// Removes a value at `from` path and adds it at a given `path`.
+    Move { from: String, path: String },
+}
+
+/// JSON Patch document, which can be deserialized from its JSON representation, ie.
+/// `[{"op":"add","path":"/a/0","value":1}]`.
+///
+/// Patch is applied onto a [MapRef] or [ArrayRef] with [JsonPatch::apply]. Maps and arrays added
+/// by a patch are inserted as nested shared types, so they can be targeted by subsequent patches.
+/// Values moved with [PatchOperation::Move] are copied using their JSON representation (see
+/// [ToJson]), so ie. moved texts become plain strings.
+///
+/// ```rust
+/// use yrs::{any, Doc, Map, Transact};
+/// use yrs::json_patch::JsonPatch;
+/// use yrs::types::ToJson;
+///
+/// let doc = Doc::new();
+/// let map = doc.get_or_insert_map("map");
+/// let mut txn = doc.transact_mut();
+///
+/// let patch: JsonPatch = serde_json::from_str(r#"[
+///   {"op":"add","path":"/list","value":[1,2]},
+///   {"op":"add","path":"/list/-","value":3},
+///   {"op":"move","from":"/list/0","path":"/first"}
+/// ]"#).unwrap();
+/// patch.apply(&mut txn, &map).unwrap();
+///
+/// assert_eq!(map.to_json(&txn), any!({"list": [2, 3], "first": 1}));
+/// ```
+///
+/// [MapRef]: crate::MapRef
+/// [ArrayRef]: crate::ArrayRef
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct JsonPatch(pub Vec<PatchOperation>);
+
+impl JsonPatch {
+    /// Applies all operations of this patch onto a given `root` shared type, which must be either
+    /// a map or an array.
+    ///
+    /// # Errors
+    ///
+    /// Patch is applied atomically: when any of its operations fails, an error is returned and
+    /// `root` is left unchanged.
+    pub fn apply<T: SharedRef>(
+        &self,
+        txn: &mut TransactionMut,
+        root: &T,
+    ) -> Result<(), PatchError> {
+        let root: Out = BranchPtr::from(root.as_ref()).into();
+        match root {
+            Out::YMap(_) | Out::YArray(_) => {}
+            _ => return Err(PatchError::NotAContainer(String::new())),
+        }
+        // changes made within a transaction cannot be reverted, so the whole patch is validated
+        // against a JSON copy of a root before applying it
+        let mut json = root.to_json(txn);
+        for op in self.0.iter() {
+            json = op.apply_json(&json)?;
+        }
+        for op in self.0.iter() {
+            op.apply_shared(txn, &root)?;
+        }
+        Ok(())
+    }
+}
+
+impl PatchOperation {
+    fn apply_json(&self, json: &Any) -> Result<Any, PatchError> {
+        match self {
+            PatchOperation::Add { path, value } => {
+                edit_json(json, &parse_pointer(path)?, path, Edit::Add(value.clone()))
+            }
+            PatchOperation::Remove { path } => {
+                edit_json(json, &parse_pointer(path)?, path, Edit::Remove)
+            }
+            PatchOperation::Replace { path, value } => {
+                let segments = parse_pointer(path)?;
+                let json = edit_json(json, &segments, path, Edit::Remove)?;
+                edit_json(&json, &segments, path, Edit::Add(value.clone()))
+            }
+            PatchOperation::Move { from, path } => {
+                let (source, target) = move_paths(from, path)?;
+                let value = get_json(json, &source, from)?;
+                let json = edit_json(json, &source, from, Edit::Remove)?;
+                edit_json(&json, &target, path, Edit::Add(value))
+            }
+        }
+    }
+
+    fn apply_shared(&self, txn: &mut TransactionMut, root: &Out) -> Result<(), PatchError> {
+        match self {
+            PatchOperation::Add { path, value } => edit_shared(
+                txn,
+                root,
+                &parse_pointer(path)?,
+                path,
+                Edit::Add(value.clone()),
+            ),
+            PatchOperation::Remove { path } => {
+                edit_shared(txn, root, &parse_pointer(path)?, path, Edit::Remove)
+            }
+            PatchOperation::Replace { path, value } => {
+                let segments = parse_pointer(path)?;
+                edit_shared(txn, root, &segments, path, Edit::Remove)?;
+                edit_shared(txn, root, &segments, path, Edit::Add(value.clone()))
+            }
+            PatchOperation::Move { from, path } => {
+                let (source, target) = move_paths(from, path)?;
+                let value = get_shared(txn, root, &source, from)?;
+                edit_shared(txn, root, &source, from, Edit::Remove)?;
+                edit_shared(txn, root, &target, path, Edit::Add(value))
+            }
+        }
+    }
+}
+
+/// Error returned when applying a [JsonPatch].
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum PatchError {
+    #[error("'{0}' is not a valid JSON pointer")]
+    InvalidPointer(String),
+    #[error("JSON patch operation cannot target the root type itself")]
+    RootTarget,
+    #[error("path '{0}' does not exist")]
+    NotFound(String),
+    #[error("path '{0}' does not point into a map or an array")]
+    NotAContainer(String),
+    #[error("cannot move '{from}' into its own child '{path}'")]
+    MoveIntoChild { from: String, path: String },
+}
+
+enum Edit {
+    Add(Any),
+    Remove,
+}
+
+fn parse_pointer(pointer: &str) -> Result<Vec<String>, PatchError> {
+    if pointer.is_empty() {
+        Err(PatchError::RootTarget)
+    } else if let Some(path) = pointer.strip_prefix('/') {
+        Ok(path
+            .split('/')
+            .map(|segment| segment.replace("~1", "/").replace("~0", "~"))
+            .collect())
+    } else {
+        Err(PatchError::InvalidPointer(pointer.to_string()))
+    }
+}
+
+fn move_paths(from: &str, path: &str) -> Result<(Vec<String>, Vec<String>), PatchError> {
+    let source = parse_pointer(from)?;
+    let target = parse_pointer(path)?;
+    if target.len() > source.len() && target.starts_with(&source) {
+        Err(PatchError::MoveIntoChild {
+            from: from.to_string(),
+            path: path.to_string(),
+        })
+    } else {
+        Ok((source, target))
+    }
+}
+
+/// Parses array index. When `allow_end` is set, index equal to array length (or `-`) is
+/// accepted, as it's used to append elements.
+fn parse_index(
+    segment: &str,
+    len: usize,
+    allow_end: bool,
+    pointer: &str,
+) -> Result<usize, PatchError> {
+    if allow_end && segment == "-" {
+        return Ok(len);
+    }
+    let valid = !segment.is_empty()
+        && segment.bytes().all(|b| b.is_ascii_digit())
+        && (segment == "0" || !segment.starts_with('0'));
+    match segment.parse::<usize>() {
+        Ok(index) if valid && (index < len || (allow_end && index == len)) => Ok(index),
+        _ => Err(PatchError::NotFound(pointer.to_string())),
+    }
+}
+
+/// Converts JSON value into its shared type counterpart: maps and arrays are inserted as
+/// [MapRef](crate::MapRef)s and [ArrayRef](crate::ArrayRef)s.
+fn to_input(value: Any) -> In {
+    match value {
+        Any::Array(values) => In::Array(
+            values
+                .iter()
+                .cloned()
+                .map(to_input)
+                .collect::<ArrayPrelim>(),
+        ),
+        Any::Map(entries) => In::Map(
+            entries
+                .iter()
+                .map(|(key, value)| (key.as_str(), to_input(value.clone())))
+                .collect::<MapPrelim>(),
+        ),
+        other => In::Any(other),
+    }
+}
+
+enum JsonNode {
+    Map(HashMap<String, Any>),
+    Array(Vec<Any>),
+}
+
+impl JsonNode {
+    fn new(value: &Any, pointer: &str) -> Result<Self, PatchError> {
+        match value {
+            Any::Map(entries) => Ok(JsonNode::Map((**entries).clone())),
+            Any::Array(values) => Ok(JsonNode::Array(values.to_vec())),
+            _ => Err(PatchError::NotAContainer(pointer.to_string())),
+        }
+    }
+
+    fn get_mut(&mut self, segment: &str, pointer: &str) -> Result<&mut Any, PatchError> {
+        match self {
+            JsonNode::Map(entries) => entries
+                .get_mut(segment)
+                .ok_or_else(|| PatchError::NotFound(pointer.to_string())),
+            JsonNode::Array(values) => {
+                let index = parse_index(segment, values.len(), false, pointer)?;
+                Ok(&mut values[index])
+            }
+        }
+    }
+
+    fn edit(&mut self, segment: &str, pointer: &str, edit: Edit) -> Result<(), PatchError> {
+        match (self, edit) {
+            (JsonNode::Map(entries), Edit::Add(value)) => {
+                entries.insert(segment.to_string(), value);
+            }
+            (JsonNode::Map(entries), Edit::Remove) => {
+                if entries.remove(segment).is_none() {
+                    return Err(PatchError::NotFound(pointer.to_string()));
+                }
+            }
+            (JsonNode::Array(values), Edit::Add(value)) => {
+                let index = parse_index(segment, values.len(), true, pointer)?;
+                values.insert(index, value);
+            }
+            (JsonNode::Array(values), Edit::Remove) => {
+                let index = parse_index(segment, values.len(), false, pointer)?;
+                values.remove(index);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl From<JsonNode> for Any {
+    fn from(node: JsonNode) -> Self {
+        match node {
+            JsonNode::Map(entries) => Any::from(entries),
+            JsonNode::Array(values) => Any::from(values),
+        }
+    }
+}
+
+fn get_json(value: &Any, segments: &[String], pointer: &str) -> Result<Any, PatchError> {
+    let mut current = value.clone();
+    for segment in segments {
+        let mut node = JsonNode::new(&current, pointer)?;
+        current = node.get_mut(segment, pointer)?.clone();
+    }
+    Ok(current)
+}
+
+/// Returns a copy of a JSON `value` with an `edit` applied at a given path.
+fn edit_json(
+    value: &Any,
+    segments: &[String],
+    pointer: &str,
+    edit: Edit,
+) -> Result<Any, PatchError> {
+    let mut node = JsonNode::new(value, pointer)?;
+    match segments {
+        [] => return Err(PatchError::RootTarget),
+        [last] => node.edit(last, pointer, edit)?,
+        [segment, rest @ ..] => {
+            let child = node.get_mut(segment, pointer)?;
+            *child = edit_json(child, rest, pointer, edit)?;
+        }
+    }
+    Ok(node.into())
+}
+
+fn child_shared(
+    txn: &TransactionMut,
+    node: &Out,
+    segment: &str,
+    pointer: &str,
+) -> Result<Out, PatchError> {
+    let child = match node {
+        Out::YMap(map) => map.get(txn, segment),
+        Out::YArray(array) => {
+            let index = parse_index(segment, array.len(txn) as usize, false, pointer)?;
+            array.get(txn, index as u32)
+        }
+        _ => return Err(PatchError::NotAContainer(pointer.to_string())),
+    };
+    child.ok_or_else(|| PatchError::NotFound(pointer.to_string()))
+}
+
+fn get_shared(
+    txn: &TransactionMut,
+    root: &Out,
+    segments: &[String],
+    pointer: &str,
+) -> Result<Any, PatchError> {
+    let mut current = root.clone();
+    for (i, segment) in segments.iter().enumerate() {
+        current = child_shared(txn, &current, segment, pointer)?;
+        if let Out::Any(value) = &current {
+            return get_json(value, &segments[i + 1..], pointer);
+        }
+    }
+    Ok(current.to_json(txn))
+}
+
+fn edit_shared(
+    txn: &mut TransactionMut,
+    root: &Out,
+    segments: &[String],
+    pointer: &str,
+    edit: Edit,
+) -> Result<(), PatchError> {
+    let (last, parents) = segments.split_last().ok_or(PatchError::RootTarget)?;
+    let mut current = root.clone();
+    for (i, segment) in parents.iter().enumerate() {
+        match child_shared(txn, &current, segment, pointer)? {
+            Out::Any(value) => {
+                // nested value is not a shared type, so it's replaced as a whole
+                let value = edit_json(&value, &segments[i + 1..], pointer, edit)?;
+                return match current {
+                    Out::YMap(map) => {
+                        map.insert(txn, segment.as_str(), value);
+                        Ok(())
+                    }
+                    Out::YArray(array) => {
+                        let index = parse_index(segment, array.len(txn) as usize, false, pointer)?;
+                        array.remove(txn, index as u32);
+                        array.insert(txn, index as u32, value);
+                        Ok(())
+                    }
+                    _ => Err(PatchError::NotAContainer(pointer.to_string())),
+                };
+            }
+            child => current = child,
+        }
+    }
+    match (current, edit) {
+        (Out::YMap(map), Edit::Add(value)) => {
+            map.insert(txn, last.as_str(), to_input(value));
+        }
+        (Out::YMap(map), Edit::Remove) => {
+            if map.remove(txn, last).is_none() {
+                return Err(PatchError::NotFound(pointer.to_string()));
+            }
+        }
+        (Out::YArray(array), Edit::Add(value)) => {
+            let index = parse_index(last, array.len(txn) as usize, true, pointer)?;
+            array.insert(txn, index as u32, to_input(value));
+        }
+        (Out::YArray(array), Edit::Remove) => {
+            let index = parse_index(last, array.len(txn) as usize, false, pointer)?;
+            array.remove(txn, index as u32);
+        }
+        _ => return Err(PatchError::NotAContainer(pointer.to_string())),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::json_patch::{JsonPatch, PatchError};
+    use crate::types::ToJson;
+    use crate::{any, Array, Doc, Map, Out, Transact};
+
+    fn patch(json: &str) -> JsonPatch {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn apply_patch() {
+        let doc = Doc::with_client_id(1);
+        let map = doc.get_or_insert_map("map");
+        let mut txn = doc.transact_mut();
+        map.insert(&mut txn, "raw", any!({"a": [1, 2]}));
+
+        patch(
+            r#"[
+            {"op":"add","path":"/users","value":[{"name":"John"}]},
+            {"op":"add","path":"/users/0/tags","value":["admin"]},
+            {"op":"add","path":"/users/-","value":{"name":"Jane"}},
+            {"op":"replace","path":"/users/1/name","value":"Alice"},
+            {"op":"move","from":"/users/0/tags","path":"/users/1/tags"},
+            {"op":"remove","path":"/raw/a/0"},
+            {"op":"add","path":"/a~1b","value":null}
+        ]"#,
+        )
+        .apply(&mut txn, &map)
+        .unwrap();
+
+        assert_eq!(
+            map.to_json(&txn),
+            any!({
+                "users": [{"name": "John"}, {"name": "Alice", "tags": ["admin"]}],
+                "raw": {"a": [2]},
+                "a/b": null
+            })
+        );
+        // added JSON objects and arrays are stored as shared types
+        let users = map.get(&txn, "users").unwrap();
+        match users {
+            Out::YArray(users) => assert!(matches!(users.get(&txn, 0), Some(Out::YMap(_)))),
+            other => panic!("expected array, got {:?}", other),
+        }
+        // nested primitive values remain primitive
+        assert!(matches!(map.get(&txn, "raw"), Some(Out::Any(_))));
+    }
+
+    #[test]
+    fn failed_patch_is_not_applied() {
+        let doc = Doc::with_client_id(1);
+        let array = doc.get_or_insert_array("array");
+        let mut txn = doc.transact_mut();
+        array.insert_range(&mut txn, 0, [1, 2, 3]);
+
+        let err = patch(
+            r#"[
+            {"op":"remove","path":"/0"},
+            {"op":"remove","path":"/5"}
+        ]"#,
+        )
+        .apply(&mut txn, &array)
+        .unwrap_err();
+        assert_eq!(err, PatchError::NotFound("/5".into()));
+        assert_eq!(array.to_json(&txn), any!([1, 2, 3]));
+
+        let err = patch(r#"[{"op":"add","path":"/01","value":0}]"#)
+            .apply(&mut txn, &array)
+            .unwrap_err();
+        assert_eq!(err, PatchError::NotFound("/01".into()));
+
+        let err = patch(r#"[{"op":"add","path":"","value":0}]"#)
+            .apply(&mut txn, &array)
+            .unwrap_err();
+        assert_eq!(err, PatchError::RootTarget);
+    }
+}
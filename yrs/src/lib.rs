@@ -15,6 +15,19 @@
 //! - `weak` this feature enables weak references and quotations (see: [crate::WeakRef]).
 //! - `sync` this feature modifies observers callback constraints to use `Send` and `Sync` traits.
 //!   These are required when using yrs features in multithreaded environments.
+//! - `net` this feature (which implies `sync`) enables adapters connecting documents to
+//!   asynchronous transports (see: [crate::sync::stream]) and a `BroadcastGroup` serving many
+//!   y-sync connections over the same document.
+//! - `axum` and `actix` features (which imply `net`) enable ready-made y-sync WebSocket endpoint
+//!   handlers for corresponding web frameworks.
+//! - `server` this feature (which implies `axum`) enables a y-websocket compatible server with
+//!   pluggable persistence and authorization, also available as `y-websocket-server` binary.
+//! - `encryption` this feature enables end-to-end encryption of document updates (see:
+//!   `yrs::encryption`).
+//! - `signing` this feature enables signing updates and verifying their authors (see:
+//!   `yrs::signing`).
+//! - `unicode-segmentation` this feature enables grapheme cluster aware text indexing (see:
+//!   [crate::Text::len_graphemes]).
 //!
 //! # Quick start
 //!
@@ -518,6 +531,38 @@
 //!    to encode and propagate incremental changes made by transaction to other peers.
 //! 6. Sub-document change callbacks: [Doc::observe_subdocs].
 //!
+//! # Concurrency model
+//!
+//! [Doc] can be shared between threads, however its store is guarded by a single non-blocking
+//! readers-writer flag rather than a lock:
+//!
+//! - Any number of [read-only transactions](Transaction) can be active at the same time, also
+//!   when they are created from different threads.
+//! - A [read-write transaction](TransactionMut) requires exclusive access. Acquiring it while any
+//!   other transaction is active doesn't block - [Transact::try_transact_mut] returns an error
+//!   right away, while [Transact::transact_mut] panics.
+//! - Acquiring and releasing a transaction uses acquire/release memory ordering, therefore all
+//!   changes made by a read-write transaction are visible to every transaction created after it
+//!   has been dropped, regardless of the thread.
+//!
+//! Since transactions never wait for each other, it's up to the caller to serialize writers (eg.
+//! by wrapping a document with a mutex or sending all updates through a single task). Document
+//! observers are always called synchronously, on the thread which commits a transaction.
+//!
+//! This model is covered by threaded tests, which check that concurrent readers exclude a writer,
+//! that changes committed on one thread are visible on another and that transactions repeatedly
+//! acquired and released from several threads never overlap. These tests only exercise
+//! interleavings forced by barriers or picked by the OS scheduler; they are not model checked. In particular, the following is
+//! not verified:
+//!
+//! - exhaustive interleavings of transaction acquisition and release (ie. with [loom]), since the
+//!   store flag (`atomic_refcell`) and observer lists (`arc-swap`) are not instrumented and cannot
+//!   be replaced with model checked primitives,
+//! - memory orderings used internally by these two crates, which are relied upon as documented
+//!   by them.
+//!
+//! [loom]: https://docs.rs/loom
+//!
 //! # Update encoding v1 vs. v2
 //!
 //! Yrs ships with so called lib0 encoding, which offers two different variants, both of which are
@@ -570,6 +615,7 @@
 //! - [Deep dive into internal architecture of Yrs](https://bartoszsypytkowski.com/yrs-architecture/).
 //! - [Detailed explanation of conflict-free reordering algorithm](https://bartoszsypytkowski.com/yata-move/) used by Yrs.
 
+pub mod access;
 mod alt;
 pub mod block;
 mod block_store;
@@ -585,16 +631,30 @@ mod utils;
 
 pub mod any;
 pub mod atomic;
+pub mod batch;
+pub mod binding;
 mod block_iter;
 pub mod branch;
 pub mod encoding;
+#[cfg(feature = "encryption")]
+pub mod encryption;
 mod error;
+pub mod export;
 mod gc;
+pub mod import;
 mod input;
+pub mod inspect;
 pub mod iter;
+pub mod json_patch;
+pub mod lines;
+pub mod metrics;
 mod moving;
 pub mod observer;
 mod out;
+pub mod quota;
+pub mod sequence;
+#[cfg(feature = "signing")]
+pub mod signing;
 mod slice;
 mod state_vector;
 pub mod sync;
@@ -606,7 +666,9 @@ pub mod undo;
 
 pub use crate::alt::{
     diff_updates_v1, diff_updates_v2, encode_state_vector_from_update_v1,
-    encode_state_vector_from_update_v2, merge_updates_v1, merge_updates_v2,
+    encode_state_vector_from_update_v2, is_update_subset_of_v1, is_update_subset_of_v2,
+    merge3_updates_v1, merge3_updates_v2, merge_updates_v1, merge_updates_v2, MergeContribution,
+    ThreeWayMerge,
 };
 pub use crate::any::Any;
 pub use crate::block::ID;
@@ -615,11 +677,16 @@ pub use crate::branch::Hook;
 pub use crate::branch::Nested;
 pub use crate::branch::Root;
 pub use crate::doc::Doc;
+pub use crate::doc::MovePolicy;
 pub use crate::doc::OffsetKind;
 pub use crate::doc::Options;
 pub use crate::doc::Transact;
 pub use crate::event::{SubdocsEvent, SubdocsEventIter, TransactionCleanupEvent, UpdateEvent};
+pub use crate::export::ExportFormat;
 pub use crate::id_set::DeleteSet;
+pub use crate::id_set::DeleteSetDivergence;
+pub use crate::id_set::IdRange;
+pub use crate::id_set::IdSet;
 pub use crate::input::In;
 pub use crate::moving::Assoc;
 pub use crate::moving::IndexScope;
@@ -630,7 +697,11 @@ pub use crate::observer::{Observer, Subscription};
 pub use crate::out::Out;
 pub use crate::state_vector::Snapshot;
 pub use crate::state_vector::StateVector;
+pub use crate::store::BlockDump;
+pub use crate::store::BlockOp;
+pub use crate::store::BlockParent;
 pub use crate::store::Store;
+pub use crate::store::StoreDump;
 pub use crate::transaction::Origin;
 pub use crate::transaction::ReadTxn;
 pub use crate::transaction::RootRefs;
@@ -644,8 +715,11 @@ pub use crate::types::map::Map;
 pub use crate::types::map::MapPrelim;
 pub use crate::types::map::MapRef;
 pub use crate::types::text::Text;
+pub use crate::types::text::TextChunks;
 pub use crate::types::text::TextPrelim;
 pub use crate::types::text::TextRef;
+pub use crate::types::text::TextRun;
+pub use crate::types::text::TextRuns;
 #[cfg(feature = "weak")]
 pub use crate::types::weak::{Quotable, WeakPrelim, WeakRef};
 pub use crate::types::xml::Xml;
@@ -655,14 +729,20 @@ pub use crate::types::xml::XmlFragment;
 pub use crate::types::xml::XmlFragmentPrelim;
 pub use crate::types::xml::XmlFragmentRef;
 pub use crate::types::xml::XmlOut;
+pub use crate::types::xml::XmlParseError;
 pub use crate::types::xml::XmlTextPrelim;
 pub use crate::types::xml::XmlTextRef;
 pub use crate::types::DeepObservable;
 pub use crate::types::GetString;
+pub use crate::types::IndexOutOfBounds;
 pub use crate::types::Observable;
 pub use crate::types::RootRef;
 pub use crate::types::SharedRef;
+pub use crate::update::LimitExceeded;
+pub use crate::update::RecoveryReport;
 pub use crate::update::Update;
+pub use crate::update::UpdateError;
+pub use crate::update::UpdateLimits;
 
 #[deprecated(since = "0.19.0", note = "Use `yrs::Out` instead")]
 pub type Value = Out;
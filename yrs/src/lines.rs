@@ -0,0 +1,250 @@
+//! Line and column addressing of [TextRef] content, used by code editor integrations. See
+//! [LineIndex] and [Text::position_of].
+//!
+//! Lines are separated with `\n` characters, which belong to the lines they end. Columns, like all
+//! other text positions, are measured using [OffsetKind] configured for a document.
+//!
+//! [Text::position_of]: crate::Text::position_of
+
+use crate::block::ItemContent;
+use crate::branch::Branch;
+use crate::types::Delta;
+use crate::{Any, Observable, OffsetKind, Out, ReadTxn, Subscription, TextRef};
+use std::sync::{Arc, Mutex};
+
+/// Sorted positions of all line breaks within a text.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct LineBreaks {
+    len: u32,
+    breaks: Vec<u32>,
+}
+
+impl LineBreaks {
+    pub(crate) fn new(branch: &Branch, kind: OffsetKind) -> Self {
+        let mut lines = LineBreaks::default();
+        let mut current = branch.start;
+        while let Some(item) = current {
+            current = item.right;
+            if item.is_deleted() {
+                continue;
+            }
+            match &item.content {
+                // text is scanned from left to right, so breaks can be appended as they come
+                ItemContent::String(s) => {
+                    for c in s.as_str().chars() {
+                        if c == '\n' {
+                            lines.breaks.push(lines.len);
+                        }
+                        lines.len += match kind {
+                            OffsetKind::Bytes => c.len_utf8() as u32,
+                            OffsetKind::Utf16 => c.len_utf16() as u32,
+                        };
+                    }
+                }
+                _ if item.is_countable() => lines.len += item.content_len(kind),
+                _ => {}
+            }
+        }
+        lines
+    }
+
+    /// Inserts a `chunk` at a given `index`, returning its length.
+    fn insert(&mut self, index: u32, chunk: &str, kind: OffsetKind) -> u32 {
+        let mut len = 0;
+        let mut inserted = Vec::new();
+        for c in chunk.chars() {
+            if c == '\n' {
+                inserted.push(index + len);
+            }
+            len += match kind {
+                OffsetKind::Bytes => c.len_utf8() as u32,
+                OffsetKind::Utf16 => c.len_utf16() as u32,
+            };
+        }
+        self.shift(index, len);
+        let at = self.breaks.partition_point(|&b| b < index);
+        self.breaks.splice(at..at, inserted);
+        len
+    }
+
+    /// Moves line breaks placed at or after a given `index` by `len` positions forward.
+    fn shift(&mut self, index: u32, len: u32) {
+        for b in self.breaks.iter_mut() {
+            if *b >= index {
+                *b += len;
+            }
+        }
+        self.len += len;
+    }
+
+    fn remove(&mut self, index: u32, len: u32) {
+        let end = index + len;
+        self.breaks.retain(|&b| b < index || b >= end);
+        for b in self.breaks.iter_mut() {
+            if *b >= end {
+                *b -= len;
+            }
+        }
+        self.len -= len;
+    }
+
+    fn apply_delta(&mut self, delta: &[Delta], kind: OffsetKind) {
+        let mut index = 0;
+        for d in delta {
+            match d {
+                Delta::Retain(len, _) => index += len,
+                Delta::Deleted(len) => self.remove(index, *len),
+                Delta::Inserted(Out::Any(Any::String(s)), _) => {
+                    index += self.insert(index, s, kind)
+                }
+                Delta::Inserted(_, _) => {
+                    // embedded values and shared types have length of 1
+                    self.shift(index, 1);
+                    index += 1;
+                }
+            }
+        }
+    }
+
+    pub(crate) fn line_count(&self) -> u32 {
+        self.breaks.len() as u32 + 1
+    }
+
+    fn line_start(&self, line: u32) -> Option<u32> {
+        match line {
+            0 => Some(0),
+            n => self.breaks.get(n as usize - 1).map(|b| b + 1),
+        }
+    }
+
+    pub(crate) fn index_at(&self, line: u32, column: u32) -> Option<u32> {
+        let start = self.line_start(line)?;
+        // line break is the last valid column of a line
+        let end = self.breaks.get(line as usize).copied().unwrap_or(self.len);
+        let index = start.checked_add(column)?;
+        (index <= end).then_some(index)
+    }
+
+    pub(crate) fn position_of(&self, index: u32) -> Option<(u32, u32)> {
+        if index > self.len {
+            return None;
+        }
+        let line = self.breaks.partition_point(|&b| b < index) as u32;
+        let start = self.line_start(line)?;
+        Some((line, index - start))
+    }
+}
+
+/// Line break index of a [TextRef], which is maintained incrementally on every change made on that
+/// text, so that translation between indexes and (line, column) positions doesn't require
+/// traversing the whole text. Prefer it over [Text::position_of] and [Text::index_at] when
+/// positions are translated frequently, ie. by code editors or language servers.
+///
+/// Dropping an index unsubscribes it from text changes.
+///
+/// # Example
+///
+/// ```rust
+/// use yrs::lines::LineIndex;
+/// use yrs::{Doc, Text, Transact};
+///
+/// let doc = Doc::new();
+/// let text = doc.get_or_insert_text("text");
+/// let index = LineIndex::new(&text, &doc.transact());
+///
+/// text.insert(&mut doc.transact_mut(), 0, "fn main() {\n}\n");
+/// assert_eq!(index.line_count(), 3);
+/// assert_eq!(index.position_of(12), Some((1, 0)));
+///
+/// text.insert(&mut doc.transact_mut(), 12, "    println!();\n");
+/// assert_eq!(index.line_count(), 4);
+/// assert_eq!(index.index_at(2, 0), Some(28));
+/// ```
+///
+/// [Text::position_of]: crate::Text::position_of
+/// [Text::index_at]: crate::Text::index_at
+pub struct LineIndex {
+    lines: Arc<Mutex<LineBreaks>>,
+    _subscription: Subscription,
+}
+
+impl LineIndex {
+    /// Creates a line index of a given `text`, which will be kept up to date with changes made
+    /// on that text.
+    pub fn new<T: ReadTxn>(text: &TextRef, txn: &T) -> Self {
+        let kind = txn.store().options.offset_kind;
+        let lines = Arc::new(Mutex::new(LineBreaks::new(text.as_ref(), kind)));
+        let subscription = {
+            let lines = lines.clone();
+            text.observe(move |txn, event| {
+                let kind = txn.store().options.offset_kind;
+                lines.lock().unwrap().apply_delta(event.delta(txn), kind);
+            })
+        };
+        LineIndex {
+            lines,
+            _subscription: subscription,
+        }
+    }
+
+    /// Returns a number of lines of an indexed text. Empty text consists of a single line.
+    pub fn line_count(&self) -> u32 {
+        self.lines.lock().unwrap().line_count()
+    }
+
+    /// Returns an index of a given `column` within a given `line`. Returns `None` if that line
+    /// doesn't exist or is shorter than `column`.
+    pub fn index_at(&self, line: u32, column: u32) -> Option<u32> {
+        self.lines.lock().unwrap().index_at(line, column)
+    }
+
+    /// Returns a (line, column) position of a given `index`. Returns `None` if `index` is greater
+    /// than a text length.
+    pub fn position_of(&self, index: u32) -> Option<(u32, u32)> {
+        self.lines.lock().unwrap().position_of(index)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::lines::{LineBreaks, LineIndex};
+    use crate::{Doc, OffsetKind, Options, Text, Transact};
+
+    #[test]
+    fn line_index_incremental_updates() {
+        let doc = Doc::with_options(Options {
+            offset_kind: OffsetKind::Utf16,
+            ..Options::default()
+        });
+        let text = doc.get_or_insert_text("text");
+        text.insert(&mut doc.transact_mut(), 0, "ab\ncd\n");
+        let index = LineIndex::new(&text, &doc.transact());
+        assert_eq!(index.line_count(), 3);
+
+        text.insert(&mut doc.transact_mut(), 4, "😀\nx");
+        text.insert_embed(&mut doc.transact_mut(), 0, vec![1u8]);
+        text.remove_range(&mut doc.transact_mut(), 2, 2); // remove "b\n"
+        let check = {
+            let txn = doc.transact();
+            LineBreaks::new(text.as_ref(), OffsetKind::Utf16) == *index.lines.lock().unwrap()
+                && text.line_count(&txn) == index.line_count()
+        };
+        assert!(check);
+        // embed, "ac😀\nxd\n"
+        assert_eq!(index.line_count(), 3);
+        assert_eq!(index.position_of(0), Some((0, 0)));
+        assert_eq!(index.position_of(5), Some((0, 5)));
+        assert_eq!(index.position_of(6), Some((1, 0)));
+        assert_eq!(index.position_of(9), Some((2, 0)));
+        assert_eq!(index.position_of(10), None);
+        assert_eq!(index.index_at(1, 2), Some(8));
+        assert_eq!(index.index_at(1, 3), None);
+        assert_eq!(index.index_at(2, 0), Some(9));
+        assert_eq!(index.index_at(3, 0), None);
+        {
+            let txn = doc.transact();
+            assert_eq!(text.index_at(&txn, 1, 2), Some(8));
+            assert_eq!(text.position_of(&txn, 6), Some((1, 0)));
+        }
+    }
+}
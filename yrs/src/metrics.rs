@@ -0,0 +1,120 @@
+//! Hooks allowing to export internal document metrics (eg. to Prometheus) without forking the
+//! crate. See [MetricsSink] and [Doc::set_metrics_sink].
+//!
+//! [Doc::set_metrics_sink]: crate::Doc::set_metrics_sink
+
+use std::sync::Arc;
+
+/// Monotonically increasing metrics reported to a [MetricsSink].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Counter {
+    /// Number of updates applied onto a document via [TransactionMut::apply_update].
+    ///
+    /// [TransactionMut::apply_update]: crate::TransactionMut::apply_update
+    UpdatesApplied,
+    /// Number of bytes produced by encoding document state or transaction updates.
+    BytesEncoded,
+}
+
+/// Metrics describing current state of a document, reported to a [MetricsSink].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Gauge {
+    /// Number of blocks stashed in a pending update, waiting for their dependencies to arrive.
+    /// Reported every time an update is applied.
+    PendingBlocks,
+    /// Number of blocks stored in a document. Reported when a committed read-write transaction
+    /// has changed the document.
+    Blocks,
+}
+
+/// Receiver of the counters and gauges emitted by a document, it has been configured with
+/// using [Doc::set_metrics_sink]. Sink methods are called synchronously from within
+/// a transaction, so they should not block for too long.
+///
+/// [Doc::set_metrics_sink]: crate::Doc::set_metrics_sink
+pub trait MetricsSink {
+    /// Increments given `counter` by a `value`.
+    fn counter(&self, counter: Counter, value: u64);
+
+    /// Sets a current `value` of a given `gauge`.
+    fn gauge(&self, gauge: Gauge, value: u64);
+}
+
+/// Shared reference to a [MetricsSink] configured for a document.
+#[cfg(feature = "sync")]
+pub type MetricsSinkRef = Arc<dyn MetricsSink + Send + Sync + 'static>;
+
+/// Shared reference to a [MetricsSink] configured for a document.
+#[cfg(not(feature = "sync"))]
+pub type MetricsSinkRef = Arc<dyn MetricsSink + 'static>;
+
+#[cfg(test)]
+mod test {
+    use crate::metrics::{Counter, Gauge, MetricsSink};
+    use crate::updates::decoder::Decode;
+    use crate::{Doc, ReadTxn, StateVector, Text, Transact, Update};
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct TestSink {
+        counters: Mutex<HashMap<Counter, u64>>,
+        gauges: Mutex<HashMap<Gauge, u64>>,
+    }
+
+    impl MetricsSink for TestSink {
+        fn counter(&self, counter: Counter, value: u64) {
+            *self.counters.lock().unwrap().entry(counter).or_default() += value;
+        }
+
+        fn gauge(&self, gauge: Gauge, value: u64) {
+            self.gauges.lock().unwrap().insert(gauge, value);
+        }
+    }
+
+    #[test]
+    fn metrics_sink() {
+        let d1 = Doc::with_client_id(1);
+        let t1 = d1.get_or_insert_text("text");
+        let u1 = {
+            let mut txn = d1.transact_mut();
+            t1.insert(&mut txn, 0, "abc");
+            txn.encode_update_v1()
+        };
+        let u2 = {
+            let mut txn = d1.transact_mut();
+            t1.insert(&mut txn, 3, "def");
+            txn.encode_update_v1()
+        };
+
+        let sink = Arc::new(TestSink::default());
+        let d2 = Doc::with_client_id(2);
+        d2.set_metrics_sink(sink.clone()).unwrap();
+        {
+            let mut txn = d2.transact_mut();
+            txn.apply_update(Update::decode_v1(&u2).unwrap());
+        }
+        assert_eq!(sink.gauges.lock().unwrap()[&Gauge::PendingBlocks], 1);
+        // nothing was integrated yet, so block count is not reported
+        assert!(!sink.gauges.lock().unwrap().contains_key(&Gauge::Blocks));
+        {
+            let mut txn = d2.transact_mut();
+            txn.apply_update(Update::decode_v1(&u1).unwrap());
+        }
+        assert_eq!(sink.gauges.lock().unwrap()[&Gauge::PendingBlocks], 0);
+        assert_eq!(sink.gauges.lock().unwrap()[&Gauge::Blocks], 1);
+        assert_eq!(sink.counters.lock().unwrap()[&Counter::UpdatesApplied], 2);
+
+        sink.gauges.lock().unwrap().clear();
+        drop(d2.transact_mut());
+        assert!(sink.gauges.lock().unwrap().is_empty());
+
+        let bin = d2
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+        assert_eq!(
+            sink.counters.lock().unwrap()[&Counter::BytesEncoded],
+            bin.len() as u64
+        );
+    }
+}
@@ -5,7 +5,7 @@ use crate::encoding::read::Error;
 use crate::transaction::TransactionMut;
 use crate::updates::decoder::{Decode, Decoder};
 use crate::updates::encoder::{Encode, Encoder};
-use crate::{BranchID, ReadTxn, WriteTxn, ID};
+use crate::{BranchID, MovePolicy, ReadTxn, WriteTxn, ID};
 use serde::de::Visitor;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::collections::HashSet;
@@ -170,11 +170,23 @@ impl Move {
                     a.client < b.client || (a.client == b.client && a.clock < b.clock)
                 }
 
+                #[inline]
+                fn overrides(policy: MovePolicy, prev: &ID, next: &ID) -> bool {
+                    match policy {
+                        MovePolicy::HigherIdWins => is_lower(prev, next),
+                        MovePolicy::LowerIdWins => is_lower(next, prev),
+                    }
+                }
+
                 if adapt_priority
                     || next_prio < self.priority
                     || (prev_move.is_some()
                         && next_prio == self.priority
-                        && is_lower(prev_move.unwrap().id(), item.id()))
+                        && overrides(
+                            txn.store().options.move_policy,
+                            prev_move.unwrap().id(),
+                            item.id(),
+                        ))
                 {
                     if let Some(moved_ptr) = prev_move.clone() {
                         if let ItemContent::Move(m) = &moved_ptr.content {
@@ -0,0 +1,102 @@
+//! Hooks allowing multi-tenant hosts to enforce per-document storage quotas before remote content
+//! is integrated into a document. See [Doc::set_quota_hook].
+//!
+//! [Doc::set_quota_hook]: crate::Doc::set_quota_hook
+
+/// Prospective growth of a document, computed for an update before it's integrated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SizeDelta {
+    /// Number of blocks an update is about to add to a document.
+    pub blocks: u32,
+    /// Number of elements (eg. characters or array items) an update is about to add to
+    /// a document. This includes elements which are already deleted, as they still take space
+    /// in a document until they are garbage collected.
+    pub len: u64,
+    /// Number of elements stored in a document before an update is applied, which is the sum of
+    /// all clocks of document's state vector.
+    pub current_len: u64,
+}
+
+/// Callback used to accept or veto an update based on its [SizeDelta]. Returning `false` rejects
+/// an update before any of its content is integrated.
+#[cfg(feature = "sync")]
+pub type QuotaFn = Box<dyn Fn(&SizeDelta) -> bool + Send + Sync + 'static>;
+
+/// Callback used to accept or veto an update based on its [SizeDelta]. Returning `false` rejects
+/// an update before any of its content is integrated.
+#[cfg(not(feature = "sync"))]
+pub type QuotaFn = Box<dyn Fn(&SizeDelta) -> bool + 'static>;
+
+#[cfg(test)]
+mod test {
+    use crate::quota::SizeDelta;
+    use crate::update::UpdateError;
+    use crate::updates::decoder::Decode;
+    use crate::{Doc, GetString, ReadTxn, StateVector, Text, Transact, Update};
+    use assert_matches2::assert_matches;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn quota_hook_vetoes_updates() {
+        let d1 = Doc::with_client_id(1);
+        let t1 = d1.get_or_insert_text("text");
+        t1.insert(&mut d1.transact_mut(), 0, "hello");
+        let u1 = d1
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+        let sv = d1.transact().state_vector();
+        t1.insert(&mut d1.transact_mut(), 5, " world");
+        let u2 = d1.transact().encode_state_as_update_v1(&sv);
+
+        let d2 = Doc::with_client_id(2);
+        let t2 = d2.get_or_insert_text("text");
+        let deltas = Arc::new(Mutex::new(Vec::new()));
+        let d = deltas.clone();
+        d2.set_quota_hook(move |delta: &SizeDelta| {
+            d.lock().unwrap().push(*delta);
+            delta.current_len + delta.len <= 8
+        })
+        .unwrap();
+
+        d2.transact_mut()
+            .apply_update(Update::decode_v1(&u1).unwrap());
+        assert_eq!(t2.get_string(&d2.transact()), "hello");
+
+        // update which would exceed the quota is rejected as a whole
+        let res = d2
+            .transact_mut()
+            .apply_update_strict(Update::decode_v1(&u2).unwrap());
+        assert_matches!(res, Err(UpdateError::QuotaExceeded(delta)));
+        assert_eq!(
+            delta,
+            SizeDelta {
+                blocks: 1,
+                len: 6,
+                current_len: 5
+            }
+        );
+        d2.transact_mut()
+            .apply_update(Update::decode_v1(&u2).unwrap());
+        assert_eq!(t2.get_string(&d2.transact()), "hello");
+        let res = d2
+            .transact_mut()
+            .try_apply_update(Update::decode_v1(&u2).unwrap());
+        assert_matches!(res, Err(UpdateError::QuotaExceeded(_)));
+        let report = d2
+            .transact_mut()
+            .apply_updates_lenient_v1([u1.as_slice(), u2.as_slice()]);
+        assert!(!report.is_clean());
+        assert_matches!(&report.rejected[..], [(1, UpdateError::QuotaExceeded(_))]);
+        assert_eq!(t2.get_string(&d2.transact()), "hello");
+
+        // updates that add nothing new don't call the hook
+        d2.transact_mut()
+            .apply_update(Update::decode_v1(&u1).unwrap());
+        assert_eq!(deltas.lock().unwrap().len(), 5);
+
+        d2.remove_quota_hook().unwrap();
+        d2.transact_mut()
+            .apply_update(Update::decode_v1(&u2).unwrap());
+        assert_eq!(t2.get_string(&d2.transact()), "hello world");
+    }
+}
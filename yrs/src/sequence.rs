@@ -0,0 +1,327 @@
+//! Extension point for the algorithm used to order concurrently inserted elements of sequence
+//! types (texts, arrays, XML nodes) and map entries. By default Yrs uses [Yata], which is
+//! compatible with Yjs. Alternative algorithms can be configured using
+//! [Doc::set_sequence_integrator], while the block store, encoding and transaction layers remain
+//! the same.
+//!
+//! **Warning:** all peers collaborating on the same document must use the same algorithm,
+//! otherwise their document replicas will not converge.
+//!
+//! [Doc::set_sequence_integrator]: crate::Doc::set_sequence_integrator
+
+use crate::block::{Item, ID};
+use crate::branch::Branch;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+
+/// Description of a block taking part in conflict resolution performed by
+/// [SequenceIntegrator].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SequenceItem {
+    /// ID of the first element of a block.
+    pub id: ID,
+    /// Number of elements stored in a block.
+    pub len: u32,
+    /// ID of an element, which was a left neighbor of this block at the moment of insertion.
+    pub origin: Option<ID>,
+    /// ID of an element, which was a right neighbor of this block at the moment of insertion.
+    pub right_origin: Option<ID>,
+}
+
+impl SequenceItem {
+    /// Checks if a given element `id` belongs to this block.
+    pub fn contains(&self, id: &ID) -> bool {
+        self.id.client == id.client
+            && id.clock >= self.id.clock
+            && id.clock < self.id.clock + self.len
+    }
+}
+
+impl<'a> From<&'a Item> for SequenceItem {
+    fn from(item: &'a Item) -> Self {
+        SequenceItem {
+            id: item.id,
+            len: item.len,
+            origin: item.origin,
+            right_origin: item.right_origin,
+        }
+    }
+}
+
+/// Shared reference to a [SequenceIntegrator] configured for a document.
+#[cfg(feature = "sync")]
+pub type SequenceIntegratorRef = Arc<dyn SequenceIntegrator + Send + Sync + 'static>;
+
+/// Shared reference to a [SequenceIntegrator] configured for a document.
+#[cfg(not(feature = "sync"))]
+pub type SequenceIntegratorRef = Arc<dyn SequenceIntegrator + 'static>;
+
+/// Algorithm resolving the position of a block inserted concurrently with other blocks at the
+/// same place of a sequence.
+pub trait SequenceIntegrator {
+    /// Returns a number of `conflicts` a new `item` should be placed after. `conflicts` are the
+    /// blocks currently placed between item's left and right neighbors, in their document order.
+    /// Returned value must not be greater than a number of conflicts (greater values are clamped
+    /// to it), and `0` means that item should be placed right after its left neighbor.
+    ///
+    /// `parent` is a shared type the item is inserted into, which allows to choose different
+    /// algorithms for different types, ie. based on [Branch::id] or [Branch::type_ref].
+    fn position(&self, parent: &Branch, item: &SequenceItem, conflicts: &[SequenceItem]) -> usize;
+
+    /// Works like [SequenceIntegrator::position], but it's used for blocks inserted concurrently
+    /// under the same `key` of a map component (ie. [Map] entries or XML attributes). From all
+    /// conflicting blocks, the one placed last becomes a current value of that entry, while the
+    /// others are deleted. By default, it uses [SequenceIntegrator::position] - which for [Yata]
+    /// means that the write made by a peer with the highest client ID wins.
+    ///
+    /// [Map]: crate::Map
+    fn entry_position(
+        &self,
+        parent: &Branch,
+        key: &str,
+        item: &SequenceItem,
+        conflicts: &[SequenceItem],
+    ) -> usize {
+        let _ = key;
+        self.position(parent, item, conflicts)
+    }
+}
+
+/// YATA conflict resolution algorithm, compatible with Yjs. This is a default
+/// [SequenceIntegrator] used by every document.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Yata;
+
+impl SequenceIntegrator for Yata {
+    fn position(&self, _parent: &Branch, item: &SequenceItem, conflicts: &[SequenceItem]) -> usize {
+        let mut position = 0;
+        let mut conflicting = HashSet::new();
+        // start IDs of visited conflicts, used to find blocks containing their origins
+        let mut visited: BTreeMap<ID, usize> = BTreeMap::new();
+        // Let c in conflicting, b in items before origin
+        // ***{origin}bbbb{this}{c,b}{c,b}{o}***
+        // Note that conflicting is a subset of items before origin
+        for (i, other) in conflicts.iter().enumerate() {
+            conflicting.insert(i);
+            visited.insert(other.id, i);
+            if item.origin == other.origin {
+                // case 1
+                if other.id.client < item.id.client {
+                    position = i + 1;
+                    conflicting.clear();
+                } else if item.right_origin == other.right_origin {
+                    // `item` and `other` are conflicting and point to the same integration
+                    // points. The id decides which item comes first. Since `item` is to
+                    // the left of `other`, we can break here.
+                    break;
+                }
+            } else {
+                let origin = other.origin.and_then(|id| {
+                    let (_, &j) = visited.range(..=id).next_back()?;
+                    (j < i && conflicts[j].contains(&id)).then_some(j)
+                });
+                match origin {
+                    Some(j) => {
+                        if !conflicting.contains(&j) {
+                            position = i + 1;
+                            conflicting.clear();
+                        }
+                    }
+                    None => break,
+                }
+            }
+        }
+        position
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::branch::Branch;
+    use crate::sequence::{SequenceIntegrator, SequenceItem};
+    use crate::types::TypeRef;
+    use crate::updates::decoder::Decode;
+    use crate::{Doc, GetString, Map, Out, ReadTxn, StateVector, Text, Transact, Update};
+    use std::sync::Arc;
+
+    /// Orders concurrent inserts of texts by descending client ID, keeping YATA for other types.
+    struct ReverseClients;
+
+    impl SequenceIntegrator for ReverseClients {
+        fn position(
+            &self,
+            parent: &Branch,
+            item: &SequenceItem,
+            conflicts: &[SequenceItem],
+        ) -> usize {
+            if *parent.type_ref() != TypeRef::Text {
+                return crate::sequence::Yata.position(parent, item, conflicts);
+            }
+            conflicts
+                .iter()
+                .take_while(|c| c.origin == item.origin && c.id.client > item.id.client)
+                .count()
+        }
+    }
+
+    /// Resolves concurrent map writes in favour of a designated client.
+    struct Authoritative(u64);
+
+    impl SequenceIntegrator for Authoritative {
+        fn position(
+            &self,
+            parent: &Branch,
+            item: &SequenceItem,
+            conflicts: &[SequenceItem],
+        ) -> usize {
+            crate::sequence::Yata.position(parent, item, conflicts)
+        }
+
+        fn entry_position(
+            &self,
+            parent: &Branch,
+            _key: &str,
+            item: &SequenceItem,
+            conflicts: &[SequenceItem],
+        ) -> usize {
+            if conflicts.iter().any(|c| c.origin != item.origin) {
+                return self.position(parent, item, conflicts);
+            }
+            let rank = |i: &SequenceItem| (i.id.client == self.0, i.id.client);
+            conflicts
+                .iter()
+                .take_while(|c| rank(c) < rank(item))
+                .count()
+        }
+    }
+
+    #[test]
+    fn custom_map_entry_integrator() {
+        let docs: Vec<_> = (1..=3).map(Doc::with_client_id).collect();
+        for doc in docs.iter() {
+            doc.set_sequence_integrator(Arc::new(Authoritative(1)))
+                .unwrap();
+        }
+        let maps: Vec<_> = docs.iter().map(|d| d.get_or_insert_map("map")).collect();
+        for (i, (doc, map)) in docs.iter().zip(maps.iter()).enumerate() {
+            map.insert(&mut doc.transact_mut(), "key", i as u32 + 1);
+        }
+        exchange(&docs[0], &docs[1]);
+        exchange(&docs[1], &docs[2]);
+        exchange(&docs[0], &docs[1]);
+        for (doc, map) in docs.iter().zip(maps.iter()) {
+            // without a custom integrator, client 3 would win
+            assert_eq!(map.get(&doc.transact(), "key"), Some(Out::from(1)));
+        }
+    }
+
+    fn exchange(d1: &Doc, d2: &Doc) {
+        let u1 = d1
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+        let u2 = d2
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+        d1.transact_mut()
+            .apply_update(Update::decode_v1(&u2).unwrap());
+        d2.transact_mut()
+            .apply_update(Update::decode_v1(&u1).unwrap());
+    }
+
+    #[test]
+    fn custom_sequence_integrator() {
+        let d1 = Doc::with_client_id(1);
+        let d2 = Doc::with_client_id(2);
+        let t1 = d1.get_or_insert_text("text");
+        let t2 = d2.get_or_insert_text("text");
+
+        // default YATA orders concurrent inserts by ascending client ID
+        t1.insert(&mut d1.transact_mut(), 0, "a");
+        t2.insert(&mut d2.transact_mut(), 0, "b");
+        exchange(&d1, &d2);
+        assert_eq!(t1.get_string(&d1.transact()), "ab");
+        assert_eq!(t2.get_string(&d2.transact()), "ab");
+
+        let d3 = Doc::with_client_id(3);
+        let d4 = Doc::with_client_id(4);
+        d3.set_sequence_integrator(Arc::new(ReverseClients))
+            .unwrap();
+        d4.set_sequence_integrator(Arc::new(ReverseClients))
+            .unwrap();
+        let t3 = d3.get_or_insert_text("text");
+        let t4 = d4.get_or_insert_text("text");
+        t3.insert(&mut d3.transact_mut(), 0, "c");
+        t4.insert(&mut d4.transact_mut(), 0, "d");
+        exchange(&d3, &d4);
+        assert_eq!(t3.get_string(&d3.transact()), "dc");
+        assert_eq!(t4.get_string(&d4.transact()), "dc");
+    }
+
+    fn concurrent_edits(seed: u64, yata: bool) -> String {
+        let mut rng = fastrand::Rng::with_seed(seed);
+        let docs: Vec<_> = (1..=3).map(Doc::with_client_id).collect();
+        if yata {
+            for doc in docs.iter() {
+                doc.set_sequence_integrator(Arc::new(crate::sequence::Yata))
+                    .unwrap();
+            }
+        }
+        for _ in 0..20 {
+            for doc in docs.iter() {
+                let text = doc.get_or_insert_text("text");
+                let mut txn = doc.transact_mut();
+                let index = rng.u32(0..=text.len(&txn));
+                let len = rng.usize(1..4);
+                let chunk: String = (0..len).map(|_| rng.lowercase()).collect();
+                text.insert(&mut txn, index, &chunk);
+            }
+            let (a, b) = (rng.usize(0..3), rng.usize(0..3));
+            if a != b {
+                exchange(&docs[a], &docs[b]);
+            }
+        }
+        exchange(&docs[0], &docs[1]);
+        exchange(&docs[1], &docs[2]);
+        exchange(&docs[0], &docs[1]);
+        let text = docs[0].get_or_insert_text("text");
+        let result = text.get_string(&docs[0].transact());
+        for doc in docs.iter() {
+            let text = doc.get_or_insert_text("text");
+            assert_eq!(text.get_string(&doc.transact()), result);
+        }
+        result
+    }
+
+    #[test]
+    fn explicit_yata_matches_default_integration() {
+        for seed in 0..10 {
+            assert_eq!(concurrent_edits(seed, true), concurrent_edits(seed, false));
+        }
+    }
+
+    struct OutOfRange;
+
+    impl SequenceIntegrator for OutOfRange {
+        fn position(&self, _: &Branch, _: &SequenceItem, conflicts: &[SequenceItem]) -> usize {
+            conflicts.len() + 1
+        }
+    }
+
+    #[test]
+    fn out_of_range_position_is_clamped() {
+        let d1 = Doc::with_client_id(1);
+        let d2 = Doc::with_client_id(2);
+        d1.set_sequence_integrator(Arc::new(OutOfRange)).unwrap();
+        d2.set_sequence_integrator(Arc::new(OutOfRange)).unwrap();
+        let t1 = d1.get_or_insert_text("text");
+        let t2 = d2.get_or_insert_text("text");
+        t1.insert(&mut d1.transact_mut(), 0, "a");
+        t2.insert(&mut d2.transact_mut(), 0, "b");
+        exchange(&d1, &d2);
+        // remote block is integrated after all conflicting ones instead of panicking
+        assert_eq!(t1.get_string(&d1.transact()), "ab");
+        assert_eq!(t2.get_string(&d2.transact()), "ba");
+        assert!(d1.transact().store().pending_update().is_none());
+        assert!(d2.transact().store().pending_update().is_none());
+    }
+}
@@ -0,0 +1,273 @@
+//! Signed updates, which allow peers to verify that updates claiming to be authored by a given
+//! client were actually produced by an owner of that client's key. This is important in
+//! zero-trust peer-to-peer topologies, where any peer could otherwise forge updates under another
+//! client's ID.
+//!
+//! Updates are signed with Ed25519 using [UpdateSigner] and verified with [UpdateVerifier], which
+//! uses a user-provided callback to resolve a public key of a given client ID. A verifier rejects
+//! updates, which contain blocks of any client ID not resolving to the same public key as the
+//! signer. Because of that, signed updates should be relayed as they are: update merged from
+//! changes of many authors (ie. sync step 2 reply of [DefaultProtocol]) cannot be signed on their
+//! behalf. Verification can be plugged into y-sync protocol by overriding
+//! [Protocol::decode_update].
+//!
+//! Signed payloads have the following layout:
+//! `[version: u8][signer client ID: var u64][update: var-length buffer][signature: 64 bytes]`.
+//!
+//! ```rust
+//! use std::collections::HashMap;
+//! use yrs::signing::{SigningKey, UpdateSigner, UpdateVerifier};
+//! use yrs::{Doc, GetString, Text, Transact};
+//!
+//! let alice = UpdateSigner::new(1, SigningKey::from_bytes(&[7; 32]));
+//! let keys = HashMap::from([(1, alice.verifying_key())]);
+//! let verifier = UpdateVerifier::new(move |client_id| keys.get(&client_id).cloned());
+//!
+//! let local = Doc::with_client_id(1);
+//! let text = local.get_or_insert_text("text");
+//! let signed = {
+//!     let mut txn = local.transact_mut();
+//!     text.insert(&mut txn, 0, "hello");
+//!     alice.sign(&txn.encode_update_v1())
+//! };
+//!
+//! let remote = Doc::with_client_id(2);
+//! verifier.apply_update(&mut remote.transact_mut(), &signed).unwrap();
+//! let text = remote.get_or_insert_text("text");
+//! assert_eq!(text.get_string(&remote.transact()), "hello");
+//! ```
+//!
+//! [DefaultProtocol]: crate::sync::DefaultProtocol
+//! [Protocol::decode_update]: crate::sync::Protocol::decode_update
+
+use crate::block::ClientID;
+use crate::encoding::read::{self, Cursor, Read};
+use crate::encoding::write::Write;
+use crate::update::BlockCarrier;
+use crate::updates::decoder::Decode;
+use crate::{TransactionMut, Update};
+use ed25519_dalek::{Signer, Verifier};
+use thiserror::Error;
+
+pub use ed25519_dalek::{Signature, SigningKey, VerifyingKey};
+
+const VERSION: u8 = 1;
+
+/// Signs updates produced by a client with a given ID.
+#[derive(Clone)]
+pub struct UpdateSigner {
+    client_id: ClientID,
+    key: SigningKey,
+}
+
+impl UpdateSigner {
+    /// Creates a new signer for updates authored by a given `client_id`.
+    pub fn new(client_id: ClientID, key: SigningKey) -> Self {
+        UpdateSigner { client_id, key }
+    }
+
+    /// Returns a client ID, which signatures are produced for.
+    pub fn client_id(&self) -> ClientID {
+        self.client_id
+    }
+
+    /// Returns a public key, which should be used by other peers to verify signatures produced by
+    /// this signer.
+    pub fn verifying_key(&self) -> VerifyingKey {
+        self.key.verifying_key()
+    }
+
+    /// Wraps lib0 v1 encoded `update` into a signed payload.
+    pub fn sign(&self, update: &[u8]) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(update.len() + 80);
+        buf.write_u8(VERSION);
+        buf.write_var(self.client_id);
+        buf.write_buf(update);
+        let signature = self.key.sign(&buf);
+        buf.write_all(&signature.to_bytes());
+        buf
+    }
+}
+
+impl std::fmt::Debug for UpdateSigner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // never expose key material in logs
+        f.debug_struct("UpdateSigner")
+            .field("client_id", &self.client_id)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A signed payload split into its components, see [SignedUpdate::parse].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedUpdate<'a> {
+    /// ID of a client, who claims to have signed an update.
+    pub signer: ClientID,
+    /// lib0 v1 encoded update.
+    pub update: &'a [u8],
+    /// Signature of a payload.
+    pub signature: Signature,
+    signed: &'a [u8],
+}
+
+impl<'a> SignedUpdate<'a> {
+    /// Splits signed payload produced by [UpdateSigner::sign] into its components, without
+    /// verifying it.
+    pub fn parse(data: &'a [u8]) -> Result<Self, SignatureError> {
+        if data.len() < Signature::BYTE_SIZE {
+            return Err(SignatureError::Malformed);
+        }
+        let (signed, signature) = data.split_at(data.len() - Signature::BYTE_SIZE);
+        let mut cursor = Cursor::new(signed);
+        match cursor.read_u8()? {
+            VERSION => {}
+            version => return Err(SignatureError::UnsupportedVersion(version)),
+        }
+        let signer: ClientID = cursor.read_var()?;
+        let len: usize = cursor.read_var()?;
+        if cursor.next.checked_add(len) != Some(signed.len()) {
+            return Err(SignatureError::Malformed);
+        }
+        let update = &signed[cursor.next..];
+        let signature = Signature::from_slice(signature).map_err(|_| SignatureError::Malformed)?;
+        Ok(SignedUpdate {
+            signer,
+            update,
+            signature,
+            signed,
+        })
+    }
+}
+
+/// Verifies signed payloads produced by [UpdateSigner]. Public keys of clients are resolved using
+/// a callback provided by the user, ie. backed by an identity service.
+pub struct UpdateVerifier<F> {
+    resolve: F,
+}
+
+impl<F> UpdateVerifier<F>
+where
+    F: Fn(ClientID) -> Option<VerifyingKey>,
+{
+    /// Creates a new verifier, where `resolve` returns a public key of a given client ID, or
+    /// `None` if client is not known.
+    pub fn new(resolve: F) -> Self {
+        UpdateVerifier { resolve }
+    }
+
+    /// Verifies a signed payload and returns an update it contains. Verification fails if:
+    ///
+    /// - signer's public key is not known or signature doesn't match it,
+    /// - update contains blocks inserted by a client, which public key is not the same as the
+    ///   signer's one.
+    pub fn verify(&self, data: &[u8]) -> Result<Update, SignatureError> {
+        let signed = SignedUpdate::parse(data)?;
+        let key =
+            (self.resolve)(signed.signer).ok_or(SignatureError::UnknownSigner(signed.signer))?;
+        key.verify(signed.signed, &signed.signature)
+            .map_err(|_| SignatureError::InvalidSignature(signed.signer))?;
+        let update = Update::decode_v1(signed.update)?;
+        for (&client, blocks) in update.blocks.clients.iter() {
+            let has_content = blocks
+                .iter()
+                .any(|block| !matches!(block, BlockCarrier::Skip(_)));
+            if has_content && client != signed.signer && (self.resolve)(client) != Some(key) {
+                return Err(SignatureError::ForgedClient {
+                    signer: signed.signer,
+                    client,
+                });
+            }
+        }
+        Ok(update)
+    }
+
+    /// Verifies a signed payload (see [UpdateVerifier::verify]) and applies an update it contains
+    /// within a given transaction. Updates failing verification are not applied.
+    pub fn apply_update(
+        &self,
+        txn: &mut TransactionMut,
+        data: &[u8],
+    ) -> Result<(), SignatureError> {
+        let update = self.verify(data)?;
+        txn.apply_update(update);
+        Ok(())
+    }
+}
+
+/// Error returned when a signed payload couldn't be verified.
+#[derive(Debug, Error)]
+pub enum SignatureError {
+    /// Payload is not a valid signed update.
+    #[error("malformed signed payload")]
+    Malformed,
+    /// Payload has been signed using an unknown format version.
+    #[error("unsupported signed payload version: {0}")]
+    UnsupportedVersion(u8),
+    /// Public key of a signer couldn't be resolved.
+    #[error("unknown signer: {0}")]
+    UnknownSigner(ClientID),
+    /// Signature doesn't match signer's public key.
+    #[error("invalid signature of client {0}")]
+    InvalidSignature(ClientID),
+    /// Update contains changes of a client, which doesn't belong to a signer.
+    #[error("update signed by {signer} contains changes of client {client}")]
+    ForgedClient { signer: ClientID, client: ClientID },
+    /// Signed payload doesn't contain a valid update.
+    #[error("failed to decode signed update: {0}")]
+    Decoding(#[from] read::Error),
+}
+
+#[cfg(test)]
+mod test {
+    use crate::signing::{SignatureError, SigningKey, UpdateSigner, UpdateVerifier};
+    use crate::{Doc, GetString, Text, Transact};
+    use assert_matches2::assert_matches;
+    use std::collections::HashMap;
+
+    #[test]
+    fn verify_signed_updates() {
+        let alice = UpdateSigner::new(1, SigningKey::from_bytes(&[1; 32]));
+        let mallory = UpdateSigner::new(2, SigningKey::from_bytes(&[2; 32]));
+        let keys = HashMap::from([(1, alice.verifying_key()), (2, mallory.verifying_key())]);
+        let verifier = UpdateVerifier::new(move |client_id| keys.get(&client_id).cloned());
+
+        let d1 = Doc::with_client_id(1);
+        let text = d1.get_or_insert_text("text");
+        let update = {
+            let mut txn = d1.transact_mut();
+            text.insert(&mut txn, 0, "hello");
+            txn.encode_update_v1()
+        };
+
+        let d2 = Doc::with_client_id(3);
+        verifier
+            .apply_update(&mut d2.transact_mut(), &alice.sign(&update))
+            .unwrap();
+        let text2 = d2.get_or_insert_text("text");
+        assert_eq!(text2.get_string(&d2.transact()), "hello");
+
+        // mallory cannot sign changes made under alice's client ID
+        assert_matches!(
+            verifier.verify(&mallory.sign(&update)),
+            Err(SignatureError::ForgedClient {
+                signer: 2,
+                client: 1
+            })
+        );
+        // ... nor pretend to be alice
+        let forged = UpdateSigner::new(1, SigningKey::from_bytes(&[2; 32])).sign(&update);
+        assert_matches!(
+            verifier.verify(&forged),
+            Err(SignatureError::InvalidSignature(1))
+        );
+        let mut tampered = alice.sign(&update);
+        tampered[5] ^= 1;
+        assert!(verifier.verify(&tampered).is_err());
+        let unknown = UpdateSigner::new(4, SigningKey::from_bytes(&[4; 32])).sign(&update);
+        assert_matches!(
+            verifier.verify(&unknown),
+            Err(SignatureError::UnknownSigner(4))
+        );
+        assert_matches!(verifier.verify(&[1, 2, 3]), Err(SignatureError::Malformed));
+    }
+}
@@ -1,12 +1,16 @@
-use crate::block::{BlockCell, ClientID, ItemContent, ItemPtr};
-use crate::block_store::BlockStore;
+use crate::access::{AccessFn, PendingAccess};
+use crate::block::{BlockCell, ClientID, Item, ItemContent, ItemPtr};
+use crate::block_store::{BlockStore, Pivot};
 use crate::branch::{Branch, BranchPtr};
 use crate::doc::{DocAddr, Options};
 use crate::error::Error;
 use crate::event::SubdocsEvent;
-use crate::id_set::DeleteSet;
+use crate::id_set::{DeleteSet, IdSet};
+use crate::metrics::{Counter, MetricsSinkRef};
+use crate::quota::QuotaFn;
+use crate::sequence::SequenceIntegratorRef;
 use crate::slice::ItemSlice;
-use crate::types::{Path, PathSegment, TypeRef};
+use crate::types::{Path, PathSegment, TypePtr, TypeRef};
 use crate::update::PendingUpdate;
 use crate::updates::encoder::{Encode, Encoder};
 use crate::StateVector;
@@ -17,7 +21,7 @@ use crate::{
 use atomic_refcell::{AtomicRef, AtomicRefCell, AtomicRefMut, BorrowError, BorrowMutError};
 use std::borrow::Borrow;
 use std::collections::hash_map::Entry;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::ops::Deref;
 use std::sync::Arc;
 
@@ -59,6 +63,31 @@ pub struct Store {
 
     /// Dependencies between items and weak links pointing to these items.
     pub(crate) linked_by: HashMap<ItemPtr, HashSet<BranchPtr>>,
+
+    /// Optional receiver of document metrics.
+    pub(crate) metrics: Option<MetricsSinkRef>,
+
+    /// Optional hook used to veto updates, which would exceed document storage quota.
+    pub(crate) quota: Option<QuotaFn>,
+
+    /// Optional policy used to strip changes of remote updates touching forbidden types.
+    pub(crate) access: Option<AccessFn>,
+
+    /// Changes of pending updates, which scope couldn't be checked by an access policy yet.
+    pub(crate) access_pending: Vec<PendingAccess>,
+
+    /// Optional algorithm used to order concurrent inserts. [Yata] is used when not set.
+    ///
+    /// [Yata]: crate::sequence::Yata
+    pub(crate) sequence: Option<SequenceIntegratorRef>,
+
+    /// Source code locations, at which currently alive transactions over this store have been
+    /// started. Used to point to a conflicting transaction when another one cannot be acquired.
+    ///
+    /// It's guarded separately from the store itself, so that it can be read while the store is
+    /// borrowed by a conflicting transaction.
+    #[cfg(debug_assertions)]
+    pub(crate) active_txns: std::sync::Mutex<Vec<&'static std::panic::Location<'static>>>,
 }
 
 impl Store {
@@ -75,6 +104,20 @@ impl Store {
             pending: None,
             pending_ds: None,
             parent: None,
+            metrics: None,
+            quota: None,
+            access: None,
+            access_pending: Vec::new(),
+            sequence: None,
+            #[cfg(debug_assertions)]
+            active_txns: std::sync::Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Reports a number of bytes encoded to a configured [MetricsSink], if there's any.
+    pub(crate) fn report_encoded(&self, bin: &[u8]) {
+        if let Some(metrics) = self.metrics.as_ref() {
+            metrics.counter(Counter::BytesEncoded, bin.len() as u64);
         }
     }
 
@@ -102,6 +145,21 @@ impl Store {
         self.blocks.get_clock(&self.options.client_id)
     }
 
+    /// Returns an ID of the next block about to be inserted by a current store client.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a current document is an apply-only replica (see [Options::apply_only]), since
+    /// such documents are not allowed to author any content. Transactions for local changes are
+    /// refused for such documents upfront, so this can only happen if content is inserted from
+    /// within a callback receiving a transaction used to apply remote updates.
+    pub(crate) fn next_local_id(&self) -> ID {
+        if self.options.apply_only {
+            panic!("cannot insert local changes into an apply-only document replica");
+        }
+        ID::new(self.options.client_id, self.get_local_state())
+    }
+
     /// Returns a branch reference to a complex type identified by its pointer. Returns `None` if
     /// no such type could be found or was ever defined.
     pub(crate) fn get_type<K: Borrow<str>>(&self, key: K) -> Option<BranchPtr> {
@@ -168,12 +226,11 @@ impl Store {
             let clock = clock.min(blocks.clock() + 1);
             let last_idx = blocks.find_pivot(clock - 1).unwrap();
             // write # encoded structs
-            encoder.write_var(last_idx + 1);
+            encoder.write_var(last_idx.index() + 1);
             encoder.write_client(client);
             encoder.write_var(0);
-            for i in 0..last_idx {
-                let block = blocks[i].as_slice();
-                block.encode(encoder);
+            for block in blocks.iter().take(last_idx.index()) {
+                block.as_slice().encode(encoder);
             }
             let last_block = &blocks[last_idx];
             // write first struct with an offset
@@ -212,10 +269,15 @@ impl Store {
         encoder.write_var(diff.len());
         for (client, clock) in diff {
             let blocks = self.blocks.get_client(&client).unwrap();
-            let clock = clock.max(blocks.get(0).map(|i| i.clock_start()).unwrap_or_default()); // make sure the first id exists
+            let clock = clock.max(
+                blocks
+                    .get(Pivot::FIRST)
+                    .map(|i| i.clock_start())
+                    .unwrap_or_default(),
+            ); // make sure the first id exists
             let start = blocks.find_pivot(clock).unwrap();
             // write # encoded structs
-            encoder.write_var(blocks.len() - start);
+            encoder.write_var(blocks.len() - start.index());
             encoder.write_client(client);
             encoder.write_var(clock);
             let first_block = blocks.get(start).unwrap();
@@ -224,8 +286,7 @@ impl Store {
             let mut slice = first_block.as_slice();
             slice.trim_start(offset);
             slice.encode(encoder);
-            for i in (start + 1)..blocks.len() {
-                let block = &blocks[i];
+            for block in blocks.iter().skip(start.next().index()) {
                 block.as_slice().encode(encoder);
             }
         }
@@ -300,8 +361,8 @@ impl Store {
                     let dest = self.linked_by.entry(ItemPtr::from(&new)).or_default();
                     dest.extend(source);
                 }
-                blocks.insert(i + 1, BlockCell::Block(new));
-                i += 1;
+                i = i.next();
+                blocks.insert(i, BlockCell::Block(new));
                 //todo: txn merge blocks insert?
                 index = Some(i);
             }
@@ -323,7 +384,7 @@ impl Store {
                 let dest = self.linked_by.entry(ItemPtr::from(&new)).or_default();
                 dest.extend(source);
             }
-            blocks.insert(i + 1, BlockCell::Block(new));
+            blocks.insert(i.next(), BlockCell::Block(new));
             //todo: txn merge blocks insert?
         }
 
@@ -355,6 +416,61 @@ impl Store {
         slice
     }
 
+    /// Returns exact ID ranges, which are known to a remote peer (described by its `remote` state
+    /// vector), but not integrated into current store yet. Ranges already received and stashed
+    /// in a pending update are not included.
+    pub fn missing_ranges(&self, remote: &StateVector) -> IdSet {
+        let mut missing = IdSet::new();
+        for (client, &remote_clock) in remote.iter() {
+            let local_clock = self.blocks.get_clock(client);
+            if remote_clock > local_clock {
+                missing.insert(ID::new(*client, local_clock), remote_clock - local_clock);
+            }
+        }
+        if let Some(pending) = self.pending.as_ref() {
+            let mut received = IdSet::new();
+            for block in pending.update.blocks.blocks() {
+                if !block.is_skip() {
+                    received.insert(*block.id(), block.len());
+                }
+            }
+            missing = missing.difference(&received);
+        }
+        missing
+    }
+
+    /// Returns exact ID ranges integrated into current store, which are not known to a remote
+    /// peer described by its `remote` state vector. This is an inverse of [Store::missing_ranges].
+    pub fn remote_missing_ranges(&self, remote: &StateVector) -> IdSet {
+        let mut missing = IdSet::new();
+        for (client, list) in self.blocks.iter() {
+            let local_clock = list.clock();
+            let remote_clock = remote.get(client);
+            if local_clock > remote_clock {
+                missing.insert(ID::new(*client, remote_clock), local_clock - remote_clock);
+            }
+        }
+        missing
+    }
+
+    /// Returns a structured description of all blocks stored in current document, grouped by
+    /// their client. Returned [StoreDump] can also be pretty-printed using its [Display]
+    /// implementation. This is meant for debugging purposes only: the output format is not stable.
+    ///
+    /// [Display]: std::fmt::Display
+    pub fn dump(&self) -> StoreDump {
+        let mut clients = BTreeMap::new();
+        for (client, list) in self.blocks.iter() {
+            let blocks = list.iter().map(|b| BlockDump::new(*client, b)).collect();
+            clients.insert(*client, blocks);
+        }
+        StoreDump {
+            clients,
+            pending: self.pending.as_ref().map(|p| p.missing.clone()),
+            pending_ds: self.pending_ds.clone(),
+        }
+    }
+
     pub fn is_alive(&self, branch_ptr: &BranchPtr) -> bool {
         self.node_registry.contains(branch_ptr)
     }
@@ -371,6 +487,267 @@ impl Store {
     }
 }
 
+/// Structured description of a [Store] contents, returned by [Store::dump].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoreDump {
+    /// Blocks of every client, ordered by their clock.
+    pub clients: BTreeMap<ClientID, Vec<BlockDump>>,
+    /// Missing state vector of a pending update, if there's any.
+    pub pending: Option<StateVector>,
+    /// Delete set waiting to be applied, if there's any.
+    pub pending_ds: Option<DeleteSet>,
+}
+
+impl StoreDump {
+    /// Returns all blocks ordered in a way, which respects causality: every block is preceded by
+    /// the blocks it depends on, which are its left and right origins, its parent type and the
+    /// preceding blocks of the same client. Blocks that can be emitted at the same time are
+    /// ordered by their client ID.
+    ///
+    /// Blocks depending on a content missing from a dump (ie. waiting in a pending update) are
+    /// emitted as soon as no other block can be.
+    pub fn causal_order(&self) -> Vec<&BlockDump> {
+        let total: usize = self.clients.values().map(Vec::len).sum();
+        let mut result = Vec::with_capacity(total);
+        let mut cursors: BTreeMap<ClientID, usize> = BTreeMap::new();
+        let mut emitted = StateVector::default();
+        let is_emitted = |sv: &StateVector, id: &Option<ID>| match id {
+            Some(id) => sv.get(&id.client) > id.clock,
+            None => true,
+        };
+        while result.len() < total {
+            let mut progress = false;
+            for (client, blocks) in self.clients.iter() {
+                let cursor = cursors.entry(*client).or_default();
+                while let Some(block) = blocks.get(*cursor) {
+                    let parent = match &block.parent {
+                        BlockParent::Type(id) => Some(*id),
+                        _ => None,
+                    };
+                    let ready = is_emitted(&emitted, &block.origin)
+                        && is_emitted(&emitted, &block.right_origin)
+                        && is_emitted(&emitted, &parent);
+                    if !ready {
+                        break;
+                    }
+                    emitted.set_max(*client, block.id.clock + block.len);
+                    result.push(block);
+                    *cursor += 1;
+                    progress = true;
+                }
+            }
+            if !progress {
+                // dependencies are missing: emit the first pending block to move forward
+                for (client, blocks) in self.clients.iter() {
+                    let cursor = cursors.get_mut(client).unwrap();
+                    if let Some(block) = blocks.get(*cursor) {
+                        emitted.set_max(*client, block.id.clock + block.len);
+                        result.push(block);
+                        *cursor += 1;
+                        break;
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    /// Writes an operation log in a [JSON lines](https://jsonlines.org/) format: every block is
+    /// written as a single JSON object in a [causal order](StoreDump::causal_order), ie.
+    ///
+    /// ```json
+    /// {"client":1,"clock":0,"len":3,"op":"insert","parent":"text","key":null,"origin":null,"right_origin":null,"deleted":false,"content":"'abc'"}
+    /// ```
+    ///
+    /// Parent is either a name of a root type or an ID of a nested type formatted as
+    /// `<client#clock>`. Origins are formatted in the same way.
+    pub fn write_op_log<W: std::io::Write>(&self, mut writer: W) -> std::io::Result<()> {
+        for block in self.causal_order() {
+            let parent = match &block.parent {
+                BlockParent::Root(name) => Some(name.to_string()),
+                BlockParent::Type(id) => Some(id.to_string()),
+                BlockParent::Unknown => None,
+            };
+            let entry = serde_json::json!({
+                "client": block.id.client,
+                "clock": block.id.clock,
+                "len": block.len,
+                "op": block.op.to_string(),
+                "parent": parent,
+                "key": block.parent_sub.as_deref(),
+                "origin": block.origin.map(|id| id.to_string()),
+                "right_origin": block.right_origin.map(|id| id.to_string()),
+                "deleted": block.deleted,
+                "content": block.content,
+            });
+            serde_json::to_writer(&mut writer, &entry)?;
+            writer.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for StoreDump {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (client, blocks) in self.clients.iter() {
+            writeln!(f, "client {}:", client)?;
+            for block in blocks.iter() {
+                writeln!(f, "  {}", block)?;
+            }
+        }
+        if let Some(missing) = self.pending.as_ref() {
+            writeln!(f, "pending (missing: {:?})", missing)?;
+        }
+        if let Some(ds) = self.pending_ds.as_ref() {
+            writeln!(f, "pending delete set: {:?}", ds)?;
+        }
+        Ok(())
+    }
+}
+
+/// Description of a single block, being part of a [StoreDump].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockDump {
+    pub id: ID,
+    pub len: u32,
+    pub origin: Option<ID>,
+    pub right_origin: Option<ID>,
+    pub parent: BlockParent,
+    pub parent_sub: Option<Arc<str>>,
+    pub deleted: bool,
+    /// Kind of an operation which created the block.
+    pub op: BlockOp,
+    /// Short, human-readable description of the block content. Garbage collected blocks are
+    /// described as `gc`.
+    pub content: String,
+}
+
+impl BlockDump {
+    const MAX_CONTENT_LEN: usize = 64;
+
+    fn new(client: ClientID, cell: &BlockCell) -> Self {
+        match cell {
+            BlockCell::GC(gc) => BlockDump {
+                id: ID::new(client, gc.start),
+                len: gc.end - gc.start + 1,
+                origin: None,
+                right_origin: None,
+                parent: BlockParent::Unknown,
+                parent_sub: None,
+                deleted: true,
+                op: BlockOp::GC,
+                content: "gc".to_string(),
+            },
+            BlockCell::Block(item) => Self::from_item(item),
+        }
+    }
+
+    /// Creates a description of a given block `item`.
+    pub(crate) fn from_item(item: &Item) -> Self {
+        let parent = match &item.parent {
+            TypePtr::Branch(b) => match (b.item, b.name.as_ref()) {
+                (Some(ptr), _) => BlockParent::Type(*ptr.id()),
+                (None, Some(name)) => BlockParent::Root(name.clone()),
+                (None, None) => BlockParent::Unknown,
+            },
+            TypePtr::Named(name) => BlockParent::Root(name.clone()),
+            TypePtr::ID(id) => BlockParent::Type(*id),
+            TypePtr::Unknown => BlockParent::Unknown,
+        };
+        let op = match &item.content {
+            ItemContent::Format(_, _) => BlockOp::Format,
+            ItemContent::Type(_) => BlockOp::Type,
+            ItemContent::Move(_) => BlockOp::Move,
+            _ => BlockOp::Insert,
+        };
+        let mut content = item.content.to_string();
+        if let Some((i, _)) = content.char_indices().nth(Self::MAX_CONTENT_LEN) {
+            content.truncate(i);
+            content.push_str("...");
+        }
+        BlockDump {
+            id: item.id,
+            len: item.len,
+            origin: item.origin,
+            right_origin: item.right_origin,
+            parent,
+            parent_sub: item.parent_sub.clone(),
+            deleted: item.is_deleted(),
+            op,
+            content,
+        }
+    }
+}
+
+impl std::fmt::Display for BlockDump {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} len: {}, parent: {}", self.id, self.len, self.parent)?;
+        if let Some(key) = self.parent_sub.as_ref() {
+            write!(f, "['{}']", key)?;
+        }
+        if let Some(origin) = self.origin.as_ref() {
+            write!(f, ", origin-l: {}", origin)?;
+        }
+        if let Some(origin) = self.right_origin.as_ref() {
+            write!(f, ", origin-r: {}", origin)?;
+        }
+        if self.deleted {
+            write!(f, ", deleted")?;
+        }
+        write!(f, ": {}", self.content)
+    }
+}
+
+/// Parent of a block described by [BlockDump].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockParent {
+    /// Block belongs to a root-level type with a given name.
+    Root(Arc<str>),
+    /// Block belongs to a nested type defined by a block with a given ID.
+    Type(ID),
+    /// Block parent is not known, eg. because it has been garbage collected.
+    Unknown,
+}
+
+impl std::fmt::Display for BlockParent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BlockParent::Root(name) => write!(f, "'{}'", name),
+            BlockParent::Type(id) => write!(f, "{}", id),
+            BlockParent::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+/// Kind of an operation which created a block described by [BlockDump]. Deleted blocks keep the
+/// kind of an operation which created them, except for garbage collected ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockOp {
+    /// Insertion of a content (text, embeds, JSON-like values etc.).
+    Insert,
+    /// Formatting attribute boundary of a text.
+    Format,
+    /// Creation of a nested shared type.
+    Type,
+    /// Move of a range of elements within an array.
+    Move,
+    /// Garbage collected range, which content is no longer known.
+    GC,
+}
+
+impl std::fmt::Display for BlockOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            BlockOp::Insert => "insert",
+            BlockOp::Format => "format",
+            BlockOp::Type => "type",
+            BlockOp::Move => "move",
+            BlockOp::GC => "gc",
+        };
+        f.write_str(name)
+    }
+}
+
 impl Encode for Store {
     /// Encodes the document state to a binary format.
     ///
@@ -546,3 +923,173 @@ impl StoreEvents {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::store::{BlockDump, BlockOp, BlockParent};
+    use crate::types::text::{Diff, YChange};
+    use crate::update::Update;
+    use crate::updates::decoder::Decode;
+    use crate::{Doc, IdSet, Map, MapPrelim, ReadTxn, StateVector, Text, Transact, ID};
+
+    #[test]
+    fn store_dump() {
+        let doc = Doc::with_client_id(1);
+        let txt = doc.get_or_insert_text("text");
+        let map = doc.get_or_insert_map("map");
+        {
+            let mut txn = doc.transact_mut();
+            txt.insert(&mut txn, 0, "abc");
+            map.insert(&mut txn, "key", 1);
+            txt.remove_range(&mut txn, 1, 1);
+        }
+        let txn = doc.transact();
+        let dump = txn.store().dump();
+        let blocks = &dump.clients[&1];
+        assert_eq!(blocks.len(), 4);
+        assert_eq!(
+            blocks[1],
+            BlockDump {
+                id: ID::new(1, 1),
+                len: 1,
+                origin: Some(ID::new(1, 0)),
+                right_origin: None,
+                parent: BlockParent::Root("text".into()),
+                parent_sub: None,
+                deleted: true,
+                op: BlockOp::Insert,
+                content: "deleted(1)".to_string(),
+            }
+        );
+        assert_eq!(blocks[3].parent, BlockParent::Root("map".into()));
+        assert_eq!(blocks[3].parent_sub.as_deref(), Some("key"));
+        assert!(dump.pending.is_none());
+
+        let str = dump.to_string();
+        assert!(str.starts_with("client 1:\n"));
+        assert_eq!(str.lines().count(), 5);
+    }
+
+    #[test]
+    fn missing_ranges() {
+        let d1 = Doc::with_client_id(1);
+        let t1 = d1.get_or_insert_text("text");
+        let mut updates = Vec::new();
+        for chunk in ["abc", "def", "ghi"] {
+            let mut txn = d1.transact_mut();
+            t1.insert(&mut txn, 0, chunk);
+            updates.push(txn.encode_update_v1());
+        }
+        let d2 = Doc::with_client_id(2);
+        let t2 = d2.get_or_insert_text("text");
+        {
+            let mut txn = d2.transact_mut();
+            t2.insert(&mut txn, 0, "xy");
+            txn.apply_update(Update::decode_v1(&updates[0]).unwrap());
+            // received, but pending since 2nd update is missing
+            txn.apply_update(Update::decode_v1(&updates[2]).unwrap());
+        }
+
+        let remote = d1.transact().state_vector();
+        let txn = d2.transact();
+        let mut expected = IdSet::new();
+        expected.insert(ID::new(1, 3), 3);
+        assert_eq!(txn.store().missing_ranges(&remote), expected);
+
+        let mut expected = IdSet::new();
+        expected.insert(ID::new(2, 0), 2);
+        assert_eq!(txn.store().remote_missing_ranges(&remote), expected);
+        assert!(txn
+            .store()
+            .remote_missing_ranges(&txn.state_vector())
+            .is_empty());
+        assert!(txn
+            .store()
+            .missing_ranges(&StateVector::default())
+            .is_empty());
+    }
+
+    #[test]
+    fn split_blocks_are_merged_on_commit() {
+        let doc = Doc::with_client_id(1);
+        let txt = doc.get_or_insert_text("text");
+        txt.insert(&mut doc.transact_mut(), 0, "abc");
+        let snapshot = doc.transact().snapshot();
+        txt.insert(&mut doc.transact_mut(), 3, "def");
+        // prepended block is not mergeable with the previous one since it has different origin
+        txt.insert(&mut doc.transact_mut(), 0, "Z");
+        assert_eq!(doc.transact().store().dump().clients[&1].len(), 2);
+
+        // diff for a snapshot splits 'abcdef' block in the middle: split block has both left and
+        // right neighbours and should be merged back when transaction is committed
+        let _: Vec<Diff<YChange>> =
+            txt.diff_range(&mut doc.transact_mut(), None, Some(&snapshot), |c| c);
+        assert_eq!(doc.transact().store().dump().clients[&1].len(), 2);
+    }
+
+    #[test]
+    fn op_log_is_causally_ordered() {
+        let d1 = Doc::with_client_id(1);
+        let d2 = Doc::with_client_id(2);
+        let txt = d2.get_or_insert_text("text");
+        txt.insert(&mut d2.transact_mut(), 0, "ab");
+        let update = d2
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+        d1.transact_mut()
+            .apply_update(Update::decode_v1(&update).unwrap());
+
+        let txt = d1.get_or_insert_text("text");
+        let map = d1.get_or_insert_map("map");
+        {
+            let mut txn = d1.transact_mut();
+            txt.insert(&mut txn, 1, "x");
+            let nested = map.insert(&mut txn, "nested", MapPrelim::default());
+            nested.insert(&mut txn, "key", true);
+        }
+
+        let txn = d1.transact();
+        let dump = txn.store().dump();
+        let ids: Vec<_> = dump
+            .causal_order()
+            .into_iter()
+            .map(|block| (block.id, block.op))
+            .collect();
+        assert_eq!(
+            ids,
+            vec![
+                (ID::new(2, 0), BlockOp::Insert),
+                (ID::new(2, 1), BlockOp::Insert),
+                (ID::new(1, 0), BlockOp::Insert),
+                (ID::new(1, 1), BlockOp::Type),
+                (ID::new(1, 2), BlockOp::Insert),
+            ]
+        );
+
+        let mut buf = Vec::new();
+        dump.write_op_log(&mut buf).unwrap();
+        let log = String::from_utf8(buf).unwrap();
+        let lines: Vec<serde_json::Value> = log
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(
+            lines[2],
+            serde_json::json!({
+                "client": 1,
+                "clock": 0,
+                "len": 1,
+                "op": "insert",
+                "parent": "text",
+                "key": null,
+                "origin": "<2#0>",
+                "right_origin": "<2#1>",
+                "deleted": false,
+                "content": "'x'"
+            })
+        );
+        assert_eq!(lines[4]["parent"], "<1#1>");
+        assert_eq!(lines[4]["key"], "key");
+    }
+}
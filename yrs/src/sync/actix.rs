@@ -0,0 +1,90 @@
+//! [actix-web](https://docs.rs/actix-web) handlers exposing a [BroadcastGroup] as a y-sync
+//! WebSocket endpoint, compatible with y-websocket clients.
+//!
+//! ```rust,no_run
+//! use actix_web::{web, App, HttpServer};
+//! use yrs::sync::{Awareness, BroadcastGroup};
+//! use yrs::Doc;
+//!
+//! # async fn run() -> std::io::Result<()> {
+//! let group = web::Data::new(BroadcastGroup::new(Awareness::new(Doc::new()), 32).unwrap());
+//! HttpServer::new(move || {
+//!     App::new()
+//!         .app_data(group.clone())
+//!         .route("/my-room", web::get().to(yrs::sync::actix::handler))
+//! })
+//! .bind(("0.0.0.0", 8000))?
+//! .run()
+//! .await
+//! # }
+//! ```
+
+use crate::sync::broadcast::BroadcastGroup;
+use crate::sync::{Error, Protocol};
+use ::actix_web::{web, HttpRequest, HttpResponse};
+use actix_ws::{AggregatedMessage, AggregatedMessageStream, Session};
+use bytes::Bytes;
+use futures::TryStreamExt;
+
+/// Handler upgrading an HTTP request to a WebSocket connection served by a [BroadcastGroup]
+/// registered as application data. The connection is served by a task spawned on the current
+/// worker thread.
+pub async fn handler(
+    req: HttpRequest,
+    body: web::Payload,
+    group: web::Data<BroadcastGroup>,
+) -> Result<HttpResponse, ::actix_web::Error> {
+    let (response, session, stream) = actix_ws::handle(&req, body)?;
+    ::actix_web::rt::spawn(async move {
+        let _ = serve(session, stream.aggregate_continuations(), &group).await;
+    });
+    Ok(response)
+}
+
+/// Serves an already upgraded WebSocket connection using [DefaultProtocol].
+///
+/// [DefaultProtocol]: crate::sync::DefaultProtocol
+pub async fn serve(
+    session: Session,
+    stream: AggregatedMessageStream,
+    group: &BroadcastGroup,
+) -> Result<(), Error> {
+    serve_with(session, stream, group, crate::sync::DefaultProtocol).await
+}
+
+/// Serves an already upgraded WebSocket connection using a given y-sync `protocol`. Pings are
+/// answered, text and pong messages are ignored. Session is closed once the connection is over.
+pub async fn serve_with<P: Protocol>(
+    session: Session,
+    stream: AggregatedMessageStream,
+    group: &BroadcastGroup,
+    protocol: P,
+) -> Result<(), Error> {
+    let sink = futures::sink::unfold(session.clone(), |mut session, data: Bytes| async move {
+        session.binary(data).await?;
+        Ok::<_, actix_ws::Closed>(session)
+    });
+    let pong = session.clone();
+    let stream = stream
+        .try_take_while(|msg| futures::future::ok(!matches!(msg, AggregatedMessage::Close(_))))
+        .try_filter_map(move |msg| {
+            let mut pong = pong.clone();
+            async move {
+                Ok(match msg {
+                    AggregatedMessage::Binary(data) => Some(data),
+                    AggregatedMessage::Ping(data) => {
+                        let _ = pong.pong(&data).await;
+                        None
+                    }
+                    _ => None,
+                })
+            }
+        });
+    let result = group.serve_with(sink, stream, protocol).await;
+    #[cfg(feature = "tracing")]
+    if let Err(e) = &result {
+        tracing::debug!(error = %e, "y-sync connection closed");
+    }
+    let _ = session.close(None).await;
+    result
+}
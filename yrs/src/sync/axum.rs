@@ -0,0 +1,66 @@
+//! [axum](https://docs.rs/axum) handlers exposing a [BroadcastGroup] as a y-sync WebSocket
+//! endpoint, compatible with y-websocket clients.
+//!
+//! ```rust,no_run
+//! use axum::{routing::get, Router};
+//! use std::sync::Arc;
+//! use yrs::sync::{Awareness, BroadcastGroup};
+//! use yrs::Doc;
+//!
+//! # async fn run() -> std::io::Result<()> {
+//! let group = Arc::new(BroadcastGroup::new(Awareness::new(Doc::new()), 32).unwrap());
+//! let app = Router::new()
+//!     .route("/my-room", get(yrs::sync::axum::handler))
+//!     .with_state(group);
+//! let listener = tokio::net::TcpListener::bind("0.0.0.0:8000").await?;
+//! axum::serve(listener, app).await
+//! # }
+//! ```
+
+use crate::sync::broadcast::BroadcastGroup;
+use crate::sync::{Error, Protocol};
+use ::axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use ::axum::extract::State;
+use ::axum::response::Response;
+use futures::{SinkExt, StreamExt, TryStreamExt};
+use std::sync::Arc;
+
+/// Handler upgrading an HTTP request to a WebSocket connection served by a [BroadcastGroup]
+/// passed as router state.
+pub async fn handler(ws: WebSocketUpgrade, State(group): State<Arc<BroadcastGroup>>) -> Response {
+    ws.on_upgrade(move |socket| async move {
+        let _ = serve(socket, &group).await;
+    })
+}
+
+/// Serves an already upgraded WebSocket connection using [DefaultProtocol].
+///
+/// [DefaultProtocol]: crate::sync::DefaultProtocol
+pub async fn serve(socket: WebSocket, group: &BroadcastGroup) -> Result<(), Error> {
+    serve_with(socket, group, crate::sync::DefaultProtocol).await
+}
+
+/// Serves an already upgraded WebSocket connection using a given y-sync `protocol`. Text, ping and
+/// pong messages are ignored.
+pub async fn serve_with<P: Protocol>(
+    socket: WebSocket,
+    group: &BroadcastGroup,
+    protocol: P,
+) -> Result<(), Error> {
+    let (sink, stream) = socket.split();
+    let sink = sink.with(|data| futures::future::ok::<_, ::axum::Error>(Message::Binary(data)));
+    let stream = stream
+        .try_take_while(|msg| futures::future::ok(!matches!(msg, Message::Close(_))))
+        .try_filter_map(|msg| {
+            futures::future::ok(match msg {
+                Message::Binary(data) => Some(data),
+                _ => None,
+            })
+        });
+    let result = group.serve_with(sink, stream, protocol).await;
+    #[cfg(feature = "tracing")]
+    if let Err(e) = &result {
+        tracing::debug!(error = %e, "y-sync connection closed");
+    }
+    result
+}
@@ -0,0 +1,419 @@
+//! [BroadcastGroup] shares a single document and its [Awareness] between many remote peers
+//! connected over the y-sync protocol, ie. all WebSocket connections made to the same room.
+//! Every change made to a document or awareness state - either locally or by any of the
+//! subscribers - is relayed to all connected peers.
+//!
+//! Connections are served by [BroadcastGroup::serve], which works with any transport exposing
+//! [Stream]/[Sink] of binary messages. Ready-made handlers for popular web frameworks can be
+//! found in `yrs::sync::axum` and `yrs::sync::actix` modules, enabled by `axum` and `actix`
+//! features respectively.
+
+use crate::block::ClientID;
+use crate::encoding::read::Cursor;
+use crate::sync::protocol::{Error, Message, MessageReader, Protocol, SyncMessage};
+use crate::sync::{Awareness, DefaultProtocol};
+use crate::updates::decoder::DecoderV1;
+use crate::updates::encoder::{Encode, Encoder, EncoderV1};
+use crate::Subscription;
+use atomic_refcell::BorrowMutError;
+use bytes::Bytes;
+use futures::future::Either;
+use futures::{Sink, SinkExt, Stream, StreamExt};
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, RwLock};
+
+/// A group of y-sync connections sharing the same document and awareness state.
+///
+/// Awareness changes are encoded as y-sync messages once and then pushed to all subscribers via
+/// bounded broadcast channel. Document updates are pushed as they are and encoded separately for
+/// every subscriber using [Protocol::encode_update] of its connection, so that ie.
+/// [EncryptedProtocol](crate::sync::EncryptedProtocol) peers never receive plaintext updates. A subscriber, which falls behind by more than
+/// `buffer_capacity` messages, is disconnected with an error, so that it can reconnect and
+/// resynchronize its state from scratch.
+pub struct BroadcastGroup {
+    awareness: Arc<RwLock<Awareness>>,
+    sender: broadcast::Sender<Broadcast>,
+    _doc_sub: Subscription,
+    _awareness_sub: Subscription,
+}
+
+impl BroadcastGroup {
+    /// Creates a new broadcast group over a given `awareness` instance (and a document it
+    /// wraps). `buffer_capacity` defines how many messages can be buffered for every subscriber
+    /// before it's considered lagging behind.
+    pub fn new(awareness: Awareness, buffer_capacity: usize) -> Result<Self, BorrowMutError> {
+        let (sender, _) = broadcast::channel(buffer_capacity);
+        let doc_sub = {
+            let sender = sender.clone();
+            awareness.doc().observe_update_v1(move |_, e| {
+                // there may be no active subscribers at the moment
+                let _ = sender.send(Broadcast::Update(e.update.clone().into()));
+            })?
+        };
+        let awareness_sub = {
+            let sender = sender.clone();
+            awareness.on_update(move |awareness, e, _| {
+                if let Ok(update) = awareness.update_with_clients(e.all_changes()) {
+                    let msg = Message::Awareness(update).encode_v1().into();
+                    let _ = sender.send(Broadcast::Message(msg));
+                }
+            })
+        };
+        Ok(BroadcastGroup {
+            awareness: Arc::new(RwLock::new(awareness)),
+            sender,
+            _doc_sub: doc_sub,
+            _awareness_sub: awareness_sub,
+        })
+    }
+
+    /// Returns a reference to an [Awareness] instance shared by this group. Changes made to it
+    /// (and its document) are broadcast to all subscribers.
+    pub fn awareness(&self) -> &Arc<RwLock<Awareness>> {
+        &self.awareness
+    }
+
+    /// Returns a number of currently connected subscribers.
+    pub fn subscriber_count(&self) -> usize {
+        self.sender.receiver_count()
+    }
+
+    /// Broadcasts an already encoded y-sync message to all subscribers. Returns a number of
+    /// subscribers which received it.
+    pub fn broadcast(&self, msg: Bytes) -> usize {
+        self.sender.send(Broadcast::Message(msg)).unwrap_or(0)
+    }
+
+    /// Serves a single connection using [DefaultProtocol]. See [BroadcastGroup::serve_with].
+    pub async fn serve<Si, St, E>(&self, sink: Si, stream: St) -> Result<(), Error>
+    where
+        Si: Sink<Bytes>,
+        Si::Error: std::error::Error + Send + Sync + 'static,
+        St: Stream<Item = Result<Bytes, E>>,
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        self.serve_with(sink, stream, DefaultProtocol).await
+    }
+
+    /// Serves a single connection, where `sink` is used to send binary y-sync messages to a remote
+    /// peer and `stream` is used to receive them. Each binary message can contain many y-sync
+    /// messages stored one after another, while every message sent to a `sink` always contains
+    /// exactly one y-sync message (as expected by y-websocket clients).
+    ///
+    /// Right after subscription, messages created by [Protocol::start] are sent to a remote peer.
+    /// Incoming messages are handled by a given `protocol`, which is also used to encode document
+    /// updates broadcast to a remote peer. Returned future completes once
+    /// a `stream` has finished or any error occurred - in both cases awareness states
+    /// introduced by a remote peer are removed.
+    pub async fn serve_with<Si, St, E, P>(
+        &self,
+        sink: Si,
+        stream: St,
+        protocol: P,
+    ) -> Result<(), Error>
+    where
+        Si: Sink<Bytes>,
+        Si::Error: std::error::Error + Send + Sync + 'static,
+        St: Stream<Item = Result<Bytes, E>>,
+        E: std::error::Error + Send + Sync + 'static,
+        P: Protocol,
+    {
+        let protocol = &protocol;
+        let broadcasts = futures::stream::unfold(self.sender.subscribe(), |mut rx| async move {
+            match rx.recv().await {
+                Ok(Broadcast::Message(msg)) => Some((Ok(msg), rx)),
+                Ok(Broadcast::Update(update)) => {
+                    let msg = protocol.encode_update(&update).encode_v1().into();
+                    Some((Ok(msg), rx))
+                }
+                Err(RecvError::Closed) => None,
+                Err(e) => Some((Err(Error::Other(e.into())), rx)),
+            }
+        });
+        let (replies, replies_rx) = futures::channel::mpsc::unbounded();
+        let start = {
+            let awareness = self.awareness.read().await;
+            let mut encoder = EncoderV1::new();
+            protocol.start(&awareness, &mut encoder)?;
+            encoder.to_vec()
+        };
+        for msg in split_messages(&start)? {
+            let _ = replies.unbounded_send(msg);
+        }
+
+        let mut clients = HashSet::new();
+        let result = {
+            let send = async {
+                futures::pin_mut!(sink);
+                let outgoing = futures::stream::select(broadcasts, replies_rx.map(Ok));
+                futures::pin_mut!(outgoing);
+                while let Some(msg) = outgoing.next().await {
+                    sink.send(msg?).await.map_err(|e| Error::Other(e.into()))?;
+                }
+                Ok(())
+            };
+            let recv = async {
+                futures::pin_mut!(stream);
+                while let Some(data) = stream.next().await {
+                    let data = data.map_err(|e| Error::Other(e.into()))?;
+                    let mut decoder = DecoderV1::new(Cursor::new(&data));
+                    for msg in MessageReader::new(&mut decoder) {
+                        let msg = msg?;
+                        if let Message::Awareness(update) = &msg {
+                            clients.extend(update.clients.keys().cloned());
+                        }
+                        if let Some(reply) = self.handle(protocol, msg).await? {
+                            let _ = replies.unbounded_send(reply.encode_v1().into());
+                        }
+                    }
+                }
+                Ok(())
+            };
+            futures::pin_mut!(send);
+            futures::pin_mut!(recv);
+            match futures::future::select(send, recv).await {
+                Either::Left((result, _)) => result,
+                Either::Right((result, _)) => result,
+            }
+        };
+        self.remove_clients(clients).await;
+        result
+    }
+
+    async fn handle<P: Protocol>(
+        &self,
+        protocol: &P,
+        msg: Message,
+    ) -> Result<Option<Message>, Error> {
+        match msg {
+            Message::Sync(SyncMessage::SyncStep1(sv)) => {
+                let awareness = self.awareness.read().await;
+                protocol.handle_sync_step1(&awareness, sv)
+            }
+            Message::Sync(SyncMessage::SyncStep2(update)) => {
+                let update = protocol.decode_update(&update)?;
+                let mut awareness = self.awareness.write().await;
+                protocol.handle_sync_step2(&mut awareness, update)
+            }
+            Message::Sync(SyncMessage::Update(update)) => {
+                let update = protocol.decode_update(&update)?;
+                let mut awareness = self.awareness.write().await;
+                protocol.handle_update(&mut awareness, update)
+            }
+            Message::Auth(deny_reason) => {
+                let awareness = self.awareness.read().await;
+                protocol.handle_auth(&awareness, deny_reason)
+            }
+            Message::AwarenessQuery => {
+                let awareness = self.awareness.read().await;
+                protocol.handle_awareness_query(&awareness)
+            }
+            Message::Awareness(update) => {
+                let mut awareness = self.awareness.write().await;
+                protocol.handle_awareness_update(&mut awareness, update)
+            }
+            Message::Custom(tag, data) => {
+                let mut awareness = self.awareness.write().await;
+                protocol.missing_handle(&mut awareness, tag, data)
+            }
+        }
+    }
+
+    async fn remove_clients(&self, clients: HashSet<ClientID>) {
+        if clients.is_empty() {
+            return;
+        }
+        let mut awareness = self.awareness.write().await;
+        for client_id in clients {
+            if awareness.clients().contains_key(&client_id) {
+                awareness.remove_state(client_id);
+            }
+        }
+    }
+}
+
+/// Payload pushed through a broadcast channel of a [BroadcastGroup].
+#[derive(Debug, Clone)]
+enum Broadcast {
+    /// Lib0 v1 encoded document update, which has to be encoded by subscriber's protocol.
+    Update(Bytes),
+    /// Already encoded y-sync message.
+    Message(Bytes),
+}
+
+/// Splits a binary payload containing many y-sync messages into separately encoded messages.
+fn split_messages(data: &[u8]) -> Result<Vec<Bytes>, Error> {
+    let mut decoder = DecoderV1::new(Cursor::new(data));
+    let mut result = Vec::new();
+    for msg in MessageReader::new(&mut decoder) {
+        result.push(msg?.encode_v1().into());
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::sync::broadcast::BroadcastGroup;
+    use crate::sync::{Awareness, Message, SyncMessage};
+    use crate::updates::decoder::Decode;
+    use crate::updates::encoder::Encode;
+    use crate::{Doc, GetString, StateVector, Text, Transact};
+    use bytes::Bytes;
+    use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+    use futures::StreamExt;
+    use serde_json::json;
+    use std::convert::Infallible;
+
+    async fn recv(rx: &mut UnboundedReceiver<Bytes>) -> Message {
+        let data = rx.next().await.unwrap();
+        Message::decode_v1(&data).unwrap()
+    }
+
+    fn send(tx: &UnboundedSender<Result<Bytes, Infallible>>, msg: Message) {
+        tx.unbounded_send(Ok(msg.encode_v1().into())).unwrap();
+    }
+
+    #[test]
+    fn broadcast_group_relays_updates_and_awareness() {
+        let doc = Doc::with_client_id(1);
+        let text = doc.get_or_insert_text("text");
+        text.insert(&mut doc.transact_mut(), 0, "hello");
+        let group = BroadcastGroup::new(Awareness::new(doc.clone()), 16).unwrap();
+
+        let (out1, mut in1) = unbounded();
+        let (tx1, stream1) = unbounded();
+        let (out2, mut in2) = unbounded();
+        let (tx2, stream2) = unbounded::<Result<Bytes, Infallible>>();
+
+        futures::executor::block_on(async {
+            let peer = Awareness::new(Doc::with_client_id(2));
+            let driver = async {
+                // handshake
+                assert!(matches!(
+                    recv(&mut in1).await,
+                    Message::Sync(SyncMessage::SyncStep1(_))
+                ));
+                assert!(matches!(recv(&mut in1).await, Message::Awareness(_)));
+                send(
+                    &tx1,
+                    Message::Sync(SyncMessage::SyncStep1(StateVector::default())),
+                );
+                let update = match recv(&mut in1).await {
+                    Message::Sync(SyncMessage::SyncStep2(update)) => update,
+                    other => panic!("unexpected message: {:?}", other),
+                };
+                let peer_text = peer.doc().get_or_insert_text("text");
+                peer.doc()
+                    .transact_mut()
+                    .apply_update(crate::Update::decode_v1(&update).unwrap());
+                assert_eq!(peer_text.get_string(&peer.doc().transact()), "hello");
+
+                // skip handshake of a second connection
+                recv(&mut in2).await;
+                recv(&mut in2).await;
+                assert_eq!(group.subscriber_count(), 2);
+
+                // updates sent by one peer are relayed to the others
+                let update = {
+                    let mut txn = peer.doc().transact_mut();
+                    peer_text.push(&mut txn, " world");
+                    txn.encode_update_v1()
+                };
+                send(&tx1, Message::Sync(SyncMessage::Update(update.clone())));
+                assert_eq!(
+                    recv(&mut in2).await,
+                    Message::Sync(SyncMessage::Update(update))
+                );
+
+                // awareness of disconnected peers is removed
+                let mut peer = peer;
+                peer.set_local_state(json!({"user": "peer"})).unwrap();
+                send(&tx1, Message::Awareness(peer.update().unwrap()));
+                assert!(matches!(recv(&mut in2).await, Message::Awareness(_)));
+                drop(tx1);
+                match recv(&mut in2).await {
+                    Message::Awareness(update) => assert_eq!(update.clients[&2].json, "null"),
+                    other => panic!("unexpected message: {:?}", other),
+                }
+                drop(tx2);
+            };
+            let (_, r1, r2) = futures::join!(
+                driver,
+                group.serve(out1, stream1),
+                group.serve(out2, stream2)
+            );
+            r1.unwrap();
+            r2.unwrap();
+        });
+        assert_eq!(text.get_string(&doc.transact()), "hello world");
+        assert!(group
+            .awareness()
+            .try_read()
+            .unwrap()
+            .clients()
+            .get(&2)
+            .is_none());
+    }
+
+    #[cfg(feature = "encryption")]
+    #[test]
+    fn broadcast_group_encrypts_relayed_updates() {
+        use crate::encryption::DocumentKey;
+        use crate::sync::EncryptedProtocol;
+
+        let key = DocumentKey::generate();
+        let doc = Doc::with_client_id(1);
+        let group = BroadcastGroup::new(Awareness::new(doc.clone()), 16).unwrap();
+
+        let (out1, mut in1) = unbounded();
+        let (tx1, stream1) = unbounded();
+        let (out2, mut in2) = unbounded();
+        let (tx2, stream2) = unbounded::<Result<Bytes, Infallible>>();
+
+        futures::executor::block_on(async {
+            let driver = async {
+                // skip handshakes
+                for rx in [&mut in1, &mut in2] {
+                    recv(rx).await;
+                    recv(rx).await;
+                }
+                assert_eq!(group.subscriber_count(), 2);
+
+                let peer = Doc::with_client_id(2);
+                let peer_text = peer.get_or_insert_text("text");
+                let update = {
+                    let mut txn = peer.transact_mut();
+                    peer_text.push(&mut txn, "secret");
+                    txn.encode_update_v1()
+                };
+                send(
+                    &tx1,
+                    Message::Sync(SyncMessage::Update(key.encrypt(&update))),
+                );
+                let payload = match recv(&mut in2).await {
+                    Message::Sync(SyncMessage::Update(payload)) => payload,
+                    other => panic!("unexpected message: {:?}", other),
+                };
+                assert!(!payload.windows(6).any(|w| w == b"secret".as_slice()));
+                let other = Doc::with_client_id(3);
+                let other_text = other.get_or_insert_text("text");
+                key.apply_update(&mut other.transact_mut(), &payload)
+                    .unwrap();
+                assert_eq!(other_text.get_string(&other.transact()), "secret");
+                drop(tx1);
+                drop(tx2);
+            };
+            let (_, r1, r2) = futures::join!(
+                driver,
+                group.serve_with(out1, stream1, EncryptedProtocol::new(key.clone())),
+                group.serve_with(out2, stream2, EncryptedProtocol::new(key.clone()))
+            );
+            r1.unwrap();
+            r2.unwrap();
+        });
+        let text = doc.get_or_insert_text("text");
+        assert_eq!(text.get_string(&doc.transact()), "secret");
+    }
+}
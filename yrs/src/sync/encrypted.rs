@@ -0,0 +1,102 @@
+//! y-sync [Protocol] exchanging end-to-end encrypted updates, see [crate::encryption].
+
+use crate::encryption::DocumentKey;
+use crate::sync::protocol::{Error, Message, Protocol, SyncMessage};
+use crate::sync::Awareness;
+use crate::{ReadTxn, StateVector, Transact, Update};
+
+/// A y-sync [Protocol] variant, which encrypts sync step 2 replies with a [DocumentKey] and
+/// expects payloads of all incoming sync step 2 and update messages to be encrypted with the same
+/// key (see [Protocol::decode_update]). Outgoing updates are encrypted by
+/// [Protocol::encode_update], which is also used by `BroadcastGroup` to relay document changes.
+///
+/// Message framing stays the same as in [DefaultProtocol], so encrypted messages can be passed
+/// through any relay (ie. a pub/sub channel), which forwards y-sync messages between peers without
+/// interpreting their payloads. Such relay doesn't need to know the key, but it also cannot take
+/// part in synchronization itself.
+///
+/// [DefaultProtocol]: crate::sync::DefaultProtocol
+#[derive(Debug, Clone)]
+pub struct EncryptedProtocol {
+    key: DocumentKey,
+}
+
+impl EncryptedProtocol {
+    pub fn new(key: DocumentKey) -> Self {
+        EncryptedProtocol { key }
+    }
+
+    /// Returns a key used by this protocol.
+    pub fn key(&self) -> &DocumentKey {
+        &self.key
+    }
+}
+
+impl Protocol for EncryptedProtocol {
+    fn handle_sync_step1(
+        &self,
+        awareness: &Awareness,
+        sv: StateVector,
+    ) -> Result<Option<Message>, Error> {
+        let update = awareness.doc().transact().encode_state_as_update_v1(&sv);
+        Ok(Some(Message::Sync(SyncMessage::SyncStep2(
+            self.key.encrypt(&update),
+        ))))
+    }
+
+    fn decode_update(&self, data: &[u8]) -> Result<Update, Error> {
+        self.key
+            .decrypt_update_v1(data)
+            .map_err(|e| Error::Other(e.into()))
+    }
+
+    fn encode_update(&self, update: &[u8]) -> Message {
+        Message::Sync(SyncMessage::Update(self.key.encrypt(update)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::encryption::DocumentKey;
+    use crate::sync::{Awareness, EncryptedProtocol, Message, Protocol, SyncMessage};
+    use crate::{Doc, GetString, ReadTxn, Text, Transact};
+
+    #[test]
+    fn encrypted_sync_steps() {
+        let protocol = EncryptedProtocol::new(DocumentKey::generate());
+        let a1 = Awareness::new(Doc::with_client_id(1));
+        let mut a2 = Awareness::new(Doc::with_client_id(2));
+        let text = a1.doc().get_or_insert_text("text");
+        text.insert(&mut a1.doc().transact_mut(), 0, "hello");
+
+        let sv = a2.doc().transact().state_vector();
+        let reply = protocol.handle_sync_step1(&a1, sv).unwrap();
+        let payload = match reply {
+            Some(Message::Sync(SyncMessage::SyncStep2(payload))) => payload,
+            other => panic!("unexpected reply: {:?}", other),
+        };
+        // relays see only opaque payloads
+        assert!(!payload.windows(5).any(|w| w == b"hello".as_slice()));
+        let other = EncryptedProtocol::new(DocumentKey::generate());
+        assert!(other.decode_update(&payload).is_err());
+
+        let update = protocol.decode_update(&payload).unwrap();
+        protocol.handle_sync_step2(&mut a2, update).unwrap();
+        let text2 = a2.doc().get_or_insert_text("text");
+        assert_eq!(text2.get_string(&a2.doc().transact()), "hello");
+
+        let update = {
+            let mut txn = a1.doc().transact_mut();
+            text.push(&mut txn, " world");
+            txn.encode_update_v1()
+        };
+        match protocol.encode_update(&update) {
+            Message::Sync(SyncMessage::Update(payload)) => {
+                let update = protocol.decode_update(&payload).unwrap();
+                protocol.handle_update(&mut a2, update).unwrap();
+            }
+            other => panic!("unexpected message: {:?}", other),
+        }
+        assert_eq!(text2.get_string(&a2.doc().transact()), "hello world");
+    }
+}
@@ -1,14 +1,33 @@
+#[cfg(feature = "actix")]
+pub mod actix;
 pub mod awareness;
+#[cfg(feature = "axum")]
+pub mod axum;
+#[cfg(feature = "net")]
+pub mod broadcast;
+#[cfg(feature = "encryption")]
+pub mod encrypted;
+pub mod ot;
 pub mod protocol;
+#[cfg(feature = "server")]
+pub mod server;
+#[cfg(feature = "net")]
+pub mod stream;
 pub mod time;
 
 pub use crate::sync::awareness::Awareness;
 pub use crate::sync::awareness::AwarenessUpdate;
+#[cfg(feature = "net")]
+pub use crate::sync::broadcast::BroadcastGroup;
+#[cfg(feature = "encryption")]
+pub use crate::sync::encrypted::EncryptedProtocol;
 pub use crate::sync::protocol::DefaultProtocol;
 pub use crate::sync::protocol::Error;
 pub use crate::sync::protocol::Message;
 pub use crate::sync::protocol::MessageReader;
 pub use crate::sync::protocol::Protocol;
 pub use crate::sync::protocol::SyncMessage;
+#[cfg(feature = "net")]
+pub use crate::sync::stream::{UpdateSink, UpdateStream};
 pub use crate::sync::time::Clock;
 pub use crate::sync::time::Timestamp;
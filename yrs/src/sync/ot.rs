@@ -0,0 +1,551 @@
+//! Adapter which allows a Yrs document to serve clients using Operational Transformation (OT)
+//! text operations in a ShareDB `ot-text` style, ie. during a migration from OT to CRDT based
+//! backend. See [OtText] for details.
+
+use crate::types::{Delta, IndexOutOfBounds};
+use crate::{Any, OffsetKind, Out, Text, TextRef, TransactionMut};
+use std::collections::{HashMap, VecDeque};
+use std::convert::TryFrom;
+use thiserror::Error;
+
+/// A single component of a [TextOp].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Component {
+    /// Skip over a given number of characters.
+    Retain(u32),
+    /// Insert a string at the current position.
+    Insert(String),
+    /// Delete a given number of characters starting at the current position.
+    Delete(u32),
+}
+
+/// Determines which of two inserts made at the same position goes first, when transforming
+/// concurrent operations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    /// Transformed operation's insert goes before the concurrent one.
+    Left,
+    /// Transformed operation's insert goes after the concurrent one.
+    Right,
+}
+
+/// OT text operation, made of a sequence of [Component]s. Operation implicitly retains all
+/// characters following its last component. Lengths of retained and deleted ranges are measured
+/// using [OffsetKind] of a document the operation is applied to, which for compatibility with
+/// JavaScript clients should be [OffsetKind::Utf16].
+///
+/// Adjacent components of the same kind are merged when an operation is built. Operations
+/// produced by transformations and conversions have no trailing retains.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TextOp(Vec<Component>);
+
+impl TextOp {
+    pub fn new() -> Self {
+        TextOp::default()
+    }
+
+    /// Returns components of this operation.
+    pub fn components(&self) -> &[Component] {
+        &self.0
+    }
+
+    /// Checks if this operation doesn't change anything.
+    pub fn is_noop(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Appends a retain component.
+    pub fn retain(mut self, len: u32) -> Self {
+        self.push(Component::Retain(len));
+        self
+    }
+
+    /// Appends an insert component.
+    pub fn insert<S: Into<String>>(mut self, chunk: S) -> Self {
+        self.push(Component::Insert(chunk.into()));
+        self
+    }
+
+    /// Appends a delete component.
+    pub fn delete(mut self, len: u32) -> Self {
+        self.push(Component::Delete(len));
+        self
+    }
+
+    fn push(&mut self, component: Component) {
+        match (self.0.last_mut(), component) {
+            (_, Component::Retain(0)) | (_, Component::Delete(0)) => {}
+            (_, Component::Insert(s)) if s.is_empty() => {}
+            (Some(Component::Retain(last)), Component::Retain(len)) => *last += len,
+            (Some(Component::Delete(last)), Component::Delete(len)) => *last += len,
+            (Some(Component::Insert(last)), Component::Insert(s)) => last.push_str(&s),
+            (_, component) => self.0.push(component),
+        }
+    }
+
+    fn trim(mut self) -> Self {
+        if let Some(Component::Retain(_)) = self.0.last() {
+            self.0.pop();
+        }
+        self
+    }
+
+    /// Returns the minimal length of a text this operation can be applied to.
+    pub fn base_len(&self) -> u32 {
+        self.0
+            .iter()
+            .map(|c| match c {
+                Component::Retain(len) | Component::Delete(len) => *len,
+                Component::Insert(_) => 0,
+            })
+            .sum()
+    }
+
+    /// Transforms this operation against a concurrent `other` operation, which has been applied
+    /// first. Returned operation can be applied on top of `other` and produces the same
+    /// result as applying `other` transformed against this operation on top of this one.
+    pub fn transform(&self, other: &TextOp, side: Side, kind: OffsetKind) -> TextOp {
+        let mut result = TextOp::new();
+        let mut a = Cursor::new(&self.0);
+        let mut b = Cursor::new(&other.0);
+        loop {
+            match (a.peek(), b.peek()) {
+                (Some(Component::Insert(s)), Some(Component::Insert(_))) if side == Side::Left => {
+                    result.push(Component::Insert(s));
+                    a.next();
+                }
+                (_, Some(Component::Insert(s))) => {
+                    result.push(Component::Retain(str_len(&s, kind)));
+                    b.next();
+                }
+                (Some(Component::Insert(s)), _) => {
+                    result.push(Component::Insert(s));
+                    a.next();
+                }
+                (None, _) => break,
+                (Some(_), None) => {
+                    result.push(a.take(u32::MAX));
+                }
+                (Some(x), Some(y)) => {
+                    let len = x.len().min(y.len());
+                    match (a.take(len), b.take(len)) {
+                        (Component::Retain(len), Component::Retain(_)) => {
+                            result.push(Component::Retain(len))
+                        }
+                        (Component::Delete(len), Component::Retain(_)) => {
+                            result.push(Component::Delete(len))
+                        }
+                        // range has already been deleted by a concurrent operation
+                        (_, _) => {}
+                    }
+                }
+            }
+        }
+        result.trim()
+    }
+
+    /// Applies this operation onto a given `text` using a provided transaction.
+    ///
+    /// # Errors
+    ///
+    /// Returns [IndexOutOfBounds] if operation's [base length](TextOp::base_len) exceeds the
+    /// length of a text. In such case text is left unchanged.
+    pub fn apply(&self, txn: &mut TransactionMut, text: &TextRef) -> Result<(), IndexOutOfBounds> {
+        IndexOutOfBounds::check(self.base_len(), text.len(txn))?;
+        let kind = txn.doc().options().offset_kind;
+        let mut index = 0;
+        for component in self.0.iter() {
+            match component {
+                Component::Retain(len) => index += len,
+                Component::Insert(chunk) => {
+                    text.insert(txn, index, chunk);
+                    index += str_len(chunk, kind);
+                }
+                Component::Delete(len) => text.remove_range(txn, index, *len),
+            }
+        }
+        Ok(())
+    }
+
+    /// Converts a text [Delta] (ie. produced by [TextEvent::delta]) into an operation.
+    /// Formatting attributes are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns [OtError::UnsupportedEmbed] if a delta inserts a non-string value, as these cannot
+    /// be expressed by OT text operations.
+    ///
+    /// [TextEvent::delta]: crate::types::text::TextEvent::delta
+    pub fn from_delta(delta: &[Delta]) -> Result<Self, OtError> {
+        let mut op = TextOp::new();
+        for d in delta.iter() {
+            match d {
+                Delta::Retain(len, _) => op.push(Component::Retain(*len)),
+                Delta::Deleted(len) => op.push(Component::Delete(*len)),
+                Delta::Inserted(Out::Any(Any::String(s)), _) => {
+                    op.push(Component::Insert(s.to_string()))
+                }
+                Delta::Inserted(_, _) => return Err(OtError::UnsupportedEmbed),
+            }
+        }
+        Ok(op.trim())
+    }
+}
+
+impl From<&TextOp> for Any {
+    /// Converts an operation into ShareDB `ot-text` format: retains are encoded as numbers,
+    /// inserts as strings and deletes as `{"d": len}` objects.
+    fn from(op: &TextOp) -> Self {
+        let components: Vec<Any> =
+            op.0.iter()
+                .map(|c| match c {
+                    Component::Retain(len) => Any::from(*len),
+                    Component::Insert(s) => Any::from(s.as_str()),
+                    Component::Delete(len) => {
+                        Any::from(HashMap::from([("d".to_string(), Any::from(*len))]))
+                    }
+                })
+                .collect();
+        Any::from(components)
+    }
+}
+
+impl TryFrom<&Any> for TextOp {
+    type Error = OtError;
+
+    /// Parses an operation from ShareDB `ot-text` format, in which it is produced by a
+    /// conversion into [Any].
+    fn try_from(value: &Any) -> Result<Self, Self::Error> {
+        fn len(value: &Any) -> Result<u32, OtError> {
+            match value {
+                Any::Number(n) if *n >= 0.0 && n.fract() == 0.0 && *n <= u32::MAX as f64 => {
+                    Ok(*n as u32)
+                }
+                Any::BigInt(n) => u32::try_from(*n).map_err(|_| OtError::Malformed),
+                _ => Err(OtError::Malformed),
+            }
+        }
+
+        let components = match value {
+            Any::Array(components) => components,
+            _ => return Err(OtError::Malformed),
+        };
+        let mut op = TextOp::new();
+        for c in components.iter() {
+            match c {
+                Any::String(s) => op.push(Component::Insert(s.to_string())),
+                Any::Map(m) => match m.get("d") {
+                    Some(n) => op.push(Component::Delete(len(n)?)),
+                    None => return Err(OtError::Malformed),
+                },
+                n => op.push(Component::Retain(len(n)?)),
+            }
+        }
+        Ok(op.trim())
+    }
+}
+
+impl Component {
+    fn len(&self) -> u32 {
+        match self {
+            Component::Retain(len) | Component::Delete(len) => *len,
+            Component::Insert(_) => 0,
+        }
+    }
+}
+
+/// Cursor over operation's components, which allows to consume retain and delete components
+/// partially.
+struct Cursor<'a> {
+    components: &'a [Component],
+    index: usize,
+    offset: u32,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(components: &'a [Component]) -> Self {
+        Cursor {
+            components,
+            index: 0,
+            offset: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<Component> {
+        let c = self.components.get(self.index)?;
+        Some(match c {
+            Component::Retain(len) => Component::Retain(len - self.offset),
+            Component::Delete(len) => Component::Delete(len - self.offset),
+            Component::Insert(s) => Component::Insert(s.clone()),
+        })
+    }
+
+    fn next(&mut self) {
+        self.index += 1;
+        self.offset = 0;
+    }
+
+    /// Consumes up to `len` characters of a current retain or delete component.
+    fn take(&mut self, len: u32) -> Component {
+        let remaining = self.components[self.index].len() - self.offset;
+        let len = len.min(remaining);
+        let taken = match &self.components[self.index] {
+            Component::Retain(_) => Component::Retain(len),
+            Component::Delete(_) => Component::Delete(len),
+            Component::Insert(_) => unreachable!("inserts are never taken partially"),
+        };
+        if len == remaining {
+            self.next();
+        } else {
+            self.offset += len;
+        }
+        taken
+    }
+}
+
+fn str_len(s: &str, kind: OffsetKind) -> u32 {
+    match kind {
+        OffsetKind::Bytes => s.len() as u32,
+        OffsetKind::Utf16 => s.encode_utf16().count() as u32,
+    }
+}
+
+/// Error returned by [OtText] and [TextOp] conversions.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum OtError {
+    #[error("operation revision {revision} is newer than current revision {current}")]
+    FutureRevision { revision: u64, current: u64 },
+    #[error("operation revision {revision} is older than the oldest kept revision {oldest}")]
+    RevisionTooOld { revision: u64, oldest: u64 },
+    #[error("text contains embedded values, which are not supported by OT text operations")]
+    UnsupportedEmbed,
+    #[error("malformed OT text operation")]
+    Malformed,
+    #[error(transparent)]
+    OutOfBounds(#[from] IndexOutOfBounds),
+}
+
+/// Revisioned OT front for a [TextRef]. Every operation applied to a text through this adapter
+/// increments its revision. Clients submit their operations together with the revision they
+/// were created against with [OtText::submit], which transforms them against all operations
+/// applied in the meantime before applying them to a text - just like an OT server would do.
+///
+/// Changes made to a text by CRDT peers must be recorded with [OtText::record], so that they are
+/// assigned a revision and OT clients can be notified about them. A common way of doing so is
+/// to subscribe for text events and record the changes made by transactions which have not been
+/// created by [OtText::submit] caller, ie. by using a dedicated [Origin] for such transactions.
+///
+/// [Origin]: crate::Origin
+pub struct OtText {
+    text: TextRef,
+    kind: OffsetKind,
+    /// Revision of the first operation kept in history.
+    oldest: u64,
+    history: VecDeque<TextOp>,
+}
+
+impl OtText {
+    /// Creates a new adapter for a given `text`, starting at revision 0. `kind` must be the same
+    /// [OffsetKind] as the one used by a document `text` belongs to.
+    pub fn new(text: TextRef, kind: OffsetKind) -> Self {
+        OtText {
+            text,
+            kind,
+            oldest: 0,
+            history: VecDeque::new(),
+        }
+    }
+
+    /// Returns a text this adapter has been created for.
+    pub fn text(&self) -> &TextRef {
+        &self.text
+    }
+
+    /// Returns current revision, which is the number of operations applied so far.
+    pub fn revision(&self) -> u64 {
+        self.oldest + self.history.len() as u64
+    }
+
+    /// Submits an operation created by a client against a given `revision`. Operation is
+    /// transformed against all operations applied since that revision, applied onto a text and
+    /// returned, so that it can be broadcast to other OT clients as the operation of a new
+    /// current revision.
+    pub fn submit(
+        &mut self,
+        txn: &mut TransactionMut,
+        revision: u64,
+        op: TextOp,
+    ) -> Result<TextOp, OtError> {
+        let current = self.revision();
+        if revision > current {
+            return Err(OtError::FutureRevision { revision, current });
+        } else if revision < self.oldest {
+            return Err(OtError::RevisionTooOld {
+                revision,
+                oldest: self.oldest,
+            });
+        }
+        let mut op = op;
+        let start = (revision - self.oldest) as usize;
+        for concurrent in self.history.range(start..) {
+            op = op.transform(concurrent, Side::Left, self.kind);
+        }
+        op.apply(txn, &self.text)?;
+        self.history.push_back(op.clone());
+        Ok(op)
+    }
+
+    /// Records changes made to a text by other means than [OtText::submit] (ie. by CRDT peers),
+    /// described by a given `delta`. Returns an operation which has been assigned a new revision
+    /// or `None` if delta didn't change text contents.
+    pub fn record(&mut self, delta: &[Delta]) -> Result<Option<TextOp>, OtError> {
+        let op = TextOp::from_delta(delta)?;
+        if op.is_noop() {
+            Ok(None)
+        } else {
+            self.history.push_back(op.clone());
+            Ok(Some(op))
+        }
+    }
+
+    /// Drops operations older than a given `revision` from the history. Clients will no longer be
+    /// able to submit operations created against these revisions.
+    pub fn forget_before(&mut self, revision: u64) {
+        while self.oldest < revision && !self.history.is_empty() {
+            self.history.pop_front();
+            self.oldest += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::sync::ot::{Component, OtError, OtText, Side, TextOp};
+    use crate::{any, Any, Doc, GetString, Observable, OffsetKind, Options, Text, Transact};
+    use std::convert::TryFrom;
+    use std::sync::{Arc, Mutex};
+
+    fn apply_str(s: &str, op: &TextOp) -> String {
+        let mut result = String::new();
+        let mut index = 0;
+        for c in op.components() {
+            match c {
+                Component::Retain(len) => {
+                    result.push_str(&s[index..index + *len as usize]);
+                    index += *len as usize;
+                }
+                Component::Insert(chunk) => result.push_str(chunk),
+                Component::Delete(len) => index += *len as usize,
+            }
+        }
+        result.push_str(&s[index..]);
+        result
+    }
+
+    #[test]
+    fn transform_converges() {
+        let base = "hello world";
+        let ops = [
+            TextOp::new().retain(5).insert(" big"),
+            TextOp::new().retain(5).insert(" small"),
+            TextOp::new().delete(6),
+            TextOp::new().retain(3).delete(5).insert("X"),
+            TextOp::new().retain(11).insert("!"),
+        ];
+        for a in ops.iter() {
+            for b in ops.iter() {
+                let a2 = a.transform(b, Side::Left, OffsetKind::Bytes);
+                let b2 = b.transform(a, Side::Right, OffsetKind::Bytes);
+                assert_eq!(
+                    apply_str(&apply_str(base, b), &a2),
+                    apply_str(&apply_str(base, a), &b2),
+                    "a: {:?}, b: {:?}",
+                    a,
+                    b
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn sharedb_format() {
+        let op = TextOp::new().retain(2).insert("ab").delete(1).delete(2);
+        let any = any!([2, "ab", {"d": 3}]);
+        assert_eq!(Any::from(&op), any);
+        assert_eq!(TextOp::try_from(&any!([2, "ab", {"d": 3}, 1])).unwrap(), op);
+        assert_eq!(TextOp::try_from(&any!([-1])), Err(OtError::Malformed));
+    }
+
+    #[test]
+    fn submit_concurrent_ops() {
+        let mut options = Options::with_client_id(1);
+        options.offset_kind = OffsetKind::Utf16;
+        let doc = Doc::with_options(options);
+        let text = doc.get_or_insert_text("text");
+        text.insert(&mut doc.transact_mut(), 0, "hello");
+        let ot = Arc::new(Mutex::new(OtText::new(text.clone(), OffsetKind::Utf16)));
+
+        // record changes made by CRDT peers
+        let ot_ref = ot.clone();
+        let _sub = text.observe(move |txn, e| {
+            if txn.origin().is_none() {
+                ot_ref.lock().unwrap().record(e.delta(txn)).unwrap();
+            }
+        });
+
+        // two clients submit operations concurrently against revision 0
+        let op1 = {
+            let mut txn = doc.transact_mut_with("ot");
+            ot.lock()
+                .unwrap()
+                .submit(&mut txn, 0, TextOp::new().retain(5).insert(" world"))
+                .unwrap()
+        };
+        let op2 = {
+            let mut txn = doc.transact_mut_with("ot");
+            ot.lock()
+                .unwrap()
+                .submit(&mut txn, 0, TextOp::new().insert("> ").delete(1))
+                .unwrap()
+        };
+        assert_eq!(op2, TextOp::new().insert("> ").delete(1));
+        assert_eq!(text.get_string(&doc.transact()), "> ello world");
+
+        // CRDT peer change is assigned the next revision
+        text.push(&mut doc.transact_mut(), "!");
+        assert_eq!(ot.lock().unwrap().revision(), 3);
+
+        // client at revision 1 catches up by applying broadcast ops
+        let op3 = {
+            let mut txn = doc.transact_mut_with("ot");
+            ot.lock()
+                .unwrap()
+                .submit(&mut txn, 1, TextOp::new().retain(11).insert("?"))
+                .unwrap()
+        };
+        assert_eq!(op3, TextOp::new().retain(12).insert("?"));
+        assert_eq!(text.get_string(&doc.transact()), "> ello world?!");
+        let client = apply_str(&apply_str("hello", &op1), &op2);
+        assert_eq!(client, "> ello world");
+
+        assert_eq!(
+            ot.lock()
+                .unwrap()
+                .submit(&mut doc.transact_mut_with("ot"), 10, TextOp::new()),
+            Err(OtError::FutureRevision {
+                revision: 10,
+                current: 4
+            })
+        );
+        ot.lock().unwrap().forget_before(2);
+        assert_eq!(
+            ot.lock()
+                .unwrap()
+                .submit(&mut doc.transact_mut_with("ot"), 1, TextOp::new()),
+            Err(OtError::RevisionTooOld {
+                revision: 1,
+                oldest: 2
+            })
+        );
+    }
+}
@@ -75,11 +75,27 @@ pub trait Protocol {
         awareness: &mut Awareness,
         update: Update,
     ) -> Result<Option<Message>, Error> {
-        let mut txn = awareness.doc().transact_mut();
-        txn.apply_update(update);
+        awareness
+            .doc()
+            .apply_update(update)
+            .map_err(|e| Error::Other(e.into()))?;
         Ok(None)
     }
 
+    /// Decodes a payload of sync-step-2 and update messages. By default it's a lib0 v1 encoded
+    /// update, but this method can be overridden if payloads are transformed in any way, ie.
+    /// encrypted.
+    fn decode_update(&self, data: &[u8]) -> Result<Update, Error> {
+        Ok(Update::decode_v1(data)?)
+    }
+
+    /// Creates an update message sent to a remote peer for a lib0 v1 encoded `update`, ie. one
+    /// produced by [Doc::observe_update_v1](crate::Doc::observe_update_v1). This is a counterpart
+    /// of [Protocol::decode_update] and should be overridden together with it.
+    fn encode_update(&self, update: &[u8]) -> Message {
+        Message::Sync(SyncMessage::Update(update.to_vec()))
+    }
+
     /// Handle continuous update send from the client. By default just apply an update to a current
     /// `awareness` document instance.
     fn handle_update(
@@ -0,0 +1,539 @@
+//! A y-websocket compatible collaboration server. Every WebSocket connection made to
+//! `ws://<host>/<room>` joins a room identified by a request path, where each room is a separate
+//! document served by its own [BroadcastGroup].
+//!
+//! Rooms are opened on the first connection and closed after the last one is gone. When
+//! a [Persistence] is configured, a room document is loaded from it when the room is opened, every
+//! update is appended to it as it comes, and the whole document state is compacted into a single
+//! update when the room is closed.
+//!
+//! The same server is available from a command line, via `y-websocket-server` binary:
+//!
+//! ```bash
+//! cargo run -p yrs --features server --bin y-websocket-server -- [--host HOST] [--port PORT] [--data-dir DIR]
+//! ```
+//!
+//! ```rust,no_run
+//! use std::sync::Arc;
+//! use yrs::sync::server::{FilePersistence, Server};
+//!
+//! # async fn run() -> std::io::Result<()> {
+//! let server = Server::new()
+//!     .with_persistence(FilePersistence::new("./data")?)
+//!     .with_auth(|req| {
+//!         if req.has_token("secret") {
+//!             Ok(())
+//!         } else {
+//!             Err("invalid token".to_string())
+//!         }
+//!     });
+//! let listener = tokio::net::TcpListener::bind("0.0.0.0:1234").await?;
+//! axum::serve(listener, Arc::new(server).router()).await
+//! # }
+//! ```
+
+use crate::encoding::read::{Cursor, Read};
+use crate::encoding::write::Write;
+use crate::sync::broadcast::BroadcastGroup;
+use crate::sync::{Awareness, Error};
+use crate::updates::decoder::Decode;
+use crate::{Doc, ReadTxn, StateVector, Subscription, Transact, Update};
+use ::axum::extract::ws::WebSocketUpgrade;
+use ::axum::extract::{Path, RawQuery, State};
+use ::axum::http::{HeaderMap, StatusCode};
+use ::axum::response::{IntoResponse, Response};
+use ::axum::routing::get;
+use ::axum::Router;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{mpsc, oneshot};
+
+/// Storage of room documents used by a [Server].
+///
+/// Methods of this trait are called on tokio's blocking thread pool, so they are free to perform
+/// blocking I/O. Calls made for the same room never overlap and are made in the same order as
+/// changes made to a room document.
+pub trait Persistence: Send + Sync + 'static {
+    /// Returns lib0 v1 encoded updates stored so far for a given `room`, in the order they were
+    /// appended. Returns an empty list for unknown rooms.
+    fn load(&self, room: &str) -> std::io::Result<Vec<Vec<u8>>>;
+
+    /// Appends lib0 v1 encoded `update` produced by a given `room` document.
+    fn append(&self, room: &str, update: &[u8]) -> std::io::Result<()>;
+
+    /// Replaces all updates stored for a given `room` with a single lib0 v1 encoded document
+    /// `state`, which is a result of merging them.
+    fn compact(&self, room: &str, state: &[u8]) -> std::io::Result<()>;
+}
+
+/// [Persistence] storing every room in a separate file inside of a given directory. Files are
+/// named after hex encoded SHA-256 hashes of room names, so any room name can be safely used.
+#[derive(Debug, Clone)]
+pub struct FilePersistence {
+    dir: PathBuf,
+}
+
+impl FilePersistence {
+    /// Creates a new file persistence in a given directory, creating that directory if needed.
+    pub fn new<P: Into<PathBuf>>(dir: P) -> std::io::Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        Ok(FilePersistence { dir })
+    }
+
+    fn path(&self, room: &str) -> PathBuf {
+        let mut name = String::with_capacity(64 + 5);
+        for b in Sha256::digest(room.as_bytes()) {
+            name.push_str(&format!("{:02x}", b));
+        }
+        name.push_str(".ydoc");
+        self.dir.join(name)
+    }
+}
+
+impl Persistence for FilePersistence {
+    fn load(&self, room: &str) -> std::io::Result<Vec<Vec<u8>>> {
+        let data = match std::fs::read(self.path(room)) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let mut cursor = Cursor::new(&data);
+        let mut updates = Vec::new();
+        while cursor.has_content() {
+            let update = cursor
+                .read_buf()
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+            updates.push(update.to_vec());
+        }
+        Ok(updates)
+    }
+
+    fn append(&self, room: &str, update: &[u8]) -> std::io::Result<()> {
+        use std::io::Write;
+        let mut buf = Vec::with_capacity(update.len() + 5);
+        buf.write_buf(update);
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.path(room))?
+            .write_all(&buf)
+    }
+
+    fn compact(&self, room: &str, state: &[u8]) -> std::io::Result<()> {
+        let path = self.path(room);
+        let tmp = path.with_extension("tmp");
+        let mut buf = Vec::with_capacity(state.len() + 5);
+        buf.write_buf(state);
+        std::fs::write(&tmp, buf)?;
+        std::fs::rename(tmp, path)
+    }
+}
+
+/// Details of an incoming connection request, passed to an authorization hook configured with
+/// [Server::with_auth].
+#[derive(Debug)]
+pub struct ConnectionRequest<'a> {
+    /// Name of a room, which connection is about to join.
+    pub room: &'a str,
+    /// Raw query string of a request URL, if any.
+    pub query: Option<&'a str>,
+    /// HTTP headers of a request.
+    pub headers: &'a HeaderMap,
+}
+
+impl<'a> ConnectionRequest<'a> {
+    /// Checks if a query string of a request contains a `token` parameter equal to a given
+    /// `expected` value. Tokens are compared in constant time.
+    pub fn has_token(&self, expected: &str) -> bool {
+        let mut found = false;
+        for param in self.query.unwrap_or_default().split('&') {
+            if let Some(token) = param.strip_prefix("token=") {
+                // don't stop early, so that a number of checked parameters doesn't leak either
+                found |= constant_time_eq(token.as_bytes(), expected.as_bytes());
+            }
+        }
+        found
+    }
+}
+
+/// Compares two byte strings in time dependent only on their lengths.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let diff = a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y));
+    std::hint::black_box(diff) == 0
+}
+
+type AuthHook = dyn Fn(&ConnectionRequest) -> Result<(), String> + Send + Sync;
+
+/// Operations performed by a background task persisting a single room.
+enum PersistOp {
+    Append(Vec<u8>),
+    /// Compacts a room and stops the task, sending back a result of compaction.
+    Compact(Vec<u8>, oneshot::Sender<std::io::Result<()>>),
+}
+
+struct Room {
+    group: Arc<BroadcastGroup>,
+    doc: Doc,
+    connections: usize,
+    persistence: Option<(mpsc::UnboundedSender<PersistOp>, Subscription)>,
+}
+
+/// State of a room shared by all connections to it. It stays `None` while a room is being opened
+/// or after it has been closed.
+type RoomSlot = Arc<tokio::sync::Mutex<Option<Room>>>;
+
+/// A y-websocket compatible server managing a set of rooms. See [module documentation] for
+/// details.
+///
+/// [module documentation]: crate::sync::server
+pub struct Server {
+    rooms: Mutex<HashMap<Arc<str>, RoomSlot>>,
+    persistence: Option<Arc<dyn Persistence>>,
+    auth: Option<Arc<AuthHook>>,
+    buffer_capacity: usize,
+}
+
+impl Server {
+    /// Creates a new server, which keeps room documents only in memory and accepts all
+    /// connections.
+    pub fn new() -> Self {
+        Server {
+            rooms: Mutex::new(HashMap::new()),
+            persistence: None,
+            auth: None,
+            buffer_capacity: 64,
+        }
+    }
+
+    /// Sets a persistence used to load and store room documents.
+    pub fn with_persistence<P: Persistence>(mut self, persistence: P) -> Self {
+        self.persistence = Some(Arc::new(persistence));
+        self
+    }
+
+    /// Sets an authorization hook called for every incoming connection request before WebSocket
+    /// upgrade. When it returns an error, request is rejected with `403 Forbidden` status code
+    /// and returned message as a response body.
+    pub fn with_auth<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&ConnectionRequest) -> Result<(), String> + Send + Sync + 'static,
+    {
+        self.auth = Some(Arc::new(f));
+        self
+    }
+
+    /// Sets a number of messages buffered for every connection, see [BroadcastGroup::new].
+    pub fn with_buffer_capacity(mut self, buffer_capacity: usize) -> Self {
+        self.buffer_capacity = buffer_capacity;
+        self
+    }
+
+    /// Returns an [axum](https://docs.rs/axum) router serving WebSocket connections under
+    /// `/<room>` paths.
+    pub fn router(self: Arc<Self>) -> Router {
+        Router::new()
+            .route("/{*room}", get(handler))
+            .with_state(self)
+    }
+
+    /// Returns names of all currently open rooms.
+    pub fn rooms(&self) -> Vec<Arc<str>> {
+        let rooms = self.rooms.lock().unwrap();
+        let mut names: Vec<_> = rooms.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Returns a group of a currently open room, if it exists.
+    pub async fn room(&self, name: &str) -> Option<Arc<BroadcastGroup>> {
+        let slot = self.rooms.lock().unwrap().get(name).cloned()?;
+        let room = slot.lock().await;
+        room.as_ref().map(|room| room.group.clone())
+    }
+
+    /// Returns a slot of a room with a given `name`, creating it if necessary, together with its
+    /// lock. Slots removed from the room map while waiting for a lock are skipped.
+    async fn lock_slot(
+        &self,
+        name: &str,
+    ) -> (RoomSlot, tokio::sync::OwnedMutexGuard<Option<Room>>) {
+        loop {
+            let slot = self
+                .rooms
+                .lock()
+                .unwrap()
+                .entry(name.into())
+                .or_default()
+                .clone();
+            let guard = slot.clone().lock_owned().await;
+            let current = self.rooms.lock().unwrap().get(name).cloned();
+            if current.is_some_and(|current| Arc::ptr_eq(&current, &slot)) {
+                return (slot, guard);
+            }
+        }
+    }
+
+    /// Removes a given room `slot` from the room map, unless it has been replaced already.
+    fn remove_slot(&self, name: &str, slot: &RoomSlot) {
+        let mut rooms = self.rooms.lock().unwrap();
+        if rooms
+            .get(name)
+            .is_some_and(|current| Arc::ptr_eq(current, slot))
+        {
+            rooms.remove(name);
+        }
+    }
+
+    /// Opens a room with a given `name` (loading it from persistence if necessary) and registers
+    /// a new connection to it. Every call must be paired with [Server::release_room].
+    pub async fn acquire_room(&self, name: &str) -> Result<Arc<BroadcastGroup>, Error> {
+        let (slot, mut guard) = self.lock_slot(name).await;
+        if let Some(room) = guard.as_mut() {
+            room.connections += 1;
+            return Ok(room.group.clone());
+        }
+        match self.open_room(name).await {
+            Ok(room) => {
+                let group = room.group.clone();
+                *guard = Some(room);
+                Ok(group)
+            }
+            Err(e) => {
+                self.remove_slot(name, &slot);
+                Err(e)
+            }
+        }
+    }
+
+    async fn open_room(&self, name: &str) -> Result<Room, Error> {
+        let doc = Doc::new();
+        let mut persistence = None;
+        if let Some(storage) = self.persistence.clone() {
+            let updates = {
+                let storage = storage.clone();
+                let room = name.to_string();
+                tokio::task::spawn_blocking(move || storage.load(&room)).await??
+            };
+            {
+                let mut txn = doc.transact_mut();
+                for update in updates {
+                    txn.apply_update(Update::decode_v1(&update)?);
+                }
+            }
+            let (sender, receiver) = mpsc::unbounded_channel();
+            tokio::spawn(persist_room(storage, name.into(), receiver));
+            let sub = {
+                let sender = sender.clone();
+                doc.observe_update_v1(move |_, e| {
+                    let _ = sender.send(PersistOp::Append(e.update.clone()));
+                })
+                .map_err(|e| Error::Other(e.to_string().into()))?
+            };
+            persistence = Some((sender, sub));
+        }
+        let group = BroadcastGroup::new(Awareness::new(doc.clone()), self.buffer_capacity)
+            .map_err(|e| Error::Other(e.to_string().into()))?;
+        Ok(Room {
+            group: Arc::new(group),
+            doc,
+            connections: 1,
+            persistence,
+        })
+    }
+
+    /// Unregisters a connection from a room with a given `name`. Once the last connection is gone,
+    /// the room is closed and its state is compacted in persistence.
+    pub async fn release_room(&self, name: &str) -> Result<(), Error> {
+        let slot = match self.rooms.lock().unwrap().get(name).cloned() {
+            Some(slot) => slot,
+            None => return Ok(()),
+        };
+        let mut guard = slot.lock().await;
+        match guard.as_mut() {
+            Some(room) if room.connections > 1 => {
+                room.connections -= 1;
+                Ok(())
+            }
+            Some(_) => {
+                let room = guard.take().unwrap();
+                // keep the slot locked until compaction is done, so that the room can't be
+                // reopened from a stale state in the meantime
+                let result = match room.persistence {
+                    Some((sender, _sub)) => {
+                        let state = room
+                            .doc
+                            .transact()
+                            .encode_state_as_update_v1(&StateVector::default());
+                        let (reply, result) = oneshot::channel();
+                        let _ = sender.send(PersistOp::Compact(state, reply));
+                        match result.await {
+                            Ok(result) => result.map_err(Error::from),
+                            Err(e) => Err(Error::Other(e.into())),
+                        }
+                    }
+                    None => Ok(()),
+                };
+                self.remove_slot(name, &slot);
+                result
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+/// Writes changes of a single room to a given `storage` one after another, until the room is
+/// compacted.
+async fn persist_room(
+    storage: Arc<dyn Persistence>,
+    room: Arc<str>,
+    mut ops: mpsc::UnboundedReceiver<PersistOp>,
+) {
+    while let Some(op) = ops.recv().await {
+        let storage = storage.clone();
+        let name = room.clone();
+        match op {
+            PersistOp::Append(update) => {
+                let result = tokio::task::spawn_blocking(move || storage.append(&name, &update));
+                if let Err(_e) = flatten(result.await) {
+                    #[cfg(feature = "tracing")]
+                    tracing::error!(room = %room, error = %_e, "failed to persist update");
+                }
+            }
+            PersistOp::Compact(state, reply) => {
+                let result = tokio::task::spawn_blocking(move || storage.compact(&name, &state));
+                let _ = reply.send(flatten(result.await));
+                return;
+            }
+        }
+    }
+}
+
+fn flatten(result: Result<std::io::Result<()>, tokio::task::JoinError>) -> std::io::Result<()> {
+    result.map_err(std::io::Error::other)?
+}
+
+impl Default for Server {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+async fn handler(
+    ws: WebSocketUpgrade,
+    Path(room): Path<String>,
+    RawQuery(query): RawQuery,
+    headers: HeaderMap,
+    State(server): State<Arc<Server>>,
+) -> Response {
+    if let Some(auth) = server.auth.as_ref() {
+        let req = ConnectionRequest {
+            room: &room,
+            query: query.as_deref(),
+            headers: &headers,
+        };
+        if let Err(reason) = auth(&req) {
+            return (StatusCode::FORBIDDEN, reason).into_response();
+        }
+    }
+    // room is acquired only once a connection is upgraded, so that failed upgrades don't
+    // leave it open forever
+    ws.on_upgrade(move |socket| async move {
+        let group = match server.acquire_room(&room).await {
+            Ok(group) => group,
+            Err(_e) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!(room = %room, error = %_e, "failed to open room");
+                return;
+            }
+        };
+        let _ = crate::sync::axum::serve(socket, &group).await;
+        drop(group);
+        if let Err(_e) = server.release_room(&room).await {
+            #[cfg(feature = "tracing")]
+            tracing::error!(room = %room, error = %_e, "failed to close room");
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::sync::server::{ConnectionRequest, FilePersistence, Persistence, Server};
+    use crate::{GetString, Text, Transact};
+    use axum::http::HeaderMap;
+
+    fn temp_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("yrs-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn rooms_are_persisted_and_compacted() {
+        let dir = temp_dir("server-rooms");
+        let persistence = FilePersistence::new(&dir).unwrap();
+        let server = Server::new().with_persistence(persistence.clone());
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let g1 = server.acquire_room("docs/1").await.unwrap();
+            let g2 = server.acquire_room("docs/1").await.unwrap();
+            assert_eq!(server.rooms().len(), 1);
+            {
+                let awareness = g1.awareness().try_read().unwrap();
+                let text = awareness.doc().get_or_insert_text("text");
+                text.insert(&mut awareness.doc().transact_mut(), 0, "hello");
+                text.push(&mut awareness.doc().transact_mut(), " world");
+            }
+            drop((g1, g2));
+
+            server.release_room("docs/1").await.unwrap();
+            assert!(server.room("docs/1").await.is_some());
+            // updates are persisted by a background task
+            while persistence.load("docs/1").unwrap().len() < 2 {
+                tokio::task::yield_now().await;
+            }
+            server.release_room("docs/1").await.unwrap();
+            assert!(server.rooms().is_empty());
+            assert_eq!(persistence.load("docs/1").unwrap().len(), 1);
+
+            // reopened room is loaded from persistence
+            let group = server.acquire_room("docs/1").await.unwrap();
+            let awareness = group.awareness().try_read().unwrap();
+            let text = awareness.doc().get_or_insert_text("text");
+            assert_eq!(text.get_string(&awareness.doc().transact()), "hello world");
+        });
+        assert!(persistence.load("other").unwrap().is_empty());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn long_room_names_are_persisted() {
+        let dir = temp_dir("server-long-names");
+        let persistence = FilePersistence::new(&dir).unwrap();
+        let room = "x".repeat(1000);
+        persistence.append(&room, &[0, 0]).unwrap();
+        assert_eq!(persistence.load(&room).unwrap(), vec![vec![0, 0]]);
+        assert!(persistence.load("x").unwrap().is_empty());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn tokens_are_checked() {
+        let headers = HeaderMap::new();
+        let req = |query| ConnectionRequest {
+            room: "room",
+            query,
+            headers: &headers,
+        };
+        assert!(req(Some("token=secret")).has_token("secret"));
+        assert!(req(Some("a=1&token=secret")).has_token("secret"));
+        assert!(!req(Some("token=secret2")).has_token("secret"));
+        assert!(!req(Some("token=")).has_token("secret"));
+        assert!(!req(None).has_token("secret"));
+    }
+}
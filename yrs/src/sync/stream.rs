@@ -0,0 +1,170 @@
+//! Adapters connecting a [Doc] to asynchronous transports: [UpdateStream] emits updates produced
+//! by a document, while [UpdateSink] applies incoming updates onto it. Both operate on lib0 v1
+//! encoded updates, so they can be plugged directly into any transport exposing
+//! [Stream]/[Sink] of [Bytes].
+//!
+//! ```rust
+//! use futures::{StreamExt, SinkExt};
+//! use yrs::{Doc, GetString, Text, Transact};
+//! use yrs::sync::stream::{UpdateSink, UpdateStream};
+//!
+//! # futures::executor::block_on(async {
+//! let local = Doc::with_client_id(1);
+//! let remote = Doc::with_client_id(2);
+//! let updates = UpdateStream::new(&local).unwrap();
+//!
+//! let text = local.get_or_insert_text("text");
+//! text.insert(&mut local.transact_mut(), 0, "hello");
+//!
+//! updates
+//!     .take(1)
+//!     .map(Ok)
+//!     .forward(UpdateSink::new(remote.clone()))
+//!     .await
+//!     .unwrap();
+//!
+//! let text = remote.get_or_insert_text("text");
+//! assert_eq!(text.get_string(&remote.transact()), "hello");
+//! # });
+//! ```
+
+use crate::sync::Error;
+use crate::updates::decoder::Decode;
+use crate::{Doc, Origin, Subscription, Update};
+use atomic_refcell::BorrowMutError;
+use bytes::Bytes;
+use futures::channel::mpsc::{unbounded, UnboundedReceiver};
+use futures::{Sink, Stream};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// [Stream] of lib0 v1 encoded updates, produced by every read-write transaction committed on
+/// a document this stream has been created for. Updates are buffered, so no update is lost when
+/// the stream is not polled for some time.
+pub struct UpdateStream {
+    receiver: UnboundedReceiver<Bytes>,
+    _subscription: Subscription,
+}
+
+impl UpdateStream {
+    /// Creates a stream of all updates produced by a given `doc`.
+    pub fn new(doc: &Doc) -> Result<Self, BorrowMutError> {
+        Self::create(doc, None)
+    }
+
+    /// Creates a stream of updates produced by a given `doc`, skipping the ones made by
+    /// transactions with a given `origin`. When used together with [UpdateSink::with_origin],
+    /// it prevents updates received from a remote peer from being sent back to it.
+    pub fn excluding<O: Into<Origin>>(doc: &Doc, origin: O) -> Result<Self, BorrowMutError> {
+        Self::create(doc, Some(origin.into()))
+    }
+
+    fn create(doc: &Doc, excluded: Option<Origin>) -> Result<Self, BorrowMutError> {
+        let (sender, receiver) = unbounded();
+        let subscription = doc.observe_update_v1(move |txn, e| {
+            if excluded.is_some() && txn.origin() == excluded.as_ref() {
+                return;
+            }
+            // receiver may have been dropped already, in which case the update is not needed
+            let _ = sender.unbounded_send(Bytes::from(e.update.clone()));
+        })?;
+        Ok(UpdateStream {
+            receiver,
+            _subscription: subscription,
+        })
+    }
+}
+
+impl Stream for UpdateStream {
+    type Item = Bytes;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.receiver).poll_next(cx)
+    }
+}
+
+/// [Sink] of lib0 v1 encoded updates, which applies every received update onto a document in
+/// a separate read-write transaction.
+pub struct UpdateSink {
+    doc: Doc,
+    origin: Option<Origin>,
+}
+
+impl UpdateSink {
+    /// Creates a sink applying updates onto a given `doc`.
+    pub fn new(doc: Doc) -> Self {
+        UpdateSink { doc, origin: None }
+    }
+
+    /// Creates a sink applying updates onto a given `doc` within transactions with a given
+    /// `origin`. See [UpdateStream::excluding].
+    pub fn with_origin<O: Into<Origin>>(doc: Doc, origin: O) -> Self {
+        UpdateSink {
+            doc,
+            origin: Some(origin.into()),
+        }
+    }
+}
+
+impl Sink<Bytes> for UpdateSink {
+    type Error = Error;
+
+    fn poll_ready(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Bytes) -> Result<(), Self::Error> {
+        let update = Update::decode_v1(&item)?;
+        let result = match self.origin.clone() {
+            Some(origin) => self.doc.apply_update_with(origin, update),
+            None => self.doc.apply_update(update),
+        };
+        result.map_err(|e| Error::Other(e.into()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::sync::stream::{UpdateSink, UpdateStream};
+    use crate::{Doc, GetString, Text, Transact};
+    use futures::{FutureExt, SinkExt, StreamExt};
+
+    #[test]
+    fn sync_docs_over_streams() {
+        futures::executor::block_on(async {
+            let d1 = Doc::with_client_id(1);
+            let d2 = Doc::with_client_id(2);
+            let mut s1 = UpdateStream::excluding(&d1, "remote").unwrap();
+            let mut s2 = UpdateStream::excluding(&d2, "remote").unwrap();
+            let mut sink1 = UpdateSink::with_origin(d1.clone(), "remote");
+            let mut sink2 = UpdateSink::with_origin(d2.clone(), "remote");
+
+            let t1 = d1.get_or_insert_text("text");
+            let t2 = d2.get_or_insert_text("text");
+            t1.insert(&mut d1.transact_mut(), 0, "hello");
+            t1.push(&mut d1.transact_mut(), " world");
+
+            let mut forwarded = (&mut s1).take(2).map(Ok);
+            sink2.send_all(&mut forwarded).await.unwrap();
+            assert_eq!(t2.get_string(&d2.transact()), "hello world");
+            // updates applied through the sink are not echoed back
+            assert!(s2.next().now_or_never().is_none());
+
+            t2.push(&mut d2.transact_mut(), "!");
+            let update = s2.next().await.unwrap();
+            sink1.send(update).await.unwrap();
+            assert_eq!(t1.get_string(&d1.transact()), "hello world!");
+            assert!(s1.next().now_or_never().is_none());
+
+            assert!(sink1.send(vec![255].into()).await.is_err());
+        });
+    }
+}
@@ -1,3 +1,6 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
 /// Timestamp used by [crate::sync::Awareness] to tag most recent updates.
 pub type Timestamp = u64;
 
@@ -30,3 +33,35 @@ impl Clock for SystemClock {
             .as_millis() as Timestamp
     }
 }
+
+/// A clock which never advances on its own. Its time can only be changed explicitly via
+/// [ManualClock::set] or [ManualClock::advance]. Clones of the same clock share the same time.
+///
+/// This makes time-dependent components (like [crate::UndoManager] capture timeout or
+/// [crate::sync::Awareness] timestamps) deterministic, i.e. for the purpose of reproducible tests.
+#[derive(Debug, Clone, Default)]
+pub struct ManualClock(Arc<AtomicU64>);
+
+impl ManualClock {
+    /// Creates a new clock set to a given timestamp.
+    pub fn new(now: Timestamp) -> Self {
+        ManualClock(Arc::new(AtomicU64::new(now)))
+    }
+
+    /// Sets current time of this clock.
+    pub fn set(&self, now: Timestamp) {
+        self.0.store(now, Ordering::SeqCst);
+    }
+
+    /// Moves current time of this clock forward by a given number of milliseconds.
+    pub fn advance(&self, millis: u64) {
+        self.0.fetch_add(millis, Ordering::SeqCst);
+    }
+}
+
+impl Clock for ManualClock {
+    #[inline]
+    fn now(&self) -> Timestamp {
+        self.0.load(Ordering::SeqCst)
+    }
+}
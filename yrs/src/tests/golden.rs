@@ -0,0 +1,142 @@
+//! Test support for golden binary fixtures generated by Yjs. See `assets/yjs-fixtures/README.md`
+//! for the description of a fixture directory layout.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::types::ToJson;
+use crate::updates::decoder::Decode;
+use crate::updates::encoder::Encode;
+use crate::{Any, Doc, GetString, ReadTxn, Snapshot, StateVector, Transact, Update, WriteTxn};
+
+const FIXTURES_DIR: &str = "../assets/yjs-fixtures";
+
+/// A single golden fixture: a group of files sharing the same name prefix.
+#[derive(Debug, Default)]
+pub struct Fixture {
+    pub name: String,
+    pub update_v1: Option<Vec<u8>>,
+    pub update_v2: Option<Vec<u8>>,
+    pub state_vector: Option<Vec<u8>>,
+    pub snapshot: Option<Vec<u8>>,
+    /// Expected document content, see [Fixture::assert_content].
+    pub expected: Option<Any>,
+}
+
+impl Fixture {
+    /// Loads all fixtures stored in a given directory, ordered by their names.
+    pub fn load_dir<P: AsRef<Path>>(dir: P) -> Vec<Fixture> {
+        let mut fixtures: BTreeMap<String, Fixture> = BTreeMap::new();
+        let entries = std::fs::read_dir(dir.as_ref())
+            .unwrap_or_else(|e| panic!("failed to read {}: {}", dir.as_ref().display(), e));
+        for entry in entries {
+            let path: PathBuf = entry.unwrap().path();
+            let file_name = path.file_name().unwrap().to_str().unwrap();
+            let (name, kind) = match file_name.split_once('.') {
+                Some(split) => split,
+                None => continue,
+            };
+            let fixture = fixtures.entry(name.to_string()).or_insert_with(|| Fixture {
+                name: name.to_string(),
+                ..Fixture::default()
+            });
+            let read = || std::fs::read(&path).unwrap();
+            match kind {
+                "update.v1.bin" => fixture.update_v1 = Some(read()),
+                "update.v2.bin" => fixture.update_v2 = Some(read()),
+                "sv.bin" => fixture.state_vector = Some(read()),
+                "snapshot.bin" => fixture.snapshot = Some(read()),
+                "json" => {
+                    let json = String::from_utf8(read()).unwrap();
+                    fixture.expected = Some(Any::from_json(&json).unwrap());
+                }
+                _ => { /* ignore unknown files ie. README */ }
+            }
+        }
+        fixtures.into_values().collect()
+    }
+
+    /// Checks all binary payloads of this fixture: each of them must decode and then encode back
+    /// into the exact same bytes. Updates are also applied onto a document and checked against
+    /// expected content.
+    pub fn verify(&self) {
+        if let Some(payload) = &self.update_v1 {
+            let update = Update::decode_v1(payload)
+                .unwrap_or_else(|e| panic!("{}: failed to decode V1 update: {}", self.name, e));
+            assert_eq!(&update.encode_v1(), payload, "{}: V1 update", self.name);
+            self.assert_content(Update::decode_v1(payload).unwrap());
+        }
+        if let Some(payload) = &self.update_v2 {
+            let update = Update::decode_v2(payload)
+                .unwrap_or_else(|e| panic!("{}: failed to decode V2 update: {}", self.name, e));
+            assert_eq!(&update.encode_v2(), payload, "{}: V2 update", self.name);
+            self.assert_content(Update::decode_v2(payload).unwrap());
+        }
+        if let Some(payload) = &self.state_vector {
+            let sv = StateVector::decode_v1(payload)
+                .unwrap_or_else(|e| panic!("{}: failed to decode state vector: {}", self.name, e));
+            assert_eq!(&sv.encode_v1(), payload, "{}: state vector", self.name);
+        }
+        if let Some(payload) = &self.snapshot {
+            let snapshot = Snapshot::decode_v1(payload)
+                .unwrap_or_else(|e| panic!("{}: failed to decode snapshot: {}", self.name, e));
+            assert_eq!(&snapshot.encode_v1(), payload, "{}: snapshot", self.name);
+        }
+    }
+
+    /// Applies an `update` onto a new document and compares its contents against expected JSON.
+    /// Expected JSON groups root types by their kind: `{"text": {"name": "content"}, ...}`, with
+    /// supported kinds being `text`, `array`, `map` and `xml`.
+    pub fn assert_content(&self, update: Update) {
+        let expected = match &self.expected {
+            Some(Any::Map(expected)) => expected,
+            Some(other) => panic!("{}: expected JSON object, got {}", self.name, other),
+            None => return,
+        };
+        let expected_sv = update.state_vector();
+        let doc = Doc::new();
+        let mut txn = doc.transact_mut();
+        txn.apply_update(update);
+        for (kind, roots) in expected.iter() {
+            let roots = match roots {
+                Any::Map(roots) => roots,
+                other => panic!(
+                    "{}: expected object of {} roots, got {}",
+                    self.name, kind, other
+                ),
+            };
+            for (name, value) in roots.iter() {
+                let actual = match kind.as_str() {
+                    "text" => Any::from(txn.get_or_insert_text(name.as_str()).get_string(&txn)),
+                    "array" => txn.get_or_insert_array(name.as_str()).to_json(&txn),
+                    "map" => txn.get_or_insert_map(name.as_str()).to_json(&txn),
+                    "xml" => Any::from(
+                        txn.get_or_insert_xml_fragment(name.as_str())
+                            .get_string(&txn),
+                    ),
+                    other => panic!("{}: unsupported root type kind: {}", self.name, other),
+                };
+                assert_eq!(&actual, value, "{}: {} '{}'", self.name, kind, name);
+            }
+        }
+        assert_eq!(
+            txn.state_vector(),
+            expected_sv,
+            "{}: state vector after applying an update",
+            self.name
+        );
+    }
+}
+
+#[test]
+fn yjs_golden_fixtures() {
+    let fixtures = Fixture::load_dir(FIXTURES_DIR);
+    assert!(
+        !fixtures.is_empty(),
+        "no fixtures found in {}",
+        FIXTURES_DIR
+    );
+    for fixture in fixtures.iter() {
+        fixture.verify();
+    }
+}
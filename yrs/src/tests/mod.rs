@@ -1,3 +1,4 @@
 mod compatibility_tests;
 mod edit_traces;
 mod edit_traces_tests;
+mod golden;
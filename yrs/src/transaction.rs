@@ -1,4 +1,6 @@
+use crate::access::PendingAccess;
 use crate::block::{Item, ItemContent, ItemPtr, Prelim, ID};
+use crate::block_store::Pivot;
 use crate::branch::{Branch, BranchPtr};
 use crate::doc::DocAddr;
 use crate::error::Error;
@@ -6,10 +8,11 @@ use crate::event::SubdocsEvent;
 use crate::gc::GCCollector;
 use crate::id_set::DeleteSet;
 use crate::iter::TxnIterator;
+use crate::metrics::{Counter, Gauge};
 use crate::slice::BlockSlice;
 use crate::store::{Store, StoreEvents, SubdocGuids, SubdocsIter};
 use crate::types::{Event, Events, RootRef, SharedRef, TypePtr};
-use crate::update::Update;
+use crate::update::{RecoveryReport, Update, UpdateError};
 use crate::utils::OptionExt;
 use crate::*;
 use atomic_refcell::{AtomicRef, AtomicRefMut};
@@ -20,6 +23,7 @@ use std::hash::Hash;
 use std::ops::{Deref, DerefMut};
 use std::pin::Pin;
 use std::sync::Arc;
+use updates::decoder::Decode;
 use updates::encoder::*;
 
 /// Trait defining read capabilities present in a transaction. Implemented by both lightweight
@@ -61,13 +65,17 @@ pub trait ReadTxn: Sized {
     fn encode_diff_v1(&self, state_vector: &StateVector) -> Vec<u8> {
         let mut encoder = EncoderV1::new();
         self.encode_diff(state_vector, &mut encoder);
-        encoder.to_vec()
+        let bin = encoder.to_vec();
+        self.store().report_encoded(&bin);
+        bin
     }
 
     fn encode_diff_v2(&self, state_vector: &StateVector) -> Vec<u8> {
         let mut encoder = EncoderV2::new();
         self.encode_diff(state_vector, &mut encoder);
-        encoder.to_vec()
+        let bin = encoder.to_vec();
+        self.store().report_encoded(&bin);
+        bin
     }
 
     fn encode_state_as_update<E: Encoder>(&self, sv: &StateVector, encoder: &mut E) {
@@ -81,7 +89,9 @@ pub trait ReadTxn: Sized {
         let mut encoder = EncoderV1::new();
         self.encode_state_as_update(sv, &mut encoder);
         // check for pending data
-        merge_pending_v1(encoder.to_vec(), self.store())
+        let bin = merge_pending_v1(encoder.to_vec(), self.store());
+        self.store().report_encoded(&bin);
+        bin
     }
 
     fn encode_state_as_update_v2(&self, sv: &StateVector) -> Vec<u8> {
@@ -89,7 +99,9 @@ pub trait ReadTxn: Sized {
         self.encode_state_as_update(sv, &mut encoder);
 
         // check for pending data
-        merge_pending_v2(encoder.to_vec(), self.store())
+        let bin = merge_pending_v2(encoder.to_vec(), self.store());
+        self.store().report_encoded(&bin);
+        bin
     }
 
     /// Check if given node is alive. Returns false if node has been deleted.
@@ -280,18 +292,87 @@ fn merge_pending_v2(update: Vec<u8>, store: &Store) -> Vec<u8> {
     }
 }
 
+/// Tracking of the source code locations, at which currently alive transactions have been
+/// started. It's only present in debug builds and used to provide better diagnostics when user
+/// code tries to start a transaction while another one is still active. Locations are stored
+/// per document in [Store::active_txns].
+#[cfg(debug_assertions)]
+mod active {
+    use crate::store::Store;
+    use std::panic::Location;
+
+    /// Registers a transaction over a store for as long as it's alive. It must be dropped before
+    /// the store borrow of its transaction is released.
+    #[derive(Debug)]
+    pub(crate) struct ActiveTxn {
+        store: *const Store,
+        location: &'static Location<'static>,
+    }
+
+    // ActiveTxn only touches a store through its mutex-guarded `active_txns` list
+    unsafe impl Send for ActiveTxn {}
+    unsafe impl Sync for ActiveTxn {}
+
+    impl ActiveTxn {
+        #[track_caller]
+        pub(crate) fn new(store: &Store) -> Self {
+            let location = Location::caller();
+            if let Ok(mut active) = store.active_txns.lock() {
+                active.push(location);
+            }
+            ActiveTxn { store, location }
+        }
+
+        /// Returns location of the most recently started transaction, which is still alive over
+        /// a given `store`.
+        pub(crate) fn location(store: *const Store) -> Option<&'static Location<'static>> {
+            // `active_txns` is guarded by its own mutex, so it can be read while the store is
+            // borrowed by another transaction
+            let active = unsafe { &(*store).active_txns };
+            active.lock().ok()?.last().copied()
+        }
+    }
+
+    impl Drop for ActiveTxn {
+        fn drop(&mut self) {
+            // store is still borrowed by a transaction owning this guard
+            let active = unsafe { &(*self.store).active_txns };
+            if let Ok(mut active) = active.lock() {
+                let location = self.location as *const Location;
+                if let Some(i) = active
+                    .iter()
+                    .rposition(|l| std::ptr::eq(*l as *const Location, location))
+                {
+                    active.remove(i);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(debug_assertions)]
+pub(crate) use active::ActiveTxn;
+
 /// A very lightweight read-only transaction. These transactions are guaranteed to not modify the
 /// contents of an underlying [Doc] and can be used to read it or for serialization purposes.
 /// For this reason it's allowed to have a multiple active read-only transactions, but it's
 /// not allowed to have any active [read-write transactions](TransactionMut) at the same time.
 #[derive(Debug)]
 pub struct Transaction<'doc> {
+    // declared first, so that it's dropped before the store borrow is released
+    #[cfg(debug_assertions)]
+    _active: ActiveTxn,
     store: AtomicRef<'doc, Store>,
 }
 
 impl<'doc> Transaction<'doc> {
+    #[track_caller]
     pub(crate) fn new(store: AtomicRef<'doc, Store>) -> Self {
-        Transaction { store }
+        Transaction {
+            #[cfg(debug_assertions)]
+            _active: ActiveTxn::new(store.deref()),
+            store,
+        }
     }
 }
 
@@ -315,12 +396,17 @@ impl<'doc> ReadTxn for Transaction<'doc> {
 /// In Yrs transactions are always auto-committing all of their changes when dropped. Rollbacks are
 /// not supported (if some operations needs to be undone, this can be achieved using [UndoManager])
 pub struct TransactionMut<'doc> {
+    // declared first, so that it's dropped before the store borrow is released
+    #[cfg(debug_assertions)]
+    _active: ActiveTxn,
     pub(crate) store: AtomicRefMut<'doc, Store>,
     /// State vector of a current transaction at the moment of its creation.
     pub(crate) before_state: StateVector,
     /// Current state vector of a transaction, which includes all performed updates.
     pub(crate) after_state: StateVector,
-    /// ID's of the blocks to be merged.
+    /// ID's of the blocks to be merged. Every block split within this transaction must push
+    /// the ID of its right half here, so that on commit it can be merged back with both of its
+    /// neighbours once they are compatible again.
     pub(crate) merge_blocks: Vec<ID>,
     /// Describes the set of deleted items by ids.
     pub(crate) delete_set: DeleteSet,
@@ -362,9 +448,14 @@ impl<'doc> Drop for TransactionMut<'doc> {
 }
 
 impl<'doc> TransactionMut<'doc> {
+    #[track_caller]
     pub(crate) fn new(doc: Doc, store: AtomicRefMut<'doc, Store>, origin: Option<Origin>) -> Self {
+        #[cfg(feature = "tracing")]
+        tracing::trace!(client_id = store.options.client_id, "transaction started");
         let begin_timestamp = store.blocks.get_state_vector();
         TransactionMut {
+            #[cfg(debug_assertions)]
+            _active: ActiveTxn::new(store.deref()),
             store,
             doc,
             origin,
@@ -436,7 +527,9 @@ impl<'doc> TransactionMut<'doc> {
     pub fn encode_update_v1(&self) -> Vec<u8> {
         let mut encoder = updates::encoder::EncoderV1::new();
         self.encode_update(&mut encoder);
-        encoder.to_vec()
+        let bin = encoder.to_vec();
+        self.store.report_encoded(&bin);
+        bin
     }
 
     /// Encodes changes made within the scope of the current transaction using lib0 v2 encoding.
@@ -450,7 +543,9 @@ impl<'doc> TransactionMut<'doc> {
     pub fn encode_update_v2(&self) -> Vec<u8> {
         let mut encoder = updates::encoder::EncoderV2::new();
         self.encode_update(&mut encoder);
-        encoder.to_vec()
+        let bin = encoder.to_vec();
+        self.store.report_encoded(&bin);
+        bin
     }
 
     /// Encodes changes made within the scope of the current transaction.
@@ -470,6 +565,8 @@ impl<'doc> TransactionMut<'doc> {
     /// Applies given `id_set` onto current transaction to run multi-range deletion.
     /// Returns a remaining of original ID set, that couldn't be applied.
     pub(crate) fn apply_delete(&mut self, ds: &DeleteSet) -> Option<DeleteSet> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("apply_delete", clients = ds.iter().count()).entered();
         let mut unapplied = DeleteSet::new();
         for (client, ranges) in ds.iter() {
             if let Some(mut blocks) = self.store.blocks.get_client_mut(client) {
@@ -501,14 +598,13 @@ impl<'doc> TransactionMut<'doc> {
                                             }
                                         }
 
-                                        index += 1;
+                                        index = index.next();
                                         self.merge_blocks.push(*split.id());
                                     }
                                     blocks = self.store.blocks.get_client_mut(client).unwrap();
                                 }
 
-                                while index < blocks.len() {
-                                    let block = &mut blocks[index];
+                                while let Some(block) = blocks.get_mut(index) {
                                     if let Some(item) = block.as_item() {
                                         if item.id.clock < clock_end {
                                             if !item.is_deleted() {
@@ -541,7 +637,7 @@ impl<'doc> TransactionMut<'doc> {
                                                         }
 
                                                         self.merge_blocks.push(*split.id());
-                                                        index += 1;
+                                                        index = index.next();
                                                     }
                                                 }
                                                 self.delete(item);
@@ -556,7 +652,7 @@ impl<'doc> TransactionMut<'doc> {
                                             break;
                                         }
                                     }
-                                    index += 1;
+                                    index = index.next();
                                 }
                             }
                         }
@@ -567,6 +663,8 @@ impl<'doc> TransactionMut<'doc> {
             }
         }
 
+        #[cfg(feature = "tracing")]
+        tracing::trace!(unapplied = unapplied.iter().count(), "delete set applied");
         if unapplied.is_empty() {
             None
         } else {
@@ -588,6 +686,10 @@ impl<'doc> TransactionMut<'doc> {
                     parent.block_len -= item.len();
                     parent.content_len -= item.content_len(store.options.offset_kind);
                 }
+            } else if item.parent_sub.is_some() {
+                if let TypePtr::Branch(mut parent) = item.parent {
+                    parent.map_len -= 1;
+                }
             }
 
             item.mark_as_deleted();
@@ -672,7 +774,80 @@ impl<'doc> TransactionMut<'doc> {
     /// Remote update integration requires that all to-be-integrated blocks must have their direct
     /// predecessors already in place. Out of order updates from the same peer will be stashed
     /// internally and their integration will be postponed until missing blocks arrive first.
-    pub fn apply_update(&mut self, update: Update) {
+    ///
+    /// # Quotas
+    ///
+    /// If a quota hook has been configured with [Doc::set_quota_hook] and it rejects an update,
+    /// the whole update is dropped. Use [TransactionMut::apply_update_strict] to be notified
+    /// about such case.
+    ///
+    /// # Access control
+    ///
+    /// If an access policy has been configured with [Doc::set_access_policy], changes it denies
+    /// are stripped from an update before it's integrated.
+    pub fn apply_update(&mut self, mut update: Update) {
+        let (_, pending) = self.check_access(&mut update);
+        if let Err(_e) = self.check_quota(&update) {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(error = %_e, "update rejected");
+            return;
+        }
+        self.apply_accepted_update(update, pending);
+    }
+
+    /// Strips changes of an `update`, which are denied by an access policy configured with
+    /// [Doc::set_access_policy]. Returns an ID of the first denied change, if any, and changes
+    /// which scope couldn't be determined yet, because they depend on blocks not delivered yet.
+    fn check_access(&self, update: &mut Update) -> (Option<ID>, Option<PendingAccess>) {
+        let policy = match self.store.access.as_ref() {
+            Some(policy) => policy,
+            None => return (None, None),
+        };
+        let origin = self.origin.as_ref();
+        let (denied, pending) =
+            crate::access::strip_denied(&self.store, origin, policy, update, None);
+        #[cfg(feature = "tracing")]
+        if let Some(id) = denied {
+            tracing::warn!(%id, "update changes denied by access policy");
+        }
+        (denied, (!pending.is_empty()).then_some(pending))
+    }
+
+    /// Checks if an `update` is accepted by a quota hook configured with [Doc::set_quota_hook].
+    fn check_quota(&self, update: &Update) -> Result<(), UpdateError> {
+        if let Some(hook) = self.store.quota.as_ref() {
+            let delta = update.size_delta(&self.store);
+            if delta.blocks > 0 && !hook(&delta) {
+                return Err(UpdateError::QuotaExceeded(delta));
+            }
+        }
+        Ok(())
+    }
+
+    fn apply_accepted_update(&mut self, update: Update, pending: Option<PendingAccess>) {
+        if let Some(pending) = pending {
+            self.store_mut().access_pending.push(pending);
+        }
+        self.integrate_update(update);
+        if let Some(metrics) = self.store.metrics.as_ref() {
+            metrics.counter(Counter::UpdatesApplied, 1);
+            let pending = self
+                .store
+                .pending
+                .as_ref()
+                .map_or(0, |p| p.update.blocks.len());
+            metrics.gauge(Gauge::PendingBlocks, pending as u64);
+        }
+    }
+
+    fn integrate_update(&mut self, update: Update) {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!(
+            "apply_update",
+            clients = update.blocks.clients_len(),
+            blocks = update.blocks.len(),
+        )
+        .entered();
         let (remaining, remaining_ds) = update.integrate(self);
         let mut retry = false;
         {
@@ -699,7 +874,10 @@ impl<'doc> TransactionMut<'doc> {
             };
         }
         if let Some(pending) = self.store_mut().pending_ds.take() {
-            let ds2 = self.apply_delete(&pending);
+            let mut pending_update = Update::new();
+            pending_update.delete_set = pending;
+            crate::access::recheck_pending(self.store_mut(), &mut pending_update);
+            let ds2 = self.apply_delete(&pending_update.delete_set);
             let ds = match (remaining_ds, ds2) {
                 (Some(mut a), Some(b)) => {
                     a.delete_set.merge(b);
@@ -714,18 +892,210 @@ impl<'doc> TransactionMut<'doc> {
             self.store_mut().pending_ds = remaining_ds.map(|update| update.delete_set);
         }
 
+        #[cfg(feature = "tracing")]
+        {
+            let store = self.store();
+            tracing::debug!(
+                pending = store.pending.is_some(),
+                pending_clients = store.pending.as_ref().map_or(0, |p| p.missing.len()),
+                pending_ds = store.pending_ds.is_some(),
+                retry,
+                "update applied"
+            );
+        }
+
         if retry {
             let store = self.store_mut();
             if let Some(pending) = store.pending.take() {
                 let ds = store.pending_ds.take().unwrap_or_default();
                 let mut ds_update = Update::new();
                 ds_update.delete_set = ds;
-                self.apply_update(pending.update);
-                self.apply_update(ds_update)
+                let mut update = pending.update;
+                crate::access::recheck_pending(store, &mut update);
+                crate::access::recheck_pending(store, &mut ds_update);
+                self.integrate_update(update);
+                self.integrate_update(ds_update)
             }
         }
     }
 
+    /// Applies a deserialized [Update] contents into a document owning current transaction, just
+    /// like [TransactionMut::apply_update] does. However if an update depends on blocks, that are
+    /// not known to the current document yet, it's rejected right away instead of being stashed
+    /// as pending update waiting for the missing blocks to arrive.
+    ///
+    /// # Errors
+    ///
+    /// Returns [UpdateError::MissingBlocks] containing exact ID ranges, that need to be integrated
+    /// before given `update` can be applied. It can be used to request retransmission of these
+    /// ranges from a remote peer. Returns [UpdateError::QuotaExceeded] if update has been rejected
+    /// by a quota hook configured with [Doc::set_quota_hook] or [UpdateError::AccessDenied] if it
+    /// contains changes denied by an access policy configured with [Doc::set_access_policy].
+    /// In such cases a document state is left unchanged.
+    pub fn try_apply_update(&mut self, mut update: Update) -> Result<(), UpdateError> {
+        let missing = update.missing_ranges(&self.store.blocks.get_state_vector());
+        if !missing.is_empty() {
+            return Err(UpdateError::MissingBlocks(missing));
+        }
+        let (denied, pending) = self.check_access(&mut update);
+        if let Some(id) = denied {
+            return Err(UpdateError::AccessDenied(id));
+        }
+        self.check_quota(&update)?;
+        self.apply_accepted_update(update, pending);
+        Ok(())
+    }
+
+    /// Applies a deserialized [Update] contents into a document owning current transaction, just
+    /// like [TransactionMut::apply_update] does, but in a strict mode: if any block of an update
+    /// refers to other blocks, which can never be satisfied (ie. a client that is neither known to
+    /// the document nor pending, or a same-client block that cannot precede it), the update is
+    /// rejected instead of silently misplacing its content. Blocks waiting for missing, but
+    /// deliverable dependencies are still stashed as pending.
+    ///
+    /// # Errors
+    ///
+    /// Returns [UpdateError::DanglingReference] pointing to the first offending block or
+    /// [UpdateError::QuotaExceeded] if update has been rejected by a quota hook configured with
+    /// [Doc::set_quota_hook] or [UpdateError::AccessDenied] if it contains changes denied by an
+    /// access policy configured with [Doc::set_access_policy]. In such cases a document state is
+    /// left unchanged.
+    pub fn apply_update_strict(&mut self, mut update: Update) -> Result<(), UpdateError> {
+        if let Some((block, reference)) = update.dangling_reference(&self.store) {
+            return Err(UpdateError::DanglingReference { block, reference });
+        }
+        let (denied, pending) = self.check_access(&mut update);
+        if let Some(id) = denied {
+            return Err(UpdateError::AccessDenied(id));
+        }
+        self.check_quota(&update)?;
+        self.apply_accepted_update(update, pending);
+        Ok(())
+    }
+
+    /// Applies a stream of updates encoded using lib0 v1 encoding in a lenient way, meant for
+    /// recovery of corrupted documents. See [TransactionMut::apply_updates_lenient].
+    pub fn apply_updates_lenient_v1<I, B>(&mut self, updates: I) -> RecoveryReport
+    where
+        I: IntoIterator<Item = B>,
+        B: AsRef<[u8]>,
+    {
+        self.apply_updates_lenient(updates, Update::decode_v1)
+    }
+
+    /// Applies a stream of updates encoded using lib0 v2 encoding in a lenient way, meant for
+    /// recovery of corrupted documents. See [TransactionMut::apply_updates_lenient].
+    pub fn apply_updates_lenient_v2<I, B>(&mut self, updates: I) -> RecoveryReport
+    where
+        I: IntoIterator<Item = B>,
+        B: AsRef<[u8]>,
+    {
+        self.apply_updates_lenient(updates, Update::decode_v2)
+    }
+
+    /// Applies a stream of serialized updates (eg. loaded from a persistent storage) onto
+    /// a current document, without failing on corrupted entries:
+    ///
+    /// - Updates which couldn't be decoded are skipped altogether.
+    /// - Updates rejected by a quota hook configured with [Doc::set_quota_hook] are skipped
+    ///   altogether.
+    /// - Changes denied by an access policy configured with [Doc::set_access_policy] are stripped
+    ///   from updates, which are otherwise applied.
+    /// - Blocks and deletions of given `updates` which couldn't be integrated after all of them
+    ///   have been applied, because they depend on missing data, are removed from the pending
+    ///   state. Pending state left by updates applied before is kept, since it still can be
+    ///   completed by the following updates.
+    ///
+    /// Everything that has been skipped is described by returned [RecoveryReport], so that
+    /// a single corrupted segment doesn't make the whole document unreadable.
+    pub fn apply_updates_lenient<I, B, F>(&mut self, updates: I, decode: F) -> RecoveryReport
+    where
+        I: IntoIterator<Item = B>,
+        B: AsRef<[u8]>,
+        F: Fn(&[u8]) -> Result<Update, crate::encoding::read::Error>,
+    {
+        let mut report = RecoveryReport::default();
+        let (prior_blocks, prior_deletes) = self.pending_ids();
+        for (i, bin) in updates.into_iter().enumerate() {
+            match decode(bin.as_ref()) {
+                Ok(mut update) => {
+                    let (denied, pending) = self.check_access(&mut update);
+                    match self.check_quota(&update) {
+                        Ok(()) => {
+                            if let Some(id) = denied {
+                                report.denied.push((i, id));
+                            }
+                            self.apply_accepted_update(update, pending)
+                        }
+                        Err(e) => {
+                            #[cfg(feature = "tracing")]
+                            tracing::warn!(index = i, error = %e, "skipped rejected update");
+                            report.rejected.push((i, e));
+                        }
+                    }
+                }
+                Err(e) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(index = i, error = %e, "skipped undecodable update");
+                    report.corrupted.push((i, e));
+                }
+            }
+        }
+        let store = self.store_mut();
+        if let Some(mut pending) = store.pending.take() {
+            let blocks = &mut pending.update.blocks.clients;
+            for client_blocks in blocks.values_mut() {
+                client_blocks.retain(|block| {
+                    let id = *block.id();
+                    if block.is_skip() || prior_blocks.contains(&id) {
+                        return true;
+                    }
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(%id, len = block.len(), "skipped unintegrated block");
+                    report.skipped_blocks.insert(id, block.len());
+                    false
+                });
+            }
+            blocks.retain(|_, client_blocks| client_blocks.iter().any(|b| !b.is_skip()));
+            let skipped = pending.update.delete_set.difference(&prior_deletes);
+            pending.update.delete_set = pending.update.delete_set.difference(&skipped);
+            report.skipped_deletes.merge(skipped);
+            if !pending.update.is_empty() {
+                store.pending = Some(pending);
+            }
+        }
+        if let Some(ds) = store.pending_ds.take() {
+            let skipped = ds.difference(&prior_deletes);
+            let ds = ds.difference(&skipped);
+            report.skipped_deletes.merge(skipped);
+            if !ds.is_empty() {
+                store.pending_ds = Some(ds);
+            }
+        }
+        report.skipped_blocks.squash();
+        report.skipped_deletes.squash();
+        report
+    }
+
+    /// Returns IDs of blocks and deletions currently waiting in the pending state.
+    fn pending_ids(&self) -> (IdSet, DeleteSet) {
+        let store = self.store();
+        let mut blocks = IdSet::new();
+        let mut deletes = DeleteSet::new();
+        if let Some(pending) = store.pending.as_ref() {
+            for block in pending.update.blocks.blocks() {
+                if !block.is_skip() {
+                    blocks.insert(*block.id(), block.len());
+                }
+            }
+            deletes.merge(pending.update.delete_set.clone());
+        }
+        if let Some(ds) = store.pending_ds.as_ref() {
+            deletes.merge(ds.clone());
+        }
+        (blocks, deletes)
+    }
+
     pub(crate) fn create_item<T: Prelim>(
         &mut self,
         pos: &block::ItemPosition,
@@ -741,8 +1111,7 @@ impl<'doc> TransactionMut<'doc> {
             } else {
                 None
             };
-            let client_id = store.options.client_id;
-            let id = ID::new(client_id, store.get_local_state());
+            let id = store.next_local_id();
 
             (left, right, origin, id)
         };
@@ -830,6 +1199,9 @@ impl<'doc> TransactionMut<'doc> {
             return;
         }
         self.committed = true;
+        #[cfg(feature = "tracing")]
+        let _span =
+            tracing::debug_span!("commit", client_id = self.store.options.client_id).entered();
 
         // 1. sort and merge delete set
         self.delete_set.squash();
@@ -895,28 +1267,46 @@ impl<'doc> TransactionMut<'doc> {
             let before_clock = self.before_state.get(client);
             if before_clock != clock {
                 let blocks = self.store.blocks.get_client_mut(client).unwrap();
-                let first_change = blocks.find_pivot(before_clock).unwrap().max(1);
-                let mut i = blocks.len() - 1;
+                let first_change = blocks
+                    .find_pivot(before_clock)
+                    .unwrap()
+                    .max(Pivot::FIRST.next());
+                let mut i = blocks.last_pivot().unwrap();
                 while i >= first_change {
                     blocks.squash_left(i);
-                    i -= 1;
+                    i = i.prev().unwrap_or_default();
                 }
             }
         }
 
-        // 7. get merge_structs and try to merge to left
-        for id in self.merge_blocks.iter() {
+        // 7. get merge_structs and try to merge to left: both the block that has been split and
+        // its right neighbour are candidates for merging
+        for id in self.merge_blocks.iter().rev() {
             if let Some(blocks) = self.store.blocks.get_client_mut(&id.client) {
                 if let Some(replaced_pos) = blocks.find_pivot(id.clock) {
-                    if replaced_pos + 1 < blocks.len() {
-                        blocks.squash_left(replaced_pos + 1);
-                    } else if replaced_pos > 0 {
+                    if blocks.get(replaced_pos.next()).is_some() {
+                        blocks.squash_left(replaced_pos.next());
+                    }
+                    if replaced_pos > Pivot::FIRST && blocks.get(replaced_pos).is_some() {
                         blocks.squash_left(replaced_pos);
                     }
                 }
             }
         }
 
+        let changed = self.before_state != self.after_state || !self.delete_set.is_empty();
+        if let (Some(metrics), true) = (self.store.metrics.as_ref(), changed) {
+            let blocks: usize = self.store.blocks.iter().map(|(_, list)| list.len()).sum();
+            metrics.gauge(Gauge::Blocks, blocks as u64);
+        }
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            changed_types = self.changed.len(),
+            merged_blocks = self.merge_blocks.len(),
+            "transaction committed"
+        );
+
         if let Some(events) = self.store.events.as_ref() {
             // 8. emit 'afterTransactionCleanup'
             events.emit_transaction_cleanup(self);
@@ -931,8 +1321,10 @@ impl<'doc> TransactionMut<'doc> {
         if let Some(mut subdocs) = self.subdocs.take() {
             let client_id = store.options.client_id;
             for (guid, subdoc) in subdocs.added.iter_mut() {
-                let mut txn = subdoc.transact_mut();
+                let mut txn = subdoc.transact_mut_unchecked(None);
                 txn.store.options.client_id = client_id;
+                txn.store.options.apply_only = store.options.apply_only;
+                txn.store.options.move_policy = store.options.move_policy;
                 if txn.store.options.collection_id.is_none() {
                     txn.store.options.collection_id = store.options.collection_id.clone();
                 }
@@ -962,6 +1354,10 @@ impl<'doc> TransactionMut<'doc> {
     }
 
     pub(crate) fn add_changed_type(&mut self, parent: BranchPtr, parent_sub: Option<Arc<str>>) {
+        if parent_sub.is_none() && parent.marker.load().is_some() {
+            // sequence component has changed, cached positions are no longer valid
+            parent.marker.store(None);
+        }
         let trigger = if let Some(ptr) = parent.item {
             (ptr.id().clock < self.before_state.get(&ptr.id().client)) && !ptr.is_deleted()
         } else {
@@ -5,8 +5,8 @@ use crate::encoding::serde::from_any;
 use crate::moving::StickyIndex;
 use crate::transaction::TransactionMut;
 use crate::types::{
-    event_change_set, AsPrelim, Branch, BranchPtr, Change, ChangeSet, DefaultPrelim, In, Out, Path,
-    RootRef, SharedRef, ToJson, TypeRef,
+    event_change_set, AsPrelim, Branch, BranchPtr, Change, ChangeSet, DefaultPrelim, Delta, In,
+    IndexOutOfBounds, Out, Path, RootRef, SharedRef, ToJson, TypeRef,
 };
 use crate::{Any, Assoc, DeepObservable, IndexedSequence, Observable, ReadTxn, ID};
 use serde::de::DeserializeOwned;
@@ -16,7 +16,7 @@ use std::collections::HashSet;
 use std::convert::{TryFrom, TryInto};
 use std::iter::FromIterator;
 use std::marker::PhantomData;
-use std::ops::{Deref, DerefMut};
+use std::ops::{Deref, DerefMut, Range};
 
 /// A collection used to store data in an indexed sequence structure. This type is internally
 /// implemented as a double linked list, which may squash values inserted directly one after another
@@ -202,6 +202,21 @@ pub trait Array: AsRef<Branch> + Sized {
         }
     }
 
+    /// Checked variant of [Array::insert]. Instead of panicking, returns an [IndexOutOfBounds]
+    /// error if provided `index` is greater than the current length of an [ArrayRef].
+    fn try_insert<V>(
+        &self,
+        txn: &mut TransactionMut,
+        index: u32,
+        value: V,
+    ) -> Result<V::Return, IndexOutOfBounds>
+    where
+        V: Prelim,
+    {
+        IndexOutOfBounds::check(index, self.len(txn))?;
+        Ok(self.insert(txn, index, value))
+    }
+
     /// Inserts multiple `values` at the given `index`. Inserting at index `0` is equivalent to
     /// prepending current array with given `values`, while inserting at array length is equivalent
     /// to appending that value at the end of it.
@@ -220,6 +235,24 @@ pub trait Array: AsRef<Branch> + Sized {
         }
     }
 
+    /// Checked variant of [Array::insert_range]. Instead of panicking, returns an
+    /// [IndexOutOfBounds] error if provided `index` is greater than the current length of
+    /// an [ArrayRef].
+    fn try_insert_range<T, V>(
+        &self,
+        txn: &mut TransactionMut,
+        index: u32,
+        values: T,
+    ) -> Result<(), IndexOutOfBounds>
+    where
+        T: IntoIterator<Item = V>,
+        V: Into<Any>,
+    {
+        IndexOutOfBounds::check(index, self.len(txn))?;
+        self.insert_range(txn, index, values);
+        Ok(())
+    }
+
     /// Inserts given `value` at the end of the current array.
     ///
     /// Returns a reference to an integrated preliminary input.
@@ -259,11 +292,94 @@ pub trait Array: AsRef<Branch> + Sized {
         }
     }
 
+    /// Checked variant of [Array::remove_range]. Instead of panicking, returns an
+    /// [IndexOutOfBounds] error if the `index`..`index + len` range doesn't fit within
+    /// a current array. In such case no elements are removed.
+    fn try_remove_range(
+        &self,
+        txn: &mut TransactionMut,
+        index: u32,
+        len: u32,
+    ) -> Result<(), IndexOutOfBounds> {
+        IndexOutOfBounds::check_range(index, len, self.len(txn))?;
+        self.remove_range(txn, index, len);
+        Ok(())
+    }
+
+    /// Applies a sequence of changes to a current array, ie. one produced by an editor binding
+    /// keeping a list of rows or outline nodes. Each [Delta::Inserted] entry inserts a single
+    /// value, [Delta::Retain] skips over a given number of elements and [Delta::Deleted] removes
+    /// them. Formatting attributes are ignored.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Array, Doc, Transact};
+    /// use yrs::types::Delta;
+    ///
+    /// let doc = Doc::new();
+    /// let array = doc.get_or_insert_array("array");
+    /// let mut txn = doc.transact_mut();
+    /// array.insert_range(&mut txn, 0, [1, 2, 3, 4]);
+    ///
+    /// array.apply_delta(&mut txn, [
+    ///     Delta::Retain(1, None),
+    ///     Delta::Deleted(2),
+    ///     Delta::Inserted("a", None),
+    ///     Delta::Retain(1, None),
+    ///     Delta::Inserted("b", None),
+    /// ]);
+    /// let values: Vec<_> = array.iter(&txn).collect();
+    /// assert_eq!(values, vec![1.into(), "a".into(), 4.into(), "b".into()]);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if retained and deleted ranges exceed the current length of an
+    /// array. In such case no changes are applied.
+    fn apply_delta<D, P>(&self, txn: &mut TransactionMut, delta: D)
+    where
+        D: IntoIterator<Item = Delta<P>>,
+        P: Prelim,
+    {
+        let delta: Vec<_> = delta.into_iter().collect();
+        let span: u32 = delta
+            .iter()
+            .map(|d| match d {
+                Delta::Retain(len, _) | Delta::Deleted(len) => *len,
+                Delta::Inserted(_, _) => 0,
+            })
+            .sum();
+        let len = self.len(txn);
+        if span > len {
+            panic!(
+                "Delta spans over {} elements, while an array has only {}",
+                span, len
+            );
+        }
+        let mut index = 0;
+        for d in delta {
+            match d {
+                Delta::Retain(len, _) => index += len,
+                Delta::Deleted(len) => self.remove_range(txn, index, len),
+                Delta::Inserted(value, _) => {
+                    let len = self.len(txn);
+                    self.insert(txn, index, value);
+                    index += self.len(txn) - len;
+                }
+            }
+        }
+    }
+
     /// Retrieves a value stored at a given `index`. Returns `None` when provided index was out
     /// of the range of a current array.
+    ///
+    /// Position of a visited block is cached until the array is modified, so that a lookup placed
+    /// at or after the previously requested index (ie. when iterating over indexes in ascending
+    /// order) doesn't need to traverse the array from its beginning.
     fn get<T: ReadTxn>(&self, txn: &T, index: u32) -> Option<Out> {
         let mut walker = BlockIter::new(BranchPtr::from(self.as_ref()));
-        if walker.try_forward(txn, index) {
+        if walker.seek(txn, index) {
             walker.read_value(txn)
         } else {
             None
@@ -419,6 +535,36 @@ pub trait Array: AsRef<Branch> + Sized {
     fn iter<'a, T: ReadTxn + 'a>(&self, txn: &'a T) -> ArrayIter<&'a T, T> {
         ArrayIter::from_ref(self.as_ref(), txn)
     }
+
+    /// Returns values stored within a given `range` of indexes. Range bounds are truncated to
+    /// the current array length. Only blocks preceding and covering requested range are visited,
+    /// so it's cheaper than collecting [Array::iter] results when only a single page of a long
+    /// array is needed.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Array, Doc, Transact};
+    ///
+    /// let doc = Doc::new();
+    /// let array = doc.get_or_insert_array("array");
+    /// let mut txn = doc.transact_mut();
+    /// array.insert_range(&mut txn, 0, [1, 2, 3, 4, 5]);
+    ///
+    /// assert_eq!(array.slice(&txn, 1..3), vec![2.into(), 3.into()]);
+    /// assert_eq!(array.slice(&txn, 4..10), vec![5.into()]);
+    /// ```
+    fn slice<T: ReadTxn>(&self, txn: &T, range: Range<u32>) -> Vec<Out> {
+        let mut walker = BlockIter::new(BranchPtr::from(self.as_ref()));
+        let end = range.end.min(self.len(txn));
+        if range.start >= end || !walker.seek(txn, range.start) {
+            return Vec::new();
+        }
+        let mut buf = vec![Out::default(); (end - range.start) as usize];
+        let read = walker.slice(txn, &mut buf);
+        buf.truncate(read as usize);
+        buf
+    }
 }
 
 pub struct ArrayIter<B, T>
@@ -640,15 +786,43 @@ impl ArrayEvent {
 mod test {
     use crate::test_utils::{exchange_updates, run_scenario, RngExt};
     use crate::types::map::MapPrelim;
-    use crate::types::{Change, DeepObservable, Event, Out, Path, PathSegment, ToJson};
+    use crate::types::{
+        Change, DeepObservable, Delta, Event, IndexOutOfBounds, Out, Path, PathSegment, ToJson,
+    };
     use crate::{
-        any, Any, Array, ArrayPrelim, Assoc, Doc, Map, MapRef, Observable, SharedRef, StateVector,
-        Transact, Update, WriteTxn, ID,
+        any, Any, Array, ArrayPrelim, Assoc, Doc, In, Map, MapRef, MovePolicy, Observable, Options,
+        SharedRef, StateVector, Transact, Update, WriteTxn, ID,
     };
     use std::collections::{HashMap, HashSet};
     use std::iter::FromIterator;
     use std::sync::{Arc, Mutex};
 
+    #[test]
+    fn try_insert_out_of_bounds() {
+        let doc = Doc::with_client_id(1);
+        let a = doc.get_or_insert_array("array");
+        let mut txn = doc.transact_mut();
+        a.insert_range(&mut txn, 0, [1, 2, 3]);
+
+        assert_eq!(
+            a.try_insert(&mut txn, 4, "a").err(),
+            Some(IndexOutOfBounds { index: 4, len: 3 })
+        );
+        assert_eq!(
+            a.try_insert_range(&mut txn, 5, ["a", "b"]),
+            Err(IndexOutOfBounds { index: 5, len: 3 })
+        );
+        assert_eq!(
+            a.try_remove_range(&mut txn, 1, 3),
+            Err(IndexOutOfBounds { index: 4, len: 3 })
+        );
+        assert_eq!(a.len(&txn), 3);
+
+        a.try_insert(&mut txn, 3, 4).unwrap();
+        a.try_remove_range(&mut txn, 0, 2).unwrap();
+        assert_eq!(a.to_json(&txn), any!([3, 4]));
+    }
+
     #[test]
     fn push_back() {
         let doc = Doc::with_client_id(1);
@@ -691,6 +865,106 @@ mod test {
         assert_eq!(actual, vec!["a".into(), "b".into(), "c".into()]);
     }
 
+    #[test]
+    fn slice_across_blocks() {
+        let doc = Doc::with_client_id(1);
+        let a = doc.get_or_insert_array("array");
+        let mut txn = doc.transact_mut();
+        a.insert_range(&mut txn, 0, [1, 2, 3]);
+        a.insert_range(&mut txn, 3, [4, 5, 6]);
+        a.remove(&mut txn, 1);
+        a.insert(&mut txn, 3, "x");
+
+        // [1, 3, 4, "x", 5, 6]
+        assert_eq!(a.slice(&txn, 1..4), vec![3.into(), 4.into(), "x".into()]);
+        assert_eq!(a.slice(&txn, 0..100), a.iter(&txn).collect::<Vec<_>>());
+        assert_eq!(a.slice(&txn, 5..6), vec![6.into()]);
+        assert_eq!(a.slice(&txn, 6..10), Vec::<Out>::new());
+        assert_eq!(a.slice(&txn, 3..2), Vec::<Out>::new());
+    }
+
+    #[test]
+    fn get_with_search_marker() {
+        let d1 = Doc::with_client_id(1);
+        let d2 = Doc::with_client_id(2);
+        let a1 = d1.get_or_insert_array("array");
+        let a2 = d2.get_or_insert_array("array");
+        let mut rng = fastrand::Rng::with_seed(2);
+        for i in 0..100 {
+            for (doc, array) in [(&d1, &a1), (&d2, &a2)] {
+                let mut txn = doc.transact_mut();
+                let len = array.len(&txn);
+                match rng.u32(0..10) {
+                    0 if len > 1 => array.remove_range(&mut txn, rng.u32(0..len - 1), 2),
+                    1 if len > 1 => array.move_to(&mut txn, rng.u32(0..len), rng.u32(0..len)),
+                    _ => array.insert_range(&mut txn, rng.u32(0..=len), [i, i]),
+                }
+                let expected: Vec<_> = array.iter(&txn).collect();
+                let actual: Vec<_> = (0..array.len(&txn))
+                    .map(|i| array.get(&txn, i).unwrap())
+                    .collect();
+                assert_eq!(actual, expected);
+                if len > 0 {
+                    assert!(array.as_ref().marker.load().is_some());
+                    let i = rng.u32(0..array.len(&txn));
+                    assert_eq!(array.get(&txn, i).as_ref(), expected.get(i as usize));
+                }
+            }
+            if i % 10 == 0 {
+                exchange_updates(&[&d1, &d2]);
+            }
+        }
+
+        let mut txn = d1.transact_mut();
+        a1.get(&txn, 10);
+        assert!(a1.as_ref().marker.load().is_some());
+        a1.insert(&mut txn, 0, "x");
+        assert!(a1.as_ref().marker.load().is_none());
+    }
+
+    #[test]
+    fn apply_delta_concurrently() {
+        let d1 = Doc::with_client_id(1);
+        let d2 = Doc::with_client_id(2);
+        let a1 = d1.get_or_insert_array("array");
+        let a2 = d2.get_or_insert_array("array");
+        a1.insert_range(&mut d1.transact_mut(), 0, [1, 2, 3]);
+        exchange_updates(&[&d1, &d2]);
+
+        a1.apply_delta(
+            &mut d1.transact_mut(),
+            [
+                Delta::Deleted(1),
+                Delta::Inserted(In::from(MapPrelim::from([("a", 1)])), None),
+                Delta::Retain(2, None),
+                Delta::Inserted(In::from("x"), None),
+            ],
+        );
+        a2.apply_delta(
+            &mut d2.transact_mut(),
+            [
+                Delta::Retain(1, None),
+                Delta::Deleted(1),
+                Delta::Inserted(5, None),
+            ],
+        );
+        exchange_updates(&[&d1, &d2]);
+
+        let expected = any!([{"a": 1}, 5, 3, "x"]);
+        assert_eq!(a1.to_json(&d1.transact()), expected);
+        assert_eq!(a2.to_json(&d2.transact()), expected);
+    }
+
+    #[test]
+    #[should_panic]
+    fn apply_delta_out_of_bounds() {
+        let doc = Doc::new();
+        let array = doc.get_or_insert_array("array");
+        let mut txn = doc.transact_mut();
+        array.insert_range(&mut txn, 0, [1, 2]);
+        array.apply_delta(&mut txn, [Delta::<In>::Retain(1, None), Delta::Deleted(2)]);
+    }
+
     #[test]
     fn basic() {
         let d1 = Doc::with_client_id(1);
@@ -1391,6 +1665,35 @@ mod test {
         )
     }
 
+    #[test]
+    fn concurrent_moves_with_policy() {
+        fn run(policy: MovePolicy) -> Any {
+            let new_doc = |client_id| {
+                Doc::with_options(Options {
+                    move_policy: policy,
+                    ..Options::with_client_id(client_id)
+                })
+            };
+            let d1 = new_doc(1);
+            let d2 = new_doc(2);
+            let a1 = d1.get_or_insert_array("array");
+            let a2 = d2.get_or_insert_array("array");
+            a1.insert_range(&mut d1.transact_mut(), 0, [1, 2, 3, 4]);
+            exchange_updates(&[&d1, &d2]);
+
+            a1.move_to(&mut d1.transact_mut(), 0, 2); // [2, 1, 3, 4]
+            a2.move_to(&mut d2.transact_mut(), 0, 4); // [2, 3, 4, 1]
+            exchange_updates(&[&d1, &d2]);
+
+            let json = a1.to_json(&d1.transact());
+            assert_eq!(a2.to_json(&d2.transact()), json);
+            json
+        }
+
+        assert_eq!(run(MovePolicy::HigherIdWins), vec![2, 3, 4, 1].into());
+        assert_eq!(run(MovePolicy::LowerIdWins), vec![2, 1, 3, 4].into());
+    }
+
     #[test]
     fn move_2() {
         let d1 = Doc::with_client_id(1);
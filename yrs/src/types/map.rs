@@ -150,17 +150,10 @@ impl DefaultPrelim for MapRef {
 }
 
 pub trait Map: AsRef<Branch> + Sized {
-    /// Returns a number of entries stored within current map.
+    /// Returns a number of entries stored within current map. This number is cached, so it
+    /// doesn't require iterating over map entries.
     fn len<T: ReadTxn>(&self, _txn: &T) -> u32 {
-        let mut len = 0;
-        let inner = self.as_ref();
-        for item in inner.map.values() {
-            //TODO: maybe it would be better to just cache len in the map itself?
-            if !item.is_deleted() {
-                len += 1;
-            }
-        }
-        len
+        self.as_ref().map_len
     }
 
     /// Returns an iterator that enables to traverse over all keys of entries stored within
@@ -209,6 +202,36 @@ pub trait Map: AsRef<Branch> + Sized {
         }
     }
 
+    /// Inserts all given `entries` into current map, overriding values of existing keys. Since all
+    /// of them are written within the same transaction, they are encoded together as a single
+    /// update.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Any, Doc, Map, Transact};
+    /// use std::collections::HashMap;
+    ///
+    /// let doc = Doc::new();
+    /// let map = doc.get_or_insert_map("map");
+    /// let state = HashMap::from([("name", Any::from("Alice")), ("age", Any::from(30))]);
+    ///
+    /// let mut txn = doc.transact_mut();
+    /// map.insert_all(&mut txn, state);
+    /// assert_eq!(map.get_str(&txn, "name").as_deref(), Some("Alice"));
+    /// assert_eq!(map.get_i64(&txn, "age"), Some(30));
+    /// ```
+    fn insert_all<I, K, V>(&self, txn: &mut TransactionMut, entries: I)
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<Arc<str>>,
+        V: Prelim,
+    {
+        for (key, value) in entries {
+            self.insert(txn, key, value);
+        }
+    }
+
     /// Tries to update a value stored under a given `key` within current map, if it's different
     /// from the current one. Returns `true` if the value was updated, `false` otherwise.
     ///
@@ -273,6 +296,49 @@ pub trait Map: AsRef<Branch> + Sized {
         self.insert(txn, key, value)
     }
 
+    /// Returns a value stored under a given `key` within current map. If there was no such entry
+    /// or it has been deleted, a value produced by `init` is inserted and returned instead.
+    ///
+    /// Unlike [Map::get_or_init], an existing value is never replaced, even if its type is
+    /// different from the one produced by `init`, and inserted value can carry any initial
+    /// content, ie. a [MapPrelim] with default fields.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, Map, MapPrelim, Out, Transact};
+    ///
+    /// let doc = Doc::new();
+    /// let map = doc.get_or_insert_map("map");
+    /// let mut txn = doc.transact_mut();
+    ///
+    /// let counter = map.get_or_insert_with(&mut txn, "counter", || 0);
+    /// assert_eq!(counter, Out::from(0));
+    /// map.insert(&mut txn, "counter", 1);
+    /// let counter = map.get_or_insert_with(&mut txn, "counter", || 0);
+    /// assert_eq!(counter, Out::from(1));
+    ///
+    /// let settings = map.get_or_insert_with(&mut txn, "settings", || {
+    ///     MapPrelim::from([("theme", "dark")])
+    /// });
+    /// let settings = settings.cast::<yrs::MapRef>().unwrap();
+    /// assert_eq!(settings.get(&txn, "theme"), Some(Out::from("dark")));
+    /// ```
+    fn get_or_insert_with<K, F, V>(&self, txn: &mut TransactionMut, key: K, init: F) -> Out
+    where
+        K: Into<Arc<str>>,
+        F: FnOnce() -> V,
+        V: Prelim,
+    {
+        let key = key.into();
+        if let Some(value) = self.get(txn, &key) {
+            return value;
+        }
+        self.insert(txn, key.clone(), init());
+        self.get(txn, &key)
+            .expect("Defect: inserted entry not found")
+    }
+
     /// Removes a stored within current map under a given `key`. Returns that value or `None` if
     /// no entry with a given `key` was present in current map.
     ///
@@ -305,6 +371,67 @@ pub trait Map: AsRef<Branch> + Sized {
         ptr.get(txn, key)
     }
 
+    /// Returns a string stored under a given `key` within current map. Returns `None` if there's no
+    /// such entry or its value is not a string.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, Map, MapPrelim, Transact};
+    ///
+    /// let doc = Doc::new();
+    /// let map = doc.get_or_insert_map("map");
+    /// let mut txn = doc.transact_mut();
+    /// map.insert(&mut txn, "name", "Alice");
+    /// map.insert(&mut txn, "age", 30);
+    /// map.insert(&mut txn, "address", MapPrelim::from([("city", "London")]));
+    ///
+    /// assert_eq!(map.get_str(&txn, "name").as_deref(), Some("Alice"));
+    /// assert_eq!(map.get_i64(&txn, "age"), Some(30));
+    /// assert_eq!(map.get_str(&txn, "age"), None);
+    /// let address = map.get_map(&txn, "address").unwrap();
+    /// assert_eq!(address.get_str(&txn, "city").as_deref(), Some("London"));
+    /// ```
+    fn get_str<T: ReadTxn>(&self, txn: &T, key: &str) -> Option<Arc<str>> {
+        self.get(txn, key)?.cast().ok()
+    }
+
+    /// Returns a number stored under a given `key` within current map as an integer, truncating
+    /// its fractional part. Returns `None` if there's no such entry or its value is not a number.
+    fn get_i64<T: ReadTxn>(&self, txn: &T, key: &str) -> Option<i64> {
+        self.get(txn, key)?.cast().ok()
+    }
+
+    /// Returns a number stored under a given `key` within current map. Returns `None` if there's
+    /// no such entry or its value is not a number.
+    fn get_f64<T: ReadTxn>(&self, txn: &T, key: &str) -> Option<f64> {
+        self.get(txn, key)?.cast().ok()
+    }
+
+    /// Returns a boolean stored under a given `key` within current map. Returns `None` if there's
+    /// no such entry or its value is not a boolean.
+    fn get_bool<T: ReadTxn>(&self, txn: &T, key: &str) -> Option<bool> {
+        self.get(txn, key)?.cast().ok()
+    }
+
+    /// Returns a nested [MapRef] stored under a given `key` within current map. Returns `None` if
+    /// there's no such entry or its value is not a map.
+    fn get_map<T: ReadTxn>(&self, txn: &T, key: &str) -> Option<MapRef> {
+        self.get(txn, key)?.cast().ok()
+    }
+
+    /// Returns a nested [ArrayRef] stored under a given `key` within current map. Returns `None`
+    /// if there's no such entry or its value is not an array.
+    fn get_array<T: ReadTxn>(&self, txn: &T, key: &str) -> Option<ArrayRef> {
+        self.get(txn, key)?.cast().ok()
+    }
+
+    /// Returns a nested [TextRef] stored under a given `key` within current map. Returns `None` if
+    /// there's no such entry or its value is not a text.
+    fn get_text<T: ReadTxn>(&self, txn: &T, key: &str) -> Option<TextRef> {
+        self.get(txn, key)?.cast().ok()
+    }
+
     /// Returns a value stored under a given `key` within current map, deserializing it into expected
     /// type if found. If value was not found, the `Any::Null` will be substituted and deserialized
     /// instead (i.e. into instance of `Option` type, if so desired).
@@ -385,6 +512,71 @@ pub trait Map: AsRef<Branch> + Sized {
             txn.delete(ptr.clone());
         }
     }
+
+    /// Returns changes made on entries of current map between two document versions described
+    /// by `from` and `to` snapshots. Keys which values didn't change are not included.
+    ///
+    /// Values of overridden and removed entries are only available if a document was configured
+    /// with [Options::skip_gc] set.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, Map, Options, ReadTxn, Transact};
+    /// use yrs::types::EntryChange;
+    ///
+    /// let doc = Doc::with_options(Options { skip_gc: true, ..Options::default() });
+    /// let map = doc.get_or_insert_map("map");
+    /// map.insert(&mut doc.transact_mut(), "a", 1);
+    /// map.insert(&mut doc.transact_mut(), "b", 2);
+    /// let from = doc.transact().snapshot();
+    ///
+    /// map.insert(&mut doc.transact_mut(), "a", 3);
+    /// map.remove(&mut doc.transact_mut(), "b");
+    /// map.insert(&mut doc.transact_mut(), "c", 4);
+    /// let to = doc.transact().snapshot();
+    ///
+    /// let diff = map.diff(&doc.transact(), &from, &to);
+    /// assert_eq!(diff.len(), 3);
+    /// assert_eq!(diff["a"], EntryChange::Updated(1.into(), 3.into()));
+    /// assert_eq!(diff["b"], EntryChange::Removed(2.into()));
+    /// assert_eq!(diff["c"], EntryChange::Inserted(4.into()));
+    /// ```
+    fn diff<T: ReadTxn>(
+        &self,
+        _txn: &T,
+        from: &Snapshot,
+        to: &Snapshot,
+    ) -> HashMap<String, EntryChange> {
+        fn visible_entry(mut current: Option<ItemPtr>, snapshot: &Snapshot) -> Option<ItemPtr> {
+            // older values of the same key are placed on the left side of the latest one
+            while let Some(item) = current {
+                if snapshot.is_visible(&item.id) {
+                    return Some(item);
+                }
+                current = item.left;
+            }
+            None
+        }
+
+        let value = |item: ItemPtr| item.content.get_last().unwrap_or_default();
+        let mut changes = HashMap::new();
+        for (key, &item) in self.as_ref().map.iter() {
+            let change = match (
+                visible_entry(Some(item), from),
+                visible_entry(Some(item), to),
+            ) {
+                (None, Some(new)) => EntryChange::Inserted(value(new)),
+                (Some(old), None) => EntryChange::Removed(value(old)),
+                (Some(old), Some(new)) if old.id != new.id => {
+                    EntryChange::Updated(value(old), value(new))
+                }
+                _ => continue,
+            };
+            changes.insert(key.to_string(), change);
+        }
+        changes
+    }
 }
 
 #[derive(Debug)]
@@ -625,7 +817,7 @@ mod test {
     use crate::updates::encoder::{Encoder, EncoderV1};
     use crate::{
         any, Any, Array, ArrayPrelim, ArrayRef, Doc, GetString, In, Map, MapPrelim, MapRef,
-        Observable, StateVector, Text, TextRef, Transact, Update, WriteTxn, XmlFragment,
+        Observable, Options, StateVector, Text, TextRef, Transact, Update, WriteTxn, XmlFragment,
         XmlFragmentRef, XmlTextPrelim, XmlTextRef,
     };
     use arc_swap::ArcSwapOption;
@@ -760,6 +952,78 @@ mod test {
         assert_eq!(m1.len(&t1), 0);
     }
 
+    #[test]
+    fn map_len_cached_under_concurrent_changes() {
+        let docs: Vec<_> = (1..=3).map(Doc::with_client_id).collect();
+        let maps: Vec<_> = docs.iter().map(|d| d.get_or_insert_map("map")).collect();
+        let mut undo = crate::UndoManager::new(&docs[0], &maps[0]);
+        let mut rng = Rng::with_seed(3);
+        for i in 0..200 {
+            let j = rng.usize(0..docs.len());
+            let map = &maps[j];
+            {
+                let mut txn = docs[j].transact_mut();
+                let key = format!("key{}", rng.u32(0..8));
+                match rng.u32(0..10) {
+                    0 => map.clear(&mut txn),
+                    1..=3 => {
+                        map.remove(&mut txn, &key);
+                    }
+                    4 => {
+                        let nested = map.insert(&mut txn, "nested", MapPrelim::default());
+                        nested.insert(&mut txn, key, i);
+                    }
+                    _ => {
+                        map.insert(&mut txn, key, i);
+                    }
+                }
+            }
+            if j == 0 && rng.bool() {
+                undo.reset();
+                if rng.bool() {
+                    undo.undo().unwrap();
+                } else {
+                    undo.redo().unwrap();
+                }
+            }
+            if rng.u32(0..4) == 0 {
+                exchange_updates(&docs.iter().collect::<Vec<_>>());
+            }
+            for (doc, map) in docs.iter().zip(maps.iter()) {
+                let txn = doc.transact();
+                assert_eq!(map.len(&txn), map.iter(&txn).count() as u32);
+                if let Some(Out::YMap(nested)) = map.get(&txn, "nested") {
+                    assert_eq!(nested.len(&txn), nested.iter(&txn).count() as u32);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn entries_share_interned_keys() {
+        let d1 = Doc::with_client_id(1);
+        let d2 = Doc::with_client_id(2);
+        let m1 = d1.get_or_insert_map("map");
+        let m2 = d2.get_or_insert_map("map");
+        for i in 0..5 {
+            m1.insert(&mut d1.transact_mut(), String::from("key"), i);
+            m2.insert(&mut d2.transact_mut(), "key", i);
+        }
+        exchange_updates(&[&d1, &d2]);
+
+        for map in [&m1, &m2] {
+            let (key, item) = map.as_ref().map.get_key_value("key").unwrap();
+            let mut count = 0;
+            let mut current = Some(*item);
+            while let Some(item) = current {
+                assert!(Arc::ptr_eq(item.parent_sub.as_ref().unwrap(), key));
+                count += 1;
+                current = item.left;
+            }
+            assert!(count > 1); // deleted entries of each client are squashed together
+        }
+    }
+
     #[test]
     fn map_clear() {
         let d1 = Doc::with_client_id(1);
@@ -1191,6 +1455,153 @@ mod test {
         assert_eq!(m.get_string(&txn), "c".to_string());
     }
 
+    #[test]
+    fn get_or_insert_with() {
+        let d1 = Doc::with_client_id(1);
+        let d2 = Doc::with_client_id(2);
+        let m1 = d1.get_or_insert_map("map");
+        let m2 = d2.get_or_insert_map("map");
+
+        m1.insert(&mut d1.transact_mut(), "a", "text");
+        m1.remove(&mut d1.transact_mut(), "a");
+        exchange_updates(&[&d1, &d2]);
+
+        let mut calls = 0;
+        {
+            let mut txn = d2.transact_mut();
+            let init = || {
+                calls += 1;
+                MapPrelim::from([("x", 1)])
+            };
+            // deleted entries are initialized again
+            let nested: MapRef = m2.get_or_insert_with(&mut txn, "a", init).cast().unwrap();
+            nested.insert(&mut txn, "y", 2);
+            // existing entries of different type are never replaced
+            let value = m2.get_or_insert_with(&mut txn, "a", || 3);
+            assert_eq!(value.cast::<MapRef>().unwrap(), nested);
+        }
+        assert_eq!(calls, 1);
+        exchange_updates(&[&d1, &d2]);
+        assert_eq!(m1.to_json(&d1.transact()), any!({"a": {"x": 1, "y": 2}}));
+    }
+
+    #[test]
+    fn typed_getters() {
+        let doc = Doc::with_client_id(1);
+        let map = doc.get_or_insert_map("map");
+        let mut txn = doc.transact_mut();
+        map.insert(&mut txn, "str", "a");
+        map.insert(&mut txn, "int", 1);
+        map.insert(&mut txn, "big", i64::MAX);
+        map.insert(&mut txn, "float", 1.5);
+        map.insert(&mut txn, "bool", true);
+        map.insert(&mut txn, "map", MapPrelim::default());
+        map.insert(&mut txn, "array", ArrayPrelim::default());
+        map.insert(&mut txn, "text", TextPrelim::new("b"));
+
+        assert_eq!(map.get_str(&txn, "str"), Some("a".into()));
+        assert_eq!(map.get_i64(&txn, "int"), Some(1));
+        assert_eq!(map.get_i64(&txn, "big"), Some(i64::MAX));
+        assert_eq!(map.get_f64(&txn, "float"), Some(1.5));
+        assert_eq!(map.get_f64(&txn, "int"), Some(1.0));
+        assert_eq!(map.get_bool(&txn, "bool"), Some(true));
+        assert!(map.get_map(&txn, "map").is_some());
+        assert!(map.get_array(&txn, "array").is_some());
+        assert_eq!(
+            map.get_text(&txn, "text").map(|t| t.get_string(&txn)),
+            Some("b".into())
+        );
+
+        // mismatched types and missing keys
+        assert_eq!(map.get_str(&txn, "int"), None);
+        assert_eq!(map.get_bool(&txn, "str"), None);
+        assert!(map.get_map(&txn, "array").is_none());
+        assert!(map.get_text(&txn, "missing").is_none());
+    }
+
+    #[test]
+    fn diff_between_snapshots() {
+        let options = Options {
+            skip_gc: true,
+            ..Options::default()
+        };
+        let d1 = Doc::with_options(Options {
+            client_id: 1,
+            ..options.clone()
+        });
+        let d2 = Doc::with_options(Options {
+            client_id: 2,
+            ..options
+        });
+        let m1 = d1.get_or_insert_map("map");
+        let m2 = d2.get_or_insert_map("map");
+        {
+            let mut txn = d1.transact_mut();
+            m1.insert(&mut txn, "same", 1);
+            m1.insert(&mut txn, "updated", 1);
+            m1.insert(&mut txn, "removed", 1);
+            m1.insert(&mut txn, "restored", 1);
+        }
+        exchange_updates(&[&d1, &d2]);
+        let from = d1.transact().snapshot();
+
+        // concurrent writes: client 2 wins over client 1
+        m1.insert(&mut d1.transact_mut(), "updated", 2);
+        m2.insert(&mut d2.transact_mut(), "updated", 3);
+        m2.remove(&mut d2.transact_mut(), "removed");
+        m1.insert(&mut d1.transact_mut(), "inserted", 1);
+        m1.insert(&mut d1.transact_mut(), "transient", 1);
+        m1.remove(&mut d1.transact_mut(), "transient");
+        m1.remove(&mut d1.transact_mut(), "restored");
+        m1.insert(&mut d1.transact_mut(), "restored", 1);
+        exchange_updates(&[&d1, &d2]);
+        let to = d2.transact().snapshot();
+        m2.insert(&mut d2.transact_mut(), "same", 2);
+
+        let expected = HashMap::from([
+            (
+                "updated".to_string(),
+                EntryChange::Updated(1.into(), 3.into()),
+            ),
+            ("removed".to_string(), EntryChange::Removed(1.into())),
+            ("inserted".to_string(), EntryChange::Inserted(1.into())),
+            (
+                "restored".to_string(),
+                EntryChange::Updated(1.into(), 1.into()),
+            ),
+        ]);
+        exchange_updates(&[&d1, &d2]);
+        assert_eq!(m1.diff(&d1.transact(), &from, &to), expected);
+        assert_eq!(m2.diff(&d2.transact(), &from, &to), expected);
+        assert!(m1.diff(&d1.transact(), &to, &to).is_empty());
+    }
+
+    #[test]
+    fn insert_all() {
+        let d1 = Doc::with_client_id(1);
+        let m1 = d1.get_or_insert_map("map");
+        m1.insert(&mut d1.transact_mut(), "a", 1);
+
+        let update = {
+            let mut txn = d1.transact_mut();
+            m1.insert_all(
+                &mut txn,
+                [
+                    ("a".to_string(), Any::from(2)),
+                    ("b".to_string(), any!("x")),
+                ],
+            );
+            txn.encode_update_v1()
+        };
+        let update = Update::decode_v1(&update).unwrap();
+        assert_eq!(update.state_vector().get(&1), 3);
+
+        let d2 = Doc::with_client_id(2);
+        let m2 = d2.get_or_insert_map("map");
+        exchange_updates(&[&d1, &d2]);
+        assert_eq!(m2.to_json(&d2.transact()), any!({"a": 2, "b": "x"}));
+    }
+
     #[test]
     fn try_update() {
         let doc = Doc::new();
@@ -361,6 +361,42 @@ pub trait DefaultPrelim {
     fn default_prelim() -> Self::Prelim;
 }
 
+/// Error returned by checked `try_` variants of index-based operations (like
+/// [Text::try_insert] or [Array::try_remove_range]) when provided index or range doesn't fit
+/// within the bounds of a current shared collection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("index {index} is outside of the bounds of a collection of length {len}")]
+pub struct IndexOutOfBounds {
+    /// Index that was requested. For range operations this is the (exclusive) end of the range.
+    pub index: u32,
+    /// Length of a collection at the moment of the operation.
+    pub len: u32,
+}
+
+impl IndexOutOfBounds {
+    /// Checks if an `index` fits within a collection of a given `len`. Index equal to `len`
+    /// is valid, as it points to the end of a collection.
+    pub(crate) fn check(index: u32, len: u32) -> Result<(), Self> {
+        if index > len {
+            Err(IndexOutOfBounds { index, len })
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Checks if a range starting at `index` and spanning over `range_len` elements fits within
+    /// a collection of a given `len`.
+    pub(crate) fn check_range(index: u32, range_len: u32, len: u32) -> Result<(), Self> {
+        match index.checked_add(range_len) {
+            Some(end) if end <= len => Ok(()),
+            end => Err(IndexOutOfBounds {
+                index: end.unwrap_or(u32::MAX),
+                len,
+            }),
+        }
+    }
+}
+
 /// Trait implemented by all Y-types, allowing for observing events which are emitted by
 /// nested types.
 #[cfg(feature = "sync")]
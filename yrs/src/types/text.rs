@@ -1,8 +1,11 @@
-use crate::block::{EmbedPrelim, Item, ItemContent, ItemPosition, ItemPtr, Prelim, Unused};
+use crate::block::{
+    split_str, ClientID, EmbedPrelim, Item, ItemContent, ItemPosition, ItemPtr, Prelim, Unused,
+};
+use crate::lines::LineBreaks;
 use crate::transaction::TransactionMut;
 use crate::types::{
-    AsPrelim, Attrs, Branch, BranchPtr, DefaultPrelim, Delta, Out, Path, RootRef, SharedRef,
-    TypePtr, TypeRef,
+    AsPrelim, Attrs, Branch, BranchPtr, DefaultPrelim, Delta, IndexOutOfBounds, Out, Path, RootRef,
+    SharedRef, TypePtr, TypeRef,
 };
 use crate::utils::OptionExt;
 use crate::*;
@@ -10,7 +13,9 @@ use std::cell::UnsafeCell;
 use std::collections::HashMap;
 use std::convert::{TryFrom, TryInto};
 use std::fmt::Formatter;
-use std::ops::{Deref, DerefMut};
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut, Range};
+use std::sync::Arc;
 
 /// A shared data type used for collaborative text editing. It enables multiple users to add and
 /// remove chunks of text in efficient manner. This type is internally represented as a mutable
@@ -210,8 +215,18 @@ pub trait Text: AsRef<Branch> + Sized {
     /// ```
     ///
     fn insert(&self, txn: &mut TransactionMut, index: u32, chunk: &str) {
+        self.insert_and_get_id(txn, index, chunk);
+    }
+
+    /// Works like [Text::insert], but returns an [ID] of the first inserted character or `None`
+    /// if `chunk` was empty. This ID stays valid even after the inserted block is merged with its
+    /// neighbours or split, so it can be used to anchor annotations or [StickyIndex]es to freshly
+    /// inserted content.
+    ///
+    /// This method will panic if provided `index` is greater than the length of a current text.
+    fn insert_and_get_id(&self, txn: &mut TransactionMut, index: u32, chunk: &str) -> Option<ID> {
         if chunk.is_empty() {
-            return;
+            return None;
         }
         let this = BranchPtr::from(self.as_ref());
         if let Some(mut pos) = find_position(this, txn, index) {
@@ -224,12 +239,25 @@ pub trait Text: AsRef<Branch> + Sized {
                     break;
                 }
             }
-            txn.create_item(&pos, value, None);
+            txn.create_item(&pos, value, None).map(|item| item.id)
         } else {
             panic!("The type or the position doesn't exist!");
         }
     }
 
+    /// Checked variant of [Text::insert]. Instead of panicking, returns an [IndexOutOfBounds]
+    /// error if provided `index` is greater than the length of a current text.
+    fn try_insert(
+        &self,
+        txn: &mut TransactionMut,
+        index: u32,
+        chunk: &str,
+    ) -> Result<(), IndexOutOfBounds> {
+        IndexOutOfBounds::check(index, self.len(txn))?;
+        self.insert(txn, index, chunk);
+        Ok(())
+    }
+
     fn apply_delta<D, P>(&self, txn: &mut TransactionMut, delta: D)
     where
         D: IntoIterator<Item = Delta<P>>,
@@ -279,18 +307,46 @@ pub trait Text: AsRef<Branch> + Sized {
         chunk: &str,
         attributes: Attrs,
     ) {
+        self.insert_with_attributes_and_get_id(txn, index, chunk, attributes);
+    }
+
+    /// Works like [Text::insert_with_attributes], but returns an [ID] of the first inserted
+    /// character or `None` if `chunk` was empty.
+    ///
+    /// This method will panic if provided `index` is greater than the length of a current text.
+    fn insert_with_attributes_and_get_id(
+        &self,
+        txn: &mut TransactionMut,
+        index: u32,
+        chunk: &str,
+        attributes: Attrs,
+    ) -> Option<ID> {
         if chunk.is_empty() {
-            return;
+            return None;
         }
         let this = BranchPtr::from(self.as_ref());
         if let Some(mut pos) = find_position(this, txn, index) {
             let value = block::PrelimString(chunk.into());
-            insert(this, txn, &mut pos, value, attributes);
+            insert(this, txn, &mut pos, value, attributes).map(|item| item.id)
         } else {
             panic!("The type or the position doesn't exist!");
         }
     }
 
+    /// Checked variant of [Text::insert_with_attributes]. Instead of panicking, returns an
+    /// [IndexOutOfBounds] error if provided `index` is greater than the length of a current text.
+    fn try_insert_with_attributes(
+        &self,
+        txn: &mut TransactionMut,
+        index: u32,
+        chunk: &str,
+        attributes: Attrs,
+    ) -> Result<(), IndexOutOfBounds> {
+        IndexOutOfBounds::check(index, self.len(txn))?;
+        self.insert_with_attributes(txn, index, chunk, attributes);
+        Ok(())
+    }
+
     /// Inserts an embed `content` at a given `index`.
     ///
     /// If `index` is `0`, this `content` will be inserted at the beginning of a current text.
@@ -317,6 +373,21 @@ pub trait Text: AsRef<Branch> + Sized {
         }
     }
 
+    /// Checked variant of [Text::insert_embed]. Instead of panicking, returns an
+    /// [IndexOutOfBounds] error if provided `index` is greater than the length of a current text.
+    fn try_insert_embed<V>(
+        &self,
+        txn: &mut TransactionMut,
+        index: u32,
+        content: V,
+    ) -> Result<V::Return, IndexOutOfBounds>
+    where
+        V: Into<EmbedPrelim<V>> + Prelim,
+    {
+        IndexOutOfBounds::check(index, self.len(txn))?;
+        Ok(self.insert_embed(txn, index, content))
+    }
+
     /// Inserts an embed `content` of text at a given `index`.
     /// If `index` is `0`, this `content` will be inserted at the beginning of a current text.
     /// If `index` is equal to current data structure length, this `chunk` will be appended at
@@ -350,9 +421,34 @@ pub trait Text: AsRef<Branch> + Sized {
     }
 
     /// Appends a given `chunk` of text at the end of a current text structure.
+    ///
+    /// Unlike [Text::insert], it doesn't need to compute an index of the insertion point: a block
+    /// appended by the most recent call is remembered, so consecutive appends (ie. to logs or chat
+    /// messages) don't need to traverse the whole text.
     fn push(&self, txn: &mut TransactionMut, chunk: &str) {
-        let idx = self.len(txn);
-        self.insert(txn, idx, chunk)
+        if chunk.is_empty() {
+            return;
+        }
+        let mut this = BranchPtr::from(self.as_ref());
+        let hint = this
+            .tail
+            .and_then(|id| txn.store().blocks.get_item(&id))
+            .filter(|item| item.parent.as_branch() == Some(&this));
+        let mut left = hint.or(this.start);
+        while let Some(right) = left.and_then(|item| item.right) {
+            left = Some(right);
+        }
+        let pos = ItemPosition {
+            parent: this.into(),
+            left,
+            right: None,
+            index: this.content_len,
+            current_attrs: None,
+        };
+        let value = crate::block::PrelimString(chunk.into());
+        if let Some(item) = txn.create_item(&pos, value, None) {
+            this.tail = Some(item.id);
+        }
     }
 
     /// Removes up to a `len` characters from a current text structure, starting at given `index`.
@@ -367,6 +463,90 @@ pub trait Text: AsRef<Branch> + Sized {
         }
     }
 
+    /// Checked variant of [Text::remove_range]. Instead of panicking, returns an
+    /// [IndexOutOfBounds] error if the `index`..`index + len` range doesn't fit within
+    /// a current text. In such case no characters are removed.
+    fn try_remove_range(
+        &self,
+        txn: &mut TransactionMut,
+        index: u32,
+        len: u32,
+    ) -> Result<(), IndexOutOfBounds> {
+        IndexOutOfBounds::check_range(index, len, self.len(txn))?;
+        self.remove_range(txn, index, len);
+        Ok(())
+    }
+
+    /// Replaces a whole content of a current text with a given `content`. Instead of removing
+    /// existing text, a difference between current and new content is computed and applied as
+    /// a minimal set of insertions and deletions, which preserves history of unchanged parts of
+    /// a text (and all [StickyIndex]es pointing to them). Embedded values are always removed.
+    ///
+    /// The cost of computing a difference grows with a number of changed characters, so if more
+    /// than 512 characters had to be inserted or removed, the whole part between a common prefix
+    /// and suffix of both texts is replaced instead.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, GetString, Text, Transact};
+    ///
+    /// let doc = Doc::new();
+    /// let text = doc.get_or_insert_text("text");
+    /// let mut txn = doc.transact_mut();
+    /// text.insert(&mut txn, 0, "hello world");
+    /// text.set_content(&mut txn, "hello, brave world");
+    ///
+    /// assert_eq!(text.get_string(&txn), "hello, brave world");
+    /// ```
+    fn set_content(&self, txn: &mut TransactionMut, content: &str) {
+        let kind = txn.store().options.offset_kind;
+        let mut old = Vec::new();
+        let mut current = self.as_ref().start;
+        while let Some(item) = current.as_deref() {
+            if !item.is_deleted() {
+                match &item.content {
+                    ItemContent::String(s) => old.extend(s.as_str().chars().map(Some)),
+                    _ if item.is_countable() => old.push(None),
+                    _ => {}
+                }
+            }
+            current = item.right;
+        }
+        let new: Vec<char> = content.chars().collect();
+
+        let char_len = |c: char| match kind {
+            OffsetKind::Bytes => c.len_utf8() as u32,
+            OffsetKind::Utf16 => c.len_utf16() as u32,
+        };
+        let mut index = 0;
+        let mut deleted = 0;
+        let mut inserted = String::new();
+        for op in diff_chars(&old, &new) {
+            match op {
+                CharOp::Delete(c) => deleted += c.map(char_len).unwrap_or(1),
+                CharOp::Insert(c) => inserted.push(c),
+                CharOp::Equal(c) => {
+                    if deleted > 0 {
+                        self.remove_range(txn, index, std::mem::take(&mut deleted));
+                    }
+                    if !inserted.is_empty() {
+                        self.insert(txn, index, &inserted);
+                        index += inserted.chars().map(char_len).sum::<u32>();
+                        inserted.clear();
+                    }
+                    index += char_len(c);
+                }
+            }
+        }
+        if deleted > 0 {
+            self.remove_range(txn, index, deleted);
+        }
+        if !inserted.is_empty() {
+            self.insert(txn, index, &inserted);
+        }
+    }
+
     /// Wraps an existing piece of text within a range described by `index`-`len` parameters with
     /// formatting blocks containing provided `attributes` metadata.
     fn format(&self, txn: &mut TransactionMut, index: u32, len: u32, attributes: Attrs) {
@@ -378,6 +558,68 @@ pub trait Text: AsRef<Branch> + Sized {
         }
     }
 
+    /// Checked variant of [Text::format]. Instead of panicking, returns an [IndexOutOfBounds]
+    /// error if the `index`..`index + len` range doesn't fit within a current text.
+    fn try_format(
+        &self,
+        txn: &mut TransactionMut,
+        index: u32,
+        len: u32,
+        attributes: Attrs,
+    ) -> Result<(), IndexOutOfBounds> {
+        IndexOutOfBounds::check_range(index, len, self.len(txn))?;
+        self.format(txn, index, len, attributes);
+        Ok(())
+    }
+
+    /// Removes formatting attributes with given `keys` from a range of text described by
+    /// `index`-`len` parameters. Removal is represented by formatting blocks with `null` values,
+    /// so that it's resolved with concurrent formatting changes the same way as in Yjs.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, Text, Transact};
+    /// use yrs::types::{Attrs, Delta};
+    ///
+    /// let doc = Doc::new();
+    /// let text = doc.get_or_insert_text("text");
+    /// let mut txn = doc.transact_mut();
+    /// let attrs = Attrs::from([("b".into(), true.into()), ("i".into(), true.into())]);
+    /// text.insert_with_attributes(&mut txn, 0, "hello", attrs);
+    /// text.remove_format(&mut txn, 0, 5, &["b"]);
+    ///
+    /// let italic = Attrs::from([("i".into(), true.into())]);
+    /// assert_eq!(text.to_delta(&txn), vec![Delta::Inserted("hello".into(), Some(Box::new(italic)))]);
+    /// ```
+    fn remove_format(&self, txn: &mut TransactionMut, index: u32, len: u32, keys: &[&str]) {
+        let attributes = keys.iter().map(|&key| (key.into(), Any::Null)).collect();
+        self.format(txn, index, len, attributes)
+    }
+
+    /// Removes formatting blocks, which have no effect, ie. because they repeat formatting already
+    /// applied or are overridden by other formatting blocks before any content. Such blocks are
+    /// usually left behind by concurrent formatting changes. Returns a number of removed blocks.
+    fn clean_formatting(&self, txn: &mut TransactionMut) -> u32 {
+        let mut cleanups = 0;
+        let mut attrs = Attrs::new();
+        let mut gap = self.as_ref().start;
+        let mut current = gap;
+        while let Some(item) = current {
+            current = item.right;
+            if !item.is_deleted() && item.is_countable() {
+                let mut next = attrs.clone();
+                cleanups += clean_format_gap(txn, gap, gap, &attrs, &mut next);
+                attrs = next;
+                gap = item.right;
+            }
+        }
+        // formatting blocks after the last piece of content
+        let mut next = attrs.clone();
+        cleanups += clean_format_gap(txn, gap, gap, &attrs, &mut next);
+        cleanups
+    }
+
     /// Returns an ordered sequence of formatted chunks, current [Text] corresponds of. These chunks
     /// may contain inserted pieces of text or more complex elements like embedded binaries of
     /// shared objects. Chunks are organized by type of inserted value and formatting attributes
@@ -427,6 +669,286 @@ pub trait Text: AsRef<Branch> + Sized {
         asm.finish()
     }
 
+    /// Returns a content of this text as a list of [Delta::Inserted] runs, compatible with Quill
+    /// and Yjs delta format. Consecutive chunks sharing the same formatting attributes are merged
+    /// into a single run, while embedded values and shared types are returned as separate runs.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, Text, Transact};
+    /// use yrs::types::{Attrs, Delta};
+    ///
+    /// let doc = Doc::new();
+    /// let text = doc.get_or_insert_text("text");
+    /// let mut txn = doc.transact_mut();
+    /// let bold = Attrs::from([("b".into(), true.into())]);
+    /// text.insert(&mut txn, 0, "hello world");
+    /// text.format(&mut txn, 6, 5, bold.clone());
+    ///
+    /// assert_eq!(text.to_delta(&txn), vec![
+    ///     Delta::Inserted("hello ".into(), None),
+    ///     Delta::Inserted("world".into(), Some(Box::new(bold))),
+    /// ]);
+    /// ```
+    fn to_delta<T: ReadTxn>(&self, txn: &T) -> Vec<Delta> {
+        self.diff(txn, YChange::identity)
+            .into_iter()
+            .map(|diff| Delta::Inserted(diff.insert, diff.attributes))
+            .collect()
+    }
+
+    /// Returns a part of this text within a given `range` without rendering the whole text. Range
+    /// bounds are measured using [OffsetKind] configured for a document and are truncated to
+    /// a text length. Like [GetString::get_string], it skips formatting attributes and embedded
+    /// content.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, Text, Transact};
+    ///
+    /// let doc = Doc::new();
+    /// let text = doc.get_or_insert_text("text");
+    /// let mut txn = doc.transact_mut();
+    /// text.insert(&mut txn, 0, "hello ");
+    /// text.insert(&mut txn, 6, "world");
+    ///
+    /// assert_eq!(text.slice(&txn, 4..8), "o wo");
+    /// assert_eq!(text.chunks(&txn, 4..8).collect::<Vec<_>>(), vec!["o ", "wo"]);
+    /// ```
+    fn slice<T: ReadTxn>(&self, txn: &T, range: Range<u32>) -> String {
+        self.chunks(txn, range).collect()
+    }
+
+    /// Returns an iterator over string chunks of this text within a given `range`. Every chunk
+    /// is borrowed from a single block, so no intermediate strings are allocated. See
+    /// [Text::slice].
+    fn chunks<'a, T: ReadTxn>(&self, txn: &'a T, range: Range<u32>) -> TextChunks<'a, T> {
+        TextChunks {
+            current: self.as_ref().start,
+            skip: range.start,
+            remaining: range.end.saturating_sub(range.start),
+            kind: txn.store().options.offset_kind,
+            _txn: PhantomData,
+        }
+    }
+
+    /// Returns an iterator over uniformly formatted string runs of this text in document order.
+    /// Every run is borrowed from a single block, together with an [ID] of that block and
+    /// formatting attributes applied to it, so that renderers and exporters can stream text
+    /// content without building intermediate strings. Deleted blocks and embedded values are
+    /// skipped - use [Text::diff] to include the latter.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, Text, Transact, ID};
+    /// use yrs::types::Attrs;
+    ///
+    /// let doc = Doc::with_client_id(1);
+    /// let text = doc.get_or_insert_text("text");
+    /// let mut txn = doc.transact_mut();
+    /// let bold = Attrs::from([("b".into(), true.into())]);
+    /// text.insert(&mut txn, 0, "hello ");
+    /// text.insert_with_attributes(&mut txn, 6, "world", bold.clone());
+    ///
+    /// let runs: Vec<_> = text
+    ///     .runs(&txn)
+    ///     .map(|run| (run.chunk, run.attributes.map(|a| (*a).clone()), run.id))
+    ///     .collect();
+    /// assert_eq!(runs, vec![
+    ///     ("hello ", None, ID::new(1, 0)),
+    ///     ("world", Some(bold), ID::new(1, 7)),
+    /// ]);
+    /// ```
+    fn runs<'a, T: ReadTxn>(&self, _txn: &'a T) -> TextRuns<'a, T> {
+        TextRuns {
+            current: self.as_ref().start,
+            attributes: None,
+            _txn: PhantomData,
+        }
+    }
+
+    /// Returns authors of content within a given `range` of this text, as a list of consecutive
+    /// subranges together with an ID of a client who inserted them. Adjacent subranges inserted
+    /// by the same client are merged. Range bounds are measured using [OffsetKind] configured for
+    /// a document and are truncated to a text length.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, ReadTxn, Text, Transact, Update};
+    /// use yrs::updates::decoder::Decode;
+    ///
+    /// let d1 = Doc::with_client_id(1);
+    /// let d2 = Doc::with_client_id(2);
+    /// let text = d1.get_or_insert_text("text");
+    /// text.insert(&mut d1.transact_mut(), 0, "hello world");
+    ///
+    /// let update = d1.transact().encode_state_as_update_v1(&Default::default());
+    /// d2.transact_mut().apply_update(Update::decode_v1(&update).unwrap());
+    /// let text2 = d2.get_or_insert_text("text");
+    /// text2.insert(&mut d2.transact_mut(), 5, ",");
+    ///
+    /// assert_eq!(text2.attribution(&d2.transact(), 0..12), vec![
+    ///     (0..5, 1),
+    ///     (5..6, 2),
+    ///     (6..12, 1),
+    /// ]);
+    /// ```
+    fn attribution<T: ReadTxn>(&self, txn: &T, range: Range<u32>) -> Vec<(Range<u32>, ClientID)> {
+        let kind = txn.store().options.offset_kind;
+        let mut spans: Vec<(Range<u32>, ClientID)> = Vec::new();
+        let mut index = 0;
+        let mut current = self.as_ref().start;
+        while let Some(item) = current {
+            if index >= range.end {
+                break;
+            }
+            current = item.right;
+            if item.is_deleted() || !item.is_countable() {
+                continue;
+            }
+            let end = index + item.content_len(kind);
+            let span = index.max(range.start)..end.min(range.end);
+            index = end;
+            if span.is_empty() {
+                continue;
+            }
+            match spans.last_mut() {
+                Some((last, client)) if *client == item.id.client && last.end == span.start => {
+                    last.end = span.end
+                }
+                _ => spans.push((span, item.id.client)),
+            }
+        }
+        spans
+    }
+
+    /// Returns an index of the first occurrence of a `pattern` within this text, measured using
+    /// [OffsetKind] configured for a document. Text is searched block by block, so matches spanning
+    /// over multiple blocks are found without rendering the whole text. Embedded values are never
+    /// matched. Returns `None` if `pattern` is empty or was not found.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, Text, Transact};
+    ///
+    /// let doc = Doc::new();
+    /// let text = doc.get_or_insert_text("text");
+    /// let mut txn = doc.transact_mut();
+    /// text.insert(&mut txn, 0, "hello world");
+    /// text.insert(&mut txn, 8, "--");
+    ///
+    /// assert_eq!(text.find(&txn, "wo--rld"), Some(6));
+    /// assert_eq!(text.find_all(&txn, "o"), vec![4, 7]);
+    /// assert_eq!(text.find(&txn, "world"), None);
+    /// ```
+    fn find<T: ReadTxn>(&self, txn: &T, pattern: &str) -> Option<u32> {
+        let kind = txn.store().options.offset_kind;
+        search(self.as_ref(), kind, pattern, true).first().copied()
+    }
+
+    /// Returns indexes of all non-overlapping occurrences of a `pattern` within this text in
+    /// ascending order. See [Text::find].
+    fn find_all<T: ReadTxn>(&self, txn: &T, pattern: &str) -> Vec<u32> {
+        let kind = txn.store().options.offset_kind;
+        search(self.as_ref(), kind, pattern, false)
+    }
+
+    /// Returns a number of lines of this text, separated with `\n` characters. Empty text consists
+    /// of a single line.
+    ///
+    /// This method traverses a whole text on every call, which makes it `O(n)`. See
+    /// [LineIndex](crate::lines::LineIndex) for a line index maintained incrementally.
+    fn line_count<T: ReadTxn>(&self, txn: &T) -> u32 {
+        LineBreaks::new(self.as_ref(), txn.store().options.offset_kind).line_count()
+    }
+
+    /// Returns an index of a given `column` within a given `line`, where both are counted from 0
+    /// and columns are measured using [OffsetKind] configured for a document. Returns `None` if
+    /// that line doesn't exist or is shorter than `column`.
+    ///
+    /// Just like [Text::line_count], this method traverses a whole text on every call. Prefer
+    /// [LineIndex](crate::lines::LineIndex) when positions are translated frequently.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, Text, Transact};
+    ///
+    /// let doc = Doc::new();
+    /// let text = doc.get_or_insert_text("text");
+    /// let mut txn = doc.transact_mut();
+    /// text.insert(&mut txn, 0, "first\nsecond");
+    ///
+    /// assert_eq!(text.line_count(&txn), 2);
+    /// assert_eq!(text.index_at(&txn, 1, 3), Some(9));
+    /// assert_eq!(text.position_of(&txn, 9), Some((1, 3)));
+    /// ```
+    fn index_at<T: ReadTxn>(&self, txn: &T, line: u32, column: u32) -> Option<u32> {
+        LineBreaks::new(self.as_ref(), txn.store().options.offset_kind).index_at(line, column)
+    }
+
+    /// Returns a (line, column) position of a given `index`. Returns `None` if `index` is greater
+    /// than length of this text. Like [Text::index_at], it traverses a whole text on every call.
+    fn position_of<T: ReadTxn>(&self, txn: &T, index: u32) -> Option<(u32, u32)> {
+        LineBreaks::new(self.as_ref(), txn.store().options.offset_kind).position_of(index)
+    }
+
+    /// Returns a number of extended grapheme clusters (user-perceived characters) visible in
+    /// a current text. Every embedded value or shared type is counted as a single grapheme.
+    #[cfg(feature = "unicode-segmentation")]
+    fn len_graphemes<T: ReadTxn>(&self, txn: &T) -> u32 {
+        grapheme_lens(self.as_ref(), txn.store().options.offset_kind).len() as u32
+    }
+
+    /// Translates a `grapheme` index into an index measured using [OffsetKind] configured for
+    /// a document, which can be used with other [Text] methods. Returns `None` if `grapheme` is
+    /// greater than [Text::len_graphemes].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, Text, Transact};
+    ///
+    /// let doc = Doc::new();
+    /// let text = doc.get_or_insert_text("text");
+    /// let mut txn = doc.transact_mut();
+    /// text.insert(&mut txn, 0, "ae\u{301}z"); // "aéz" with a combining acute accent
+    ///
+    /// assert_eq!(text.len_graphemes(&txn), 3);
+    /// let index = text.grapheme_offset(&txn, 2).unwrap();
+    /// text.insert(&mut txn, index, "!");
+    /// assert_eq!(text.grapheme_index(&txn, 2), 1); // index within é is rounded down
+    /// ```
+    #[cfg(feature = "unicode-segmentation")]
+    fn grapheme_offset<T: ReadTxn>(&self, txn: &T, grapheme: u32) -> Option<u32> {
+        let lens = grapheme_lens(self.as_ref(), txn.store().options.offset_kind);
+        let prefix = lens.get(..grapheme as usize)?;
+        Some(prefix.iter().sum())
+    }
+
+    /// Translates an `index` measured using [OffsetKind] configured for a document into
+    /// a grapheme index. Indexes pointing within a grapheme cluster are rounded down to the start
+    /// of that cluster, so that cursors never split user-perceived characters.
+    #[cfg(feature = "unicode-segmentation")]
+    fn grapheme_index<T: ReadTxn>(&self, txn: &T, index: u32) -> u32 {
+        let lens = grapheme_lens(self.as_ref(), txn.store().options.offset_kind);
+        let mut end = 0;
+        let mut grapheme = 0;
+        for len in lens {
+            end += len;
+            if end > index {
+                break;
+            }
+            grapheme += 1;
+        }
+        grapheme
+    }
+
     /// Returns the Delta representation of this YText type.
     fn diff_range<D, F>(
         &self,
@@ -449,6 +971,49 @@ pub trait Text: AsRef<Branch> + Sized {
         asm.process(self.as_ref().start, hi, lo, None, None);
         asm.finish()
     }
+
+    /// Returns a content of this text as it was at the time when a given `snapshot` was taken:
+    /// content inserted after a snapshot is treated as absent and content deleted after
+    /// a snapshot as present. Unlike [Text::diff_range], it only requires a read-only transaction.
+    ///
+    /// Deleted content is preserved only by documents created with [Options::skip_gc] enabled,
+    /// otherwise garbage collected content cannot be restored.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, GetString, Options, ReadTxn, Text, Transact};
+    ///
+    /// let doc = Doc::with_options(Options { skip_gc: true, ..Options::default() });
+    /// let text = doc.get_or_insert_text("text");
+    /// text.insert(&mut doc.transact_mut(), 0, "hello world");
+    /// let snapshot = doc.transact().snapshot();
+    ///
+    /// text.remove_range(&mut doc.transact_mut(), 0, 6);
+    /// text.push(&mut doc.transact_mut(), "!");
+    ///
+    /// let txn = doc.transact();
+    /// assert_eq!(text.get_string(&txn), "world!");
+    /// assert_eq!(text.get_string_at(&txn, &snapshot), "hello world");
+    /// ```
+    fn get_string_at<T: ReadTxn>(&self, _txn: &T, snapshot: &Snapshot) -> String {
+        let mut s = String::new();
+        let mut current = self.as_ref().start;
+        while let Some(item) = current {
+            current = item.right;
+            if let ItemContent::String(chunk) = &item.content {
+                // block clocks are measured in UTF-16 code units
+                let mut id = item.id;
+                for c in chunk.as_str().chars() {
+                    if snapshot.is_visible(&id) {
+                        s.push(c);
+                    }
+                    id.clock += c.len_utf16() as u32;
+                }
+            }
+        }
+        s
+    }
 }
 
 impl From<BranchPtr> for TextRef {
@@ -686,6 +1251,203 @@ where
     }
 }
 
+/// Finds occurrences of a `pattern` within a text `branch` using Knuth-Morris-Pratt algorithm,
+/// which consumes text char by char, so that blocks don't need to be concatenated.
+fn search(branch: &Branch, kind: OffsetKind, pattern: &str, first_only: bool) -> Vec<u32> {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let mut matches = Vec::new();
+    if pattern.is_empty() {
+        return matches;
+    }
+    let char_len = |c: char| match kind {
+        OffsetKind::Bytes => c.len_utf8() as u32,
+        OffsetKind::Utf16 => c.len_utf16() as u32,
+    };
+    let pattern_len: u32 = pattern.iter().map(|c| char_len(*c)).sum();
+    let mut fallback = vec![0usize; pattern.len()];
+    let mut matched = 0;
+    for i in 1..pattern.len() {
+        while matched > 0 && pattern[i] != pattern[matched] {
+            matched = fallback[matched - 1];
+        }
+        if pattern[i] == pattern[matched] {
+            matched += 1;
+        }
+        fallback[i] = matched;
+    }
+
+    let mut index = 0;
+    let mut matched = 0;
+    let mut current = branch.start;
+    while let Some(item) = current {
+        current = item.right;
+        if item.is_deleted() {
+            continue;
+        }
+        match &item.content {
+            ItemContent::String(s) => {
+                for c in s.as_str().chars() {
+                    index += char_len(c);
+                    while matched > 0 && c != pattern[matched] {
+                        matched = fallback[matched - 1];
+                    }
+                    if c == pattern[matched] {
+                        matched += 1;
+                    }
+                    if matched == pattern.len() {
+                        matches.push(index - pattern_len);
+                        if first_only {
+                            return matches;
+                        }
+                        matched = 0;
+                    }
+                }
+            }
+            _ if item.is_countable() => {
+                index += item.content_len(kind);
+                matched = 0;
+            }
+            _ => {}
+        }
+    }
+    matches
+}
+
+enum CharOp {
+    Equal(char),
+    /// Removal of a character or an embedded value (`None`).
+    Delete(Option<char>),
+    Insert(char),
+}
+
+/// Maximum number of insertions and deletions [diff_chars] searches for. Myers' algorithm needs
+/// `O(D^2)` memory to recover an edit script, so bigger differences fall back to a single
+/// replacement.
+const MAX_DIFF_EDITS: usize = 512;
+
+/// Computes the shortest edit script turning `old` into `new` using Myers' algorithm. Embedded
+/// values (`None`) never match any character. If the script would be longer than
+/// [MAX_DIFF_EDITS], all characters between a common prefix and suffix are replaced instead.
+fn diff_chars(old: &[Option<char>], new: &[char]) -> Vec<CharOp> {
+    let eq = |o: &Option<char>, n: &char| *o == Some(*n);
+    let prefix = old.iter().zip(new).take_while(|(o, n)| eq(o, n)).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(o, n)| eq(o, n))
+        .count();
+    let a = &old[prefix..old.len() - suffix];
+    let b = &new[prefix..new.len() - suffix];
+
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = (a.len() + b.len()).min(MAX_DIFF_EDITS);
+    let at = |k: isize| (k + max as isize + 1) as usize;
+    let mut v = vec![0isize; 2 * max + 3];
+    // trace[d] holds a snapshot of `v` for diagonals -d-1..=d+1, which are the only ones read
+    // when backtracking from step d
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+    let mut found = false;
+    'search: for d in 0..=max as isize {
+        trace.push(v[at(-d - 1)..=at(d + 1)].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && v[at(k - 1)] < v[at(k + 1)]) {
+                v[at(k + 1)]
+            } else {
+                v[at(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && eq(&a[x as usize], &b[y as usize]) {
+                x += 1;
+                y += 1;
+            }
+            v[at(k)] = x;
+            if x >= n && y >= m {
+                found = true;
+                break 'search;
+            }
+        }
+    }
+
+    if !found {
+        let mut ops = Vec::with_capacity(old.len() + b.len());
+        ops.extend(new[..prefix].iter().map(|c| CharOp::Equal(*c)));
+        ops.extend(a.iter().map(|c| CharOp::Delete(*c)));
+        ops.extend(b.iter().map(|c| CharOp::Insert(*c)));
+        ops.extend(new[new.len() - suffix..].iter().map(|c| CharOp::Equal(*c)));
+        return ops;
+    }
+
+    let mut ops = Vec::with_capacity(a.len() + b.len());
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let (d, k) = (d as isize, x - y);
+        let at = |k: isize| (k + d + 1) as usize;
+        let prev_k = if k == -d || (k != d && v[at(k - 1)] < v[at(k + 1)]) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = v[at(prev_k)];
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            ops.push(CharOp::Equal(b[y as usize]));
+        }
+        if d > 0 {
+            if x == prev_x {
+                ops.push(CharOp::Insert(b[prev_y as usize]));
+            } else {
+                ops.push(CharOp::Delete(a[prev_x as usize]));
+            }
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+    ops.extend(new[..prefix].iter().rev().map(|c| CharOp::Equal(*c)));
+    ops.reverse();
+    ops.extend(new[new.len() - suffix..].iter().map(|c| CharOp::Equal(*c)));
+    ops
+}
+
+/// Returns lengths of all grapheme clusters of a given text `branch`. Grapheme clusters can span
+/// over multiple blocks, so consecutive strings are segmented together.
+#[cfg(feature = "unicode-segmentation")]
+fn grapheme_lens(branch: &Branch, kind: OffsetKind) -> Vec<u32> {
+    use unicode_segmentation::UnicodeSegmentation;
+
+    fn flush(run: &mut String, kind: OffsetKind, lens: &mut Vec<u32>) {
+        for grapheme in run.graphemes(true) {
+            let len = match kind {
+                OffsetKind::Bytes => grapheme.len(),
+                OffsetKind::Utf16 => grapheme.encode_utf16().count(),
+            };
+            lens.push(len as u32);
+        }
+        run.clear();
+    }
+
+    let mut lens = Vec::new();
+    let mut run = String::new();
+    let mut current = branch.start;
+    while let Some(item) = current.as_deref() {
+        if !item.is_deleted() {
+            match &item.content {
+                ItemContent::String(s) => run.push_str(s),
+                _ if item.is_countable() => {
+                    flush(&mut run, kind, &mut lens);
+                    lens.push(item.len);
+                }
+                _ => {}
+            }
+        }
+        current = item.right;
+    }
+    flush(&mut run, kind, &mut lens);
+    lens
+}
+
 pub(crate) fn diff_between<D, F>(
     ptr: Option<ItemPtr>,
     start: Option<&StickyIndex>,
@@ -980,10 +1742,9 @@ fn insert_attributes(
             // save negated attribute (set null if currentVal undefined)
             negated_attrs.insert(k.clone(), current_value.clone());
 
-            let client_id = store.options.client_id;
             let parent = this.into();
             let mut item = Item::new(
-                ID::new(client_id, store.blocks.get_clock(&client_id)),
+                store.next_local_id(),
                 pos.left.clone(),
                 pos.left.map(|ptr| ptr.last_id()),
                 pos.right.clone(),
@@ -1031,10 +1792,9 @@ fn insert_negated_attributes(
 
     let mut store = txn.store_mut();
     for (k, v) in attrs {
-        let client_id = store.options.client_id;
         let parent = this.into();
         let mut item = Item::new(
-            ID::new(client_id, store.blocks.get_clock(&client_id)),
+            store.next_local_id(),
             pos.left.clone(),
             pos.left.map(|ptr| ptr.last_id()),
             pos.right.clone(),
@@ -1055,6 +1815,9 @@ fn insert_negated_attributes(
     }
 }
 
+/// Removes formatting blocks between `start` and the first piece of content at or after `end`,
+/// which are either overridden by the following blocks or repeat `start_attrs` applied before
+/// them. Updates `end_attrs` with formatting applied by the blocks following `end`.
 fn clean_format_gap(
     txn: &mut TransactionMut,
     mut start: Option<ItemPtr>,
@@ -1062,32 +1825,36 @@ fn clean_format_gap(
     start_attrs: &Attrs,
     end_attrs: &mut Attrs,
 ) -> u32 {
-    while let Some(item) = end.as_deref() {
-        match &item.content {
-            ItemContent::String(_) | ItemContent::Embed(_) => break,
-            ItemContent::Format(key, value) if !item.is_deleted() => {
-                update_current_attributes(end_attrs, key.as_ref(), value);
+    let mut last = HashMap::new();
+    while let Some(item) = end {
+        if !item.is_deleted() {
+            match &item.content {
+                ItemContent::Format(key, value) => {
+                    update_current_attributes(end_attrs, key.as_ref(), value);
+                    last.insert(key.clone(), item);
+                }
+                _ if item.is_countable() => break,
+                _ => {}
             }
-            _ => {}
         }
-        end = item.right.clone();
+        end = item.right;
     }
 
     let mut cleanups = 0;
     while start != end {
-        if let Some(item) = start.as_deref() {
-            let right = item.right.clone();
+        if let Some(item) = start {
             if !item.is_deleted() {
                 if let ItemContent::Format(key, value) = &item.content {
                     let e = end_attrs.get(key).unwrap_or(&Any::Null);
                     let s = start_attrs.get(key).unwrap_or(&Any::Null);
-                    if e != value.as_ref() || s == value.as_ref() {
-                        txn.delete(start.unwrap());
+                    let overridden = matches!(last.get(key), Some(other) if *other != item);
+                    if overridden || e != value.as_ref() || s == value.as_ref() {
+                        txn.delete(item);
                         cleanups += 1;
                     }
                 }
             }
-            start = right;
+            start = item.right;
         } else {
             break;
         }
@@ -1095,6 +1862,99 @@ fn clean_format_gap(
     cleanups
 }
 
+/// Iterator over string chunks of a text within a given range, returned by [Text::chunks].
+pub struct TextChunks<'a, T> {
+    current: Option<ItemPtr>,
+    skip: u32,
+    remaining: u32,
+    kind: OffsetKind,
+    _txn: PhantomData<&'a T>,
+}
+
+impl<'a, T: ReadTxn> Iterator for TextChunks<'a, T> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.remaining > 0 {
+            let ptr = self.current?;
+            // blocks are not freed while a transaction is borrowed
+            let item: &'a Item = unsafe { &*(ptr.deref() as *const Item) };
+            self.current = item.right;
+            if item.is_deleted() || !item.is_countable() {
+                continue;
+            }
+            let len = item.content_len(self.kind);
+            if self.skip >= len {
+                self.skip -= len;
+                continue;
+            }
+            let start = std::mem::take(&mut self.skip);
+            let taken = self.remaining.min(len - start);
+            self.remaining -= taken;
+            if let ItemContent::String(s) = &item.content {
+                let (_, rest) = split_str(s.as_str(), start as usize, self.kind);
+                let (chunk, _) = split_str(rest, taken as usize, self.kind);
+                return Some(chunk);
+            }
+        }
+        None
+    }
+}
+
+/// A string run of a text returned by [Text::runs].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextRun<'a> {
+    /// ID of a block containing this run.
+    pub id: ID,
+    /// Text content of a run.
+    pub chunk: &'a str,
+    /// Formatting attributes applied to this run. They are shared between consecutive runs with
+    /// the same formatting.
+    pub attributes: Option<Arc<Attrs>>,
+}
+
+/// Iterator over string runs of a text, returned by [Text::runs].
+pub struct TextRuns<'a, T> {
+    current: Option<ItemPtr>,
+    attributes: Option<Arc<Attrs>>,
+    _txn: PhantomData<&'a T>,
+}
+
+impl<'a, T: ReadTxn> Iterator for TextRuns<'a, T> {
+    type Item = TextRun<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let ptr = self.current?;
+            // blocks are not freed while a transaction is borrowed
+            let item: &'a Item = unsafe { &*(ptr.deref() as *const Item) };
+            self.current = item.right;
+            if item.is_deleted() {
+                continue;
+            }
+            match &item.content {
+                ItemContent::String(s) => {
+                    return Some(TextRun {
+                        id: item.id,
+                        chunk: s.as_str(),
+                        attributes: self.attributes.clone(),
+                    })
+                }
+                ItemContent::Format(key, value) => {
+                    let mut attrs = self.attributes.as_deref().cloned().unwrap_or_default();
+                    update_current_attributes(&mut attrs, key, value);
+                    self.attributes = if attrs.is_empty() {
+                        None
+                    } else {
+                        Some(Arc::new(attrs))
+                    };
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
 /// A representation of an uniformly-formatted chunk of rich context stored by [TextRef] or
 /// [XmlTextRef]. It contains a value (which could be a string, embedded object or another shared
 /// type) with optional formatting attributes wrapping around this chunk. It can also contain some
@@ -1507,20 +2367,53 @@ mod test {
     use crate::test_utils::{exchange_updates, run_scenario, RngExt};
     use crate::transaction::ReadTxn;
     use crate::types::text::{Attrs, ChangeKind, Delta, Diff, YChange};
-    use crate::types::Out;
+    use crate::types::{IndexOutOfBounds, Out};
     use crate::updates::decoder::Decode;
     use crate::updates::encoder::{Encode, Encoder, EncoderV1};
     use crate::{
-        any, Any, ArrayPrelim, Doc, GetString, Map, MapPrelim, MapRef, Observable, StateVector,
-        Text, Transact, Update, WriteTxn, ID,
+        any, Any, ArrayPrelim, Assoc, Doc, GetString, Map, MapPrelim, MapRef, Observable, Snapshot,
+        StateVector, StickyIndex, Text, Transact, Update, WriteTxn, ID,
     };
     use arc_swap::ArcSwapOption;
     use fastrand::Rng;
     use std::collections::HashMap;
     use std::sync::atomic::{AtomicBool, Ordering};
-    use std::sync::Arc;
+    use std::sync::{Arc, Mutex};
     use std::time::Duration;
 
+    #[test]
+    fn try_insert_out_of_bounds() {
+        let doc = Doc::with_client_id(1);
+        let txt = doc.get_or_insert_text("test");
+        let mut txn = doc.transact_mut();
+        txt.push(&mut txn, "abc");
+
+        assert_eq!(
+            txt.try_insert(&mut txn, 4, "d"),
+            Err(IndexOutOfBounds { index: 4, len: 3 })
+        );
+        assert_eq!(
+            txt.try_remove_range(&mut txn, 2, 2),
+            Err(IndexOutOfBounds { index: 4, len: 3 })
+        );
+        assert_eq!(
+            txt.try_remove_range(&mut txn, 1, u32::MAX),
+            Err(IndexOutOfBounds {
+                index: u32::MAX,
+                len: 3
+            })
+        );
+        assert_eq!(
+            txt.try_format(&mut txn, 3, 1, Attrs::new()),
+            Err(IndexOutOfBounds { index: 4, len: 3 })
+        );
+        assert_eq!(txt.get_string(&txn), "abc");
+
+        txt.try_insert(&mut txn, 3, "d").unwrap();
+        txt.try_remove_range(&mut txn, 0, 1).unwrap();
+        assert_eq!(txt.get_string(&txn), "bcd");
+    }
+
     #[test]
     fn insert_empty_string() {
         let doc = Doc::new();
@@ -2669,6 +3562,396 @@ mod test {
         assert_eq!(delta, vec![Diff::new(linebreak.into(), None)]);
     }
 
+    #[cfg(feature = "unicode-segmentation")]
+    #[test]
+    fn grapheme_indexing() {
+        let doc = Doc::with_options(Options {
+            offset_kind: OffsetKind::Utf16,
+            ..Options::default()
+        });
+        let mut txn = doc.transact_mut();
+        let txt = txn.get_or_insert_text("text");
+        // family emoji is a single grapheme built of 11 UTF-16 code units
+        txt.insert(&mut txn, 0, "a👨‍👩‍👧‍👦");
+        txt.insert(&mut txn, 1, "e");
+        txt.insert(&mut txn, 2, "\u{301}");
+        txt.insert_embed(&mut txn, 3, any!({"image": "x.png"}));
+        assert_eq!(txt.len_graphemes(&txn), 4);
+        assert_eq!(txt.grapheme_offset(&txn, 3), Some(4));
+        assert_eq!(txt.grapheme_offset(&txn, 4), Some(15));
+        assert_eq!(txt.grapheme_offset(&txn, 5), None);
+        assert_eq!(txt.grapheme_index(&txn, 2), 1);
+        assert_eq!(txt.grapheme_index(&txn, 10), 3);
+        assert_eq!(txt.grapheme_index(&txn, 15), 4);
+    }
+
+    #[test]
+    fn set_content_minimal_edits() {
+        let doc = Doc::with_options(Options {
+            offset_kind: OffsetKind::Utf16,
+            ..Options::default()
+        });
+        let txt = doc.get_or_insert_text("text");
+        txt.insert(&mut doc.transact_mut(), 0, "zażółć gęślą jaźń");
+        let deltas = Arc::new(Mutex::new(Vec::new()));
+        let _sub = {
+            let deltas = deltas.clone();
+            txt.observe(move |txn, e| deltas.lock().unwrap().push(e.delta(txn).to_vec()))
+        };
+
+        txt.set_content(&mut doc.transact_mut(), "zażółć 😀 gęślą");
+        assert_eq!(txt.get_string(&doc.transact()), "zażółć 😀 gęślą");
+        txt.insert_embed(&mut doc.transact_mut(), 7, any!({"image": "x.png"}));
+        txt.set_content(&mut doc.transact_mut(), "zażółć 😀 gęślą!");
+        assert_eq!(txt.get_string(&doc.transact()), "zażółć 😀 gęślą!");
+        assert_eq!(txt.len(&doc.transact()), 16);
+        txt.set_content(&mut doc.transact_mut(), "");
+        assert_eq!(txt.len(&doc.transact()), 0);
+
+        assert_eq!(
+            deltas.lock().unwrap().as_slice(),
+            &[
+                vec![
+                    Delta::Retain(7, None),
+                    Delta::Inserted("😀 ".into(), None),
+                    Delta::Retain(5, None),
+                    Delta::Deleted(5),
+                ],
+                vec![
+                    Delta::Retain(7, None),
+                    Delta::Inserted(any!({"image": "x.png"}).into(), None),
+                ],
+                vec![
+                    Delta::Retain(7, None),
+                    Delta::Deleted(1),
+                    Delta::Retain(8, None),
+                    Delta::Inserted("!".into(), None),
+                ],
+                vec![Delta::Deleted(16)],
+            ]
+        );
+
+        let mut rng = Rng::with_seed(1);
+        for _ in 0..100 {
+            let len = rng.usize(0..20);
+            let content: String = (0..len)
+                .map(|_| rng.choice(['a', 'b', 'ę', '😀']).unwrap())
+                .collect();
+            txt.set_content(&mut doc.transact_mut(), &content);
+            assert_eq!(txt.get_string(&doc.transact()), content);
+        }
+    }
+
+    #[test]
+    fn set_content_large_difference() {
+        let doc = Doc::with_client_id(1);
+        let txt = doc.get_or_insert_text("text");
+        let old = format!("<{}>", "a".repeat(2000));
+        txt.insert(&mut doc.transact_mut(), 0, &old);
+        let deltas = Arc::new(Mutex::new(Vec::new()));
+        let _sub = {
+            let deltas = deltas.clone();
+            txt.observe(move |txn, e| deltas.lock().unwrap().push(e.delta(txn).to_vec()))
+        };
+
+        // small edits of a long text are still minimal
+        let new = format!("<{}b{}>", "a".repeat(1000), "a".repeat(1000));
+        txt.set_content(&mut doc.transact_mut(), &new);
+        assert_eq!(txt.get_string(&doc.transact()), new);
+
+        // too many edits replace everything between common prefix and suffix
+        let new = format!("<{}>", "ab".repeat(1000));
+        txt.set_content(&mut doc.transact_mut(), &new);
+        assert_eq!(txt.get_string(&doc.transact()), new);
+
+        assert_eq!(
+            deltas.lock().unwrap().as_slice(),
+            &[
+                vec![Delta::Retain(1001, None), Delta::Inserted("b".into(), None)],
+                vec![
+                    Delta::Retain(2, None),
+                    Delta::Deleted(2000),
+                    Delta::Inserted(format!("b{}", "ab".repeat(999)).into(), None),
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn find_across_blocks() {
+        let doc = Doc::with_options(Options {
+            offset_kind: OffsetKind::Utf16,
+            ..Options::default()
+        });
+        let mut txn = doc.transact_mut();
+        let txt = txn.get_or_insert_text("text");
+        txt.insert(&mut txn, 0, "😀aab");
+        txt.insert(&mut txn, 5, "aaab");
+        txt.insert_embed(&mut txn, 9, any!({"image": "x.png"}));
+        txt.insert(&mut txn, 10, "aab");
+        txt.insert(&mut txn, 4, "x");
+        txt.remove_range(&mut txn, 4, 1);
+        assert_eq!(txt.find(&txn, "aaab"), Some(5));
+        assert_eq!(txt.find_all(&txn, "aab"), vec![2, 6, 10]);
+        // matches never span over embedded values
+        assert_eq!(txt.find(&txn, "baa"), Some(4));
+        assert_eq!(txt.find_all(&txn, "baa"), vec![4]);
+        assert_eq!(txt.find_all(&txn, "aa"), vec![2, 5, 10]);
+        assert_eq!(txt.find(&txn, ""), None);
+        assert_eq!(txt.find(&txn, "c"), None);
+    }
+
+    #[test]
+    fn runs_skip_deleted_blocks() {
+        let doc = Doc::with_client_id(1);
+        let mut txn = doc.transact_mut();
+        let txt = txn.get_or_insert_text("text");
+        let bold = Attrs::from([("b".into(), true.into())]);
+        txt.insert(&mut txn, 0, "abcdef");
+        txt.format(&mut txn, 1, 3, bold.clone());
+        txt.remove_range(&mut txn, 0, 1);
+        txt.insert_embed(&mut txn, 5, any!({"image": "x.png"}));
+        let runs: Vec<_> = txt
+            .runs(&txn)
+            .map(|run| (run.chunk, run.attributes.as_deref().cloned(), run.id))
+            .collect();
+        assert_eq!(
+            runs,
+            vec![
+                ("bcd", Some(bold), ID::new(1, 1)),
+                ("ef", None, ID::new(1, 4)),
+            ]
+        );
+    }
+
+    #[test]
+    fn attribution_of_concurrent_inserts() {
+        let d1 = Doc::with_client_id(1);
+        let d2 = Doc::with_client_id(2);
+        let t1 = d1.get_or_insert_text("text");
+        let t2 = d2.get_or_insert_text("text");
+        t1.insert(&mut d1.transact_mut(), 0, "aaaa");
+        exchange_updates(&[&d1, &d2]);
+        t2.insert(&mut d2.transact_mut(), 2, "bb");
+        t1.insert(&mut d1.transact_mut(), 4, "cc");
+        t1.remove_range(&mut d1.transact_mut(), 0, 1);
+        exchange_updates(&[&d1, &d2]);
+
+        // "a" + "a" + "bb" + "aa" + "cc"
+        let txn = d1.transact();
+        assert_eq!(
+            t1.attribution(&txn, 0..100),
+            t2.attribution(&d2.transact(), 0..100)
+        );
+        assert_eq!(
+            t1.attribution(&txn, 0..100),
+            vec![(0..1, 1), (1..3, 2), (3..7, 1)]
+        );
+        assert_eq!(t1.attribution(&txn, 2..4), vec![(2..3, 2), (3..4, 1)]);
+        assert_eq!(t1.attribution(&txn, 7..9), vec![]);
+    }
+
+    #[test]
+    fn push_after_remote_changes_and_gc() {
+        let d1 = Doc::with_client_id(1);
+        let d2 = Doc::with_client_id(2);
+        let t1 = d1.get_or_insert_text("text");
+        let t2 = d2.get_or_insert_text("text");
+        for i in 0..3 {
+            t1.push(&mut d1.transact_mut(), &i.to_string());
+        }
+        exchange_updates(&[&d1, &d2]);
+        t2.push(&mut d2.transact_mut(), "b");
+        exchange_updates(&[&d1, &d2]);
+        // pushed after a block appended by a remote peer
+        t1.push(&mut d1.transact_mut(), "a");
+        assert_eq!(t1.get_string(&d1.transact()), "012ba");
+
+        // remembered block has been garbage collected
+        t1.remove_range(&mut d1.transact_mut(), 0, 5);
+        t1.push(&mut d1.transact_mut(), "c");
+        t1.insert(&mut d1.transact_mut(), 1, "d");
+        t1.push(&mut d1.transact_mut(), "e");
+        exchange_updates(&[&d1, &d2]);
+        assert_eq!(t1.get_string(&d1.transact()), "cde");
+        assert_eq!(t2.get_string(&d2.transact()), "cde");
+    }
+
+    #[test]
+    fn clean_formatting_trailing_gap() {
+        let d1 = Doc::with_client_id(1);
+        let d2 = Doc::with_client_id(2);
+        let t1 = d1.get_or_insert_text("text");
+        let t2 = d2.get_or_insert_text("text");
+        let bold = Attrs::from([("b".into(), true.into())]);
+        t1.insert(&mut d1.transact_mut(), 0, "abcd");
+        exchange_updates(&[&d1, &d2]);
+
+        // concurrent formatting at the end of a text leaves duplicated markers after the last
+        // piece of content
+        t1.format(&mut d1.transact_mut(), 2, 2, bold.clone());
+        t2.format(&mut d2.transact_mut(), 2, 2, bold.clone());
+        exchange_updates(&[&d1, &d2]);
+        let expected = vec![
+            Delta::Inserted("ab".into(), None),
+            Delta::Inserted("cd".into(), Some(Box::new(bold.clone()))),
+        ];
+        assert_eq!(t2.to_delta(&d2.transact()), expected);
+        assert_eq!(t2.clean_formatting(&mut d2.transact_mut()), 2);
+        assert_eq!(t2.clean_formatting(&mut d2.transact_mut()), 0);
+        assert_eq!(t2.to_delta(&d2.transact()), expected);
+
+        // text appended afterwards is not formatted
+        exchange_updates(&[&d1, &d2]);
+        t1.push(&mut d1.transact_mut(), "e");
+        exchange_updates(&[&d1, &d2]);
+        let expected = vec![
+            Delta::Inserted("ab".into(), None),
+            Delta::Inserted("cd".into(), Some(Box::new(bold))),
+            Delta::Inserted("e".into(), None),
+        ];
+        assert_eq!(t1.to_delta(&d1.transact()), expected);
+        assert_eq!(t2.to_delta(&d2.transact()), expected);
+    }
+
+    #[test]
+    fn concurrent_format_removal_and_cleanup() {
+        let d1 = Doc::with_client_id(1);
+        let d2 = Doc::with_client_id(2);
+        let t1 = d1.get_or_insert_text("text");
+        let t2 = d2.get_or_insert_text("text");
+        let bold = Attrs::from([("b".into(), true.into())]);
+        t1.insert(&mut d1.transact_mut(), 0, "abcd");
+        exchange_updates(&[&d1, &d2]);
+
+        // concurrent formatting of the same range leaves duplicated markers
+        t1.format(&mut d1.transact_mut(), 1, 2, bold.clone());
+        t2.format(&mut d2.transact_mut(), 1, 2, bold.clone());
+        exchange_updates(&[&d1, &d2]);
+        let expected = vec![
+            Delta::Inserted("a".into(), None),
+            Delta::Inserted("bc".into(), Some(Box::new(bold.clone()))),
+            Delta::Inserted("d".into(), None),
+        ];
+        assert_eq!(t2.to_delta(&d2.transact()), expected);
+        assert_eq!(t2.clean_formatting(&mut d2.transact_mut()), 2);
+        assert_eq!(t2.clean_formatting(&mut d2.transact_mut()), 0);
+        assert_eq!(t2.to_delta(&d2.transact()), expected);
+
+        t1.remove_format(&mut d1.transact_mut(), 0, 2, &["b"]);
+        exchange_updates(&[&d1, &d2]);
+        let expected = vec![
+            Delta::Inserted("ab".into(), None),
+            Delta::Inserted("c".into(), Some(Box::new(bold))),
+            Delta::Inserted("d".into(), None),
+        ];
+        assert_eq!(t1.to_delta(&d1.transact()), expected);
+        assert_eq!(t2.to_delta(&d2.transact()), expected);
+    }
+
+    #[test]
+    fn insert_and_get_id_returns_stable_id() {
+        let doc = Doc::with_client_id(1);
+        let txt = doc.get_or_insert_text("text");
+        let mut txn = doc.transact_mut();
+        assert_eq!(txt.insert_and_get_id(&mut txn, 0, ""), None);
+        let hello = txt.insert_and_get_id(&mut txn, 0, "hello").unwrap();
+        let world = txt
+            .insert_with_attributes_and_get_id(
+                &mut txn,
+                5,
+                " world",
+                Attrs::from([("b".into(), true.into())]),
+            )
+            .unwrap();
+        assert_eq!(hello, ID::new(1, 0));
+        assert_eq!(world, ID::new(1, 6)); // after "hello" and a formatting block
+        txn.commit();
+
+        // split the "hello" block
+        txt.insert(&mut txn, 2, "__");
+        let pos = StickyIndex::from_id(world, Assoc::After);
+        assert_eq!(pos.get_offset(&txn).unwrap().index, 7);
+        let item = txn.store().blocks.get_item(&hello).unwrap();
+        assert_eq!(item.id, hello);
+    }
+
+    #[test]
+    fn get_string_at_snapshots() {
+        let options = Options {
+            skip_gc: true,
+            offset_kind: OffsetKind::Utf16,
+            ..Options::default()
+        };
+        let d1 = Doc::with_options(Options {
+            client_id: 1,
+            ..options.clone()
+        });
+        let d2 = Doc::with_options(Options {
+            client_id: 2,
+            ..options
+        });
+        let t1 = d1.get_or_insert_text("text");
+        let t2 = d2.get_or_insert_text("text");
+        t1.insert(&mut d1.transact_mut(), 0, "z😀łw");
+        exchange_updates(&[&d1, &d2]);
+        let s1 = d1.transact().snapshot();
+
+        t1.remove_range(&mut d1.transact_mut(), 1, 2);
+        t2.insert(&mut d2.transact_mut(), 4, "ą");
+        exchange_updates(&[&d1, &d2]);
+        let s2 = d1.transact().snapshot();
+        t1.remove_range(&mut d1.transact_mut(), 0, 4);
+
+        let txn = d1.transact();
+        assert_eq!(t1.get_string(&txn), "");
+        assert_eq!(t1.get_string_at(&txn, &s1), "z😀łw");
+        assert_eq!(t1.get_string_at(&txn, &s2), "złąw");
+        assert_eq!(t1.get_string_at(&txn, &Snapshot::default()), "");
+    }
+
+    #[test]
+    fn slice_within_blocks() {
+        let doc = Doc::with_options(Options {
+            offset_kind: OffsetKind::Utf16,
+            ..Options::default()
+        });
+        let mut txn = doc.transact_mut();
+        let txt = txn.get_or_insert_text("text");
+        txt.insert(&mut txn, 0, "zażółć");
+        txt.insert_embed(&mut txn, 6, any!({"image": "x.png"}));
+        txt.insert(&mut txn, 7, "gęślą jaźń");
+        txt.remove_range(&mut txn, 9, 4);
+        assert_eq!(txt.get_string(&txn), "zażółćgęjaźń");
+        assert_eq!(txt.slice(&txn, 2..9), "żółćgę");
+        assert_eq!(
+            txt.chunks(&txn, 4..10).collect::<Vec<_>>(),
+            vec!["łć", "gę", "j"]
+        );
+        assert_eq!(txt.slice(&txn, 10..100), "aźń");
+        assert_eq!(txt.slice(&txn, 20..30), "");
+    }
+
+    #[test]
+    fn to_delta_merges_runs() {
+        let doc = Doc::with_client_id(1);
+        let mut txn = doc.transact_mut();
+        let txt = txn.get_or_insert_text("text");
+        let italic = Attrs::from([("i".into(), true.into())]);
+        txt.insert(&mut txn, 0, "ab");
+        txt.insert(&mut txn, 2, "cd");
+        txt.insert_embed(&mut txn, 4, any!({"image": "x.png"}));
+        txt.insert_with_attributes(&mut txn, 5, "ef", italic.clone());
+        assert_eq!(
+            txt.to_delta(&txn),
+            vec![
+                Delta::Inserted("abcd".into(), None),
+                Delta::Inserted(any!({"image": "x.png"}).into(), None),
+                Delta::Inserted("ef".into(), Some(Box::new(italic))),
+            ]
+        );
+    }
+
     #[test]
     fn delta_with_shared_ref() {
         let d1 = Doc::with_client_id(1);
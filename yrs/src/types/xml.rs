@@ -5,6 +5,7 @@ use std::convert::{TryFrom, TryInto};
 use std::fmt::Write;
 use std::marker::PhantomData;
 use std::ops::{Deref, DerefMut};
+use std::str::FromStr;
 use std::sync::Arc;
 
 use crate::block::{EmbedPrelim, Item, ItemContent, ItemPosition, ItemPtr, Prelim};
@@ -13,7 +14,8 @@ use crate::transaction::TransactionMut;
 use crate::types::text::{diff_between, TextEvent, YChange};
 use crate::types::{
     event_change_set, event_keys, AsPrelim, Branch, BranchPtr, Change, ChangeSet, DefaultPrelim,
-    Delta, Entries, EntryChange, MapRef, Out, Path, RootRef, SharedRef, ToJson, TypePtr, TypeRef,
+    Delta, Entries, EntryChange, IndexOutOfBounds, MapRef, Out, Path, RootRef, SharedRef, ToJson,
+    TypePtr, TypeRef,
 };
 use crate::{
     Any, ArrayRef, BranchID, DeepObservable, GetString, In, IndexedSequence, Map, Observable,
@@ -934,6 +936,252 @@ impl From<XmlFragmentPrelim> for In {
     }
 }
 
+impl FromStr for XmlFragmentPrelim {
+    type Err = XmlParseError;
+
+    /// Parses an XML (or a well-formed subset of HTML) string into a tree of preliminary XML nodes.
+    /// Comments, processing instructions and doctype declarations are skipped. Contents of CDATA
+    /// sections are inserted as text, merged with any text surrounding them. Attributes without
+    /// a value are stored with an empty string as their value. Elements nested more than 128 levels
+    /// deep are rejected with [XmlParseError::TooDeep].
+    fn from_str(xml: &str) -> Result<Self, Self::Err> {
+        let mut parser = XmlParser {
+            input: xml,
+            pos: 0,
+            depth: 0,
+        };
+        let nodes = parser.nodes()?;
+        if parser.rest().is_empty() {
+            Ok(XmlFragmentPrelim(nodes))
+        } else {
+            // nodes are parsed until the end of input or a closing tag with no matching opening one
+            Err(XmlParseError::UnexpectedChar {
+                ch: '/',
+                pos: parser.pos + 1,
+            })
+        }
+    }
+}
+
+/// Error returned when parsing an XML string with [XmlFragment::insert_xml] or
+/// [XmlFragmentPrelim::from_str].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum XmlParseError {
+    /// Input ended before all elements were closed.
+    #[error("unexpected end of XML input")]
+    UnexpectedEof,
+    /// Input contains a character that's not valid at a given byte position.
+    #[error("unexpected character '{ch}' at position {pos}")]
+    UnexpectedChar { ch: char, pos: usize },
+    /// Closing tag at a given byte position doesn't match the most recently opened element.
+    #[error("closing tag </{found}> at position {pos} doesn't match <{expected}>")]
+    MismatchedTag {
+        expected: String,
+        found: String,
+        pos: usize,
+    },
+    /// Input contains an unknown or malformed entity reference.
+    #[error("unknown entity reference '&{0}'")]
+    UnknownEntity(String),
+    /// Element opened at a given byte position exceeds the maximum nesting depth.
+    #[error("element at position {pos} is nested too deeply")]
+    TooDeep { pos: usize },
+}
+
+struct XmlParser<'a> {
+    input: &'a str,
+    pos: usize,
+    depth: usize,
+}
+
+impl<'a> XmlParser<'a> {
+    /// Maximum number of nested elements. Parser is recursive, so deeper inputs are rejected
+    /// instead of overflowing the stack.
+    const MAX_DEPTH: usize = 128;
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn peek(&self) -> Result<char, XmlParseError> {
+        self.rest()
+            .chars()
+            .next()
+            .ok_or(XmlParseError::UnexpectedEof)
+    }
+
+    fn unexpected(&self) -> XmlParseError {
+        match self.peek() {
+            Ok(ch) => XmlParseError::UnexpectedChar { ch, pos: self.pos },
+            Err(e) => e,
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), XmlParseError> {
+        if self.peek()? == expected {
+            self.pos += expected.len_utf8();
+            Ok(())
+        } else {
+            Err(self.unexpected())
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    /// Moves past the first occurrence of a given `end` pattern.
+    fn skip_past(&mut self, end: &str) -> Result<(), XmlParseError> {
+        let i = self.rest().find(end).ok_or(XmlParseError::UnexpectedEof)?;
+        self.pos += i + end.len();
+        Ok(())
+    }
+
+    fn name(&mut self) -> Result<&'a str, XmlParseError> {
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| c.is_whitespace() || matches!(c, '/' | '>' | '<' | '=' | '"' | '\''))
+            .unwrap_or(rest.len());
+        if len == 0 {
+            return Err(self.unexpected());
+        }
+        self.pos += len;
+        Ok(&rest[..len])
+    }
+
+    /// Parses nodes until the end of input or a closing tag.
+    fn nodes(&mut self) -> Result<Vec<XmlIn>, XmlParseError> {
+        let mut nodes = Vec::new();
+        let mut text = String::new();
+        loop {
+            let rest = self.rest();
+            if rest.is_empty() || rest.starts_with("</") {
+                break;
+            } else if let Some(cdata) = rest.strip_prefix("<![CDATA[") {
+                let len = cdata.find("]]>").ok_or(XmlParseError::UnexpectedEof)?;
+                text.push_str(&cdata[..len]);
+                self.pos += "<![CDATA[".len() + len + "]]>".len();
+            } else if rest.starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if rest.starts_with("<?") || rest.starts_with("<!") {
+                self.skip_past(">")?;
+            } else if rest.starts_with('<') {
+                if !text.is_empty() {
+                    nodes.push(XmlTextPrelim::new(std::mem::take(&mut text)).into());
+                }
+                nodes.push(self.element()?.into());
+            } else {
+                let len = rest.find('<').unwrap_or(rest.len());
+                text.push_str(&unescape(&rest[..len])?);
+                self.pos += len;
+            }
+        }
+        if !text.is_empty() {
+            nodes.push(XmlTextPrelim::new(text).into());
+        }
+        Ok(nodes)
+    }
+
+    fn element(&mut self) -> Result<XmlElementPrelim, XmlParseError> {
+        if self.depth >= Self::MAX_DEPTH {
+            return Err(XmlParseError::TooDeep { pos: self.pos });
+        }
+        self.expect('<')?;
+        let tag = self.name()?;
+        let mut element = XmlElementPrelim::empty(tag);
+        loop {
+            self.skip_whitespace();
+            match self.peek()? {
+                '/' => {
+                    self.pos += 1;
+                    self.expect('>')?;
+                    return Ok(element);
+                }
+                '>' => {
+                    self.pos += 1;
+                    break;
+                }
+                _ => {
+                    let name = self.name()?;
+                    self.skip_whitespace();
+                    let value = if self.rest().starts_with('=') {
+                        self.pos += 1;
+                        self.skip_whitespace();
+                        self.attribute_value()?
+                    } else {
+                        String::new()
+                    };
+                    element.attributes.insert(name.into(), value);
+                }
+            }
+        }
+        self.depth += 1;
+        element.children = self.nodes()?;
+        self.depth -= 1;
+        let pos = self.pos;
+        self.expect('<')?;
+        self.expect('/')?;
+        let closing = self.name()?;
+        if closing != tag {
+            return Err(XmlParseError::MismatchedTag {
+                expected: tag.to_string(),
+                found: closing.to_string(),
+                pos,
+            });
+        }
+        self.skip_whitespace();
+        self.expect('>')?;
+        Ok(element)
+    }
+
+    fn attribute_value(&mut self) -> Result<String, XmlParseError> {
+        let quote = self.peek()?;
+        if quote != '"' && quote != '\'' {
+            return Err(self.unexpected());
+        }
+        self.pos += 1;
+        let rest = self.rest();
+        let len = rest.find(quote).ok_or(XmlParseError::UnexpectedEof)?;
+        self.pos += len + 1;
+        unescape(&rest[..len])
+    }
+}
+
+/// Replaces entity references (ie. `&amp;` or `&#x41;`) with characters they represent.
+fn unescape(s: &str) -> Result<String, XmlParseError> {
+    let mut result = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(i) = rest.find('&') {
+        result.push_str(&rest[..i]);
+        rest = &rest[i + 1..];
+        let end = rest
+            .find(';')
+            .ok_or_else(|| XmlParseError::UnknownEntity(rest.to_string()))?;
+        let entity = &rest[..end];
+        let c = match entity {
+            "lt" => '<',
+            "gt" => '>',
+            "amp" => '&',
+            "quot" => '"',
+            "apos" => '\'',
+            "nbsp" => '\u{a0}',
+            _ => entity
+                .strip_prefix('#')
+                .and_then(|code| match code.strip_prefix(['x', 'X']) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                    None => code.parse().ok(),
+                })
+                .and_then(char::from_u32)
+                .ok_or_else(|| XmlParseError::UnknownEntity(format!("{};", entity)))?,
+        };
+        result.push(c);
+        rest = &rest[end + 1..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
 /// (Obsolete) an Yjs-compatible XML node used for nesting Map elements.
 #[derive(Debug, Clone)]
 pub struct XmlHookRef(BranchPtr);
@@ -1073,6 +1321,58 @@ pub trait XmlFragment: AsRef<Branch> {
         }
     }
 
+    /// Checked variant of [XmlFragment::insert]. Instead of panicking, returns an
+    /// [IndexOutOfBounds] error if provided `index` is greater than the number of children of
+    /// a current XML node.
+    fn try_insert<V>(
+        &self,
+        txn: &mut TransactionMut,
+        index: u32,
+        xml_node: V,
+    ) -> Result<V::Return, IndexOutOfBounds>
+    where
+        V: XmlPrelim,
+    {
+        IndexOutOfBounds::check(index, self.len(txn))?;
+        Ok(self.insert(txn, index, xml_node))
+    }
+
+    /// Parses a given `xml` string and inserts all resulting top-level nodes at the given `index`.
+    /// If `xml` is not well-formed, an error is returned and current XML node is left unchanged.
+    /// See [XmlFragmentPrelim::from_str] for details of supported syntax.
+    ///
+    /// Using `index` value that's higher than current array length results in panic.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, GetString, Transact, XmlFragment};
+    ///
+    /// let doc = Doc::new();
+    /// let xml = doc.get_or_insert_xml_fragment("prosemirror");
+    /// let mut txn = doc.transact_mut();
+    /// xml.insert_xml(&mut txn, 0, "<p class=\"title\">Hello <b>world</b></p><hr/>")
+    ///     .unwrap();
+    ///
+    /// assert_eq!(xml.len(&txn), 2);
+    /// assert_eq!(
+    ///     xml.get_string(&txn),
+    ///     "<p class=\"title\">Hello <b>world</b></p><hr></hr>"
+    /// );
+    /// ```
+    fn insert_xml(
+        &self,
+        txn: &mut TransactionMut,
+        index: u32,
+        xml: &str,
+    ) -> Result<(), XmlParseError> {
+        let XmlFragmentPrelim(nodes) = xml.parse()?;
+        for (i, node) in nodes.into_iter().enumerate() {
+            self.insert(txn, index + i as u32, node);
+        }
+        Ok(())
+    }
+
     /// Inserts given `value` at the end of the current array.
     fn push_back<V>(&self, txn: &mut TransactionMut, xml_node: V) -> V::Return
     where
@@ -1108,6 +1408,20 @@ pub trait XmlFragment: AsRef<Branch> {
         }
     }
 
+    /// Checked variant of [XmlFragment::remove_range]. Instead of panicking, returns an
+    /// [IndexOutOfBounds] error if the `index`..`index + len` range doesn't fit within children
+    /// of a current XML node. In such case no nodes are removed.
+    fn try_remove_range(
+        &self,
+        txn: &mut TransactionMut,
+        index: u32,
+        len: u32,
+    ) -> Result<(), IndexOutOfBounds> {
+        IndexOutOfBounds::check_range(index, len, self.len(txn))?;
+        self.remove_range(txn, index, len);
+        Ok(())
+    }
+
     /// Retrieves a value stored at a given `index`. Returns `None` when provided index was out
     /// of the range of a current array.
     fn get<T: ReadTxn>(&self, _txn: &T, index: u32) -> Option<XmlOut> {
@@ -1510,7 +1824,7 @@ mod test {
 
     use crate::test_utils::exchange_updates;
     use crate::transaction::ReadTxn;
-    use crate::types::xml::{Xml, XmlFragment, XmlOut};
+    use crate::types::xml::{Xml, XmlFragment, XmlOut, XmlParseError};
     use crate::types::{Attrs, Change, EntryChange, Out};
     use crate::updates::decoder::Decode;
     use crate::updates::encoder::{Encoder, EncoderV1};
@@ -1519,6 +1833,112 @@ mod test {
         XmlElementPrelim, XmlTextPrelim, XmlTextRef,
     };
 
+    #[test]
+    fn insert_xml() {
+        let doc = Doc::with_client_id(1);
+        let f = doc.get_or_insert_xml_fragment("xml");
+        let mut txn = doc.transact_mut();
+        f.insert_xml(&mut txn, 0, "<hr/>").unwrap();
+        f.insert_xml(
+            &mut txn,
+            0,
+            "<?xml version=\"1.0\"?><!-- intro --><p id='a' hidden>1 &lt; 2 &amp;&#x20;\
+            <b  title = \"&quot;x&quot;\" >bold</b ></p>tail",
+        )
+        .unwrap();
+
+        assert_eq!(f.len(&txn), 3);
+        let p = f.get(&txn, 0).unwrap().into_xml_element().unwrap();
+        assert_eq!(p.tag().as_ref(), "p");
+        assert_eq!(p.get_attribute(&txn, "id"), Some("a".into()));
+        assert_eq!(p.get_attribute(&txn, "hidden"), Some("".into()));
+        let text = p.get(&txn, 0).unwrap().into_xml_text().unwrap();
+        assert_eq!(text.get_string(&txn), "1 < 2 & ");
+        let b = p.get(&txn, 1).unwrap().into_xml_element().unwrap();
+        assert_eq!(b.get_attribute(&txn, "title"), Some("\"x\"".into()));
+        assert_eq!(
+            f.get(&txn, 1)
+                .unwrap()
+                .into_xml_text()
+                .unwrap()
+                .get_string(&txn),
+            "tail"
+        );
+        assert_eq!(
+            f.get(&txn, 2)
+                .unwrap()
+                .into_xml_element()
+                .unwrap()
+                .tag()
+                .as_ref(),
+            "hr"
+        );
+    }
+
+    #[test]
+    fn insert_malformed_xml() {
+        let doc = Doc::with_client_id(1);
+        let f = doc.get_or_insert_xml_fragment("xml");
+        let mut txn = doc.transact_mut();
+        assert_eq!(
+            f.insert_xml(&mut txn, 0, "<p><b></p>"),
+            Err(XmlParseError::MismatchedTag {
+                expected: "b".into(),
+                found: "p".into(),
+                pos: 6,
+            })
+        );
+        assert_eq!(
+            f.insert_xml(&mut txn, 0, "<p>"),
+            Err(XmlParseError::UnexpectedEof)
+        );
+        assert_eq!(
+            f.insert_xml(&mut txn, 0, "a</p>"),
+            Err(XmlParseError::UnexpectedChar { ch: '/', pos: 2 })
+        );
+        assert_eq!(
+            f.insert_xml(&mut txn, 0, "<p a=b></p>"),
+            Err(XmlParseError::UnexpectedChar { ch: 'b', pos: 5 })
+        );
+        assert_eq!(
+            f.insert_xml(&mut txn, 0, "&copy;"),
+            Err(XmlParseError::UnknownEntity("copy;".into()))
+        );
+        assert_eq!(
+            f.insert_xml(&mut txn, 0, "<p><![CDATA[a]]</p>"),
+            Err(XmlParseError::UnexpectedEof)
+        );
+        assert_eq!(f.len(&txn), 0);
+    }
+
+    #[test]
+    fn insert_xml_cdata() {
+        let doc = Doc::with_client_id(1);
+        let f = doc.get_or_insert_xml_fragment("xml");
+        let mut txn = doc.transact_mut();
+        f.insert_xml(&mut txn, 0, "<p>a &amp; <![CDATA[<b>&amp;</b>]]> c</p>")
+            .unwrap();
+        let p = f.get(&txn, 0).unwrap().into_xml_element().unwrap();
+        assert_eq!(p.len(&txn), 1);
+        let text = p.get(&txn, 0).unwrap().into_xml_text().unwrap();
+        assert_eq!(text.get_string(&txn), "a & <b>&amp;</b> c");
+    }
+
+    #[test]
+    fn insert_xml_too_deep() {
+        let doc = Doc::with_client_id(1);
+        let f = doc.get_or_insert_xml_fragment("xml");
+        let mut txn = doc.transact_mut();
+        let nested = |depth: usize| "<a>".repeat(depth) + &"</a>".repeat(depth);
+        f.insert_xml(&mut txn, 0, &nested(128)).unwrap();
+        assert_eq!(f.len(&txn), 1);
+        assert_eq!(
+            f.insert_xml(&mut txn, 0, &nested(100_000)),
+            Err(XmlParseError::TooDeep { pos: 128 * 3 })
+        );
+        assert_eq!(f.len(&txn), 1);
+    }
+
     #[test]
     fn insert_attribute() {
         let d1 = Doc::with_client_id(1);
@@ -925,6 +925,7 @@ mod test {
     use std::sync::atomic::{AtomicUsize, Ordering};
     use std::sync::Arc;
 
+    use crate::sync::time::ManualClock;
     use crate::test_utils::exchange_updates;
     use crate::types::text::{Diff, YChange};
     use crate::types::{Attrs, ToJson};
@@ -936,6 +937,30 @@ mod test {
         XmlElementRef, XmlFragment, XmlTextPrelim,
     };
 
+    #[test]
+    fn undo_capture_timeout_with_manual_clock() {
+        let doc = Doc::with_client_id(1);
+        let txt = doc.get_or_insert_text("test");
+        let clock = ManualClock::new(1000);
+        let mut mgr = UndoManager::with_scope_and_options(&doc, &txt, {
+            let mut o = Options::default();
+            o.timestamp = Arc::new(clock.clone());
+            o
+        });
+
+        txt.insert(&mut doc.transact_mut(), 0, "a");
+        clock.advance(100);
+        txt.insert(&mut doc.transact_mut(), 1, "b");
+        // changes made within capture timeout are grouped together
+        clock.advance(1000);
+        txt.insert(&mut doc.transact_mut(), 2, "c");
+
+        mgr.undo().unwrap();
+        assert_eq!(txt.get_string(&doc.transact()), "ab");
+        mgr.undo().unwrap();
+        assert_eq!(txt.get_string(&doc.transact()), "");
+    }
+
     #[test]
     fn undo_text() {
         let d1 = Doc::with_client_id(1);
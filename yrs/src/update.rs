@@ -1,28 +1,30 @@
 use std::cmp::Ordering;
 use std::collections::hash_map::Entry;
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::hash::BuildHasherDefault;
 use std::sync::Arc;
 
 use crate::block::{
-    BlockRange, ClientID, Item, ItemContent, ItemPtr, BLOCK_GC_REF_NUMBER, BLOCK_SKIP_REF_NUMBER,
-    HAS_ORIGIN, HAS_PARENT_SUB, HAS_RIGHT_ORIGIN,
+    BlockRange, ClientID, Item, ItemContent, ItemPtr, BLOCK_GC_REF_NUMBER,
+    BLOCK_ITEM_DELETED_REF_NUMBER, BLOCK_ITEM_STRING_REF_NUMBER, BLOCK_SKIP_REF_NUMBER, HAS_ORIGIN,
+    HAS_PARENT_SUB, HAS_RIGHT_ORIGIN,
 };
-use crate::encoding::read::Error;
-use crate::id_set::DeleteSet;
+use crate::encoding::read::{Cursor, Error};
+use crate::id_set::{DeleteSet, DeleteSetDivergence, IdSet};
+use crate::quota::SizeDelta;
 use crate::slice::ItemSlice;
-#[cfg(test)]
 use crate::store::Store;
 use crate::transaction::TransactionMut;
 use crate::types::TypePtr;
-use crate::updates::decoder::{Decode, Decoder};
+use crate::updates::decoder::{Decode, Decoder, DecoderV1, DecoderV2};
 use crate::updates::encoder::{Encode, Encoder};
 use crate::utils::client_hasher::ClientHasher;
 use crate::{OffsetKind, StateVector, ID};
+use thiserror::Error;
 
 #[derive(Debug, Default, PartialEq)]
 pub(crate) struct UpdateBlocks {
-    clients: HashMap<ClientID, VecDeque<BlockCarrier>, BuildHasherDefault<ClientHasher>>,
+    pub(crate) clients: HashMap<ClientID, VecDeque<BlockCarrier>, BuildHasherDefault<ClientHasher>>,
 }
 
 impl UpdateBlocks {
@@ -39,6 +41,17 @@ impl UpdateBlocks {
         self.clients.is_empty()
     }
 
+    /// Returns a number of clients which blocks are included in current update.
+    #[cfg(feature = "tracing")]
+    pub(crate) fn clients_len(&self) -> usize {
+        self.clients.len()
+    }
+
+    /// Returns a total number of blocks included in current update.
+    pub(crate) fn len(&self) -> usize {
+        self.clients.values().map(|blocks| blocks.len()).sum()
+    }
+
     /// Returns an iterator that allows a traversal of all of the blocks
     /// which consist into this [Update].
     pub(crate) fn blocks(&self) -> Blocks<'_> {
@@ -107,12 +120,287 @@ impl Update {
     pub fn state_vector(&self) -> StateVector {
         let mut sv = StateVector::default();
         for (&client, blocks) in self.blocks.clients.iter() {
-            let last_id = blocks[blocks.len() - 1].last_id();
-            sv.set_max(client, last_id.clock + 1);
+            if let Some(last) = blocks.back() {
+                sv.set_max(client, last.last_id().clock + 1);
+            }
         }
         sv
     }
 
+    /// Checks if all information carried by current update is already known to a document
+    /// described by a given state vector `sv`, so that applying this update would have no effect.
+    /// This is useful for cheaply dropping duplicated retransmissions.
+    ///
+    /// Since state vectors don't carry information about deletions, updates with non-empty delete
+    /// set are never considered a subset.
+    pub fn is_subset_of(&self, sv: &StateVector) -> bool {
+        if !self.delete_set.is_empty() {
+            return false;
+        }
+        self.blocks.clients.iter().all(|(client, blocks)| {
+            let clock = sv.get(client);
+            blocks
+                .iter()
+                .filter(|block| !block.is_skip())
+                .all(|block| block.id().clock + block.len() <= clock)
+        })
+    }
+
+    /// Encoded counterpart of [Update::is_subset_of]. Instead of building the update, it only
+    /// reads clock ranges of the blocks of every client. The whole input is always read, so that
+    /// truncated or malformed updates are reported as errors.
+    pub(crate) fn is_encoded_subset_of<D: Decoder>(
+        decoder: &mut D,
+        sv: &StateVector,
+    ) -> Result<bool, Error> {
+        let mut subset = true;
+        let clients_len: u32 = decoder.read_var()?;
+        for _ in 0..clients_len {
+            let blocks_len: u32 = decoder.read_var()?;
+            let client = decoder.read_client()?;
+            let known = sv.get(&client);
+            let mut clock: u32 = decoder.read_var()?;
+            for _ in 0..blocks_len {
+                let (len, skip) = Self::decode_block_len(decoder)?;
+                let end = clock.checked_add(len).ok_or(Error::UnexpectedValue)?;
+                if !skip && len > 0 && end > known {
+                    subset = false;
+                }
+                clock = end;
+            }
+        }
+        let delete_set = DeleteSet::decode(decoder)?;
+        Ok(subset && delete_set.is_empty())
+    }
+
+    /// Reads a single block, returning its length and whether it's a skip block. Unlike
+    /// [Update::decode_block] it doesn't build the block, and string contents are not copied.
+    fn decode_block_len<D: Decoder>(decoder: &mut D) -> Result<(u32, bool), Error> {
+        let info = decoder.read_info()?;
+        match info {
+            BLOCK_SKIP_REF_NUMBER => Ok((decoder.read_var()?, true)),
+            BLOCK_GC_REF_NUMBER => Ok((decoder.read_len()?, false)),
+            info => {
+                let cant_copy_parent_info = info & (HAS_ORIGIN | HAS_RIGHT_ORIGIN) == 0;
+                if info & HAS_ORIGIN != 0 {
+                    decoder.read_left_id()?;
+                }
+                if info & HAS_RIGHT_ORIGIN != 0 {
+                    decoder.read_right_id()?;
+                }
+                if cant_copy_parent_info {
+                    if decoder.read_parent_info()? {
+                        decoder.read_string()?;
+                    } else {
+                        decoder.read_left_id()?;
+                    }
+                    if info & HAS_PARENT_SUB != 0 {
+                        decoder.read_string()?;
+                    }
+                }
+                let len = match info & 0b1111 {
+                    BLOCK_ITEM_STRING_REF_NUMBER => {
+                        decoder.read_string()?.encode_utf16().count() as u32
+                    }
+                    BLOCK_ITEM_DELETED_REF_NUMBER => decoder.read_len()?,
+                    _ => ItemContent::decode(decoder, info)?.len(OffsetKind::Utf16),
+                };
+                Ok((len, false))
+            }
+        }
+    }
+
+    /// Returns exact ID ranges, which current update depends on, but which are neither present in
+    /// a document described by a given `state` vector nor included within this update itself.
+    ///
+    /// If returned set is not empty, this update cannot be integrated in full: blocks depending on
+    /// missing ranges would be stashed as pending until these arrive. Sync layers can use this
+    /// information to request retransmission of specific ranges from the remote peer.
+    pub fn missing_ranges(&self, state: &StateVector) -> IdSet {
+        let mut missing = IdSet::new();
+        // clocks up to which all data is either known to the document or covered by this update
+        let mut available = state.clone();
+        for (&client, blocks) in self.blocks.clients.iter() {
+            for block in blocks.iter() {
+                if block.is_skip() {
+                    continue;
+                }
+                let id = block.id();
+                let clock = available.get(&client);
+                if id.clock > clock {
+                    missing.insert(ID::new(client, clock), id.clock - clock);
+                } else {
+                    available.set_max(client, id.clock + block.len());
+                }
+            }
+        }
+
+        let mut require = |id: &ID| {
+            let clock = available.get(&id.client);
+            if id.clock >= clock {
+                missing.insert(ID::new(id.client, clock), id.clock + 1 - clock);
+            }
+        };
+        for blocks in self.blocks.clients.values() {
+            for block in blocks.iter() {
+                if let BlockCarrier::Item(item) = block {
+                    Self::dependencies(item, &mut require);
+                }
+            }
+        }
+
+        for (&client, range) in self.delete_set.iter() {
+            let clock = available.get(&client);
+            for r in range.iter() {
+                if r.end > clock {
+                    let start = r.start.max(clock);
+                    missing.insert(ID::new(client, start), r.end - start);
+                }
+            }
+        }
+
+        missing.squash();
+        missing
+    }
+
+    /// Cross-checks current update's delete set against the blocks it contains: every block
+    /// encoded as deleted (or garbage collected) is expected to be included in the delete set.
+    /// Since an update is allowed to delete blocks, which contents are still encoded (or not
+    /// included in it at all), [DeleteSetDivergence::not_deleted] is always empty.
+    pub fn check_delete_set(&self) -> DeleteSetDivergence {
+        let mut deleted = IdSet::new();
+        for block in self.blocks.blocks() {
+            let is_deleted = match block {
+                BlockCarrier::Item(item) => matches!(item.content, ItemContent::Deleted(_)),
+                BlockCarrier::GC(_) => true,
+                BlockCarrier::Skip(_) => false,
+            };
+            if is_deleted {
+                deleted.insert(*block.id(), block.len());
+            }
+        }
+        let mut ds = IdSet::new();
+        for (&client, range) in self.delete_set.iter() {
+            ds.insert_range(client, range.clone());
+        }
+        DeleteSetDivergence {
+            not_deleted: IdSet::new(),
+            not_listed: deleted.difference(&ds),
+        }
+    }
+
+    /// Returns a first found reference from a block in current update (as a pair of block ID and
+    /// referenced ID), which can never be satisfied, if an update would be applied onto
+    /// a given `store`. These are:
+    ///
+    /// - References to a block of the same client, which has a clock value equal or higher than
+    ///   the referencing block itself, as blocks of every client are integrated in order.
+    /// - References to clients neither known to a document, nor present in its pending update
+    ///   or current update itself.
+    pub(crate) fn dangling_reference(&self, store: &Store) -> Option<(ID, ID)> {
+        let is_known = |client: &ClientID| {
+            self.blocks.clients.contains_key(client)
+                || store.blocks.get_clock(client) > 0
+                || store
+                    .pending
+                    .as_ref()
+                    .is_some_and(|p| p.update.blocks.clients.contains_key(client))
+        };
+        for blocks in self.blocks.clients.values() {
+            for block in blocks.iter() {
+                if let BlockCarrier::Item(item) = block {
+                    let mut dangling = None;
+                    Self::dependencies(item, &mut |dep: &ID| {
+                        if dangling.is_none()
+                            && ((dep.client == item.id.client && dep.clock >= item.id.clock)
+                                || !is_known(&dep.client))
+                        {
+                            dangling = Some(*dep);
+                        }
+                    });
+                    if let Some(dep) = dangling {
+                        return Some((item.id, dep));
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Computes how much a document owning a given `store` would grow if current update was
+    /// integrated into it. Blocks and their parts already present in a store are not counted.
+    pub(crate) fn size_delta(&self, store: &Store) -> SizeDelta {
+        let mut delta = SizeDelta {
+            current_len: store
+                .blocks
+                .get_state_vector()
+                .iter()
+                .map(|(_, &clock)| clock as u64)
+                .sum(),
+            ..SizeDelta::default()
+        };
+        for (client, blocks) in self.blocks.clients.iter() {
+            let clock = store.blocks.get_clock(client);
+            for block in blocks.iter() {
+                if let BlockCarrier::Skip(_) = block {
+                    continue;
+                }
+                let start = block.id().clock;
+                let end = start + block.len();
+                if end > clock {
+                    delta.blocks += 1;
+                    delta.len += (end - start.max(clock)) as u64;
+                }
+            }
+        }
+        delta
+    }
+
+    /// Calls `f` for every [ID] a given `item` refers to and therefore needs to be present in
+    /// the block store before that item can be integrated.
+    fn dependencies<F>(item: &Item, f: &mut F)
+    where
+        F: FnMut(&ID),
+    {
+        if let Some(origin) = &item.origin {
+            f(origin);
+        }
+        if let Some(right_origin) = &item.right_origin {
+            f(right_origin);
+        }
+        match &item.parent {
+            TypePtr::ID(parent_id) => f(parent_id),
+            TypePtr::Branch(parent) => {
+                if let Some(block) = &parent.item {
+                    f(block.id());
+                }
+            }
+            _ => {}
+        }
+        match &item.content {
+            ItemContent::Move(m) => {
+                if let Some(start) = m.start.id() {
+                    f(start);
+                }
+                if let Some(end) = m.end.id() {
+                    f(end);
+                }
+            }
+            #[cfg(feature = "weak")]
+            ItemContent::Type(branch) => {
+                if let crate::types::TypeRef::WeakLink(source) = &branch.type_ref {
+                    if let Some(start) = source.quote_start.id() {
+                        f(start);
+                    }
+                    if let Some(end) = source.quote_end.id() {
+                        f(end);
+                    }
+                }
+            }
+            _ => { /* do nothing */ }
+        }
+    }
+
     /// Merges another update into current one. Their blocks are deduplicated and reordered.
     pub fn merge(&mut self, other: Self) {
         for (client, other_blocks) in other.blocks.clients {
@@ -713,8 +1001,64 @@ impl Encode for Update {
 
 impl Decode for Update {
     fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, Error> {
+        Self::decode_with_limits(decoder, &UpdateLimits::UNLIMITED)
+    }
+
+    fn decode_v1(data: &[u8]) -> Result<Self, Error> {
+        Self::decode_v1_with_limits(data, &UpdateLimits::UNLIMITED)
+    }
+
+    fn decode_v2(data: &[u8]) -> Result<Self, Error> {
+        Self::decode_v2_with_limits(data, &UpdateLimits::UNLIMITED)
+    }
+}
+
+impl Update {
+    /// Decodes an [Update] using lib0 v1 encoding, failing with [Error::LimitExceeded] as soon
+    /// as any of the provided `limits` is violated.
+    pub fn decode_v1_with_limits(data: &[u8], limits: &UpdateLimits) -> Result<Self, Error> {
+        let mut decoder = DecoderV1::from(data);
+        let update = Self::decode_with_limits(&mut decoder, limits)?;
+        #[cfg(feature = "tracing")]
+        update.trace_decoded(data.len(), 1);
+        Ok(update)
+    }
+
+    /// Decodes an [Update] using lib0 v2 encoding, failing with [Error::LimitExceeded] as soon
+    /// as any of the provided `limits` is violated.
+    pub fn decode_v2_with_limits(data: &[u8], limits: &UpdateLimits) -> Result<Self, Error> {
+        let mut decoder = DecoderV2::new(Cursor::new(data))?;
+        let update = Self::decode_with_limits(&mut decoder, limits)?;
+        #[cfg(feature = "tracing")]
+        update.trace_decoded(data.len(), 2);
+        Ok(update)
+    }
+
+    #[cfg(feature = "tracing")]
+    fn trace_decoded(&self, bytes: usize, version: u8) {
+        tracing::debug!(
+            bytes,
+            version,
+            clients = self.blocks.clients_len(),
+            blocks = self.blocks.len(),
+            "update decoded"
+        );
+    }
+
+    /// Decodes an [Update], failing with [Error::LimitExceeded] as soon as any of the provided
+    /// `limits` is violated. This allows to reject crafted updates before they are integrated
+    /// into a document. String length limit is configured on a given `decoder` with
+    /// [Decoder::set_max_len], so it also applies to anything read by it afterwards.
+    pub fn decode_with_limits<D: Decoder>(
+        decoder: &mut D,
+        limits: &UpdateLimits,
+    ) -> Result<Self, Error> {
+        decoder.set_max_len(limits.max_string_len);
         // read blocks
         let clients_len: u32 = decoder.read_var()?;
+        if clients_len as usize > limits.max_clients {
+            return Err(Error::LimitExceeded(LimitExceeded::Clients));
+        }
         let mut clients = HashMap::with_hasher(BuildHasherDefault::default());
         clients.try_reserve(clients_len as usize)?;
 
@@ -724,30 +1068,157 @@ impl Decode for Update {
 
             let client = decoder.read_client()?;
             let mut clock: u32 = decoder.read_var()?;
-            let blocks = blocks
+            let client_blocks = blocks
                 .clients
                 .entry(client)
                 .or_insert_with(|| VecDeque::new());
             // Attempt to pre-allocate memory for the blocks. If the capacity overflows and
             // allocation fails, return an error.
-            blocks.try_reserve(blocks_len)?;
+            client_blocks.try_reserve(blocks_len)?;
 
             for _ in 0..blocks_len {
                 let id = ID::new(client, clock);
                 if let Some(block) = Self::decode_block(id, decoder)? {
                     // due to bug in the past it was possible for empty bugs to be generated
                     // even though they had no effect on the document store
-                    clock += block.len();
-                    blocks.push_back(block);
+                    clock = clock
+                        .checked_add(block.len())
+                        .ok_or(Error::UnexpectedValue)?;
+                    client_blocks.push_back(block);
                 }
             }
+            if client_blocks.is_empty() {
+                // client entries without any blocks would stop the integration loop early
+                blocks.clients.remove(&client);
+            }
         }
         // read delete set
-        let delete_set = DeleteSet::decode(decoder)?;
-        Ok(Update { blocks, delete_set })
+        let delete_set = DeleteSet::decode_limited(decoder, limits.max_delete_ranges)?;
+        let update = Update { blocks, delete_set };
+        if limits.max_depth != usize::MAX {
+            update.check_depth(limits.max_depth)?;
+        }
+        Ok(update)
+    }
+
+    /// Checks if shared types defined within current update are not nested deeper than
+    /// `max_depth`. Since update doesn't carry information about the document it will be applied
+    /// to, types with parents outside of current update are considered to be root-level ones.
+    fn check_depth(&self, max_depth: usize) -> Result<(), LimitExceeded> {
+        // depths of the shared types, given items are located in, root-level types have depth 1
+        let mut depths: HashMap<ID, usize> = HashMap::new();
+        let mut visiting: HashSet<ID> = HashSet::new();
+        let mut stack: Vec<&Item> = Vec::new();
+        for blocks in self.blocks.clients.values() {
+            for block in blocks.iter() {
+                let item = match block {
+                    BlockCarrier::Item(item) if matches!(item.content, ItemContent::Type(_)) => {
+                        item.as_ref()
+                    }
+                    _ => continue,
+                };
+                // resolve item depth without recursion, as it could overflow the call stack
+                stack.push(item);
+                while let Some(&top) = stack.last() {
+                    if depths.contains_key(&top.id) {
+                        stack.pop();
+                        continue;
+                    }
+                    let (dep, nested) = match &top.parent {
+                        TypePtr::ID(id) => (self.find_item(id), true),
+                        TypePtr::Unknown => {
+                            let origin = top.origin.as_ref().or(top.right_origin.as_ref());
+                            (origin.and_then(|id| self.find_item(id)), false)
+                        }
+                        _ => (None, false),
+                    };
+                    let depth = match dep {
+                        Some(dep) if nested && !matches!(dep.content, ItemContent::Type(_)) => {
+                            Some(1)
+                        }
+                        Some(dep) => match depths.get(&dep.id) {
+                            Some(&depth) if nested => Some(depth.saturating_add(1)),
+                            Some(&depth) => Some(depth),
+                            // dependency cycle: this update is malformed anyway
+                            None if visiting.contains(&dep.id) => Some(1),
+                            None => {
+                                visiting.insert(dep.id);
+                                stack.push(dep);
+                                None
+                            }
+                        },
+                        None => Some(1),
+                    };
+                    if let Some(depth) = depth {
+                        depths.insert(top.id, depth);
+                        stack.pop();
+                    }
+                }
+                if depths[&item.id] >= max_depth {
+                    return Err(LimitExceeded::Depth);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn find_item(&self, id: &ID) -> Option<&Item> {
+        let blocks = self.blocks.clients.get(&id.client)?;
+        let idx = blocks
+            .partition_point(|block| block.id().clock <= id.clock)
+            .checked_sub(1)?;
+        match &blocks[idx] {
+            BlockCarrier::Item(item) if id.clock < item.id.clock + item.len => Some(item),
+            _ => None,
+        }
+    }
+}
+
+/// Hard limits enforced by [Update::decode_with_limits], which protect against crafted updates
+/// trying to exhaust server resources.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpdateLimits {
+    /// Max nesting depth of shared types defined by an update. Root-level types have depth of 1.
+    pub max_depth: usize,
+    /// Max length (in bytes) of every string and binary buffer read from an update: text chunks,
+    /// binary contents, root type names, map keys, formatting attributes and strings or buffers
+    /// nested in JSON-like values.
+    pub max_string_len: usize,
+    /// Max number of clients, which blocks are included in an update.
+    pub max_clients: usize,
+    /// Max number of clock ranges stored in an update's delete set.
+    pub max_delete_ranges: usize,
+}
+
+impl UpdateLimits {
+    /// Limits which don't restrict decoded updates in any way.
+    pub const UNLIMITED: Self = UpdateLimits {
+        max_depth: usize::MAX,
+        max_string_len: usize::MAX,
+        max_clients: usize::MAX,
+        max_delete_ranges: usize::MAX,
+    };
+}
+
+impl Default for UpdateLimits {
+    fn default() -> Self {
+        Self::UNLIMITED
     }
 }
 
+/// Resource limit violated while decoding an update. See [UpdateLimits].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum LimitExceeded {
+    #[error("shared types are nested too deeply")]
+    Depth,
+    #[error("string content is too long")]
+    StringLength,
+    #[error("too many clients")]
+    Clients,
+    #[error("too many delete set ranges")]
+    DeleteRanges,
+}
+
 /// Similar to [Peekable], but can be used in situation when [Peekable::peek] is not allowed
 /// due to a lack of of `&mut self` reference. [Memo] can be proactively advanced using
 /// [Memo::advance] which works similar to [Peekable::peek], but later peeked element can still be
@@ -931,6 +1402,58 @@ impl Encode for BlockCarrier {
     }
 }
 
+/// Summary of data skipped by [TransactionMut::apply_updates_lenient].
+#[derive(Debug, Default)]
+pub struct RecoveryReport {
+    /// Indexes of updates that couldn't be decoded, together with corresponding errors.
+    pub corrupted: Vec<(usize, Error)>,
+    /// Indexes of updates that have been rejected (ie. by a quota hook), together with
+    /// corresponding errors.
+    pub rejected: Vec<(usize, UpdateError)>,
+    /// Indexes of updates, which changes have been partially denied by an access policy,
+    /// together with IDs of their first denied changes.
+    pub denied: Vec<(usize, ID)>,
+    /// IDs of blocks which couldn't be integrated, because their dependencies were missing.
+    pub skipped_blocks: IdSet,
+    /// Deletions which couldn't be applied, because deleted blocks were missing.
+    pub skipped_deletes: DeleteSet,
+}
+
+impl RecoveryReport {
+    /// Returns true if all updates have been applied in full.
+    pub fn is_clean(&self) -> bool {
+        self.corrupted.is_empty()
+            && self.rejected.is_empty()
+            && self.denied.is_empty()
+            && self.skipped_blocks.is_empty()
+            && self.skipped_deletes.is_empty()
+    }
+}
+
+/// Error returned when an [Update] could not be applied onto a document.
+#[derive(Debug, Error)]
+pub enum UpdateError {
+    /// Update refers to blocks, which are neither present in a document store nor included in
+    /// the update itself. Contains exact ID ranges, that need to be delivered first.
+    #[error("update depends on blocks missing from the document: {0}")]
+    MissingBlocks(IdSet),
+    /// Block of an update refers to another block, which can never be delivered. This usually
+    /// means that an update has been produced by a faulty encoder.
+    #[error("block {block} refers to {reference} which can never be integrated")]
+    DanglingReference { block: ID, reference: ID },
+    /// Update has been rejected by a quota hook configured with [Doc::set_quota_hook].
+    ///
+    /// [Doc::set_quota_hook]: crate::Doc::set_quota_hook
+    #[error("update adding {} elements in {} blocks has been rejected by a quota hook", .0.len, .0.blocks)]
+    QuotaExceeded(SizeDelta),
+    /// Update contains a change of block with a given ID, which has been denied by an access
+    /// policy configured with [Doc::set_access_policy].
+    ///
+    /// [Doc::set_access_policy]: crate::Doc::set_access_policy
+    #[error("change of block {0} has been denied by access policy")]
+    AccessDenied(ID),
+}
+
 /// A pending update which contains unapplied blocks from the update which created it.
 #[derive(Debug, PartialEq)]
 pub struct PendingUpdate {
@@ -1072,12 +1595,17 @@ mod test {
 
     use crate::block::{Item, ItemContent};
     use crate::encoding::read::Cursor;
+    use crate::encoding::read::Error;
+    use crate::id_set::DeleteSet;
     use crate::types::{Delta, TypePtr};
-    use crate::update::{BlockCarrier, Update};
+    use crate::update::{BlockCarrier, LimitExceeded, Update, UpdateError, UpdateLimits};
     use crate::updates::decoder::{Decode, DecoderV1};
+    use crate::updates::encoder::Encode;
     use crate::{
-        Doc, GetString, Options, ReadTxn, StateVector, Text, Transact, XmlFragment, XmlOut, ID,
+        any, Array, ArrayPrelim, Doc, GetString, IdSet, Map, Options, ReadTxn, StateVector, Text,
+        Transact, XmlFragment, XmlOut, ID,
     };
+    use assert_matches2::assert_matches;
 
     #[test]
     fn update_decode() {
@@ -1332,6 +1860,406 @@ mod test {
         assert_eq!(str, "nenor");
     }
 
+    #[test]
+    fn try_apply_update_with_missing_blocks() {
+        let d1 = Doc::with_client_id(1);
+        let txt = d1.get_or_insert_text("test");
+        let u1 = {
+            let mut txn = d1.transact_mut();
+            txt.insert(&mut txn, 0, "abc");
+            txn.encode_update_v1()
+        };
+        let u2 = {
+            let mut txn = d1.transact_mut();
+            txt.insert(&mut txn, 3, "def");
+            txn.encode_update_v1()
+        };
+        let u3 = {
+            let mut txn = d1.transact_mut();
+            txt.remove_range(&mut txn, 1, 4);
+            txn.encode_update_v1()
+        };
+
+        let d2 = Doc::with_client_id(2);
+        let txt2 = d2.get_or_insert_text("test");
+        let mut txn = d2.transact_mut();
+
+        // update 2 depends on blocks from update 1
+        let err = txn.try_apply_update(decode_update(&u2)).unwrap_err();
+        assert_matches!(err, UpdateError::MissingBlocks(missing));
+        let mut expected = IdSet::new();
+        expected.insert(ID::new(1, 0), 3);
+        assert_eq!(missing, expected);
+        // rejected update should not be stashed in pending state
+        assert!(txn.store().pending_update().is_none());
+        assert_eq!(txn.state_vector(), StateVector::default());
+
+        // deletions are also reported
+        let err = txn.try_apply_update(decode_update(&u3)).unwrap_err();
+        assert_matches!(err, UpdateError::MissingBlocks(missing));
+        let mut expected = IdSet::new();
+        expected.insert(ID::new(1, 1), 4);
+        assert_eq!(missing, expected);
+
+        txn.try_apply_update(decode_update(&u1)).unwrap();
+        txn.try_apply_update(decode_update(&u2)).unwrap();
+        txn.try_apply_update(decode_update(&u3)).unwrap();
+        assert_eq!(txt2.get_string(&txn), "af");
+    }
+
+    #[test]
+    fn missing_ranges_of_dependencies() {
+        let d1 = Doc::with_client_id(1);
+        let txt1 = d1.get_or_insert_text("test");
+        let d2 = Doc::with_client_id(2);
+        let txt2 = d2.get_or_insert_text("test");
+
+        let u1 = {
+            let mut txn = d1.transact_mut();
+            txt1.insert(&mut txn, 0, "hello");
+            txn.encode_update_v1()
+        };
+        d2.transact_mut().apply_update(decode_update(&u1));
+        let u2 = {
+            let mut txn = d2.transact_mut();
+            txt2.insert(&mut txn, 2, "--");
+            txn.encode_update_v1()
+        };
+
+        // update from client 2 has origin pointing to client 1 block
+        let update = decode_update(&u2);
+        let mut expected = IdSet::new();
+        expected.insert(ID::new(1, 0), 3);
+        assert_eq!(update.missing_ranges(&StateVector::default()), expected);
+
+        let sv = d1.transact().state_vector();
+        assert!(update.missing_ranges(&sv).is_empty());
+    }
+
+    #[test]
+    fn malformed_update_parent_not_a_type() {
+        let doc = Doc::with_client_id(1);
+        let txt = doc.get_or_insert_text("text");
+        txt.insert(&mut doc.transact_mut(), 0, "abc");
+
+        // block (2,0) with parent pointing to a string block (1,0)
+        let bin = [1, 1, 2, 0, 4, 0, 1, 0, 1, b'x', 0];
+        let mut txn = doc.transact_mut();
+        txn.apply_update(decode_update(&bin));
+        assert_eq!(txt.get_string(&txn), "abc");
+        assert_eq!(txn.state_vector().get(&2), 1);
+    }
+
+    #[test]
+    fn malformed_update_forward_origin() {
+        let doc = Doc::with_client_id(1);
+        let txt = doc.get_or_insert_text("text");
+
+        // block (2,0) with left origin (2,10) pointing past its own client clock
+        let bin = [1, 1, 2, 0, 4 | 0x80, 2, 10, 1, b'x', 0];
+        let mut txn = doc.transact_mut();
+        txn.apply_update(decode_update(&bin));
+        assert_eq!(txt.get_string(&txn), "");
+    }
+
+    #[test]
+    fn malformed_update_empty_client_entry() {
+        // client 9 without any blocks, followed by block (1,0) inserting "a" into root "text"
+        let bin = [
+            2, 0, 9, 0, 1, 1, 0, 4, 1, 4, b't', b'e', b'x', b't', 1, b'a', 0,
+        ];
+        let update = decode_update(&bin);
+        let mut expected = StateVector::default();
+        expected.set_max(1, 1);
+        assert_eq!(update.state_vector(), expected);
+
+        let doc = Doc::with_client_id(2);
+        let txt = doc.get_or_insert_text("text");
+        let mut txn = doc.transact_mut();
+        txn.apply_update(update);
+        assert_eq!(txt.get_string(&txn), "a");
+    }
+
+    #[test]
+    fn malformed_update_clock_overflow() {
+        // delete set range starting at u32::MAX
+        let bin = [0, 1, 1, 1, 0xff, 0xff, 0xff, 0xff, 0x0f, 5];
+        assert!(Update::decode_v1(&bin).is_err());
+    }
+
+    #[test]
+    fn decode_with_nesting_depth_limit() {
+        let doc = Doc::with_client_id(1);
+        let root = doc.get_or_insert_array("array");
+        {
+            let mut txn = doc.transact_mut();
+            root.push_back(&mut txn, 1);
+            // nested types placed after other elements have only origin pointers
+            let a = root.push_back(&mut txn, ArrayPrelim::default());
+            a.push_back(&mut txn, 2);
+            let b = a.push_back(&mut txn, ArrayPrelim::default());
+            b.push_back(&mut txn, ArrayPrelim::default());
+        }
+        let bin = doc
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+
+        let limits = UpdateLimits {
+            max_depth: 3,
+            ..UpdateLimits::default()
+        };
+        let err = Update::decode_v1_with_limits(&bin, &limits).unwrap_err();
+        assert!(matches!(err, Error::LimitExceeded(LimitExceeded::Depth)));
+        let limits = UpdateLimits {
+            max_depth: 4,
+            ..UpdateLimits::default()
+        };
+        Update::decode_v1_with_limits(&bin, &limits).unwrap();
+    }
+
+    #[test]
+    fn decode_with_size_limits() {
+        let d1 = Doc::with_client_id(1);
+        let t1 = d1.get_or_insert_text("t");
+        t1.insert(&mut d1.transact_mut(), 0, "hello world");
+        t1.remove_range(&mut d1.transact_mut(), 1, 2);
+        t1.remove_range(&mut d1.transact_mut(), 4, 2);
+        let d2 = Doc::with_client_id(2);
+        let t2 = d2.get_or_insert_text("t");
+        t2.insert(&mut d2.transact_mut(), 0, "abc");
+        let u2 = d2
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+        d1.transact_mut().apply_update(decode_update(&u2));
+        let bin = d1
+            .transact()
+            .encode_state_as_update_v2(&StateVector::default());
+
+        let check = |limits: UpdateLimits| match Update::decode_v2_with_limits(&bin, &limits) {
+            Err(Error::LimitExceeded(e)) => Some(e),
+            Err(e) => panic!("unexpected error: {}", e),
+            Ok(_) => None,
+        };
+        let defaults = UpdateLimits::default();
+        assert_eq!(check(defaults.clone()), None);
+        let limits = UpdateLimits {
+            max_clients: 1,
+            ..defaults.clone()
+        };
+        assert_eq!(check(limits), Some(LimitExceeded::Clients));
+        let limits = UpdateLimits {
+            max_string_len: 2,
+            ..defaults.clone()
+        };
+        assert_eq!(check(limits), Some(LimitExceeded::StringLength));
+        let limits = UpdateLimits {
+            max_delete_ranges: 1,
+            ..defaults.clone()
+        };
+        assert_eq!(check(limits), Some(LimitExceeded::DeleteRanges));
+        let limits = UpdateLimits {
+            max_clients: 2,
+            max_string_len: 3,
+            max_delete_ranges: 2,
+            ..defaults
+        };
+        assert_eq!(check(limits), None);
+
+        // limit applies to strings and buffers nested in JSON-like values and to map keys
+        let d3 = Doc::with_client_id(3);
+        let map = d3.get_or_insert_map("m");
+        map.insert(&mut d3.transact_mut(), "k", any!({"nested": "value"}));
+        let any_bin = d3
+            .transact()
+            .encode_state_as_update_v2(&StateVector::default());
+        let d4 = Doc::with_client_id(4);
+        let map = d4.get_or_insert_map("m");
+        map.insert(&mut d4.transact_mut(), "long key", 1);
+        let key_bin = d4
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+        let d5 = Doc::with_client_id(5);
+        let array = d5.get_or_insert_array("a");
+        array.push_back(&mut d5.transact_mut(), vec![0u8; 8]);
+        let buf_bin = d5
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+        let limits = UpdateLimits {
+            max_string_len: 4,
+            ..UpdateLimits::default()
+        };
+        for (bin, v2) in [(&any_bin, true), (&key_bin, false), (&buf_bin, false)] {
+            let result = if v2 {
+                Update::decode_v2_with_limits(bin, &limits)
+            } else {
+                Update::decode_v1_with_limits(bin, &limits)
+            };
+            assert_matches!(
+                result,
+                Err(Error::LimitExceeded(LimitExceeded::StringLength))
+            );
+        }
+        let any_v1 = Update::decode_v2(&any_bin).unwrap().encode_v1();
+        assert_matches!(
+            Update::decode_v1_with_limits(&any_v1, &limits),
+            Err(Error::LimitExceeded(LimitExceeded::StringLength))
+        );
+        let limits = UpdateLimits {
+            max_string_len: 8,
+            ..UpdateLimits::default()
+        };
+        Update::decode_v2_with_limits(&any_bin, &limits).unwrap();
+        Update::decode_v1_with_limits(&key_bin, &limits).unwrap();
+        Update::decode_v1_with_limits(&buf_bin, &limits).unwrap();
+    }
+
+    #[test]
+    fn decode_with_limits_rejects_before_allocation() {
+        // delete set declaring u32::MAX ranges for a single client
+        let bin = [0, 1, 1, 0xff, 0xff, 0xff, 0xff, 0x0f];
+        let limits = UpdateLimits {
+            max_delete_ranges: 1000,
+            ..UpdateLimits::default()
+        };
+        let err = Update::decode_v1_with_limits(&bin, &limits).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::LimitExceeded(LimitExceeded::DeleteRanges)
+        ));
+    }
+
+    #[test]
+    fn apply_updates_lenient() {
+        let d1 = Doc::with_client_id(1);
+        let txt = d1.get_or_insert_text("test");
+        let mut updates = Vec::new();
+        for (idx, chunk) in ["abc", "def", "ghi"].into_iter().enumerate() {
+            let mut txn = d1.transact_mut();
+            txt.insert(&mut txn, 3 * idx as u32, chunk);
+            updates.push(txn.encode_update_v1());
+        }
+        {
+            let mut txn = d1.transact_mut();
+            txt.remove_range(&mut txn, 4, 1);
+            updates.push(txn.encode_update_v1());
+        }
+        // replace 2nd update with a corrupted one
+        updates[1] = vec![1, 1, 1, 3, 255];
+
+        let d2 = Doc::with_client_id(2);
+        let txt2 = d2.get_or_insert_text("test");
+        let mut txn = d2.transact_mut();
+        let report = txn.apply_updates_lenient_v1(&updates);
+
+        assert!(!report.is_clean());
+        assert_eq!(report.corrupted.len(), 1);
+        assert_eq!(report.corrupted[0].0, 1);
+        let mut expected = IdSet::new();
+        expected.insert(ID::new(1, 6), 3);
+        assert_eq!(report.skipped_blocks, expected);
+        let mut expected = DeleteSet::new();
+        expected.insert(ID::new(1, 4), 1);
+        assert_eq!(report.skipped_deletes, expected);
+
+        assert_eq!(txt2.get_string(&txn), "abc");
+        assert!(txn.store().pending_update().is_none());
+        assert!(txn.store().pending_ds().is_none());
+    }
+
+    #[test]
+    fn apply_updates_lenient_keeps_prior_pending_state() {
+        let d3 = Doc::with_client_id(3);
+        let txt3 = d3.get_or_insert_text("test");
+        let mut prior = Vec::new();
+        for (index, chunk) in ["w", "xy", "z"].into_iter().enumerate() {
+            let mut txn = d3.transact_mut();
+            txt3.insert(&mut txn, index as u32, chunk);
+            prior.push(txn.encode_update_v1());
+        }
+        {
+            let mut txn = d3.transact_mut();
+            txt3.remove_range(&mut txn, 1, 1);
+            prior.push(txn.encode_update_v1());
+        }
+
+        let d1 = Doc::with_client_id(1);
+        let txt1 = d1.get_or_insert_text("other");
+        let mut updates = Vec::new();
+        for chunk in ["abc", "def"] {
+            let mut txn = d1.transact_mut();
+            txt1.push(&mut txn, chunk);
+            updates.push(txn.encode_update_v1());
+        }
+
+        // block and deletion of client 3 are pending before lenient apply
+        let d2 = Doc::with_client_id(2);
+        let txt2 = d2.get_or_insert_text("test");
+        let other2 = d2.get_or_insert_text("other");
+        let mut txn = d2.transact_mut();
+        for update in [&prior[0], &prior[2], &prior[3]] {
+            txn.apply_update(Update::decode_v1(update).unwrap());
+        }
+        assert!(txn.store().pending_update().is_some());
+        assert!(txn.store().pending_ds().is_some());
+
+        let report = txn.apply_updates_lenient_v1(&updates[1..]);
+        let mut expected = IdSet::new();
+        expected.insert(ID::new(1, 3), 3);
+        assert_eq!(report.skipped_blocks, expected);
+        assert!(report.skipped_deletes.is_empty());
+        assert_eq!(other2.get_string(&txn), "");
+
+        // prior pending state is completed by a following update
+        txn.apply_update(Update::decode_v1(&prior[1]).unwrap());
+        assert_eq!(txt2.get_string(&txn), txt3.get_string(&d3.transact()));
+        assert!(txn.store().pending_update().is_none());
+        assert!(txn.store().pending_ds().is_none());
+    }
+
+    #[test]
+    fn apply_update_strict() {
+        let doc = Doc::with_client_id(1);
+        let txt = doc.get_or_insert_text("text");
+
+        // block (2,0) with left origin (2,10) pointing past its own client clock
+        let bin = [1, 1, 2, 0, 4 | 0x80, 2, 10, 1, b'x', 0];
+        let err = doc
+            .transact_mut()
+            .apply_update_strict(decode_update(&bin))
+            .unwrap_err();
+        assert_matches!(err, UpdateError::DanglingReference { block, reference });
+        assert_eq!(block, ID::new(2, 0));
+        assert_eq!(reference, ID::new(2, 10));
+
+        let d1 = Doc::with_client_id(7);
+        let t1 = d1.get_or_insert_text("text");
+        let u1 = {
+            let mut txn = d1.transact_mut();
+            t1.insert(&mut txn, 0, "abc");
+            txn.encode_update_v1()
+        };
+        let d2 = Doc::with_client_id(8);
+        let t2 = d2.get_or_insert_text("text");
+        d2.transact_mut().apply_update(decode_update(&u1));
+        let u2 = {
+            let mut txn = d2.transact_mut();
+            t2.insert(&mut txn, 3, "def");
+            txn.encode_update_v1()
+        };
+
+        // client 7 is not known to the document
+        let mut txn = doc.transact_mut();
+        let err = txn.apply_update_strict(decode_update(&u2)).unwrap_err();
+        assert_matches!(err, UpdateError::DanglingReference { block, reference });
+        assert_eq!(block, ID::new(8, 0));
+        assert_eq!(reference, ID::new(7, 2));
+        assert_eq!(txn.state_vector(), StateVector::default());
+
+        txn.apply_update_strict(decode_update(&u1)).unwrap();
+        txn.apply_update_strict(decode_update(&u2)).unwrap();
+        assert_eq!(txt.get_string(&txn), "abcdef");
+    }
+
     fn decode_update(bin: &[u8]) -> Update {
         Update::decode(&mut DecoderV1::new(Cursor::new(bin))).unwrap()
     }
@@ -1,5 +1,5 @@
 use crate::block::ClientID;
-use crate::encoding::read::{Cursor, Error, Read};
+use crate::encoding::read::{check_len, Cursor, Error, LimitedRead, Read};
 use crate::*;
 use std::sync::Arc;
 
@@ -70,16 +70,26 @@ pub trait Decoder: Read {
 
     /// Consume a rest of the decoded buffer data and return it without parsing.
     fn read_to_end(&mut self) -> Result<&[u8], Error>;
+
+    /// Sets the max length (in bytes) of every string and binary buffer read by current decoder
+    /// from now on, including keys and values nested in JSON-like data. Reading a longer one
+    /// fails with [LimitExceeded::StringLength](crate::update::LimitExceeded::StringLength).
+    /// Decoders which don't support limits ignore it.
+    fn set_max_len(&mut self, _max_len: usize) {}
 }
 
 /// Version 1 of lib0 decoder.
 pub struct DecoderV1<'a> {
     cursor: Cursor<'a>,
+    max_len: usize,
 }
 
 impl<'a> DecoderV1<'a> {
     pub fn new(cursor: Cursor<'a>) -> Self {
-        DecoderV1 { cursor }
+        DecoderV1 {
+            cursor,
+            max_len: usize::MAX,
+        }
     }
 
     fn read_id(&mut self) -> Result<ID, Error> {
@@ -111,6 +121,12 @@ impl<'a> Read for DecoderV1<'a> {
     fn read_exact(&mut self, len: usize) -> Result<&[u8], Error> {
         self.cursor.read_exact(len)
     }
+
+    fn read_buf(&mut self) -> Result<&[u8], Error> {
+        let len: u32 = self.cursor.read_var()?;
+        check_len(len as usize, self.max_len)?;
+        self.cursor.read_exact(len as usize)
+    }
 }
 
 impl<'a> Decoder for DecoderV1<'a> {
@@ -187,6 +203,11 @@ impl<'a> Decoder for DecoderV1<'a> {
     fn read_to_end(&mut self) -> Result<&[u8], Error> {
         Ok(&self.cursor.buf[self.cursor.next..])
     }
+
+    #[inline]
+    fn set_max_len(&mut self, max_len: usize) {
+        self.max_len = max_len;
+    }
 }
 
 /// Version 2 of lib0 decoder.
@@ -203,6 +224,7 @@ pub struct DecoderV2<'a> {
     parent_info_decoder: RleDecoder<'a>,
     type_ref_decoder: UIntOptRleDecoder<'a>,
     len_decoder: UIntOptRleDecoder<'a>,
+    max_len: usize,
 }
 
 impl<'a> DecoderV2<'a> {
@@ -240,6 +262,7 @@ impl<'a> DecoderV2<'a> {
             parent_info_decoder: RleDecoder::new(Cursor::new(parent_info_buf)),
             type_ref_decoder: UIntOptRleDecoder::new(Cursor::new(type_ref_buf)),
             len_decoder: UIntOptRleDecoder::new(Cursor::new(len_buf)),
+            max_len: usize::MAX,
         })
     }
 
@@ -288,9 +311,17 @@ impl<'a> Read for DecoderV2<'a> {
         self.cursor.read_u8()
     }
 
+    fn read_buf(&mut self) -> Result<&[u8], Error> {
+        let len: u32 = self.cursor.read_var()?;
+        check_len(len as usize, self.max_len)?;
+        self.cursor.read_exact(len as usize)
+    }
+
     #[inline]
     fn read_string(&mut self) -> Result<&str, Error> {
-        self.string_decoder.read_str()
+        let str = self.string_decoder.read_str()?;
+        check_len(str.len(), self.max_len)?;
+        Ok(str)
     }
 }
 
@@ -300,13 +331,24 @@ impl<'a> Decoder for DecoderV2<'a> {
     }
 
     fn read_ds_clock(&mut self) -> Result<u32, Error> {
-        self.ds_curr_val += self.cursor.read_var::<u32>()?;
+        let diff = self.cursor.read_var::<u32>()?;
+        self.ds_curr_val = self
+            .ds_curr_val
+            .checked_add(diff)
+            .ok_or(Error::UnexpectedValue)?;
         Ok(self.ds_curr_val)
     }
 
     fn read_ds_len(&mut self) -> Result<u32, Error> {
-        let diff = self.cursor.read_var::<u32>()? + 1;
-        self.ds_curr_val += diff;
+        let diff = self
+            .cursor
+            .read_var::<u32>()?
+            .checked_add(1)
+            .ok_or(Error::UnexpectedValue)?;
+        self.ds_curr_val = self
+            .ds_curr_val
+            .checked_add(diff)
+            .ok_or(Error::UnexpectedValue)?;
         Ok(diff)
     }
 
@@ -345,11 +387,11 @@ impl<'a> Decoder for DecoderV2<'a> {
     }
 
     fn read_any(&mut self) -> Result<Any, Error> {
-        Any::decode(&mut self.cursor)
+        Any::decode(&mut LimitedRead::new(&mut self.cursor, self.max_len))
     }
 
     fn read_json(&mut self) -> Result<Any, Error> {
-        Any::decode(&mut self.cursor)
+        Any::decode(&mut LimitedRead::new(&mut self.cursor, self.max_len))
     }
 
     fn read_key(&mut self) -> Result<Arc<str>, Error> {
@@ -357,7 +399,7 @@ impl<'a> Decoder for DecoderV2<'a> {
         if let Some(key) = self.keys.get(key_clock as usize) {
             Ok(key.clone())
         } else {
-            let key: Arc<str> = self.string_decoder.read_str()?.into();
+            let key: Arc<str> = self.read_string()?.into();
             self.keys.push(key.clone());
             Ok(key)
         }
@@ -367,6 +409,11 @@ impl<'a> Decoder for DecoderV2<'a> {
     fn read_to_end(&mut self) -> Result<&[u8], Error> {
         Ok(&self.cursor.buf[self.cursor.next..])
     }
+
+    #[inline]
+    fn set_max_len(&mut self, max_len: usize) {
+        self.max_len = max_len;
+    }
 }
 
 struct IntDiffOptRleDecoder<'a> {
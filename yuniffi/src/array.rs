@@ -0,0 +1,68 @@
+use crate::{YError, YValue};
+use yrs::types::ToJson;
+use yrs::{Any, Array, ArrayRef, Doc, Transact};
+
+/// A shared data type storing a sequence of JSON-like values.
+#[derive(uniffi::Object)]
+pub struct YArray {
+    doc: Doc,
+    array: ArrayRef,
+}
+
+impl YArray {
+    pub(crate) fn new(doc: Doc, array: ArrayRef) -> Self {
+        YArray { doc, array }
+    }
+}
+
+#[uniffi::export]
+impl YArray {
+    /// Returns a number of elements stored within this array.
+    pub fn len(&self) -> Result<u32, YError> {
+        Ok(self.array.len(&self.doc.try_transact()?))
+    }
+
+    /// Checks if this array is empty.
+    pub fn is_empty(&self) -> Result<bool, YError> {
+        Ok(self.len()? == 0)
+    }
+
+    /// Returns an element stored at a given `index`, if any.
+    pub fn get(&self, index: u32) -> Result<Option<YValue>, YError> {
+        let txn = self.doc.try_transact()?;
+        Ok(self
+            .array
+            .get(&txn, index)
+            .map(|v| YValue::from_out(&txn, v)))
+    }
+
+    /// Inserts a `value` at a given `index`.
+    pub fn insert(&self, index: u32, value: YValue) -> Result<(), YError> {
+        let mut txn = self.doc.try_transact_mut()?;
+        Ok(self
+            .array
+            .try_insert(&mut txn, index, Any::from(value))
+            .map(|_| ())?)
+    }
+
+    /// Appends a `value` at the end of this array.
+    pub fn push(&self, value: YValue) -> Result<(), YError> {
+        let mut txn = self.doc.try_transact_mut()?;
+        self.array.push_back(&mut txn, Any::from(value));
+        Ok(())
+    }
+
+    /// Removes `len` elements, starting at a given `index`.
+    pub fn remove_range(&self, index: u32, len: u32) -> Result<(), YError> {
+        let mut txn = self.doc.try_transact_mut()?;
+        Ok(self.array.try_remove_range(&mut txn, index, len)?)
+    }
+
+    /// Returns all elements of this array.
+    pub fn to_list(&self) -> Result<Vec<YValue>, YError> {
+        match YValue::from(self.array.to_json(&self.doc.try_transact()?)) {
+            YValue::List { values } => Ok(values),
+            _ => Ok(Vec::new()),
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use crate::array::YArray;
+use crate::map::YMap;
+use crate::text::YText;
+use crate::YError;
+use std::sync::{Arc, Mutex};
+use yrs::updates::decoder::Decode;
+use yrs::updates::encoder::Encode;
+use yrs::{
+    Doc, OffsetKind, Options, ReadTxn, StateVector, Subscription, Transact, Update, WriteTxn,
+};
+
+/// A Yrs document. All shared collections live within a scope of their corresponding documents.
+#[derive(uniffi::Object)]
+pub struct YDoc {
+    doc: Doc,
+}
+
+#[uniffi::export]
+impl YDoc {
+    /// Creates a new document. If `client_id` is not provided, a random one is generated. Text
+    /// indexes are measured in UTF-16 code units, which makes them compatible with Yjs as well as
+    /// with Swift `String.utf16` and Kotlin/Java strings.
+    #[uniffi::constructor]
+    pub fn new(client_id: Option<u64>) -> Arc<Self> {
+        let mut options = match client_id {
+            Some(client_id) => Options::with_client_id(client_id),
+            None => Options::default(),
+        };
+        options.offset_kind = OffsetKind::Utf16;
+        Arc::new(YDoc {
+            doc: Doc::with_options(options),
+        })
+    }
+
+    /// Returns unique peer identifier of this document.
+    pub fn client_id(&self) -> u64 {
+        self.doc.client_id()
+    }
+
+    /// Returns a root level text type stored under a given `name`, creating it if necessary.
+    pub fn get_text(&self, name: String) -> Result<Arc<YText>, YError> {
+        let text = self.doc.try_transact_mut()?.get_or_insert_text(name);
+        Ok(Arc::new(YText::new(self.doc.clone(), text)))
+    }
+
+    /// Returns a root level map type stored under a given `name`, creating it if necessary.
+    pub fn get_map(&self, name: String) -> Result<Arc<YMap>, YError> {
+        let map = self.doc.try_transact_mut()?.get_or_insert_map(name);
+        Ok(Arc::new(YMap::new(self.doc.clone(), map)))
+    }
+
+    /// Returns a root level array type stored under a given `name`, creating it if necessary.
+    pub fn get_array(&self, name: String) -> Result<Arc<YArray>, YError> {
+        let array = self.doc.try_transact_mut()?.get_or_insert_array(name);
+        Ok(Arc::new(YArray::new(self.doc.clone(), array)))
+    }
+
+    /// Returns a lib0 v1 encoded state vector of this document. It can be sent to a remote peer,
+    /// which will use it to compute the changes this document is missing.
+    pub fn state_vector(&self) -> Result<Vec<u8>, YError> {
+        Ok(self.doc.try_transact()?.state_vector().encode_v1())
+    }
+
+    /// Encodes all changes that a peer with a given lib0 v1 encoded `state_vector` is missing
+    /// as a lib0 v1 update. Empty `state_vector` encodes the whole document state.
+    pub fn encode_diff(&self, state_vector: Vec<u8>) -> Result<Vec<u8>, YError> {
+        let sv = if state_vector.is_empty() {
+            StateVector::default()
+        } else {
+            StateVector::decode_v1(&state_vector)?
+        };
+        Ok(self.doc.try_transact()?.encode_state_as_update_v1(&sv))
+    }
+
+    /// Applies a lib0 v1 encoded `update` received from a remote peer.
+    pub fn apply_update(&self, update: Vec<u8>) -> Result<(), YError> {
+        let update = Update::decode_v1(&update)?;
+        self.doc.try_transact_mut()?.apply_update(update);
+        Ok(())
+    }
+
+    /// Registers an `observer` notified with a lib0 v1 encoded update every time this document
+    /// has been changed. Observer stays registered until returned subscription is dropped or
+    /// cancelled.
+    pub fn observe_updates(
+        &self,
+        observer: Arc<dyn UpdateObserver>,
+    ) -> Result<Arc<YSubscription>, YError> {
+        let sub = self
+            .doc
+            .observe_update_v1(move |_, e| observer.on_update(e.update.clone()))
+            .map_err(|e| YError::TransactionFailed {
+                message: e.to_string(),
+            })?;
+        Ok(Arc::new(YSubscription(Mutex::new(Some(sub)))))
+    }
+}
+
+/// Receiver of the updates produced by a document, see [YDoc::observe_updates].
+#[uniffi::export(with_foreign)]
+pub trait UpdateObserver: Send + Sync {
+    /// Called with a lib0 v1 encoded update after a document has been changed.
+    fn on_update(&self, update: Vec<u8>);
+}
+
+/// Handle to an observer registered on a document. Observer is unregistered when this handle
+/// is dropped or [YSubscription::cancel] is called.
+#[derive(uniffi::Object)]
+pub struct YSubscription(Mutex<Option<Subscription>>);
+
+// Subscriptions are only created from callbacks calling `UpdateObserver`, which is Send + Sync.
+unsafe impl Send for YSubscription {}
+unsafe impl Sync for YSubscription {}
+
+#[uniffi::export]
+impl YSubscription {
+    /// Unregisters an observer. Calling this method more than once has no effect.
+    pub fn cancel(&self) {
+        self.0.lock().unwrap().take();
+    }
+}
@@ -0,0 +1,216 @@
+//! [UniFFI](https://mozilla.github.io/uniffi-rs/) bindings for [Yrs](yrs), which allow Swift and
+//! Kotlin applications to keep document replicas and synchronize them with other peers.
+//!
+//! Unlike the Rust API, transactions are not exposed: every method call opens its own
+//! transaction, which is committed before the method returns. Swift and Kotlin sources can be
+//! generated with a `uniffi-bindgen` binary built with `cli` feature:
+//!
+//! ```bash
+//! cargo build -p yuniffi --release
+//! cargo run -p yuniffi --features cli --bin uniffi-bindgen -- \
+//!     generate --library target/release/libyuniffi.so --language swift --out-dir out
+//! ```
+
+use std::collections::HashMap;
+use yrs::{Any, Out, ReadTxn};
+
+mod array;
+mod doc;
+mod map;
+mod text;
+
+pub use crate::array::YArray;
+pub use crate::doc::{UpdateObserver, YDoc, YSubscription};
+pub use crate::map::YMap;
+pub use crate::text::YText;
+
+uniffi::setup_scaffolding!();
+
+/// Error returned by the methods of the shared types.
+#[derive(Debug, Clone, PartialEq, thiserror::Error, uniffi::Error)]
+pub enum YError {
+    /// Binary payload (update or state vector) could not be decoded.
+    #[error("failed to decode payload: {message}")]
+    InvalidPayload { message: String },
+    /// Transaction could not be acquired, because another one is already active.
+    #[error("failed to acquire transaction: {message}")]
+    TransactionFailed { message: String },
+    /// Index used by an operation exceeded the length of a collection.
+    #[error("index {index} is out of bounds of collection of length {len}")]
+    IndexOutOfBounds { index: u32, len: u32 },
+}
+
+impl From<yrs::encoding::read::Error> for YError {
+    fn from(e: yrs::encoding::read::Error) -> Self {
+        YError::InvalidPayload {
+            message: e.to_string(),
+        }
+    }
+}
+
+impl From<yrs::doc::TransactionAcqError> for YError {
+    fn from(e: yrs::doc::TransactionAcqError) -> Self {
+        YError::TransactionFailed {
+            message: e.to_string(),
+        }
+    }
+}
+
+impl From<yrs::IndexOutOfBounds> for YError {
+    fn from(e: yrs::IndexOutOfBounds) -> Self {
+        YError::IndexOutOfBounds {
+            index: e.index,
+            len: e.len,
+        }
+    }
+}
+
+/// A JSON-like value stored in a [YMap] or [YArray]. Nested shared types are returned using their
+/// JSON representation.
+#[derive(Debug, Clone, PartialEq, uniffi::Enum)]
+pub enum YValue {
+    Null,
+    Bool { value: bool },
+    Int { value: i64 },
+    Float { value: f64 },
+    String { value: String },
+    Bytes { value: Vec<u8> },
+    List { values: Vec<YValue> },
+    Map { entries: HashMap<String, YValue> },
+}
+
+impl From<YValue> for Any {
+    fn from(value: YValue) -> Self {
+        match value {
+            YValue::Null => Any::Null,
+            YValue::Bool { value } => Any::Bool(value),
+            YValue::Int { value } => Any::BigInt(value),
+            YValue::Float { value } => Any::Number(value),
+            YValue::String { value } => Any::from(value),
+            YValue::Bytes { value } => Any::from(value),
+            YValue::List { values } => {
+                Any::from(values.into_iter().map(Any::from).collect::<Vec<_>>())
+            }
+            YValue::Map { entries } => Any::from(
+                entries
+                    .into_iter()
+                    .map(|(k, v)| (k, Any::from(v)))
+                    .collect::<HashMap<_, _>>(),
+            ),
+        }
+    }
+}
+
+impl From<Any> for YValue {
+    fn from(value: Any) -> Self {
+        match value {
+            Any::Null | Any::Undefined => YValue::Null,
+            Any::Bool(value) => YValue::Bool { value },
+            Any::Number(value) => YValue::Float { value },
+            Any::BigInt(value) => YValue::Int { value },
+            Any::String(value) => YValue::String {
+                value: value.to_string(),
+            },
+            Any::Buffer(value) => YValue::Bytes {
+                value: value.to_vec(),
+            },
+            Any::Array(values) => YValue::List {
+                values: values.iter().cloned().map(YValue::from).collect(),
+            },
+            Any::Map(entries) => YValue::Map {
+                entries: entries
+                    .iter()
+                    .map(|(k, v)| (k.clone(), YValue::from(v.clone())))
+                    .collect(),
+            },
+        }
+    }
+}
+
+impl YValue {
+    pub(crate) fn from_out<T: ReadTxn>(txn: &T, value: Out) -> Self {
+        use yrs::types::ToJson;
+        match value {
+            Out::Any(any) => YValue::from(any),
+            other => YValue::from(other.to_json(txn)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{UpdateObserver, YDoc, YError, YValue};
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct Updates(Mutex<Vec<Vec<u8>>>);
+
+    impl UpdateObserver for Updates {
+        fn on_update(&self, update: Vec<u8>) {
+            self.0.lock().unwrap().push(update);
+        }
+    }
+
+    #[test]
+    fn sync_replicas() {
+        let d1 = YDoc::new(Some(1));
+        let updates = Arc::new(Updates::default());
+        let sub = d1.observe_updates(updates.clone()).unwrap();
+
+        let text = d1.get_text("text".into()).unwrap();
+        text.insert(0, "hello".into()).unwrap();
+        text.push(" world".into()).unwrap();
+        assert_eq!(
+            text.insert(100, "!".into()),
+            Err(YError::IndexOutOfBounds {
+                index: 100,
+                len: 11
+            })
+        );
+        let map = d1.get_map("map".into()).unwrap();
+        map.insert(
+            "key".into(),
+            YValue::List {
+                values: vec![YValue::Int { value: 1 }, YValue::Null],
+            },
+        )
+        .unwrap();
+        sub.cancel();
+        map.insert("ignored".into(), YValue::Bool { value: true })
+            .unwrap();
+        assert_eq!(updates.0.lock().unwrap().len(), 3);
+
+        // offline replica catches up using all updates it has missed
+        let d2 = YDoc::new(Some(2));
+        let diff = d1.encode_diff(d2.state_vector().unwrap()).unwrap();
+        d2.apply_update(diff).unwrap();
+        assert_eq!(
+            d2.get_text("text".into()).unwrap().get_string().unwrap(),
+            "hello world"
+        );
+        let mut expected = HashMap::new();
+        expected.insert(
+            "key".to_string(),
+            YValue::List {
+                values: vec![YValue::Int { value: 1 }, YValue::Null],
+            },
+        );
+        expected.insert("ignored".to_string(), YValue::Bool { value: true });
+        assert_eq!(
+            d2.get_map("map".into()).unwrap().to_map().unwrap(),
+            expected
+        );
+
+        let array = d2.get_array("array".into()).unwrap();
+        array.push(YValue::String { value: "a".into() }).unwrap();
+        array.insert(0, YValue::Float { value: 0.5 }).unwrap();
+        array.remove_range(1, 1).unwrap();
+        assert_eq!(array.to_list().unwrap(), vec![YValue::Float { value: 0.5 }]);
+
+        assert!(matches!(
+            d2.apply_update(vec![255]),
+            Err(YError::InvalidPayload { .. })
+        ));
+    }
+}
@@ -0,0 +1,58 @@
+use crate::{YError, YValue};
+use std::collections::HashMap;
+use yrs::types::ToJson;
+use yrs::{Any, Doc, Map, MapRef, Transact};
+
+/// A shared data type storing JSON-like values under string keys.
+#[derive(uniffi::Object)]
+pub struct YMap {
+    doc: Doc,
+    map: MapRef,
+}
+
+impl YMap {
+    pub(crate) fn new(doc: Doc, map: MapRef) -> Self {
+        YMap { doc, map }
+    }
+}
+
+#[uniffi::export]
+impl YMap {
+    /// Returns a number of entries stored within this map.
+    pub fn len(&self) -> Result<u32, YError> {
+        Ok(self.map.len(&self.doc.try_transact()?))
+    }
+
+    /// Checks if this map is empty.
+    pub fn is_empty(&self) -> Result<bool, YError> {
+        Ok(self.len()? == 0)
+    }
+
+    /// Returns a value stored under a given `key`, if any.
+    pub fn get(&self, key: String) -> Result<Option<YValue>, YError> {
+        let txn = self.doc.try_transact()?;
+        Ok(self.map.get(&txn, &key).map(|v| YValue::from_out(&txn, v)))
+    }
+
+    /// Inserts a `value` under a given `key`, replacing the previous entry if any.
+    pub fn insert(&self, key: String, value: YValue) -> Result<(), YError> {
+        let mut txn = self.doc.try_transact_mut()?;
+        self.map.insert(&mut txn, key, Any::from(value));
+        Ok(())
+    }
+
+    /// Removes an entry stored under a given `key`, returning its value if it existed.
+    pub fn remove(&self, key: String) -> Result<Option<YValue>, YError> {
+        let mut txn = self.doc.try_transact_mut()?;
+        let old = self.map.remove(&mut txn, &key);
+        Ok(old.map(|v| YValue::from_out(&txn, v)))
+    }
+
+    /// Returns all entries of this map.
+    pub fn to_map(&self) -> Result<HashMap<String, YValue>, YError> {
+        match YValue::from(self.map.to_json(&self.doc.try_transact()?)) {
+            YValue::Map { entries } => Ok(entries),
+            _ => Ok(HashMap::new()),
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use crate::YError;
+use yrs::{Doc, GetString, Text, TextRef, Transact};
+
+/// A shared data type used for collaborative text editing. All indexes and lengths are measured in
+/// UTF-16 code units.
+#[derive(uniffi::Object)]
+pub struct YText {
+    doc: Doc,
+    text: TextRef,
+}
+
+impl YText {
+    pub(crate) fn new(doc: Doc, text: TextRef) -> Self {
+        YText { doc, text }
+    }
+}
+
+#[uniffi::export]
+impl YText {
+    /// Returns the length of this text.
+    pub fn len(&self) -> Result<u32, YError> {
+        Ok(self.text.len(&self.doc.try_transact()?))
+    }
+
+    /// Checks if this text is empty.
+    pub fn is_empty(&self) -> Result<bool, YError> {
+        Ok(self.len()? == 0)
+    }
+
+    /// Returns the string content of this text.
+    pub fn get_string(&self) -> Result<String, YError> {
+        Ok(self.text.get_string(&self.doc.try_transact()?))
+    }
+
+    /// Inserts a `chunk` of text at a given `index`.
+    pub fn insert(&self, index: u32, chunk: String) -> Result<(), YError> {
+        let mut txn = self.doc.try_transact_mut()?;
+        Ok(self.text.try_insert(&mut txn, index, &chunk)?)
+    }
+
+    /// Appends a `chunk` of text at the end of this text.
+    pub fn push(&self, chunk: String) -> Result<(), YError> {
+        let mut txn = self.doc.try_transact_mut()?;
+        self.text.push(&mut txn, &chunk);
+        Ok(())
+    }
+
+    /// Removes `len` code units of text, starting at a given `index`.
+    pub fn remove_range(&self, index: u32, len: u32) -> Result<(), YError> {
+        let mut txn = self.doc.try_transact_mut()?;
+        Ok(self.text.try_remove_range(&mut txn, index, len)?)
+    }
+}